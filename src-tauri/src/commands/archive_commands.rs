@@ -0,0 +1,227 @@
+use crate::error_handling::{Error, ErrorCode};
+use crate::filesystem::archive::browse::{self, ArchivePath};
+use crate::filesystem::archive::{ArchiveEntryKind, ExtractOptions};
+use crate::models::{access_rights_to_string_from_mode, format_system_time, Entries};
+use crate::{log_error, models};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Shown when an archive does not store a timestamp
+const NO_TIME: &str = "1970-01-01 00:00:00";
+
+fn format_unix_time(time: Option<i64>) -> String {
+    time.and_then(|secs| u64::try_from(secs).ok())
+        .map(|secs| format_system_time(UNIX_EPOCH + Duration::from_secs(secs)))
+        .unwrap_or_else(|| NO_TIME.to_string())
+}
+
+fn parse_archive_path(path: &str) -> Result<ArchivePath, String> {
+    ArchivePath::parse(path).ok_or_else(|| {
+        log_error!("Path does not point into an archive: {}", path);
+        Error::new(
+            ErrorCode::InvalidInput,
+            format!("Path does not point into an archive: {}", path),
+        )
+        .to_json()
+    })
+}
+
+fn io_error(err: std::io::Error) -> String {
+    log_error!("Failed to read archive: {}", err);
+    let code = match err.kind() {
+        std::io::ErrorKind::NotFound => ErrorCode::ResourceNotFound,
+        std::io::ErrorKind::InvalidInput => ErrorCode::InvalidInput,
+        _ => ErrorCode::InternalError,
+    };
+    Error::new(code, format!("Failed to read archive: {}", err)).to_json()
+}
+
+/// Lists a directory inside a zip, tar or 7z archive without extracting anything.
+/// A path inside an archive is the path of the archive followed by the path inside it,
+/// e.g. `/path/to/backup.tar.gz/photos`. The archive path alone lists its root.
+/// The returned entries use such paths too, so they can be passed back to this command,
+/// `build_preview` and `extract_archive_member`.
+///
+/// # Arguments
+/// * `path` - The path of the archive, or of a directory inside it
+///
+/// # Returns
+/// * `Ok(String)` - The content as JSON, in the same format as `open_directory`
+/// * `Err(String)` - If the path does not point into an archive or the archive could not be read
+///
+/// # Example
+/// ```rust
+/// let result = open_archive_directory("/path/to/backup.tar.gz/photos".to_string()).await;
+/// match result {
+///     Ok(json) => println!("Directory content: {}", json),
+///     Err(err) => println!("Error listing archive: {}", err),
+/// }
+/// ```
+#[tauri::command]
+pub async fn open_archive_directory(path: String) -> Result<String, String> {
+    let archive_path = parse_archive_path(&path)?;
+    let entries = browse::archive_entries(&archive_path.archive).map_err(io_error)?;
+    let listing = browse::list_directory(&entries, &archive_path.inner).map_err(io_error)?;
+
+    let directories = listing
+        .directories
+        .into_iter()
+        .map(|directory| models::Directory {
+            name: directory.path.rsplit('/').next().unwrap_or_default().to_string(),
+            path: archive_path.member_path(&directory.path),
+            is_symlink: false,
+            access_rights_as_string: access_rights_to_string_from_mode(directory.mode.unwrap_or(0o755)),
+            access_rights_as_number: directory.mode.unwrap_or(0o755),
+            size_in_bytes: 0,
+            sub_file_count: directory.sub_file_count,
+            sub_dir_count: directory.sub_dir_count,
+            created: NO_TIME.to_string(),
+            last_modified: format_unix_time(directory.modified),
+            accessed: NO_TIME.to_string(),
+        })
+        .collect();
+    let files = listing
+        .files
+        .into_iter()
+        .map(|file| models::File {
+            name: file.path.rsplit('/').next().unwrap_or_default().to_string(),
+            path: archive_path.member_path(&file.path),
+            is_symlink: file.kind == ArchiveEntryKind::Symlink,
+            access_rights_as_string: access_rights_to_string_from_mode(file.mode.unwrap_or(0o644)),
+            access_rights_as_number: file.mode.unwrap_or(0o644),
+            size_in_bytes: file.size,
+            created: NO_TIME.to_string(),
+            last_modified: format_unix_time(file.modified),
+            accessed: NO_TIME.to_string(),
+        })
+        .collect();
+
+    serde_json::to_string(&Entries { directories, files }).map_err(|err| {
+        log_error!("Failed to serialize entries: {}", err);
+        Error::new(
+            ErrorCode::InternalError,
+            format!("Failed to serialize entries: {}", err),
+        )
+        .to_json()
+    })
+}
+
+/// Extracts a single file or directory from an archive, leaving the archive as it is.
+/// Names that already exist in the destination get a unique name.
+///
+/// # Arguments
+/// * `path` - The path of the member inside the archive, as returned by `open_archive_directory`
+/// * `destination_path` - Optional destination directory, defaults to the directory of the archive
+/// * `options` - Optional password and limits, see `ExtractOptions`. Dry runs are not supported.
+///
+/// # Returns
+/// * `Ok(String)` - The path of the extracted file or directory
+/// * `Err(String)` - If the member does not exist, is too large or could not be extracted
+///
+/// # Example
+/// ```rust
+/// let result = extract_archive_member(
+///     "/path/to/backup.tar.gz/photos/cat.jpg".to_string(),
+///     Some("/path/to/destination".to_string()),
+///     None,
+/// ).await;
+/// ```
+#[tauri::command]
+pub async fn extract_archive_member(
+    path: String,
+    destination_path: Option<String>,
+    options: Option<ExtractOptions>,
+) -> Result<String, String> {
+    let archive_path = parse_archive_path(&path)?;
+    if archive_path.inner.is_empty() {
+        log_error!("No member of the archive given: {}", path);
+        return Err(Error::new(
+            ErrorCode::InvalidInput,
+            "No member of the archive given, use unzip to extract the whole archive".to_string(),
+        )
+        .to_json());
+    }
+
+    let dest_dir = match &destination_path {
+        Some(dest) => Path::new(dest).to_path_buf(),
+        None => archive_path.archive.parent().unwrap_or(Path::new(".")).to_path_buf(),
+    };
+    if !dest_dir.is_dir() {
+        log_error!("Destination directory does not exist: {}", dest_dir.display());
+        return Err(Error::new(
+            ErrorCode::ResourceNotFound,
+            format!("Destination directory does not exist: {}", dest_dir.display()),
+        )
+        .to_json());
+    }
+
+    let options = options.unwrap_or_default();
+    let extracted = browse::extract_member(&archive_path.archive, &archive_path.inner, &dest_dir, &options)
+        .map_err(io_error)?;
+    Ok(extracted.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests_archive_commands {
+    use super::*;
+    use crate::filesystem::archive::{create_writer, ArchiveFormat, ArchiveOptions};
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn create_archive(dir: &Path) -> String {
+        let source = dir.join("readme.txt");
+        fs::write(&source, "hello").unwrap();
+        let archive = dir.join("docs.zip");
+        let mut writer = create_writer(&archive, ArchiveFormat::Zip, &ArchiveOptions::default()).unwrap();
+        writer.add_file("docs/readme.txt", &source, &mut fs::File::open(&source).unwrap()).unwrap();
+        writer.add_file("docs/guide/intro.md", &source, &mut fs::File::open(&source).unwrap()).unwrap();
+        writer.finish().unwrap();
+        archive.to_string_lossy().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_open_archive_directory() {
+        let temp_dir = tempdir().unwrap();
+        let archive = create_archive(temp_dir.path());
+
+        let root: Entries = serde_json::from_str(&open_archive_directory(archive.clone()).await.unwrap()).unwrap();
+        assert_eq!(root.directories.len(), 1);
+        assert_eq!(root.directories[0].name, "docs");
+        assert_eq!((root.directories[0].sub_file_count, root.directories[0].sub_dir_count), (1, 1));
+
+        let docs: Entries = serde_json::from_str(&open_archive_directory(root.directories[0].path.clone()).await.unwrap()).unwrap();
+        assert_eq!(docs.files.len(), 1);
+        assert_eq!(docs.files[0].name, "readme.txt");
+        assert_eq!(docs.files[0].size_in_bytes, 5);
+        assert_eq!(docs.files[0].access_rights_as_string, "rwxr-xr-x");
+        assert_eq!(docs.directories[0].name, "guide");
+
+        let err = open_archive_directory(format!("{}/missing", archive)).await.unwrap_err();
+        assert!(err.contains("No directory"), "{}", err);
+        let err = open_archive_directory(temp_dir.path().to_string_lossy().to_string()).await.unwrap_err();
+        assert!(err.contains("does not point into an archive"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_extract_archive_member() {
+        let temp_dir = tempdir().unwrap();
+        let archive = create_archive(temp_dir.path());
+
+        // The archive directory already contains readme.txt
+        let extracted = extract_archive_member(format!("{}/docs/readme.txt", archive), None, None).await.unwrap();
+        assert_eq!(Path::new(&extracted), temp_dir.path().join("readme (1).txt"));
+        assert_eq!(fs::read_to_string(&extracted).unwrap(), "hello");
+        assert!(Path::new(&archive).exists());
+
+        assert!(extract_archive_member(archive.clone(), None, None).await.is_err());
+        let err = extract_archive_member(format!("{}/docs/readme.txt", archive), Some("/does/not/exist".to_string()), None)
+            .await
+            .unwrap_err();
+        assert!(err.contains("Destination directory does not exist"), "{}", err);
+
+        let limited = ExtractOptions { max_total_size: Some(2), ..ExtractOptions::default() };
+        let err = extract_archive_member(format!("{}/docs", archive), None, Some(limited)).await.unwrap_err();
+        assert!(err.contains("expands to more than 2 bytes"), "{}", err);
+        assert!(!temp_dir.path().join("docs").exists());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::commands::volume_operations_commands::get_system_volumes_information;
+use crate::error_handling::{Error, ErrorCode};
+use crate::filesystem::disk_usage::DiskUsageOptions;
+use crate::models::VolumeInformation;
+use crate::state::disk_usage_data::{AnalysisId, DiskUsageSink, DiskUsageState};
+use crate::{log_error, log_warn};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, State};
+
+/// Event sent with a `DiskUsageUpdate` payload while and after a disk usage analysis runs
+pub const DISK_USAGE_UPDATED_EVENT: &str = "disk-usage-updated";
+
+/// Forwards analysis updates to the frontend as Tauri events.
+fn app_event_sink(app: AppHandle) -> DiskUsageSink {
+    Arc::new(move |update| {
+        if let Err(err) = app.emit(DISK_USAGE_UPDATED_EVENT, update) {
+            log_warn!("Failed to emit disk usage update: {}", err);
+        }
+    })
+}
+
+/// Finds the volume `path` is located on, which is the one with the longest matching mount point.
+fn volume_of(path: &Path) -> Option<VolumeInformation> {
+    get_system_volumes_information()
+        .into_iter()
+        .filter(|volume| path.starts_with(&volume.mount_point))
+        .max_by_key(|volume| volume.mount_point.len())
+}
+
+pub fn analyze_disk_usage_impl(
+    path: String,
+    options: DiskUsageOptions,
+    sink: DiskUsageSink,
+    state: Arc<Mutex<DiskUsageState>>,
+) -> Result<AnalysisId, String> {
+    let canonical = Path::new(&path).canonicalize().map_err(|err| {
+        log_error!("Cannot access {}: {}", path, err);
+        Error::new(ErrorCode::ResourceNotFound, format!("Cannot access {}: {}", path, err)).to_json()
+    })?;
+    if !canonical.is_dir() {
+        log_error!("Path is not a directory: {}", path);
+        return Err(Error::new(ErrorCode::InvalidInput, format!("Path is not a directory: {}", path)).to_json());
+    }
+    if options.top_n == 0 {
+        return Err(Error::new(ErrorCode::InvalidInput, "top_n must be greater than 0".to_string()).to_json());
+    }
+
+    let volume = volume_of(&canonical);
+    let mut analyses = state.lock().map_err(|_| "Failed to lock disk usage state")?;
+    analyses
+        .start(canonical.to_string_lossy().to_string(), options, volume, sink)
+        .map_err(|err| Error::new(ErrorCode::InternalError, err).to_json())
+}
+
+/// Analyzes what takes up the space in a folder or on a volume, in the background.
+/// Builds a size tree with the largest children of every directory down to `max_depth`,
+/// the remaining children of a directory are combined into an "Other" node, together
+/// with the total size per file extension. The tree fits treemap and sunburst charts.
+/// A partial tree is sent as `disk-usage-updated` event about every second while the
+/// analysis runs, the final one once it is done.
+///
+/// # Arguments
+/// * `path` - The folder or mount point to analyze
+/// * `top_n` - Largest children kept per directory and largest file types, 20 if not set
+/// * `max_depth` - Levels below `path` that get children in the tree, 4 if not set
+/// * `same_file_system` - Whether to skip directories on other filesystems, true if not set
+///
+/// # Returns
+/// * `Ok(AnalysisId)` - The id of the analysis, part of every update
+/// * `Err(String)` - If the path is not an accessible directory or `top_n` is 0
+///
+/// # Example
+/// ```rust
+/// let analysis_id = analyze_disk_usage(app, "/var".into(), Some(10), None, None, state)?;
+/// ```
+#[tauri::command]
+pub fn analyze_disk_usage(
+    app: AppHandle,
+    path: String,
+    top_n: Option<usize>,
+    max_depth: Option<usize>,
+    same_file_system: Option<bool>,
+    state: State<Arc<Mutex<DiskUsageState>>>,
+) -> Result<AnalysisId, String> {
+    let defaults = DiskUsageOptions::default();
+    let options = DiskUsageOptions {
+        top_n: top_n.unwrap_or(defaults.top_n),
+        max_depth: max_depth.unwrap_or(defaults.max_depth),
+        same_file_system: same_file_system.unwrap_or(defaults.same_file_system),
+    };
+    analyze_disk_usage_impl(path, options, app_event_sink(app), state.inner().clone())
+}
+
+pub fn cancel_disk_usage_analysis_impl(
+    analysis_id: AnalysisId,
+    state: Arc<Mutex<DiskUsageState>>,
+) -> Result<(), String> {
+    let analyses = state.lock().map_err(|_| "Failed to lock disk usage state")?;
+    analyses
+        .cancel(analysis_id)
+        .map_err(|err| Error::new(ErrorCode::ResourceNotFound, err).to_json())
+}
+
+/// Cancels a running disk usage analysis. A final `disk-usage-updated` event with
+/// the status `Cancelled` is sent once it has stopped.
+///
+/// # Arguments
+/// * `analysis_id` - The id returned by `analyze_disk_usage`
+///
+/// # Returns
+/// * `Ok(())` - If the analysis was cancelled
+/// * `Err(String)` - If the analysis is unknown or has already finished
+#[tauri::command]
+pub fn cancel_disk_usage_analysis(
+    analysis_id: AnalysisId,
+    state: State<Arc<Mutex<DiskUsageState>>>,
+) -> Result<(), String> {
+    cancel_disk_usage_analysis_impl(analysis_id, state.inner().clone())
+}
+
+#[cfg(test)]
+mod tests_disk_usage_commands {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_invalid_analysis_requests() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("file.txt");
+        fs::write(&file, "data").unwrap();
+        let state = Arc::new(Mutex::new(DiskUsageState::new()));
+        let sink: DiskUsageSink = Arc::new(|_| {});
+
+        let missing = temp_dir.path().join("missing").to_string_lossy().to_string();
+        let result = analyze_disk_usage_impl(missing, DiskUsageOptions::default(), sink.clone(), state.clone());
+        assert!(result.unwrap_err().contains("ResourceNotFound"));
+
+        let result = analyze_disk_usage_impl(file.to_string_lossy().to_string(), DiskUsageOptions::default(), sink.clone(), state.clone());
+        assert!(result.unwrap_err().contains("InvalidInput"));
+
+        let options = DiskUsageOptions { top_n: 0, ..DiskUsageOptions::default() };
+        let result = analyze_disk_usage_impl(temp_dir.path().to_string_lossy().to_string(), options, sink, state.clone());
+        assert!(result.is_err());
+
+        assert!(cancel_disk_usage_analysis_impl(42, state).unwrap_err().contains("ResourceNotFound"));
+    }
+}
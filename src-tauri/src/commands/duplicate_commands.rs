@@ -0,0 +1,204 @@
+use crate::commands::file_system_operation_commands::record_operation;
+use crate::commands::hash_commands::{get_checksum_method, ChecksumMethod};
+use crate::error_handling::{Error, ErrorCode};
+use crate::filesystem::duplicates::{
+    self, DuplicateAction, DuplicateActionOutcome, DuplicateReport,
+};
+use crate::filesystem::operation_journal::JournalOperation;
+use crate::log_error;
+use crate::state::operation_journal_data::OperationJournalState;
+use crate::state::SettingsState;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+/// Finds files with identical content below one or more directories.
+/// Files are grouped by size, then by a hash of their first 64 KiB and finally by the hash
+/// of their whole content, so only files that are likely duplicates are read completely.
+/// Symlinks are not followed and hard links of the same file are only counted once.
+///
+/// # Arguments
+/// * `paths` - The directories to search, overlapping directories are searched once
+/// * `checksum_method` - The hash to compare files with, the `default_checksum_hash` setting if not set
+/// * `min_size` - Files smaller than this many bytes are ignored, empty files always are
+/// * `state` - The application's settings state containing the default hash algorithm
+///
+/// # Returns
+/// * `Ok(DuplicateReport)` - The duplicate groups, most reclaimable space first
+/// * `Err(String)` - If no paths were given or a path does not exist
+///
+/// # Example
+/// ```rust
+/// let report = find_duplicates(vec!["/home/user/photos".into()], None, Some(1024), state).await?;
+/// println!("{} bytes can be freed", report.total_reclaimable_bytes);
+/// ```
+#[tauri::command]
+pub async fn find_duplicates(
+    paths: Vec<String>,
+    checksum_method: Option<ChecksumMethod>,
+    min_size: Option<u64>,
+    state: State<'_, Arc<Mutex<SettingsState>>>,
+) -> Result<DuplicateReport, String> {
+    find_duplicates_impl(paths, checksum_method, min_size, state.inner().clone()).await
+}
+
+pub async fn find_duplicates_impl(
+    paths: Vec<String>,
+    checksum_method: Option<ChecksumMethod>,
+    min_size: Option<u64>,
+    state: Arc<Mutex<SettingsState>>,
+) -> Result<DuplicateReport, String> {
+    if paths.is_empty() {
+        log_error!("No paths provided");
+        return Err(Error::new(ErrorCode::InvalidInput, "No paths provided".to_string()).to_json());
+    }
+    let mut roots = Vec::new();
+    for path in &paths {
+        if !Path::new(path).exists() {
+            log_error!("Path does not exist: {}", path);
+            return Err(Error::new(ErrorCode::ResourceNotFound, format!("Path does not exist: {}", path)).to_json());
+        }
+        roots.push(PathBuf::from(path));
+    }
+    let method = match checksum_method {
+        Some(method) => method,
+        None => get_checksum_method(state).await.map_err(|e| e.to_string())?,
+    };
+
+    // Walking and hashing whole directory trees must not block the async runtime
+    tokio::task::spawn_blocking(move || duplicates::find_duplicates(&roots, method, min_size.unwrap_or(1)))
+        .await
+        .map_err(|err| Error::new(ErrorCode::InternalError, format!("Duplicate search failed: {}", err)).to_json())
+}
+
+/// Removes the extra copies of a file found by `find_duplicates`.
+/// Every copy is compared byte by byte with the kept file first and left alone if it differs.
+/// A failing copy does not stop the others.
+///
+/// # Arguments
+/// * `keep` - The copy to keep
+/// * `duplicates` - The copies to remove
+/// * `action` - `Trash` to move the copies to the trash, which can be undone with
+///   `undo_last_operation`, or `Hardlink` to replace them with hard links to `keep`
+/// * `journal` - The operation journal that records the trashed copies
+///
+/// # Returns
+/// * `Ok(Vec<DuplicateActionOutcome>)` - One outcome per copy, in order
+/// * `Err(String)` - If `keep` is not a file
+///
+/// # Example
+/// ```rust
+/// let outcomes = remove_duplicates(group.paths[0].clone(), group.paths[1..].to_vec(), DuplicateAction::Hardlink, journal)?;
+/// ```
+#[tauri::command]
+pub fn remove_duplicates(
+    keep: String,
+    duplicates: Vec<String>,
+    action: DuplicateAction,
+    journal: State<Arc<Mutex<OperationJournalState>>>,
+) -> Result<Vec<DuplicateActionOutcome>, String> {
+    remove_duplicates_impl(keep, duplicates, action, journal.inner().clone())
+}
+
+pub fn remove_duplicates_impl(
+    keep: String,
+    duplicates: Vec<String>,
+    action: DuplicateAction,
+    journal: Arc<Mutex<OperationJournalState>>,
+) -> Result<Vec<DuplicateActionOutcome>, String> {
+    let keep_path = Path::new(&keep);
+    if !keep_path.is_file() {
+        log_error!("File to keep does not exist: {}", keep);
+        return Err(Error::new(ErrorCode::ResourceNotFound, format!("File to keep does not exist: {}", keep)).to_json());
+    }
+    let keep_canonical = keep_path.canonicalize().ok();
+
+    let outcomes = duplicates
+        .into_iter()
+        .map(|path| {
+            let copy = Path::new(&path);
+            let result = if copy.canonicalize().ok() == keep_canonical {
+                Err("The copy is the file to keep".to_string())
+            } else {
+                match duplicates::same_content(keep_path, copy) {
+                    Ok(true) => remove_copy(keep_path, copy, action, &journal),
+                    Ok(false) => Err("The content differs from the file to keep".to_string()),
+                    Err(err) => Err(format!("Failed to compare: {}", err)),
+                }
+            };
+            if let Err(err) = &result {
+                log_error!("Failed to remove duplicate {}: {}", path, err);
+            }
+            DuplicateActionOutcome { path, error: result.err() }
+        })
+        .collect();
+    Ok(outcomes)
+}
+
+fn remove_copy(
+    keep: &Path,
+    copy: &Path,
+    action: DuplicateAction,
+    journal: &Arc<Mutex<OperationJournalState>>,
+) -> Result<(), String> {
+    match action {
+        DuplicateAction::Trash => {
+            let original_path = copy.canonicalize().map_err(|err| err.to_string())?;
+            trash::delete(copy).map_err(|err| format!("Failed to move to trash: {}", err))?;
+            record_operation(journal, JournalOperation::Trash { path: original_path.to_string_lossy().to_string() });
+            Ok(())
+        }
+        DuplicateAction::Hardlink => {
+            duplicates::replace_with_hardlink(keep, copy).map_err(|err| format!("Failed to create hard link: {}", err))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_duplicate_commands {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn create_settings_state() -> Arc<Mutex<SettingsState>> {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        Arc::new(Mutex::new(SettingsState::new_with_path(temp_file.path().to_path_buf())))
+    }
+
+    #[tokio::test]
+    async fn test_find_and_hardlink_duplicates() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = temp_dir.path().join("first.txt");
+        let second = temp_dir.path().join("second.txt");
+        let different = temp_dir.path().join("different.txt");
+        fs::write(&first, "content").unwrap();
+        fs::write(&second, "content").unwrap();
+        fs::write(&different, "CONTENT").unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+
+        let report = find_duplicates_impl(vec![root.clone()], None, None, create_settings_state()).await.unwrap();
+        assert_eq!(report.groups.len(), 1);
+        assert_eq!(report.total_reclaimable_bytes, 7);
+
+        let journal = Arc::new(Mutex::new(OperationJournalState::new()));
+        let keep = report.groups[0].paths[0].clone();
+        let outcomes = remove_duplicates_impl(
+            keep.clone(),
+            vec![second.to_string_lossy().to_string(), different.to_string_lossy().to_string(), keep.clone()],
+            DuplicateAction::Hardlink,
+            journal,
+        )
+        .unwrap();
+        assert_eq!(outcomes[0].error, None);
+        assert!(outcomes[1].error.is_some());
+        assert!(outcomes[2].error.is_some());
+        assert_eq!(fs::read_to_string(&different).unwrap(), "CONTENT");
+
+        let report = find_duplicates_impl(vec![root], Some(ChecksumMethod::MD5), None, create_settings_state()).await.unwrap();
+        assert!(report.groups.is_empty());
+
+        let missing = temp_dir.path().join("missing").to_string_lossy().to_string();
+        assert!(find_duplicates_impl(vec![missing], None, None, create_settings_state()).await.is_err());
+        assert!(find_duplicates_impl(vec![], None, None, create_settings_state()).await.is_err());
+    }
+}
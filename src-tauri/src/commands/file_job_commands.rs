@@ -0,0 +1,518 @@
+use crate::error_handling::{Error, ErrorCode};
+use crate::filesystem::archive::{ArchiveFormat, ArchiveOptions, ExtractOptions};
+use crate::filesystem::conflict_policy::{ConflictChoice, ConflictDecision, ConflictPolicy};
+use crate::state::file_job_data::{
+    FileJobState, JobEvent, JobEventSink, JobId, JobRequest, JobSnapshot,
+};
+use crate::{log_error, log_warn};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, State};
+
+/// Event sent with a `JobProgress` payload while a job is queued or running
+pub const FILE_JOB_PROGRESS_EVENT: &str = "file-job-progress";
+
+/// Event sent with a `JobSummary` payload once a job has finished
+pub const FILE_JOB_FINISHED_EVENT: &str = "file-job-finished";
+
+/// Event sent with a `FileConflict` payload when a job waits for `resolve_file_conflict`
+pub const FILE_CONFLICT_EVENT: &str = "file-conflict";
+
+/// Forwards job events to the frontend as Tauri events.
+fn app_event_sink(app: AppHandle) -> JobEventSink {
+    Arc::new(move |event| {
+        let result = match &event {
+            JobEvent::Progress(progress) => app.emit(FILE_JOB_PROGRESS_EVENT, progress),
+            JobEvent::Finished(summary) => app.emit(FILE_JOB_FINISHED_EVENT, summary),
+            JobEvent::Conflict(conflict) => app.emit(FILE_CONFLICT_EVENT, conflict),
+        };
+        if let Err(err) = result {
+            log_warn!("Failed to emit file job event: {}", err);
+        }
+    })
+}
+
+fn require_paths(paths: &[String], what: &str) -> Result<(), String> {
+    if paths.is_empty() {
+        log_error!("No {} provided", what);
+        return Err(Error::new(ErrorCode::InvalidInput, format!("No {} provided", what)).to_json());
+    }
+    Ok(())
+}
+
+pub fn submit_job_impl(
+    request: JobRequest,
+    sink: JobEventSink,
+    state: Arc<Mutex<FileJobState>>,
+) -> Result<JobId, String> {
+    match &request {
+        JobRequest::Copy { sources, .. } => require_paths(sources, "source paths")?,
+        JobRequest::Zip { sources, destination, options } => {
+            require_paths(sources, "source paths")?;
+            if sources.len() > 1 && destination.is_none() {
+                return Err(Error::new(
+                    ErrorCode::InvalidInput,
+                    "Destination path required for multiple sources".to_string(),
+                )
+                .to_json());
+            }
+            // Options that do not fit the format fail before the job is queued
+            let zip_path = match destination {
+                Some(dest) => PathBuf::from(dest),
+                None => Path::new(&sources[0]).with_extension("zip"),
+            };
+            ArchiveFormat::for_new_archive(&zip_path)
+                .and_then(|format| options.validate(format).map(|_| ()))
+                .map_err(|err| {
+                    log_error!("Invalid archive options: {}", err);
+                    Error::new(ErrorCode::InvalidInput, err).to_json()
+                })?;
+        }
+        JobRequest::Unzip { archives, destination, options } => {
+            require_paths(archives, "zip files")?;
+            if archives.len() > 1 && destination.is_none() {
+                return Err(Error::new(
+                    ErrorCode::InvalidInput,
+                    "Destination path required for multiple zip files".to_string(),
+                )
+                .to_json());
+            }
+            if options.dry_run {
+                return Err(Error::new(
+                    ErrorCode::InvalidInput,
+                    "Dry runs are not supported for jobs, use unzip".to_string(),
+                )
+                .to_json());
+            }
+        }
+        JobRequest::Trash { paths } => require_paths(paths, "paths")?,
+    }
+
+    let jobs = state.lock().map_err(|_| "Failed to lock file job state")?;
+    jobs.submit(request, sink)
+        .map_err(|err| Error::new(ErrorCode::InternalError, err).to_json())
+}
+
+/// Queues copying files and directories into a destination directory.
+/// Progress is reported through `file-job-progress` events and the result through
+/// a `file-job-finished` event, which lists every conflict and how it was resolved.
+/// With `ConflictPolicy::Ask` the job sends a `file-conflict` event per conflict and
+/// waits for the answer through `resolve_file_conflict`.
+///
+/// # Arguments
+/// * `source_paths` - Files and directories to copy
+/// * `destination_dir` - The directory to copy into, created if it does not exist
+/// * `conflict_policy` - How to handle existing names, `Rename` if not given
+///
+/// # Returns
+/// * `Ok(JobId)` - The id of the queued job
+/// * `Err(String)` - If no sources were given
+///
+/// # Example
+/// ```rust
+/// let job_id = queue_copy_job(app, vec!["/home/user/photos".into()], "/mnt/backup".into(), Some(ConflictPolicy::Ask), state)?;
+/// ```
+#[tauri::command]
+pub fn queue_copy_job(
+    app: AppHandle,
+    source_paths: Vec<String>,
+    destination_dir: String,
+    conflict_policy: Option<ConflictPolicy>,
+    state: State<Arc<Mutex<FileJobState>>>,
+) -> Result<JobId, String> {
+    submit_job_impl(
+        JobRequest::Copy {
+            sources: source_paths,
+            destination_dir,
+            conflict_policy: conflict_policy.unwrap_or_default(),
+        },
+        app_event_sink(app),
+        state.inner().clone(),
+    )
+}
+
+/// Queues zipping files and directories, with the same rules as `zip`.
+///
+/// # Arguments
+/// * `source_paths` - Files and directories to add to the archive
+/// * `destination_path` - The archive to create, required for more than one source
+/// * `options` - Optional archive options, as for `zip`
+///
+/// # Returns
+/// * `Ok(JobId)` - The id of the queued job
+/// * `Err(String)` - If the arguments are invalid
+#[tauri::command]
+pub fn queue_zip_job(
+    app: AppHandle,
+    source_paths: Vec<String>,
+    destination_path: Option<String>,
+    options: Option<ArchiveOptions>,
+    state: State<Arc<Mutex<FileJobState>>>,
+) -> Result<JobId, String> {
+    submit_job_impl(
+        JobRequest::Zip {
+            sources: source_paths,
+            destination: destination_path,
+            options: options.unwrap_or_default(),
+        },
+        app_event_sink(app),
+        state.inner().clone(),
+    )
+}
+
+/// Queues extracting zip files, with the same rules as `unzip`.
+///
+/// # Arguments
+/// * `zip_paths` - The archives to extract
+/// * `destination_path` - The directory to extract into, required for more than one archive
+/// * `options` - Optional password and limits, as for `unzip`. Dry runs are not supported.
+///
+/// # Returns
+/// * `Ok(JobId)` - The id of the queued job
+/// * `Err(String)` - If the arguments are invalid
+#[tauri::command]
+pub fn queue_unzip_job(
+    app: AppHandle,
+    zip_paths: Vec<String>,
+    destination_path: Option<String>,
+    options: Option<ExtractOptions>,
+    state: State<Arc<Mutex<FileJobState>>>,
+) -> Result<JobId, String> {
+    submit_job_impl(
+        JobRequest::Unzip {
+            archives: zip_paths,
+            destination: destination_path,
+            options: options.unwrap_or_default(),
+        },
+        app_event_sink(app),
+        state.inner().clone(),
+    )
+}
+
+/// Queues moving files and directories to the trash.
+///
+/// # Arguments
+/// * `paths` - The files and directories to trash
+///
+/// # Returns
+/// * `Ok(JobId)` - The id of the queued job
+/// * `Err(String)` - If no paths were given
+#[tauri::command]
+pub fn queue_trash_job(
+    app: AppHandle,
+    paths: Vec<String>,
+    state: State<Arc<Mutex<FileJobState>>>,
+) -> Result<JobId, String> {
+    submit_job_impl(JobRequest::Trash { paths }, app_event_sink(app), state.inner().clone())
+}
+
+/// Applies a pause, resume or cancel action to a job.
+pub fn control_job_impl(
+    job_id: JobId,
+    action: fn(&FileJobState, JobId) -> Result<(), String>,
+    state: Arc<Mutex<FileJobState>>,
+) -> Result<(), String> {
+    let jobs = state.lock().map_err(|_| "Failed to lock file job state")?;
+    action(&jobs, job_id).map_err(|err| {
+        log_error!("Failed to update job {}: {}", job_id, err);
+        Error::new(ErrorCode::InvalidInput, err).to_json()
+    })
+}
+
+/// Pauses a queued or running job. The job stops within one copied chunk.
+///
+/// # Arguments
+/// * `job_id` - The id returned when the job was queued
+///
+/// # Returns
+/// * `Ok(())` - If the job was paused
+/// * `Err(String)` - If the job is unknown or has already finished
+#[tauri::command]
+pub fn pause_file_job(job_id: JobId, state: State<Arc<Mutex<FileJobState>>>) -> Result<(), String> {
+    control_job_impl(job_id, FileJobState::pause, state.inner().clone())
+}
+
+/// Resumes a paused job.
+///
+/// # Arguments
+/// * `job_id` - The id returned when the job was queued
+///
+/// # Returns
+/// * `Ok(())` - If the job was resumed
+/// * `Err(String)` - If the job is unknown or not paused
+#[tauri::command]
+pub fn resume_file_job(job_id: JobId, state: State<Arc<Mutex<FileJobState>>>) -> Result<(), String> {
+    control_job_impl(job_id, FileJobState::resume, state.inner().clone())
+}
+
+/// Cancels a queued, running or paused job. Partially written output of the
+/// item being processed is removed, items that were already done are kept.
+///
+/// # Arguments
+/// * `job_id` - The id returned when the job was queued
+///
+/// # Returns
+/// * `Ok(())` - If the job is being cancelled
+/// * `Err(String)` - If the job is unknown or has already finished
+#[tauri::command]
+pub fn cancel_file_job(job_id: JobId, state: State<Arc<Mutex<FileJobState>>>) -> Result<(), String> {
+    control_job_impl(job_id, FileJobState::cancel, state.inner().clone())
+}
+
+pub fn resolve_file_conflict_impl(
+    job_id: JobId,
+    conflict_id: u64,
+    decision: ConflictDecision,
+    state: Arc<Mutex<FileJobState>>,
+) -> Result<(), String> {
+    let jobs = state.lock().map_err(|_| "Failed to lock file job state")?;
+    jobs.resolve_conflict(job_id, conflict_id, decision).map_err(|err| {
+        log_error!("Failed to resolve conflict {} of job {}: {}", conflict_id, job_id, err);
+        Error::new(ErrorCode::InvalidInput, err).to_json()
+    })
+}
+
+/// Answers a `file-conflict` event of a copy job using `ConflictPolicy::Ask`.
+/// The choice is recorded in the conflicts of the job's summary.
+///
+/// # Arguments
+/// * `job_id` - The job id from the event
+/// * `conflict_id` - The conflict id from the event
+/// * `choice` - `Skip`, `Overwrite` (merges directories) or `Rename`
+/// * `apply_to_all` - Use the same choice for all further conflicts of the job
+///
+/// # Returns
+/// * `Ok(())` - If the job continues with the choice
+/// * `Err(String)` - If the job is not waiting for this conflict
+///
+/// # Example
+/// ```rust
+/// resolve_file_conflict(conflict.job_id, conflict.conflict_id, ConflictChoice::Rename, Some(true), state)?;
+/// ```
+#[tauri::command]
+pub fn resolve_file_conflict(
+    job_id: JobId,
+    conflict_id: u64,
+    choice: ConflictChoice,
+    apply_to_all: Option<bool>,
+    state: State<Arc<Mutex<FileJobState>>>,
+) -> Result<(), String> {
+    let decision = ConflictDecision {
+        choice,
+        apply_to_all: apply_to_all.unwrap_or(false),
+    };
+    resolve_file_conflict_impl(job_id, conflict_id, decision, state.inner().clone())
+}
+
+pub fn get_file_jobs_impl(state: Arc<Mutex<FileJobState>>) -> Result<Vec<JobSnapshot>, String> {
+    let jobs = state.lock().map_err(|_| "Failed to lock file job state")?;
+    jobs.jobs()
+}
+
+/// Returns the progress of all known jobs, oldest first, including the summary
+/// of finished jobs.
+///
+/// # Returns
+/// * `Ok(Vec<JobSnapshot>)` - The known jobs
+/// * `Err(String)` - If the job state could not be read
+#[tauri::command]
+pub fn get_file_jobs(state: State<Arc<Mutex<FileJobState>>>) -> Result<Vec<JobSnapshot>, String> {
+    get_file_jobs_impl(state.inner().clone())
+}
+
+/// Returns the progress of a single job, including its summary once it has finished.
+///
+/// # Arguments
+/// * `job_id` - The id returned when the job was queued
+///
+/// # Returns
+/// * `Ok(JobSnapshot)` - The state of the job
+/// * `Err(String)` - If the job is unknown
+#[tauri::command]
+pub fn get_file_job(job_id: JobId, state: State<Arc<Mutex<FileJobState>>>) -> Result<JobSnapshot, String> {
+    let jobs = state.inner().lock().map_err(|_| "Failed to lock file job state")?;
+    jobs.job(job_id)
+        .map_err(|err| Error::new(ErrorCode::ResourceNotFound, err).to_json())
+}
+
+/// Removes all finished jobs from the job list.
+///
+/// # Returns
+/// * `Ok(usize)` - The number of jobs removed
+/// * `Err(String)` - If the job state could not be read
+#[tauri::command]
+pub fn clear_finished_file_jobs(state: State<Arc<Mutex<FileJobState>>>) -> Result<usize, String> {
+    let jobs = state.inner().lock().map_err(|_| "Failed to lock file job state")?;
+    jobs.clear_finished()
+}
+
+#[cfg(test)]
+mod tests_file_job_commands {
+    use super::*;
+    use crate::state::file_job_data::{JobItemStatus, JobStatus};
+    use std::fs;
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    fn create_state() -> Arc<Mutex<FileJobState>> {
+        Arc::new(Mutex::new(FileJobState::new()))
+    }
+
+    fn noop_sink() -> JobEventSink {
+        Arc::new(|_| {})
+    }
+
+    fn wait_for_summary(state: &Arc<Mutex<FileJobState>>, job_id: JobId) -> JobSnapshot {
+        let deadline = Instant::now() + Duration::from_secs(20);
+        loop {
+            let snapshot = get_file_jobs_impl(state.clone())
+                .unwrap()
+                .into_iter()
+                .find(|job| job.progress.job_id == job_id)
+                .unwrap();
+            if snapshot.summary.is_some() {
+                return snapshot;
+            }
+            assert!(Instant::now() < deadline, "job did not finish in time");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_zip_and_unzip_jobs() {
+        let temp_dir = TempDir::new().unwrap();
+        let folder = temp_dir.path().join("docs");
+        fs::create_dir_all(folder.join("sub")).unwrap();
+        fs::write(folder.join("a.txt"), "alpha").unwrap();
+        fs::write(folder.join("sub/b.txt"), "beta").unwrap();
+
+        let state = create_state();
+        let zip_id = submit_job_impl(
+            JobRequest::Zip {
+                sources: vec![folder.to_string_lossy().to_string()],
+                destination: None,
+                options: ArchiveOptions::default(),
+            },
+            noop_sink(),
+            state.clone(),
+        )
+        .unwrap();
+        let summary = wait_for_summary(&state, zip_id).summary.unwrap();
+        assert_eq!(summary.status, JobStatus::Completed);
+        assert_eq!(summary.files_done, 2);
+        let zip_path = temp_dir.path().join("docs.zip");
+        assert!(zip_path.exists());
+
+        let out = temp_dir.path().join("out");
+        fs::create_dir(&out).unwrap();
+        let unzip_id = submit_job_impl(
+            JobRequest::Unzip {
+                archives: vec![zip_path.to_string_lossy().to_string()],
+                destination: Some(out.to_string_lossy().to_string()),
+                options: ExtractOptions::default(),
+            },
+            noop_sink(),
+            state.clone(),
+        )
+        .unwrap();
+        let summary = wait_for_summary(&state, unzip_id).summary.unwrap();
+        assert_eq!(summary.items[0].status, JobItemStatus::Completed);
+        assert_eq!(fs::read_to_string(out.join("docs/docs/sub/b.txt")).unwrap(), "beta");
+    }
+
+    #[test]
+    fn test_ask_policy_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let destination = temp_dir.path().join("destination");
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&destination).unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(source.join(name), "new").unwrap();
+            fs::write(destination.join(name), "old").unwrap();
+        }
+
+        let state = create_state();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let sink: JobEventSink = Arc::new(move |event| {
+            if let JobEvent::Conflict(conflict) = event {
+                sender.send(conflict).unwrap();
+            }
+        });
+        let sources: Vec<String> = ["a.txt", "b.txt", "c.txt"]
+            .iter()
+            .map(|name| source.join(name).to_string_lossy().to_string())
+            .collect();
+        let job_id = submit_job_impl(
+            JobRequest::Copy {
+                sources,
+                destination_dir: destination.to_string_lossy().to_string(),
+                conflict_policy: ConflictPolicy::Ask,
+            },
+            sink,
+            state.clone(),
+        )
+        .unwrap();
+
+        // Skip the first conflict, rename the second and everything after it
+        let first = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(first.destination.ends_with("a.txt"));
+        let skip = ConflictDecision { choice: ConflictChoice::Skip, apply_to_all: false };
+        assert!(resolve_file_conflict_impl(job_id, first.conflict_id + 1, skip, state.clone()).is_err());
+        resolve_file_conflict_impl(job_id, first.conflict_id, skip, state.clone()).unwrap();
+
+        let second = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        let rename_all = ConflictDecision { choice: ConflictChoice::Rename, apply_to_all: true };
+        resolve_file_conflict_impl(job_id, second.conflict_id, rename_all, state.clone()).unwrap();
+
+        let summary = wait_for_summary(&state, job_id).summary.unwrap();
+        assert!(receiver.try_recv().is_err(), "no further questions after apply_to_all");
+        assert_eq!(summary.status, JobStatus::Completed);
+        assert_eq!(summary.items[0].status, JobItemStatus::Skipped);
+        assert_eq!(summary.items[0].conflicts[0].choice, ConflictChoice::Skip);
+        assert!(summary.items[0].conflicts[0].asked);
+        assert_eq!(summary.items[2].conflicts[0].choice, ConflictChoice::Rename);
+        assert_eq!(fs::read_to_string(destination.join("a.txt")).unwrap(), "old");
+        assert_eq!(fs::read_to_string(destination.join("c (1).txt")).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_invalid_job_requests() {
+        let state = create_state();
+
+        let result = submit_job_impl(JobRequest::Trash { paths: vec![] }, noop_sink(), state.clone());
+        assert!(result.unwrap_err().contains("InvalidInput"));
+
+        let result = submit_job_impl(
+            JobRequest::Zip { sources: vec!["/a".into(), "/b".into()], destination: None, options: ArchiveOptions::default() },
+            noop_sink(),
+            state.clone(),
+        );
+        assert!(result.unwrap_err().contains("Destination path required for multiple sources"));
+
+        let result = submit_job_impl(
+            JobRequest::Zip {
+                sources: vec!["/a".into()],
+                destination: Some("/a.tar.gz".into()),
+                options: ArchiveOptions { password: Some("secret".into()), ..ArchiveOptions::default() },
+            },
+            noop_sink(),
+            state.clone(),
+        );
+        assert!(result.unwrap_err().contains("Only zip archives can be encrypted"));
+
+        let result = submit_job_impl(
+            JobRequest::Unzip {
+                archives: vec!["/a.zip".into()],
+                destination: None,
+                options: ExtractOptions { dry_run: true, ..ExtractOptions::default() },
+            },
+            noop_sink(),
+            state.clone(),
+        );
+        assert!(result.unwrap_err().contains("Dry runs are not supported for jobs"));
+
+        let result = control_job_impl(42, FileJobState::pause, state);
+        assert!(result.unwrap_err().contains("Unknown job"));
+    }
+}
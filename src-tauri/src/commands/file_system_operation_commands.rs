@@ -1,15 +1,28 @@
 use crate::error_handling::{Error, ErrorCode};
+use crate::filesystem::archive::{self, ArchiveFormat, ArchiveOptions, ExtractOptions, Extraction};
+use crate::filesystem::conflict_policy::DirectConflictPolicy;
+use crate::filesystem::conflict_policy::ConflictChoice;
+use crate::filesystem::directory_listing::{
+    self, ChildCounts, DirectoryChunk, ListingOptions, DEFAULT_CHUNK_SIZE,
+};
+use crate::filesystem::file_jobs::{copy_with_policy, CopyOutcome};
+use crate::filesystem::file_move::move_path;
+use crate::filesystem::folder_size::{cached_folder_size, FolderSizeCache};
+use crate::filesystem::operation_journal::{copy_manifest, JournalOperation};
 use crate::models::{
     count_subdirectories, count_subfiles, format_system_time, get_access_permission_number,
     get_access_permission_string, Entries,
 };
-use crate::{log_error, models};
+use crate::state::folder_size_data::FolderSizeState;
+use crate::state::operation_journal_data::{JournalEntry, OperationJournalState};
+use crate::state::{SettingsState, SortBy, SortDirection};
+use crate::{log_error, log_info, models};
 use std::fs;
 use std::fs::read_dir;
-use std::io::Write;
 use std::path::Path;
-use zip::write::FileOptions;
-use zip::ZipWriter;
+use std::sync::{Arc, Mutex};
+use tauri::ipc::Channel;
+use tauri::State;
 
 /// Opens a file at the given path and returns its contents as a string.
 /// Should only be used for text files.
@@ -90,9 +103,12 @@ pub async fn open_in_default_app(path: &str) -> Result<(), String> {
 }
 
 /// Opens a directory at the given path and returns its contents as a json string.
+/// Directories get the size measured by `calculate_folder_sizes` if none of their
+/// subdirectories changed since, 0 otherwise.
 ///
 /// # Arguments
 /// - `path` - A string slice that holds the path to the directory to be opened.
+/// - `state` - The folder size state, whose cache provides the sizes of directories.
 ///
 /// # Returns
 /// - `Ok(Entries)` - If the directory was successfully opened and read.
@@ -100,7 +116,7 @@ pub async fn open_in_default_app(path: &str) -> Result<(), String> {
 ///
 /// # Example
 /// ```rust
-/// let result = open_directory("/path/to/directory").await;
+/// let result = open_directory("/path/to/directory", state).await;
 /// match result {
 ///    Ok(entries) => {
 ///       for dir in entries.directories {
@@ -114,7 +130,20 @@ pub async fn open_in_default_app(path: &str) -> Result<(), String> {
 /// }
 /// ```
 #[tauri::command]
-pub async fn open_directory(path: String) -> Result<String, String> {
+pub async fn open_directory(path: String, state: State<'_, Arc<Mutex<FolderSizeState>>>) -> Result<String, String> {
+    let folder_sizes = folder_size_cache(state.inner())?;
+    open_directory_impl(path, &folder_sizes).await
+}
+
+/// The cache of the folder size calculations, to fill in the sizes of listed directories.
+fn folder_size_cache(state: &Arc<Mutex<FolderSizeState>>) -> Result<Arc<FolderSizeCache>, String> {
+    let folder_sizes = state.lock().map_err(|_| {
+        Error::new(ErrorCode::InternalError, "Failed to lock folder size state".to_string()).to_json()
+    })?;
+    Ok(folder_sizes.cache())
+}
+
+pub async fn open_directory_impl(path: String, folder_sizes: &FolderSizeCache) -> Result<String, String> {
     let path_obj = Path::new(&path);
 
     // Check if path exists
@@ -183,7 +212,7 @@ pub async fn open_directory(path: String) -> Result<String, String> {
                 is_symlink: path_of_entry.is_symlink(),
                 access_rights_as_string: get_access_permission_string(metadata.permissions(), true),
                 access_rights_as_number: get_access_permission_number(metadata.permissions(), true),
-                size_in_bytes: 0,
+                size_in_bytes: cached_folder_size(&path_of_entry, folder_sizes).map_or(0, |size| size.size_in_bytes),
                 sub_file_count: path_of_entry.to_str().map(count_subfiles).unwrap_or(0),
                 sub_dir_count: path_of_entry.to_str().map(count_subdirectories).unwrap_or(0),
                 created: metadata
@@ -249,6 +278,156 @@ pub async fn open_directory(path: String) -> Result<String, String> {
     Ok(json)
 }
 
+/// Lists a directory in sorted chunks that are sent through a channel as they are ready.
+/// Meant for huge folders, where `open_directory` would build one very large response.
+/// Directories come first, each group sorted by the given or the configured sort settings.
+/// The child counts of directories are left empty unless requested, use `get_child_counts`
+/// to fetch them for the visible directories only. Directories get their size like in
+/// `open_directory`, also when sorting by size.
+///
+/// # Arguments
+/// - `path` - The path of the directory to list.
+/// - `on_chunk` - The channel that receives the `DirectoryChunk`s.
+/// - `sort_by` - Name, Size, Date or Type. Defaults to the `sort_by` setting.
+/// - `sort_direction` - Ascending or Descending. Defaults to the `sort_direction` setting.
+/// - `chunk_size` - Number of entries per chunk, 500 if not set.
+/// - `include_child_counts` - Whether to count the children of every listed directory.
+/// - `folder_size_state` - The folder size state, whose cache provides the sizes of directories.
+///
+/// # Returns
+/// - `Ok(usize)` - The number of listed entries, once the last chunk was sent.
+/// - `Err(String)` - If the path is not a readable directory or the channel was closed.
+///
+/// # Example
+/// ```rust
+/// let result = stream_directory("/path/to/directory".to_string(), channel, None, None, None, None, state, folder_size_state).await;
+/// match result {
+///     Ok(total) => println!("Listed {} entries", total),
+///     Err(err) => println!("Error listing directory: {}", err),
+/// }
+/// ```
+#[tauri::command]
+pub async fn stream_directory(
+    path: String,
+    on_chunk: Channel<DirectoryChunk>,
+    sort_by: Option<SortBy>,
+    sort_direction: Option<SortDirection>,
+    chunk_size: Option<usize>,
+    include_child_counts: Option<bool>,
+    settings_state: State<'_, Arc<Mutex<SettingsState>>>,
+    folder_size_state: State<'_, Arc<Mutex<FolderSizeState>>>,
+) -> Result<usize, String> {
+    let settings_state = settings_state.inner().clone();
+    let options = listing_options(sort_by, sort_direction, chunk_size, include_child_counts, settings_state)?;
+    let folder_sizes = folder_size_cache(folder_size_state.inner())?;
+
+    // Reading and sorting a huge folder must not block the async runtime
+    tokio::task::spawn_blocking(move || {
+        stream_directory_impl(&path, options, &folder_sizes, |chunk| {
+            on_chunk
+                .send(chunk)
+                .map_err(|err| format!("Failed to send directory chunk: {}", err))
+        })
+    })
+    .await
+    .map_err(|err| {
+        log_error!("Directory listing task failed: {}", err);
+        Error::new(
+            ErrorCode::InternalError,
+            format!("Directory listing task failed: {}", err),
+        )
+        .to_json()
+    })?
+}
+
+/// Resolves the listing options, falling back to the sort settings.
+pub fn listing_options(
+    sort_by: Option<SortBy>,
+    sort_direction: Option<SortDirection>,
+    chunk_size: Option<usize>,
+    include_child_counts: Option<bool>,
+    settings_state: Arc<Mutex<SettingsState>>,
+) -> Result<ListingOptions, String> {
+    if chunk_size == Some(0) {
+        log_error!("Chunk size must be at least 1");
+        return Err(Error::new(
+            ErrorCode::InvalidInput,
+            "Chunk size must be at least 1".to_string(),
+        )
+        .to_json());
+    }
+
+    let (default_sort_by, default_sort_direction) = {
+        let settings_state = settings_state.lock().map_err(|_| {
+            Error::new(ErrorCode::InternalError, "Failed to lock settings state".to_string()).to_json()
+        })?;
+        let settings = settings_state.0.lock().map_err(|_| {
+            Error::new(ErrorCode::InternalError, "Failed to lock settings".to_string()).to_json()
+        })?;
+        (settings.sort_by, settings.sort_direction)
+    };
+
+    Ok(ListingOptions {
+        sort_by: sort_by.unwrap_or(default_sort_by),
+        sort_direction: sort_direction.unwrap_or(default_sort_direction),
+        chunk_size: chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE),
+        child_counts: include_child_counts.unwrap_or(false),
+    })
+}
+
+pub fn stream_directory_impl(
+    path: &str,
+    options: ListingOptions,
+    folder_sizes: &FolderSizeCache,
+    send: impl FnMut(DirectoryChunk) -> Result<(), String>,
+) -> Result<usize, String> {
+    let path_obj = Path::new(path);
+
+    if !path_obj.exists() {
+        log_error!("Directory does not exist: {}", path);
+        return Err(Error::new(
+            ErrorCode::ResourceNotFound,
+            format!("Directory does not exist: {}", path),
+        )
+        .to_json());
+    }
+
+    if !path_obj.is_dir() {
+        log_error!("Path is not a directory: {}", path);
+        return Err(Error::new(
+            ErrorCode::InvalidInput,
+            format!("Path is not a directory: {}", path),
+        )
+        .to_json());
+    }
+
+    directory_listing::stream_directory(path_obj, options, folder_sizes, send).map_err(|err| {
+        log_error!("Failed to list {}: {}", path, err);
+        Error::new(ErrorCode::InternalError, err).to_json()
+    })
+}
+
+/// Counts the files and directories directly inside each of the given directories.
+/// Used to fill in the counts left out by `stream_directory`, e.g. for the visible rows only.
+///
+/// # Arguments
+/// - `paths` - The directories to count the children of.
+///
+/// # Returns
+/// - `Vec<ChildCounts>` - The counts in the order of `paths`. Unreadable paths count zero.
+///
+/// # Example
+/// ```rust
+/// let counts = get_child_counts(vec!["/path/to/directory".to_string()]).await;
+/// println!("{} files", counts[0].sub_file_count);
+/// ```
+#[tauri::command]
+pub async fn get_child_counts(paths: Vec<String>) -> Vec<ChildCounts> {
+    tokio::task::spawn_blocking(move || directory_listing::child_counts(&paths))
+        .await
+        .unwrap_or_default()
+}
+
 /// Creates a file at the given absolute path. Returns a string if there was an error.
 /// This function does not create any parent directories.
 ///
@@ -268,7 +447,19 @@ pub async fn open_directory(path: String) -> Result<String, String> {
 /// }
 /// ```
 #[tauri::command]
-pub async fn create_file(folder_path_abs: &str, file_name: &str) -> Result<(), String> {
+pub async fn create_file(
+    folder_path_abs: &str,
+    file_name: &str,
+    journal: State<'_, Arc<Mutex<OperationJournalState>>>,
+) -> Result<(), String> {
+    create_file_impl(folder_path_abs, file_name, journal.inner().clone()).await
+}
+
+pub async fn create_file_impl(
+    folder_path_abs: &str,
+    file_name: &str,
+    journal: Arc<Mutex<OperationJournalState>>,
+) -> Result<(), String> {
     // Check if the folder path exists and is valid
     let path = Path::new(folder_path_abs);
     if !path.exists() {
@@ -294,7 +485,12 @@ pub async fn create_file(folder_path_abs: &str, file_name: &str) -> Result<(), S
 
     // Create the file
     match fs::File::create(&file_path) {
-        Ok(_) => Ok(()),
+        Ok(_) => {
+            record_operation(&journal, JournalOperation::CreateFile {
+                path: file_path.to_string_lossy().to_string(),
+            });
+            Ok(())
+        }
         Err(err) => {
             log_error!(
                 "File could not be created: {} error: {}",
@@ -331,7 +527,19 @@ pub async fn create_file(folder_path_abs: &str, file_name: &str) -> Result<(), S
 /// }
 /// ```
 #[tauri::command]
-pub async fn create_directory(folder_path_abs: &str, folder_name: &str) -> Result<(), String> {
+pub async fn create_directory(
+    folder_path_abs: &str,
+    folder_name: &str,
+    journal: State<'_, Arc<Mutex<OperationJournalState>>>,
+) -> Result<(), String> {
+    create_directory_impl(folder_path_abs, folder_name, journal.inner().clone()).await
+}
+
+pub async fn create_directory_impl(
+    folder_path_abs: &str,
+    folder_name: &str,
+    journal: Arc<Mutex<OperationJournalState>>,
+) -> Result<(), String> {
     // Check if the folder path exists and is valid
     let parent_path = Path::new(folder_path_abs);
     if !parent_path.exists() {
@@ -357,7 +565,12 @@ pub async fn create_directory(folder_path_abs: &str, folder_name: &str) -> Resul
 
     // Create the directory
     match fs::create_dir(&dir_path) {
-        Ok(_) => Ok(()),
+        Ok(_) => {
+            record_operation(&journal, JournalOperation::CreateDirectory {
+                path: dir_path.to_string_lossy().to_string(),
+            });
+            Ok(())
+        }
         Err(err) => {
             log_error!(
                 "Failed to create directory: {} err: {}",
@@ -394,7 +607,19 @@ pub async fn create_directory(folder_path_abs: &str, folder_name: &str) -> Resul
 /// }
 /// ```
 #[tauri::command]
-pub async fn rename(old_path: &str, new_path: &str) -> Result<(), String> {
+pub async fn rename(
+    old_path: &str,
+    new_path: &str,
+    journal: State<'_, Arc<Mutex<OperationJournalState>>>,
+) -> Result<(), String> {
+    rename_impl(old_path, new_path, journal.inner().clone()).await
+}
+
+pub async fn rename_impl(
+    old_path: &str,
+    new_path: &str,
+    journal: Arc<Mutex<OperationJournalState>>,
+) -> Result<(), String> {
     let old_path_obj = Path::new(old_path);
     let new_path_obj = Path::new(new_path);
 
@@ -420,7 +645,13 @@ pub async fn rename(old_path: &str, new_path: &str) -> Result<(), String> {
 
     // Rename the file or directory
     match fs::rename(old_path, new_path) {
-        Ok(_) => Ok(()),
+        Ok(_) => {
+            record_operation(&journal, JournalOperation::Rename {
+                old_path: old_path.to_string(),
+                new_path: new_path.to_string(),
+            });
+            Ok(())
+        }
         Err(err) => {
             log_error!("Failed to rename: {}", err);
             Err(Error::new(
@@ -432,6 +663,93 @@ pub async fn rename(old_path: &str, new_path: &str) -> Result<(), String> {
     }
 }
 
+/// Moves a file or directory to a new path, also across mount points.
+/// On the same filesystem this is an atomic rename. Otherwise the source is copied,
+/// every file is verified against the source and the source is deleted afterwards.
+/// Timestamps and permissions are kept. If the copy fails, the partial copy is removed
+/// and the source is left untouched.
+///
+/// # Arguments
+/// - `source_path` - The current path of the file or directory
+/// - `destination_path` - The full new path, which must not exist yet
+///
+/// # Returns
+/// - `Ok(())` if the move was successful
+/// - `Err(String)` if there was an error during the operation
+///
+/// # Example
+/// ```rust
+/// let result = move_file_or_dir("/home/user/report.pdf", "/mnt/usb/report.pdf", state).await;
+/// match result {
+///     Ok(_) => println!("Moved successfully!"),
+///     Err(err) => println!("Error moving: {}", err),
+/// }
+/// ```
+#[tauri::command]
+pub async fn move_file_or_dir(
+    source_path: &str,
+    destination_path: &str,
+    journal: State<'_, Arc<Mutex<OperationJournalState>>>,
+) -> Result<(), String> {
+    move_file_or_dir_impl(source_path, destination_path, journal.inner().clone()).await
+}
+
+pub async fn move_file_or_dir_impl(
+    source_path: &str,
+    destination_path: &str,
+    journal: Arc<Mutex<OperationJournalState>>,
+) -> Result<(), String> {
+    let source = Path::new(source_path);
+    let destination = Path::new(destination_path);
+
+    if fs::symlink_metadata(source).is_err() {
+        log_error!("Source path does not exist: {}", source_path);
+        return Err(Error::new(
+            ErrorCode::ResourceNotFound,
+            format!("Source path does not exist: {}", source_path),
+        )
+        .to_json());
+    }
+
+    if fs::symlink_metadata(destination).is_ok() {
+        log_error!("Destination path already exists: {}", destination_path);
+        return Err(Error::new(
+            ErrorCode::ResourceAlreadyExists,
+            format!("Destination path already exists: {}", destination_path),
+        )
+        .to_json());
+    }
+
+    // Moving a directory into itself would never finish on the copy path
+    let inside_source = match (fs::canonicalize(source), destination.parent().map(fs::canonicalize)) {
+        (Ok(source), Some(Ok(parent))) => parent.starts_with(source),
+        _ => false,
+    };
+    if inside_source {
+        log_error!("Cannot move {} into itself", source_path);
+        return Err(Error::new(
+            ErrorCode::InvalidInput,
+            format!("Cannot move '{}' into itself", source_path),
+        )
+        .to_json());
+    }
+
+    match move_path(source, destination) {
+        Ok(method) => {
+            log_info!("Moved {} to {} ({:?})", source_path, destination_path, method);
+            record_operation(&journal, JournalOperation::Move {
+                old_path: source_path.to_string(),
+                new_path: destination_path.to_string(),
+            });
+            Ok(())
+        }
+        Err(err) => {
+            log_error!("Failed to move: {}", err);
+            Err(Error::new(ErrorCode::InternalError, err).to_json())
+        }
+    }
+}
+
 /// Deletes a file at the given path. Returns a string if there was an error.
 /// This function moves the file to the trash instead of deleting it permanently.
 ///
@@ -451,9 +769,24 @@ pub async fn rename(old_path: &str, new_path: &str) -> Result<(), String> {
 /// }
 /// ```
 #[tauri::command]
-pub async fn move_to_trash(path: &str) -> Result<(), String> {
+pub async fn move_to_trash(
+    path: &str,
+    journal: State<'_, Arc<Mutex<OperationJournalState>>>,
+) -> Result<(), String> {
+    move_to_trash_impl(path, journal.inner().clone()).await
+}
+
+pub async fn move_to_trash_impl(path: &str, journal: Arc<Mutex<OperationJournalState>>) -> Result<(), String> {
+    // The trash remembers the canonical path, which is needed to find the item again on undo
+    let original_path = fs::canonicalize(path).map(|path| path.to_string_lossy().to_string());
+
     match trash::delete(path) {
-        Ok(_) => Ok(()),
+        Ok(_) => {
+            if let Ok(original_path) = original_path {
+                record_operation(&journal, JournalOperation::Trash { path: original_path });
+            }
+            Ok(())
+        }
         Err(err) => {
             log_error!("Failed to move file or directory to trash: {}", err);
             Err(Error::new(
@@ -468,7 +801,7 @@ pub async fn move_to_trash(path: &str) -> Result<(), String> {
 /// Generates a unique destination path by appending a number if the path already exists.
 /// For example: "file.txt" -> "file (1).txt" -> "file (2).txt"
 /// For directories: "folder" -> "folder (1)" -> "folder (2)"
-fn generate_unique_path(original_path: &str) -> String {
+pub(crate) fn generate_unique_path(original_path: &str) -> String {
     let path = Path::new(original_path);
     
     if !path.exists() {
@@ -510,28 +843,47 @@ fn generate_unique_path(original_path: &str) -> String {
 
 /// Copies a file or directory from the source path to the destination path.
 /// This function does not create any parent directories.
-/// If the destination already exists, it will generate a unique name by appending a number.
-/// If the source is a directory, it will recursively copy all files and subdirectories.
 /// If the source is a directory, it will recursively copy all files and subdirectories.
+/// If the destination already exists, the conflict policy decides what happens:
+/// by default a unique name is generated by appending a number ("file (1).txt").
+/// With `Overwrite` and `OverwriteIfNewer` directories are merged and each conflicting
+/// file inside is decided on its own. If the copy fails, everything it created is removed.
 ///
 /// # Arguments
 /// - `source_path` - A string slice that holds the path to the source file or directory.
 /// - `destination_path` - A string slice that holds the path to the destination.
+/// - `conflict_policy` - How to handle existing destinations: skip, overwrite, overwrite_if_newer
+///   or rename (default). To ask the user per conflict, copy with `queue_copy_job` instead.
 ///
 /// # Returns
-/// - `Ok(u64)` - The total size of copied files in bytes.
+/// - `Ok(CopyOutcome)` - Where the source was copied to, the number of files and bytes copied
+///   and every conflict with the choice that was made.
 /// - `Err(String)` - If there was an error during the copy process.
 ///
 /// # Example
 /// ```rust
-/// let result = copy_file_or_dir("/path/to/source.txt", "/path/to/destination.txt").await;
+/// let result = copy_file_or_dir("/path/to/source.txt", "/path/to/destination.txt", Some(DirectConflictPolicy::Skip)).await;
 /// match result {
-///     Ok(size) => println!("File copied successfully! Size: {} bytes", size),
+///     Ok(outcome) => println!("File copied successfully! Size: {} bytes", outcome.bytes),
 ///     Err(err) => println!("Error copying file: {}", err),
 /// }
 /// ```
 #[tauri::command]
-pub async fn copy_file_or_dir(source_path: &str, destination_path: &str) -> Result<u64, String> {
+pub async fn copy_file_or_dir(
+    source_path: &str,
+    destination_path: &str,
+    conflict_policy: Option<DirectConflictPolicy>,
+    journal: State<'_, Arc<Mutex<OperationJournalState>>>,
+) -> Result<CopyOutcome, String> {
+    copy_file_or_dir_impl(source_path, destination_path, conflict_policy, journal.inner().clone()).await
+}
+
+pub async fn copy_file_or_dir_impl(
+    source_path: &str,
+    destination_path: &str,
+    conflict_policy: Option<DirectConflictPolicy>,
+    journal: Arc<Mutex<OperationJournalState>>,
+) -> Result<CopyOutcome, String> {
     // Check if the source path exists
     if !Path::new(source_path).exists() {
         log_error!("Source path does not exist: {}", source_path);
@@ -542,108 +894,149 @@ pub async fn copy_file_or_dir(source_path: &str, destination_path: &str) -> Resu
         .to_json());
     }
 
-    // Generate a unique destination path if the original already exists
-    let final_destination_path = generate_unique_path(destination_path);
-
-    if Path::new(source_path).is_dir() {
-        // If the source is a directory, recursively copy it
-        let mut total_size = 0;
+    let policy = conflict_policy.unwrap_or_default().into();
+    let outcome = copy_with_policy(Path::new(source_path), Path::new(destination_path), policy).map_err(|err| {
+        log_error!("Failed to copy {}: {}", source_path, err);
+        Error::new(
+            ErrorCode::InternalError,
+            format!("Failed to copy '{}': {}", source_path, err),
+        )
+        .to_json()
+    })?;
 
-        // Create the destination directory
-        fs::create_dir_all(&final_destination_path).map_err(|err| {
-            log_error!("Failed to create destination directory: {}", err);
-            Error::new(
-                ErrorCode::InternalError,
-                format!("Failed to create destination directory: {}", err),
-            )
-            .to_json()
-        })?;
+    // A copy that replaced existing files cannot be undone, the replaced data is gone
+    let overwrote = outcome.conflicts.iter().any(|conflict| conflict.choice == ConflictChoice::Overwrite);
+    match &outcome.written_to {
+        Some(written_to) if !overwrote => match copy_manifest(Path::new(written_to)) {
+            Ok(manifest) => record_operation(&journal, JournalOperation::Copy {
+                source_path: source_path.to_string(),
+                destination_path: written_to.clone(),
+                manifest,
+            }),
+            Err(err) => log_error!("Failed to list copy {}, it cannot be undone: {}", written_to, err),
+        },
+        Some(_) => log_info!("Copy of {} overwrote existing files and is not added to the undo history", source_path),
+        None => {}
+    }
+    Ok(outcome)
+}
 
-        // Read all entries in the source directory
-        for entry in read_dir(source_path).map_err(|err| {
-            log_error!("Failed to read source directory: {}", err);
-            Error::new(
-                ErrorCode::InternalError,
-                format!("Failed to read source directory: {}", err),
-            )
-            .to_json()
-        })? {
-            let entry = entry.map_err(|err| {
-                log_error!("Failed to read directory entry: {}", err);
-                Error::new(
-                    ErrorCode::InternalError,
-                    format!("Failed to read directory entry: {}", err),
-                )
-                .to_json()
-            })?;
-
-            let entry_path = entry.path();
-            let file_name = entry.file_name();
-            let dest_path = Path::new(&final_destination_path).join(file_name);
-
-            if entry_path.is_file() {
-                // Copy file
-                let size = fs::copy(&entry_path, &dest_path).map_err(|err| {
-                    log_error!("Failed to copy file: {}", err);
-                    Error::new(
-                        ErrorCode::InternalError,
-                        format!("Failed to copy file '{}': {}", entry_path.display(), err),
-                    )
-                    .to_json()
-                })?;
-                total_size += size;
-            } else if entry_path.is_dir() {
-                // Recursively copy subdirectory
-                let sub_size = Box::pin(copy_file_or_dir(
-                    entry_path.to_str().unwrap_or("[invalid source path]"),
-                    dest_path.to_str().unwrap_or("[invalid dest path]"),
-                ))
-                .await?;
-                total_size += sub_size;
-            }
+/// Adds a successful operation to the undo history.
+/// A failure to record is only logged, the operation itself has already succeeded.
+pub(crate) fn record_operation(journal: &Arc<Mutex<OperationJournalState>>, operation: JournalOperation) {
+    match journal.lock() {
+        Ok(mut journal) => {
+            journal.record(operation);
         }
-
-        Ok(total_size)
-    } else {
-        // Copy a single file
-        let size = fs::copy(source_path, &final_destination_path).map_err(|err| {
-            log_error!("Failed to copy file: {}", err);
-            Error::new(
-                ErrorCode::InternalError,
-                format!("Failed to copy file: {}", err),
-            )
-            .to_json()
-        })?;
-        Ok(size)
+        Err(_) => log_error!("Failed to lock operation journal, the operation cannot be undone"),
     }
 }
-/// Zips files and directories to a destination zip file.
+
+pub fn undo_last_operation_impl(journal: Arc<Mutex<OperationJournalState>>) -> Result<JournalEntry, String> {
+    let mut journal = journal.lock().map_err(|_| {
+        Error::new(ErrorCode::InternalError, "Failed to lock operation journal".to_string()).to_json()
+    })?;
+    journal.undo().map_err(|err| {
+        log_error!("Failed to undo operation: {}", err);
+        Error::new(ErrorCode::InternalError, err).to_json()
+    })
+}
+
+/// Reverts the most recent `rename`, `move_file_or_dir`, `create_file`, `create_directory`, `copy_file_or_dir`
+/// or `move_to_trash` and makes it available to `redo_operation`.
+/// The history is kept across restarts and holds the last 100 operations.
+/// An operation that cannot be reverted anymore, e.g. a created file that was written to
+/// in the meantime, is removed from the history and an error is returned.
+///
+/// # Returns
+/// - `Ok(JournalEntry)` - The operation that was undone.
+/// - `Err(String)` - If there is nothing to undo or the operation could not be reverted.
+///
+/// # Example
+/// ```rust
+/// let result = undo_last_operation(state);
+/// match result {
+///     Ok(entry) => println!("Undid {:?}", entry.operation),
+///     Err(err) => println!("Error undoing operation: {}", err),
+/// }
+/// ```
+#[tauri::command]
+pub fn undo_last_operation(journal: State<Arc<Mutex<OperationJournalState>>>) -> Result<JournalEntry, String> {
+    undo_last_operation_impl(journal.inner().clone())
+}
+
+pub fn redo_operation_impl(journal: Arc<Mutex<OperationJournalState>>) -> Result<JournalEntry, String> {
+    let mut journal = journal.lock().map_err(|_| {
+        Error::new(ErrorCode::InternalError, "Failed to lock operation journal".to_string()).to_json()
+    })?;
+    journal.redo().map_err(|err| {
+        log_error!("Failed to redo operation: {}", err);
+        Error::new(ErrorCode::InternalError, err).to_json()
+    })
+}
+
+/// Performs the most recently undone operation again.
+/// Performing any new operation clears the operations that can be redone.
+///
+/// # Returns
+/// - `Ok(JournalEntry)` - The operation that was redone.
+/// - `Err(String)` - If there is nothing to redo or the operation could not be performed.
+///
+/// # Example
+/// ```rust
+/// let result = redo_operation(state);
+/// match result {
+///     Ok(entry) => println!("Redid {:?}", entry.operation),
+///     Err(err) => println!("Error redoing operation: {}", err),
+/// }
+/// ```
+#[tauri::command]
+pub fn redo_operation(journal: State<Arc<Mutex<OperationJournalState>>>) -> Result<JournalEntry, String> {
+    redo_operation_impl(journal.inner().clone())
+}
+
+/// Packs files and directories into an archive.
+/// The format is picked from the extension of the destination: `.tar`, `.tar.gz`/`.tgz`,
+/// `.tar.bz2`/`.tbz2`, `.tar.xz`/`.txz` and `.tar.zst`/`.tzst` create tar archives,
+/// anything else a zip file.
 /// If only one source path is provided and no destination is specified, creates a zip file with the same name.
 /// For multiple source paths, the destination path must be specified.
 ///
 /// # Arguments
-/// * `source_paths` - Vector of paths to files/directories to be zipped
-/// * `destination_path` - Optional destination path for the zip file
+/// * `source_paths` - Vector of paths to files/directories to be archived
+/// * `destination_path` - Optional destination path for the archive
+/// * `options` - Optional compression method and level, password, symlink handling and
+///   include/exclude globs, see `ArchiveOptions`
 ///
 /// # Returns
-/// * `Ok(())` - If the zip file was successfully created
-/// * `Err(String)` - If there was an error during the zipping process
+/// * `Ok(())` - If the archive was successfully created
+/// * `Err(String)` - If there was an error during the archiving process
 ///
 /// # Example
 /// ```rust
 /// // Single file/directory with auto destination
-/// let result = zip(vec!["/path/to/file.txt"], None).await;
+/// let result = zip(vec!["/path/to/file.txt"], None, None).await;
 ///
 /// // Multiple files to specific destination
 /// let result = zip(
 ///     vec!["/path/to/file1.txt", "/path/to/dir1"],
-///     Some("/path/to/archive.zip")
+///     Some("/path/to/archive.tar.gz"),
+///     None
 /// ).await;
+///
+/// // Encrypted zip without log files
+/// let options = ArchiveOptions {
+///     password: Some("secret".to_string()),
+///     exclude: vec!["*.log".to_string()],
+///     ..ArchiveOptions::default()
+/// };
+/// let result = zip(vec!["/path/to/dir1"], Some("/path/to/bundle.zip"), Some(options)).await;
 /// ```
 #[tauri::command]
 pub async fn zip(
     source_paths: Vec<String>,
     destination_path: Option<String>,
+    options: Option<ArchiveOptions>,
 ) -> Result<(), String> {
     if source_paths.is_empty() {
         log_error!("No source paths provided");
@@ -668,24 +1061,20 @@ pub async fn zip(
         .to_json());
     };
 
-    // Create zip file
-    let zip_file = fs::File::create(&zip_path).map_err(|e| {
-        log_error!("Failed to create zip file: {}", e);
-        Error::new(
-            ErrorCode::InternalError,
-            format!("Failed to create zip file: {}", e),
-        )
-        .to_json()
+    let options = options.unwrap_or_default();
+    let format = ArchiveFormat::for_new_archive(&zip_path).map_err(|e| {
+        log_error!("{}", e);
+        Error::new(ErrorCode::InvalidInput, e).to_json()
+    })?;
+    let selection = options.validate(format).map_err(|e| {
+        log_error!("Invalid archive options: {}", e);
+        Error::new(ErrorCode::InvalidInput, e).to_json()
     })?;
 
-    let mut zip = ZipWriter::new(zip_file);
-    let options: FileOptions<()> = FileOptions::default()
-        .compression_method(zip::CompressionMethod::Deflated)
-        .unix_permissions(0o755);
-
-    // Process each source path
-    for source_path in source_paths {
-        let source = Path::new(&source_path);
+    // Check every source before anything is written
+    let mut sources = Vec::with_capacity(source_paths.len());
+    for source_path in &source_paths {
+        let source = Path::new(source_path);
         if !source.exists() {
             log_error!("Source path does not exist: {}", source_path);
             return Err(Error::new(
@@ -700,115 +1089,91 @@ pub async fn zip(
             .ok_or_else(|| "Invalid source name".to_string())?
             .to_str()
             .ok_or_else(|| "Invalid characters in source name".to_string())?;
-
-        if source.is_file() {
-            zip.start_file(base_name, options).map_err(|e| {
-                let err_msg = format!("Error adding file to zip: {}", e);
-                log_error!(&err_msg);
-                err_msg
-            })?;
-            let content = fs::read(source).map_err(|e| {
-                let err_msg = format!("Error reading file: {}", e);
-                log_error!(&err_msg);
-                err_msg
-            })?;
-            zip.write_all(&content).map_err(|e| {
-                let err_msg = format!("Error writing to zip: {}", e);
-                log_error!(&err_msg);
-                err_msg
-            })?;
-        } else if source.is_dir() {
-            for entry in walkdir::WalkDir::new(source) {
-                let entry = entry.map_err(|e| {
-                    let err_msg = format!("Error reading directory: {}", e);
-                    log_error!(&err_msg);
-                    err_msg
-                })?;
-                let path = entry.path();
-
-                if path.is_file() {
-                    let relative = path.strip_prefix(source).map_err(|e| {
-                        log_error!("Failed to strip prefix: {}", e);
-                        Error::new(
-                            ErrorCode::InternalError,
-                            format!("Error creating relative path: {}", e),
-                        )
-                        .to_json()
-                    })?;
-                    let name = format!(
-                        "{}/{}",
-                        base_name,
-                        relative
-                            .to_str()
-                            .ok_or_else(|| "Invalid characters in path".to_string())?
-                            .replace('\\', "/")
-                    );
-
-                    zip.start_file(&name, options).map_err(|e| {
-                        log_error!("Error adding file to zip: {}", e);
-                        Error::new(
-                            ErrorCode::InternalError,
-                            format!("Error adding file to zip: {}", e),
-                        )
-                        .to_json()
-                    })?;
-                    let content = fs::read(path).map_err(|e| {
-                        log_error!("Error reading file: {}", e);
-                        Error::new(
-                            ErrorCode::InternalError,
-                            format!("Error reading file: {}", e),
-                        )
-                        .to_json()
-                    })?;
-                    zip.write_all(&content).map_err(|e| {
-                        log_error!("Error writing to zip: {}", e);
-                        Error::new(
-                            ErrorCode::InternalError,
-                            format!("Error writing to zip: {}", e),
-                        )
-                        .to_json()
-                    })?;
-                }
-            }
-        }
+        sources.push((source, base_name));
     }
 
-    zip.finish().map_err(|e| {
-        log_error!("Error finalizing zip file: {}", e);
+    let mut writer = archive::create_writer(&zip_path, format, &options).map_err(|e| {
+        log_error!("Failed to create zip file: {}", e);
         Error::new(
             ErrorCode::InternalError,
-            format!("Error finalizing zip file: {}", e),
+            format!("Failed to create zip file: {}", e),
         )
         .to_json()
     })?;
+
+    let result = sources.into_iter().try_for_each(|(source, base_name)| {
+        let members = archive::collect_members(source, base_name, &selection)
+            .map_err(|e| format!("Error reading directory: {}", e))?;
+        for member in members {
+            archive::add_member(writer.as_mut(), &member)
+                .map_err(|e| format!("Error adding file to zip: {}", e))?;
+        }
+        Ok(())
+    });
+    let result = result.and_then(|()| {
+        writer
+            .finish()
+            .map_err(|e| format!("Error finalizing zip file: {}", e))
+    });
+
+    if let Err(err_msg) = result {
+        log_error!(&err_msg);
+        // An incomplete archive is of no use
+        if let Err(e) = fs::remove_file(&zip_path) {
+            log_error!("Failed to remove incomplete archive: {}", e);
+        }
+        return Err(Error::new(ErrorCode::InternalError, err_msg).to_json());
+    }
     Ok(())
 }
 
-/// Extracts zip files to specified destinations.
-/// If extracting a single zip file without a specified destination,
-/// extracts to a directory with the same name as the zip file.
+/// Extracts archives to specified destinations.
+/// The format of each archive is detected from its content: zip, 7z, and tar, uncompressed
+/// or compressed with gzip, bzip2, xz or zstd.
+/// If extracting a single archive without a specified destination,
+/// extracts to a directory with the same name as the archive.
+/// An archive containing just one file is extracted directly into the destination.
+///
+/// Archives with absolute paths or `..` in their entries are refused, as are archives that
+/// go over the size or entry limits of `options`. A failed archive leaves nothing behind.
 ///
 /// # Arguments
-/// * `zip_paths` - Vector of paths to zip files
+/// * `zip_paths` - Vector of paths to archives
 /// * `destination_path` - Optional destination directory for extraction
+/// * `options` - Optional password, limits and dry run, see `ExtractOptions`
 ///
 /// # Returns
-/// * `Ok(())` - If all zip files were successfully extracted
+/// * `Ok(Vec<Extraction>)` - Where each archive was extracted to. A dry run lists what
+///   would be written and keeps the archives.
 /// * `Err(String)` - If there was an error during extraction
 ///
 /// # Example
 /// ```rust
-/// // Single zip with auto destination
-/// let result = unzip(vec!["/path/to/archive.zip"], None).await;
+/// // Single archive with auto destination
+/// let result = unzip(vec!["/path/to/archive.zip"], None, None).await;
 ///
-/// // Multiple zips to specific destination
+/// // Multiple archives to specific destination
 /// let result = unzip(
-///     vec!["/path/to/zip1.zip", "/path/to/zip2.zip"],
-///     Some("/path/to/extracted")
+///     vec!["/path/to/zip1.zip", "/path/to/backup.tar.gz"],
+///     Some("/path/to/extracted"),
+///     None,
 /// ).await;
+///
+/// // See what an encrypted archive contains without extracting it
+/// let options = ExtractOptions {
+///     password: Some("secret".to_string()),
+///     dry_run: true,
+///     ..ExtractOptions::default()
+/// };
+/// let result = unzip(vec!["/path/to/secret.zip"], None, Some(options)).await;
 /// ```
 #[tauri::command]
-pub async fn unzip(zip_paths: Vec<String>, destination_path: Option<String>) -> Result<(), String> {
+pub async fn unzip(
+    zip_paths: Vec<String>,
+    destination_path: Option<String>,
+    options: Option<ExtractOptions>,
+) -> Result<Vec<Extraction>, String> {
+    let options = options.unwrap_or_default();
     if zip_paths.is_empty() {
         log_error!("No zip files provided");
         return Err(
@@ -864,6 +1229,7 @@ pub async fn unzip(zip_paths: Vec<String>, destination_path: Option<String>) ->
         .to_json());
     }
 
+    let mut extractions = Vec::with_capacity(zip_paths.len());
     for zip_path in zip_paths.clone() {
         let zip_path = Path::new(&zip_path);
         if !zip_path.exists() {
@@ -875,146 +1241,21 @@ pub async fn unzip(zip_paths: Vec<String>, destination_path: Option<String>) ->
             .to_json());
         }
 
-        // Determine extraction path for this zip
-        let zip_name = match zip_path.file_stem() {
-            Some(name) => name,
-            None => {
-                log_error!("Invalid zip filename");
-                return Err(Error::new(
-                    ErrorCode::InvalidInput,
-                    "Invalid zip filename".to_string(),
-                )
-                .to_json());
-            }
-        };
-
-        // Open and read zip file first to analyze contents
-        let file = fs::File::open(zip_path).map_err(|e| {
-            log_error!("Failed to open zip file: {}", e);
-            Error::new(
-                ErrorCode::InternalError,
-                format!("Failed to open zip file: {}", e),
-            )
-            .to_json()
-        })?;
-
-        let mut archive = zip::ZipArchive::new(file).map_err(|e| {
-            log_error!("Failed to read zip archive: {}", e);
-            Error::new(
-                ErrorCode::InternalError,
-                format!("Failed to read zip archive: {}", e),
-            )
-            .to_json()
+        let extraction = archive::extract(zip_path, dest_path, &options, &mut |_, content, outfile| {
+            std::io::copy(content, outfile)
+        })
+        .map_err(|e| {
+            log_error!("Failed to extract {}: {}", zip_path.display(), e);
+            // Unsafe, too large, corrupt or encrypted archives
+            let code = match e.kind() {
+                std::io::ErrorKind::InvalidInput | std::io::ErrorKind::InvalidData => ErrorCode::InvalidInput,
+                _ => ErrorCode::InternalError,
+            };
+            Error::new(code, format!("Failed to extract '{}': {}", zip_path.display(), e)).to_json()
         })?;
-
-        // Check if zip contains only a single file (not directory)
-        let file_entries: Vec<_> = (0..archive.len())
-            .filter_map(|i| {
-                archive.by_index(i).ok().and_then(|file| {
-                    if !file.name().ends_with('/') {
-                        Some(file.name().to_string())
-                    } else {
-                        None
-                    }
-                })
-            })
-            .collect();
-
-        let is_single_file = file_entries.len() == 1 && archive.len() == 1;
-
-        // Determine extraction path based on content
-        let extract_path = if is_single_file {
-            // For single file, extract directly to destination directory
-            dest_path.to_path_buf()
-        } else {
-            // For multiple files or directories, create subdirectory
-            let extract_path_initial = dest_path.join(zip_name);
-            let unique_extract_path_string = generate_unique_path(&extract_path_initial.to_string_lossy());
-            Path::new(&unique_extract_path_string).to_path_buf()
-        };
-
-        // Create extraction directory only if needed (not for single file to current dir)
-        if !is_single_file {
-            if let Err(e) = fs::create_dir_all(&extract_path) {
-                log_error!("Failed to create extraction directory: {}", e);
-                return Err(Error::new(
-                    ErrorCode::InternalError,
-                    format!("Failed to create extraction directory: {}", e),
-                )
-                .to_json());
-            }
-        }
-
-        for i in 0..archive.len() {
-            let mut file = archive.by_index(i).map_err(|e| {
-                log_error!("Failed to read zip entry: {}", e);
-                Error::new(
-                    ErrorCode::InternalError,
-                    format!("Failed to read zip entry: {}", e),
-                )
-                .to_json()
-            })?;
-
-            if file.name().ends_with('/') {
-                // For directories, create them if they don't exist
-                let outpath = extract_path.join(file.mangled_name());
-                fs::create_dir_all(&outpath).map_err(|e| {
-                    log_error!("Failed to create directory: {}", e);
-                    Error::new(
-                        ErrorCode::InternalError,
-                        format!("Failed to create directory '{}': {}", outpath.display(), e),
-                    )
-                    .to_json()
-                })?;
-            } else {
-                // For files, determine the output path based on extraction type
-                let outpath = if is_single_file {
-                    // For single file, extract directly to destination with original filename
-                    let original_filename = Path::new(file.name()).file_name()
-                        .unwrap_or_else(|| std::ffi::OsStr::new("extracted_file"));
-                    extract_path.join(original_filename)
-                } else {
-                    // For multiple files, use the full path structure
-                    extract_path.join(file.mangled_name())
-                };
-
-                // Generate a unique path if the file already exists
-                let unique_outpath_string = generate_unique_path(&outpath.to_string_lossy());
-                let unique_outpath = Path::new(&unique_outpath_string);
-                
-                if let Some(parent) = unique_outpath.parent() {
-                    if !parent.exists() {
-                        fs::create_dir_all(parent).map_err(|e| {
-                            log_error!("Failed to create parent directory: {}", e);
-                            Error::new(
-                                ErrorCode::InternalError,
-                                format!(
-                                    "Failed to create parent directory '{}': {}",
-                                    parent.display(),
-                                    e
-                                ),
-                            )
-                            .to_json()
-                        })?;
-                    }
-                }
-                let mut outfile = fs::File::create(&unique_outpath).map_err(|e| {
-                    log_error!("Failed to create file: {}", e);
-                    Error::new(
-                        ErrorCode::InternalError,
-                        format!("Failed to create file {}': {}", unique_outpath.display(), e),
-                    )
-                    .to_json()
-                })?;
-                std::io::copy(&mut file, &mut outfile).map_err(|e| {
-                    log_error!("Failed to write file: {}", e);
-                    Error::new(
-                        ErrorCode::InternalError,
-                        format!("Failed to write file '{}': {}", unique_outpath.display(), e),
-                    )
-                    .to_json()
-                })?;
-            }
+        extractions.push(extraction);
+        if options.dry_run {
+            continue;
         }
 
         // Remove the zip file after successful extraction
@@ -1025,13 +1266,20 @@ pub async fn unzip(zip_paths: Vec<String>, destination_path: Option<String>) ->
         }
     }
 
-    Ok(())
+    Ok(extractions)
 }
 
 #[cfg(test)]
 mod tests_file_system_operation_commands {
     use super::*;
+    use std::io::Write;
     use tempfile::tempdir;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    fn create_journal() -> Arc<Mutex<OperationJournalState>> {
+        Arc::new(Mutex::new(OperationJournalState::new()))
+    }
 
     #[tokio::test]
     async fn open_file_test() {
@@ -1193,7 +1441,7 @@ mod tests_file_system_operation_commands {
         eprintln!("Test file exists: {:?}", test_path);
 
         // Move the file to the trash
-        let result = move_to_trash(test_path.to_str().unwrap()).await;
+        let result = move_to_trash_impl(test_path.to_str().unwrap(), create_journal()).await;
 
         // Verify that the operation was successful
         assert!(result.is_ok(), "Failed to move file to trash: {:?}", result);
@@ -1227,7 +1475,7 @@ mod tests_file_system_operation_commands {
         eprintln!("Test file exists: {:?}", invalid_test_path);
 
         // Move the file to the trash
-        let result = move_to_trash(invalid_test_path.to_str().unwrap()).await;
+        let result = move_to_trash_impl(invalid_test_path.to_str().unwrap(), create_journal()).await;
 
         // Verify that the operation was successful
         assert!(
@@ -1266,7 +1514,7 @@ mod tests_file_system_operation_commands {
         let test_path = temp_dir.path().join("create_file_test.txt");
 
         // Call the function to create the file
-        let result = create_file(temp_dir.path().to_str().unwrap(), "create_file_test.txt").await;
+        let result = create_file_impl(temp_dir.path().to_str().unwrap(), "create_file_test.txt", create_journal()).await;
 
         // Verify that the operation was successful
         assert!(result.is_ok(), "Failed to create file: {:?}", result);
@@ -1285,7 +1533,7 @@ mod tests_file_system_operation_commands {
         let test_path = temp_dir.path().join("missing_dir");
 
         // Call the function to create the file
-        let result = create_file(test_path.to_str().unwrap(), "create_file_test.txt").await;
+        let result = create_file_impl(test_path.to_str().unwrap(), "create_file_test.txt", create_journal()).await;
 
         // Verify that the operation was successful
         assert!(
@@ -1324,7 +1572,7 @@ mod tests_file_system_operation_commands {
         fs::File::create(&test_path).unwrap();
 
         // Call the function to create the file
-        let result = create_file(test_path.to_str().unwrap(), "create_file_test.txt").await;
+        let result = create_file_impl(test_path.to_str().unwrap(), "create_file_test.txt", create_journal()).await;
 
         // Verify that the operation was successful
         assert!(
@@ -1361,7 +1609,7 @@ mod tests_file_system_operation_commands {
 
         // Call the function to create the directory
         let result =
-            create_directory(temp_dir.path().to_str().unwrap(), "create_directory_test").await;
+            create_directory_impl(temp_dir.path().to_str().unwrap(), "create_directory_test", create_journal()).await;
 
         // Verify that the operation was successful
         assert!(result.is_ok(), "Failed to create directory: {:?}", result);
@@ -1381,9 +1629,10 @@ mod tests_file_system_operation_commands {
         let test_path = temp_dir.path().join("missing_dir");
 
         // Call the function to create the directory
-        let result = create_directory(
+        let result = create_directory_impl(
             test_path.join("not_a_parent_directory").to_str().unwrap(),
             "create_directory_test",
+            create_journal(),
         )
         .await;
 
@@ -1450,7 +1699,7 @@ mod tests_file_system_operation_commands {
         println!("Sub File 2 created: {:?}", sub_file2_path);
 
         // Call the open_directory function
-        let result = open_directory(temp_dir.path().to_str().unwrap().to_string()).await;
+        let result = open_directory_impl(temp_dir.path().to_str().unwrap().to_string(), &FolderSizeCache::new()).await;
 
         // Verify that the operation was successful
         assert!(result.is_ok(), "Failed to open directory: {:?}", result);
@@ -1482,7 +1731,7 @@ mod tests_file_system_operation_commands {
         );
 
         // Verify subdirectory contents
-        let subdir_result = open_directory(sub_dir_path.to_str().unwrap().to_string()).await;
+        let subdir_result = open_directory_impl(sub_dir_path.to_str().unwrap().to_string(), &FolderSizeCache::new()).await;
         assert!(
             subdir_result.is_ok(),
             "Failed to open subdirectory: {:?}",
@@ -1524,7 +1773,7 @@ mod tests_file_system_operation_commands {
         test_path.push("open_directory_test.txt");
 
         // Open the file and read its contents
-        let result = open_directory(test_path.to_str().unwrap().to_string()).await;
+        let result = open_directory_impl(test_path.to_str().unwrap().to_string(), &FolderSizeCache::new()).await;
 
         // Verify that the operation was successful
         assert!(
@@ -1570,7 +1819,7 @@ mod tests_file_system_operation_commands {
         assert!(test_path.exists(), "Test file should exist before reading");
 
         // Open the file and read its contents
-        let result = open_directory(test_path.to_str().unwrap().to_string()).await;
+        let result = open_directory_impl(test_path.to_str().unwrap().to_string(), &FolderSizeCache::new()).await;
 
         // Verify that the operation was successful
         assert!(
@@ -1618,7 +1867,7 @@ mod tests_file_system_operation_commands {
         // Rename the file
         let new_name = "renamed_file.txt";
         let new_path = temp_dir.path().join(new_name);
-        let result = rename(test_path.to_str().unwrap(), new_path.to_str().unwrap()).await;
+        let result = rename_impl(test_path.to_str().unwrap(), new_path.to_str().unwrap(), create_journal()).await;
 
         // Verify that the operation was successful
         assert!(result.is_ok(), "Failed to rename file: {:?}", result);
@@ -1641,7 +1890,7 @@ mod tests_file_system_operation_commands {
         // Rename the file
         let new_name = "renamed_file.txt";
         let new_path = temp_dir.path().join(new_name);
-        let result = rename(test_path.to_str().unwrap(), new_path.to_str().unwrap()).await;
+        let result = rename_impl(test_path.to_str().unwrap(), new_path.to_str().unwrap(), create_journal()).await;
 
         // Verify that the operation was successful
         assert!(
@@ -1688,7 +1937,7 @@ mod tests_file_system_operation_commands {
         let new_path = temp_dir.path().join(new_name);
         fs::File::create(&new_path).unwrap(); // Create the new path to simulate conflict
 
-        let result = rename(test_path.to_str().unwrap(), new_path.to_str().unwrap()).await;
+        let result = rename_impl(test_path.to_str().unwrap(), new_path.to_str().unwrap(), create_journal()).await;
 
         // Verify that the operation was successful
         assert!(
@@ -1739,7 +1988,7 @@ mod tests_file_system_operation_commands {
         // Rename the directory
         let new_name = "renamed_directory";
         let new_path = temp_dir.path().join(new_name);
-        let result = rename(test_path.to_str().unwrap(), new_path.to_str().unwrap()).await;
+        let result = rename_impl(test_path.to_str().unwrap(), new_path.to_str().unwrap(), create_journal()).await;
 
         // Verify that the operation was successful
         assert!(result.is_ok(), "Failed to rename directory: {:?}", result);
@@ -1748,6 +1997,92 @@ mod tests_file_system_operation_commands {
         assert!(new_path.exists(), "Directory should exist at the new path");
     }
 
+    #[tokio::test]
+    async fn stream_directory_uses_sort_settings_test() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        fs::write(temp_dir.path().join("a.txt"), "a").unwrap();
+        fs::write(temp_dir.path().join("b.txt"), "bbb").unwrap();
+        fs::create_dir(temp_dir.path().join("folder")).unwrap();
+
+        let settings_file = tempfile::NamedTempFile::new().unwrap();
+        let settings_state = Arc::new(Mutex::new(SettingsState::new_with_path(settings_file.path().to_path_buf())));
+        {
+            let settings_state = settings_state.lock().unwrap();
+            // Values as written by the frontend
+            settings_state.update_setting_field("sort_by", serde_json::json!("Size")).unwrap();
+            settings_state.update_setting_field("sort_direction", serde_json::json!("Descending")).unwrap();
+        }
+
+        let options = listing_options(None, None, Some(2), None, settings_state.clone()).unwrap();
+        let mut chunks = Vec::new();
+        let total = stream_directory_impl(temp_dir.path().to_str().unwrap(), options, &FolderSizeCache::new(), |chunk| {
+            chunks.push(chunk);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(total, 3);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].directories[0].name, "folder");
+        assert_eq!(chunks[0].files[0].name, "b.txt");
+        assert_eq!(chunks[1].files[0].name, "a.txt");
+
+        // A closed channel stops the listing
+        let result = stream_directory_impl(temp_dir.path().to_str().unwrap(), options, &FolderSizeCache::new(), |_| Err("closed".to_string()));
+        assert!(result.unwrap_err().contains("closed"));
+
+        assert!(listing_options(None, None, Some(0), None, settings_state).is_err());
+        let missing = temp_dir.path().join("missing");
+        let result = stream_directory_impl(missing.to_str().unwrap(), options, &FolderSizeCache::new(), |_| Ok(()));
+        assert!(result.unwrap_err().contains("ResourceNotFound"));
+    }
+
+    #[tokio::test]
+    async fn move_file_or_dir_test() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let source = temp_dir.path().join("move_source");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("file.txt"), "content").unwrap();
+        let destination = temp_dir.path().join("move_destination");
+        let journal = create_journal();
+
+        move_file_or_dir_impl(source.to_str().unwrap(), destination.to_str().unwrap(), journal.clone())
+            .await
+            .unwrap();
+        assert!(!source.exists());
+        assert_eq!(fs::read_to_string(destination.join("file.txt")).unwrap(), "content");
+
+        // The move can be undone
+        undo_last_operation_impl(journal.clone()).unwrap();
+        assert!(source.join("file.txt").exists());
+        assert!(!destination.exists());
+
+        // A directory cannot be moved into itself
+        let result = move_file_or_dir_impl(
+            source.to_str().unwrap(),
+            source.join("inner").to_str().unwrap(),
+            journal.clone(),
+        )
+        .await;
+        assert!(result.unwrap_err().contains("InvalidInput"));
+    }
+
+    #[tokio::test]
+    async fn failed_to_move_because_destination_already_exists_test() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let source = temp_dir.path().join("source.txt");
+        let destination = temp_dir.path().join("destination.txt");
+        fs::write(&source, "source").unwrap();
+        fs::write(&destination, "destination").unwrap();
+
+        let result =
+            move_file_or_dir_impl(source.to_str().unwrap(), destination.to_str().unwrap(), create_journal()).await;
+
+        assert!(result.unwrap_err().contains("ResourceAlreadyExists"));
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "destination");
+        assert!(source.exists());
+    }
+
     #[tokio::test]
     async fn copy_file_test() {
         use tempfile::tempdir;
@@ -1769,7 +2104,7 @@ mod tests_file_system_operation_commands {
         let new_name = "copied_file.txt";
         let new_path = temp_dir.path().join(new_name);
         let result =
-            copy_file_or_dir(test_path.to_str().unwrap(), new_path.to_str().unwrap()).await;
+            copy_file_or_dir_impl(test_path.to_str().unwrap(), new_path.to_str().unwrap(), None, create_journal()).await;
 
         // Verify that the operation was successful
         assert!(result.is_ok(), "Failed to copy file: {:?}", result);
@@ -1834,9 +2169,11 @@ mod tests_file_system_operation_commands {
         // Copy the directory
         let copied_dir_name = "copied_directory";
         let copied_dir_path = temp_dir.path().join(copied_dir_name);
-        let result = copy_file_or_dir(
+        let result = copy_file_or_dir_impl(
             test_path.to_str().unwrap(),
             copied_dir_path.to_str().unwrap(),
+            None,
+            create_journal(),
         )
         .await;
 
@@ -1883,6 +2220,89 @@ mod tests_file_system_operation_commands {
         );
     }
 
+    #[tokio::test]
+    async fn copy_with_conflict_policies_test() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let source = temp_dir.path().join("source");
+        let destination = temp_dir.path().join("destination");
+        fs::create_dir_all(source.join("sub")).unwrap();
+        fs::create_dir_all(destination.join("sub")).unwrap();
+        fs::write(source.join("sub/shared.txt"), "new").unwrap();
+        fs::write(source.join("sub/only_source.txt"), "source").unwrap();
+        fs::write(destination.join("sub/shared.txt"), "old").unwrap();
+        let (source_str, destination_str) = (source.to_str().unwrap(), destination.to_str().unwrap());
+
+        // Skip leaves the existing directory untouched
+        let outcome = copy_file_or_dir_impl(source_str, destination_str, Some(DirectConflictPolicy::Skip), create_journal()).await.unwrap();
+        assert_eq!(outcome.written_to, None);
+        assert_eq!(outcome.conflicts.len(), 1);
+        assert!(!destination.join("sub/only_source.txt").exists());
+
+        // Rename (the default) copies next to it
+        let outcome = copy_file_or_dir_impl(source_str, destination_str, None, create_journal()).await.unwrap();
+        assert!(outcome.written_to.unwrap().ends_with("destination (1)"));
+        assert!(temp_dir.path().join("destination (1)/sub/shared.txt").exists());
+
+        // Overwrite merges the directories and replaces conflicting files
+        let outcome = copy_file_or_dir_impl(source_str, destination_str, Some(DirectConflictPolicy::Overwrite), create_journal()).await.unwrap();
+        assert_eq!(outcome.files, 2);
+        assert_eq!(outcome.conflicts.len(), 3, "destination, sub and shared.txt conflict");
+        assert_eq!(fs::read_to_string(destination.join("sub/shared.txt")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(destination.join("sub/only_source.txt")).unwrap(), "source");
+    }
+
+    #[tokio::test]
+    async fn undo_and_redo_operations_test() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let folder = temp_dir.path().to_str().unwrap();
+        let journal = create_journal();
+
+        create_directory_impl(folder, "photos", journal.clone()).await.unwrap();
+        let photos = temp_dir.path().join("photos");
+        let renamed = temp_dir.path().join("pictures");
+        rename_impl(photos.to_str().unwrap(), renamed.to_str().unwrap(), journal.clone())
+            .await
+            .unwrap();
+
+        // Undo the rename, then the directory creation
+        let entry = undo_last_operation_impl(journal.clone()).unwrap();
+        assert!(matches!(entry.operation, JournalOperation::Rename { .. }));
+        assert!(photos.exists() && !renamed.exists());
+        undo_last_operation_impl(journal.clone()).unwrap();
+        assert!(!photos.exists());
+        assert!(undo_last_operation_impl(journal.clone()).is_err(), "Nothing should be left to undo");
+
+        // Redo both in the original order
+        redo_operation_impl(journal.clone()).unwrap();
+        redo_operation_impl(journal.clone()).unwrap();
+        assert!(!photos.exists() && renamed.exists());
+        assert!(redo_operation_impl(journal).is_err(), "Nothing should be left to redo");
+    }
+
+    #[tokio::test]
+    async fn undo_copy_and_move_to_trash_test() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let source = temp_dir.path().join("undo_trash_test.txt");
+        fs::write(&source, "content").unwrap();
+        let copy = temp_dir.path().join("undo_trash_test (1).txt");
+        let journal = create_journal();
+
+        let outcome = copy_file_or_dir_impl(source.to_str().unwrap(), source.to_str().unwrap(), None, journal.clone())
+            .await
+            .unwrap();
+        assert_eq!(outcome.written_to.as_deref(), copy.to_str());
+        move_to_trash_impl(source.to_str().unwrap(), journal.clone()).await.unwrap();
+        assert!(!source.exists());
+
+        // The trashed file is restored to its original location
+        undo_last_operation_impl(journal.clone()).unwrap();
+        assert_eq!(fs::read_to_string(&source).unwrap(), "content");
+
+        undo_last_operation_impl(journal.clone()).unwrap();
+        assert!(!copy.exists());
+        assert!(source.exists(), "Undoing the copy must not touch the source");
+    }
+
     #[tokio::test]
     async fn failed_to_copy_file_or_dir_because_source_path_does_not_exist_test() {
         use tempfile::tempdir;
@@ -1899,7 +2319,7 @@ mod tests_file_system_operation_commands {
         let new_path = temp_dir.path().join(new_name);
 
         let result =
-            copy_file_or_dir(test_path.to_str().unwrap(), new_path.to_str().unwrap()).await;
+            copy_file_or_dir_impl(test_path.to_str().unwrap(), new_path.to_str().unwrap(), None, create_journal()).await;
 
         // Verify that the operation was successful
         assert!(
@@ -1940,7 +2360,7 @@ mod tests_file_system_operation_commands {
         );
 
         // Zip the file
-        let result = zip(vec![test_file_path.to_str().unwrap().to_string()], None).await;
+        let result = zip(vec![test_file_path.to_str().unwrap().to_string()], None, None).await;
         assert!(result.is_ok(), "Failed to zip file: {:?}", result);
 
         // Check if zip file was created
@@ -1956,9 +2376,120 @@ mod tests_file_system_operation_commands {
         assert_eq!(file.name(), "test_file.txt", "Incorrect filename in zip");
     }
 
+    #[tokio::test]
+    async fn zip_and_unzip_tar_gz_test() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let source_dir = temp_dir.path().join("docs");
+        fs::create_dir_all(source_dir.join("nested")).expect("Failed to create directories");
+        fs::write(source_dir.join("a.txt"), "A").expect("Failed to write test file");
+        fs::write(source_dir.join("nested/b.txt"), "B").expect("Failed to write test file");
+
+        // The extension decides the format
+        let archive_path = temp_dir.path().join("docs.tar.gz");
+        let result = zip(
+            vec![source_dir.to_str().unwrap().to_string()],
+            Some(archive_path.to_str().unwrap().to_string()),
+            None,
+        )
+        .await;
+        assert!(result.is_ok(), "Failed to create archive: {:?}", result);
+        assert_eq!(ArchiveFormat::detect(&archive_path).unwrap(), Some(ArchiveFormat::TarGz));
+
+        let extract_dir = temp_dir.path().join("out");
+        fs::create_dir(&extract_dir).expect("Failed to create extraction directory");
+        let result = unzip(
+            vec![archive_path.to_str().unwrap().to_string()],
+            Some(extract_dir.to_str().unwrap().to_string()),
+            None,
+        )
+        .await;
+        assert!(result.is_ok(), "Failed to extract archive: {:?}", result);
+        assert_eq!(fs::read_to_string(extract_dir.join("docs/docs/nested/b.txt")).unwrap(), "B");
+        assert!(!archive_path.exists(), "Archive should be removed after extraction");
+
+        // 7z archives can only be read
+        let result = zip(
+            vec![source_dir.to_str().unwrap().to_string()],
+            Some(temp_dir.path().join("docs.7z").to_str().unwrap().to_string()),
+            None,
+        )
+        .await;
+        assert!(result.unwrap_err().contains("can only be extracted"));
+    }
+
+    #[tokio::test]
+    async fn unzip_refuses_malicious_archives_test() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let corpus = temp_dir.path().join("corpus");
+        fs::create_dir(&corpus).expect("Failed to create corpus directory");
+        let extract_dir = temp_dir.path().join("out");
+        fs::create_dir(&extract_dir).expect("Failed to create extraction directory");
+
+        for archive_path in archive::test_corpus::path_traversal(&corpus) {
+            let result = unzip(
+                vec![archive_path.to_str().unwrap().to_string()],
+                Some(extract_dir.to_str().unwrap().to_string()),
+                None,
+            )
+            .await;
+            let err = result.unwrap_err();
+            assert!(err.contains("Unsafe path in archive"), "{}", err);
+            assert!(err.contains("\"code\":408"), "{}", err);
+            assert!(archive_path.exists(), "A refused archive should be kept");
+        }
+        assert_eq!(fs::read_dir(&extract_dir).unwrap().count(), 0);
+
+        let bomb = archive::test_corpus::zip_bomb(temp_dir.path().join("bomb.zip"), 4 * 1024 * 1024);
+        let options = ExtractOptions { max_total_size: Some(1024), ..ExtractOptions::default() };
+        let result = unzip(
+            vec![bomb.to_str().unwrap().to_string()],
+            Some(extract_dir.to_str().unwrap().to_string()),
+            Some(options),
+        )
+        .await;
+        assert!(result.unwrap_err().contains("expands to more than 1024 bytes"));
+        assert_eq!(fs::read_dir(&extract_dir).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn unzip_dry_run_test() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let zip_path = archive::test_corpus::zip_with(
+            temp_dir.path().join("docs.zip"),
+            &[("docs/a.txt", b"A"), ("docs/b.txt", b"BB")],
+        );
+
+        let options = ExtractOptions { dry_run: true, ..ExtractOptions::default() };
+        let result = unzip(vec![zip_path.to_str().unwrap().to_string()], None, Some(options)).await;
+        let extractions = result.expect("Dry run should succeed");
+        assert_eq!(extractions.len(), 1);
+        assert_eq!(extractions[0].path, temp_dir.path().join("docs"));
+        assert_eq!((extractions[0].files, extractions[0].bytes), (2, 3));
+        assert_eq!(extractions[0].planned[1].path, temp_dir.path().join("docs/docs/b.txt"));
+        assert!(zip_path.exists(), "A dry run should keep the archive");
+        assert!(!temp_dir.path().join("docs").exists(), "A dry run should not write anything");
+    }
+
+    #[tokio::test]
+    async fn failed_to_zip_because_of_invalid_options_test() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let source_path = temp_dir.path().join("notes.txt");
+        fs::write(&source_path, "notes").expect("Failed to write test file");
+        let archive_path = temp_dir.path().join("notes.tar.gz");
+
+        let result = zip(
+            vec![source_path.to_str().unwrap().to_string()],
+            Some(archive_path.to_str().unwrap().to_string()),
+            Some(ArchiveOptions { password: Some("secret".to_string()), ..ArchiveOptions::default() }),
+        )
+        .await;
+        assert!(result.unwrap_err().contains("Only zip archives can be encrypted"));
+        assert!(!archive_path.exists(), "No archive should be created for invalid options");
+    }
+
     #[tokio::test]
     async fn failed_to_zip_because_no_source_paths_provided_test() {
-        let result = zip(vec![], None).await;
+        let result = zip(vec![], None, None).await;
 
         assert!(
             result.is_err(),
@@ -2002,6 +2533,7 @@ mod tests_file_system_operation_commands {
                 file2_path.to_str().unwrap().to_string(),
             ],
             None,
+            None,
         )
         .await;
 
@@ -2054,6 +2586,7 @@ mod tests_file_system_operation_commands {
                 non_existing_file_path.to_str().unwrap().to_string(),
             ],
             result_zip,
+            None,
         )
         .await;
 
@@ -2096,7 +2629,7 @@ mod tests_file_system_operation_commands {
         zip.finish().unwrap();
 
         // Test extraction without specifying destination
-        let result = unzip(vec![zip_path.to_str().unwrap().to_string()], None).await;
+        let result = unzip(vec![zip_path.to_str().unwrap().to_string()], None, None).await;
 
         assert!(result.is_ok(), "Failed to extract zip: {:?}", result);
 
@@ -2114,7 +2647,7 @@ mod tests_file_system_operation_commands {
 
     #[tokio::test]
     async fn failed_to_unzip_because_no_zip_files_provided_test() {
-        let result = unzip(vec![], None).await;
+        let result = unzip(vec![], None, None).await;
 
         assert!(
             result.is_err(),
@@ -2149,7 +2682,7 @@ mod tests_file_system_operation_commands {
         let zip_path = temp_dir.path().join("non_existing.zip");
 
         // Test extraction of a non-existing zip file
-        let result = unzip(vec![zip_path.to_str().unwrap().to_string()], None).await;
+        let result = unzip(vec![zip_path.to_str().unwrap().to_string()], None, None).await;
 
         assert!(
             result.is_err(),
@@ -2205,6 +2738,7 @@ mod tests_file_system_operation_commands {
                 zip2_path.to_str().unwrap().to_string(),
             ],
             None,
+            None,
         )
         .await;
 
@@ -2261,6 +2795,7 @@ mod tests_file_system_operation_commands {
                 zip2_path.to_str().unwrap().to_string(),
             ], // needs to be more than one path
             Some(invalid_dest.to_str().unwrap().to_string()),
+            None,
         )
         .await;
 
@@ -0,0 +1,126 @@
+use crate::error_handling::{Error, ErrorCode};
+use crate::state::folder_size_data::{FolderSizeSink, FolderSizeState};
+use crate::{log_error, log_warn};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, State};
+
+/// Event sent with a `FolderSizeUpdate` payload while and after a folder is measured
+pub const FOLDER_SIZE_UPDATED_EVENT: &str = "folder-size-updated";
+
+/// Forwards size updates to the frontend as Tauri events.
+fn app_event_sink(app: AppHandle) -> FolderSizeSink {
+    Arc::new(move |update| {
+        if let Err(err) = app.emit(FOLDER_SIZE_UPDATED_EVENT, update) {
+            log_warn!("Failed to emit folder size update: {}", err);
+        }
+    })
+}
+
+pub fn calculate_folder_sizes_impl(
+    paths: Vec<String>,
+    sink: FolderSizeSink,
+    state: Arc<Mutex<FolderSizeState>>,
+) -> Result<Vec<String>, String> {
+    if paths.is_empty() {
+        log_error!("No paths provided");
+        return Err(Error::new(ErrorCode::InvalidInput, "No paths provided".to_string()).to_json());
+    }
+
+    let folder_sizes = state.lock().map_err(|_| "Failed to lock folder size state")?;
+    folder_sizes
+        .calculate(paths, sink)
+        .map_err(|err| Error::new(ErrorCode::InternalError, err).to_json())
+}
+
+/// Calculates the recursive sizes of folders in the background.
+/// Every folder sends `folder-size-updated` events with its running totals while it is
+/// measured and a final event with the status `Completed`, `Cancelled` or `Failed`.
+/// Directory listings are cached with their modification time, so measuring a folder
+/// again only reads the directories that changed in the meantime.
+///
+/// # Arguments
+/// * `paths` - The folders to measure, one after another
+///
+/// # Returns
+/// * `Ok(Vec<String>)` - The paths that were started. Paths that are already being measured are left out, their events are sent anyway
+/// * `Err(String)` - If no paths were given
+///
+/// # Example
+/// ```rust
+/// let started = calculate_folder_sizes(app, vec!["/home/user/photos".into()], state)?;
+/// ```
+#[tauri::command]
+pub fn calculate_folder_sizes(
+    app: AppHandle,
+    paths: Vec<String>,
+    state: State<Arc<Mutex<FolderSizeState>>>,
+) -> Result<Vec<String>, String> {
+    calculate_folder_sizes_impl(paths, app_event_sink(app), state.inner().clone())
+}
+
+pub fn cancel_folder_sizes_impl(
+    paths: Option<Vec<String>>,
+    state: Arc<Mutex<FolderSizeState>>,
+) -> Result<usize, String> {
+    let folder_sizes = state.lock().map_err(|_| "Failed to lock folder size state")?;
+    folder_sizes
+        .cancel(paths.as_deref())
+        .map_err(|err| Error::new(ErrorCode::InternalError, err).to_json())
+}
+
+/// Cancels folder size calculations, e.g. when the user leaves the directory.
+/// Each cancelled folder sends a final `folder-size-updated` event with the status `Cancelled`.
+///
+/// # Arguments
+/// * `paths` - The folders to stop measuring, every folder if not given
+///
+/// # Returns
+/// * `Ok(usize)` - The number of cancelled calculations
+/// * `Err(String)` - If the state could not be locked
+#[tauri::command]
+pub fn cancel_folder_sizes(
+    paths: Option<Vec<String>>,
+    state: State<Arc<Mutex<FolderSizeState>>>,
+) -> Result<usize, String> {
+    cancel_folder_sizes_impl(paths, state.inner().clone())
+}
+
+#[cfg(test)]
+mod tests_folder_size_commands {
+    use super::*;
+    use crate::state::folder_size_data::{FolderSizeStatus, FolderSizeUpdate};
+    use std::fs;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_calculate_and_cancel_folder_sizes() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("file.txt"), "hello").unwrap();
+        let path = temp_dir.path().to_string_lossy().to_string();
+        let state = Arc::new(Mutex::new(FolderSizeState::new()));
+
+        assert!(calculate_folder_sizes_impl(vec![], Arc::new(|_| {}), state.clone()).is_err());
+
+        let (sender, receiver) = mpsc::channel::<FolderSizeUpdate>();
+        let sender = Mutex::new(sender);
+        let sink: FolderSizeSink = Arc::new(move |update| {
+            let _ = sender.lock().unwrap().send(update);
+        });
+        let started = calculate_folder_sizes_impl(vec![path.clone()], sink, state.clone()).unwrap();
+        assert_eq!(started, vec![path.clone()]);
+
+        let update = loop {
+            let update = receiver.recv_timeout(Duration::from_secs(20)).unwrap();
+            if update.status != FolderSizeStatus::Calculating {
+                break update;
+            }
+        };
+        assert_eq!(update.status, FolderSizeStatus::Completed);
+        assert_eq!(update.size.size_in_bytes, 5);
+
+        // Nothing is left running once the folder is done
+        assert_eq!(cancel_folder_sizes_impl(None, state).unwrap(), 0);
+    }
+}
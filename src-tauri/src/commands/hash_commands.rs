@@ -1,15 +1,21 @@
 use std::fmt::Display;
+use crate::filesystem::checksum_file::{self, ChecksumFileVerification};
+use crate::filesystem::hashing::{hash_file, StreamingHasher};
+use crate::filesystem::manifest::{self, DirectoryManifest, ManifestVerification};
+use crate::error_handling::{Error, ErrorCode};
+use crate::state::hashing_data::HashingState;
+use crate::{log_error, log_warn};
 use crate::state::SettingsState;
-use crc32fast::Hasher;
-use md5::{Digest as Md5Digest, Md5 as Md5Hasher};
 use serde::{Deserialize, Serialize};
-use sha2::{Digest as Sha2Digest, Sha256, Sha384, Sha512};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
 use tauri::State;
-use tokio::fs::File;
-use tokio::io::AsyncReadExt;
+
+/// Minimum time between two progress updates of `gen_hashes`
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub enum HashError {
@@ -17,15 +23,20 @@ pub enum HashError {
     InvalidChecksumMethod,
     FileOperationError,
     ClipboardError,
+    Cancelled,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub enum ChecksumMethod {
     MD5,
+    SHA1,
     SHA256,
     SHA384,
     SHA512,
     CRC32,
+    BLAKE3,
+    /// The 64 bit variant of XXH3
+    XXH3,
 }
 
 impl FromStr for ChecksumMethod {
@@ -34,15 +45,51 @@ impl FromStr for ChecksumMethod {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_uppercase().as_str() {
             "MD5" => Ok(ChecksumMethod::MD5),
+            "SHA1" => Ok(ChecksumMethod::SHA1),
             "SHA256" => Ok(ChecksumMethod::SHA256),
             "SHA384" => Ok(ChecksumMethod::SHA384),
             "SHA512" => Ok(ChecksumMethod::SHA512),
             "CRC32" => Ok(ChecksumMethod::CRC32),
+            "BLAKE3" => Ok(ChecksumMethod::BLAKE3),
+            "XXH3" => Ok(ChecksumMethod::XXH3),
             _ => Err(HashError::InvalidChecksumMethod),
         }
     }
 }
 
+impl Display for ChecksumMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            ChecksumMethod::MD5 => "MD5",
+            ChecksumMethod::SHA1 => "SHA1",
+            ChecksumMethod::SHA256 => "SHA256",
+            ChecksumMethod::SHA384 => "SHA384",
+            ChecksumMethod::SHA512 => "SHA512",
+            ChecksumMethod::CRC32 => "CRC32",
+            ChecksumMethod::BLAKE3 => "BLAKE3",
+            ChecksumMethod::XXH3 => "XXH3",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl ChecksumMethod {
+    /// Guesses the method from the length of a hex encoded hash.
+    /// BLAKE3 hashes have the same length as SHA256 ones and are never guessed.
+    pub fn from_hash_length(length: usize) -> Option<Self> {
+        match length {
+            8 => Some(ChecksumMethod::CRC32),
+            16 => Some(ChecksumMethod::XXH3),
+            32 => Some(ChecksumMethod::MD5),
+            40 => Some(ChecksumMethod::SHA1),
+            64 => Some(ChecksumMethod::SHA256),
+            96 => Some(ChecksumMethod::SHA384),
+            128 => Some(ChecksumMethod::SHA512),
+            _ => None,
+        }
+    }
+}
+
 impl Display for HashError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str = match self {
@@ -50,12 +97,13 @@ impl Display for HashError {
             HashError::InvalidChecksumMethod => "Invalid checksum method".to_string(),
             HashError::FileOperationError => "File operation failed".to_string(),
             HashError::ClipboardError => "Failed to copy to clipboard".to_string(),
+            HashError::Cancelled => "Hash calculation was cancelled".to_string(),
         };
         write!(f, "{}", str)
     }
 }
 
-async fn get_checksum_method(
+pub(crate) async fn get_checksum_method(
     state: Arc<Mutex<SettingsState>>,
 ) -> Result<ChecksumMethod, HashError> {
     let settings_state = state.lock().map_err(|_| HashError::SettingsLockError)?;
@@ -66,65 +114,31 @@ async fn get_checksum_method(
     Ok(inner_settings.backend_settings.default_checksum_hash.clone())
 }
 
-fn calculate_md5(data: &[u8]) -> String {
-    let mut hasher = Md5Hasher::new();
-    hasher.update(data);
-    let result = hasher.finalize();
-    hex::encode(result)
-}
-
-fn calculate_sha256(data: &[u8]) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(data);
-    format!("{:x}", hasher.finalize())
-}
-
-fn calculate_sha384(data: &[u8]) -> String {
-    let mut hasher = Sha384::new();
-    hasher.update(data);
-    format!("{:x}", hasher.finalize())
-}
-
-fn calculate_sha512(data: &[u8]) -> String {
-    let mut hasher = Sha512::new();
+/// Hashes `data` with the given method and returns the hex encoded hash.
+pub(crate) fn checksum(method: &ChecksumMethod, data: &[u8]) -> String {
+    let mut hasher = StreamingHasher::new(method);
     hasher.update(data);
-    format!("{:x}", hasher.finalize())
-}
-
-fn calculate_crc32(data: &[u8]) -> String {
-    let mut hasher = Hasher::new();
-    hasher.update(data);
-    let checksum = hasher.finalize();
-    format!("{:08x}", checksum)
-}
-
-async fn calculate_hash(method: ChecksumMethod, data: &[u8]) -> Result<String, HashError> {
-    let result = match method {
-        ChecksumMethod::MD5 => calculate_md5(data),
-        ChecksumMethod::SHA256 => calculate_sha256(data),
-        ChecksumMethod::SHA384 => calculate_sha384(data),
-        ChecksumMethod::SHA512 => calculate_sha512(data),
-        ChecksumMethod::CRC32 => calculate_crc32(data),
-    };
-    Ok(result)
+    hasher.finalize()
 }
 
-async fn read_file(path: &Path) -> Result<Vec<u8>, HashError> {
-    if !path.exists() && path.is_dir() {
-        return Err(HashError::FileOperationError);
-    }
-    let mut file = File::open(path)
-        .await
-        .map_err(|_| HashError::FileOperationError)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)
-        .await
-        .map_err(|_| HashError::FileOperationError)?;
-    Ok(buffer)
+/// Hashes a file without loading it into memory. Directories are hashed by their manifest,
+/// see `gen_directory_manifest`.
+async fn hash_path(method: ChecksumMethod, path: &Path) -> Result<String, HashError> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        if path.is_dir() {
+            manifest::hash_directory(&path, method, &[], None).map(|manifest| manifest.root_hash)
+        } else {
+            hash_file(&path, &[method], None, |_| {}).map(|mut hashes| hashes.remove(0))
+        }
+    })
+    .await
+    .map_err(|_| HashError::FileOperationError)?
 }
 
 /// Generates a hash for the given file and returns it as a string.
-/// The hash algorithm used is determined by the application settings (MD5, SHA256, SHA384, SHA512, or CRC32).
+/// The hash algorithm used is determined by the application settings (MD5, SHA1, SHA256, SHA384, SHA512, CRC32, BLAKE3 or XXH3).
+/// For a directory the root hash of its manifest is returned, see `gen_directory_manifest`.
 ///
 /// # Arguments
 /// * `path` - A string representing the absolute path to the file to generate a hash for.
@@ -157,10 +171,7 @@ pub async fn gen_hash_and_return_string_impl(
     let checksum_method = get_checksum_method(state)
         .await
         .map_err(|e| e.to_string())?;
-    let data = read_file(Path::new(&path))
-        .await
-        .map_err(|e| e.to_string())?;
-    let hash = calculate_hash(checksum_method, &data)
+    let hash = hash_path(checksum_method, Path::new(&path))
         .await
         .map_err(|e| e.to_string())?;
 
@@ -204,10 +215,7 @@ pub async fn gen_hash_and_save_to_file_impl(
     let checksum_method = get_checksum_method(state)
         .await
         .map_err(|e| e.to_string())?;
-    let data = read_file(Path::new(&source_path))
-        .await
-        .map_err(|e| e.to_string())?;
-    let hash = calculate_hash(checksum_method, &data)
+    let hash = hash_path(checksum_method, Path::new(&source_path))
         .await
         .map_err(|e| e.to_string())?;
 
@@ -220,6 +228,7 @@ pub async fn gen_hash_and_save_to_file_impl(
 
 /// Compares a file's generated hash with a provided hash value.
 /// The hash algorithm used is determined by the application settings.
+/// A directory is compared by the root hash of its manifest, see `gen_directory_manifest`.
 ///
 /// # Arguments
 /// * `path` - A string representing the absolute path to the file to check.
@@ -255,14 +264,315 @@ pub async fn compare_file_or_dir_with_hash_impl(
     let checksum_method = get_checksum_method(state)
         .await
         .map_err(|e| e.to_string())?;
-    let data = read_file(Path::new(&path))
+    let calculated_hash = hash_path(checksum_method, Path::new(&path))
         .await
         .map_err(|e| e.to_string())?;
-    let calculated_hash = calculate_hash(checksum_method, &data)
+
+    Ok(calculated_hash.eq_ignore_ascii_case(&hash_to_compare))
+}
+
+/// Checks that a path is an existing directory and returns it canonicalized.
+fn existing_directory(path: &str) -> Result<PathBuf, String> {
+    let canonical = Path::new(path).canonicalize().map_err(|err| {
+        log_error!("Cannot access {}: {}", path, err);
+        Error::new(ErrorCode::ResourceNotFound, format!("Cannot access {}: {}", path, err)).to_json()
+    })?;
+    if !canonical.is_dir() {
+        log_error!("Path is not a directory: {}", path);
+        return Err(Error::new(ErrorCode::InvalidInput, format!("Path is not a directory: {}", path)).to_json());
+    }
+    Ok(canonical)
+}
+
+/// Hashes every file in a directory tree and returns the manifest of the directory.
+/// The manifest lists the files sorted by their relative path, with `/` as separator, and
+/// has a root hash that is the hash of the manifest text. Symlinks are not followed.
+/// If `output_path` is given, the manifest is written there in the format of `sha256sum`,
+/// so it can also be checked with `sha256sum -c`. A manifest written into the hashed
+/// directory does not list itself.
+///
+/// # Arguments
+/// * `path` - A string representing the absolute path to the directory to hash.
+/// * `output_path` - Where to write the manifest, it is only returned if not given.
+/// * `checksum_method` - The hash algorithm. Defaults to the algorithm from the settings.
+/// * `state` - The application's settings state containing the default hash algorithm.
+///
+/// # Returns
+/// * `Ok(DirectoryManifest)` - The method, the sorted entries and the root hash.
+/// * `Err(String)` - An error message if the directory cannot be hashed or the manifest cannot be written.
+///
+/// # Example
+/// ```rust
+/// let manifest = gen_directory_manifest("/path/to/release".into(), Some("/path/to/release/SHA256SUMS".into()), None, state).await?;
+/// println!("Root hash: {}", manifest.root_hash);
+/// ```
+#[tauri::command]
+pub async fn gen_directory_manifest(
+    path: String,
+    output_path: Option<String>,
+    checksum_method: Option<ChecksumMethod>,
+    state: State<'_, Arc<Mutex<SettingsState>>>,
+) -> Result<DirectoryManifest, String> {
+    gen_directory_manifest_impl(path, output_path, checksum_method, state.inner().clone()).await
+}
+
+pub async fn gen_directory_manifest_impl(
+    path: String,
+    output_path: Option<String>,
+    checksum_method: Option<ChecksumMethod>,
+    state: Arc<Mutex<SettingsState>>,
+) -> Result<DirectoryManifest, String> {
+    let root = existing_directory(&path)?;
+    let method = match checksum_method {
+        Some(method) => method,
+        None => get_checksum_method(state).await.map_err(|e| e.to_string())?,
+    };
+    let excluded = output_path.iter().map(|output| absolute_path(output)).collect::<Vec<_>>();
+
+    let manifest = tokio::task::spawn_blocking(move || manifest::hash_directory(&root, method, &excluded, None))
         .await
+        .map_err(|_| HashError::FileOperationError.to_string())?
         .map_err(|e| e.to_string())?;
 
-    Ok(calculated_hash.eq_ignore_ascii_case(&hash_to_compare))
+    if let Some(output_path) = output_path {
+        tokio::fs::write(&output_path, manifest.to_checksum_list())
+            .await
+            .map_err(|_| "Failed to write manifest to file".to_string())?;
+    }
+    Ok(manifest)
+}
+
+/// Checks a directory against a manifest in `sha256sum` format, e.g. one written by
+/// `gen_directory_manifest`. The paths in the manifest are relative to the directory.
+///
+/// # Arguments
+/// * `path` - A string representing the absolute path to the directory to check.
+/// * `manifest_path` - The manifest file. If it is inside the directory it is not reported as extra.
+/// * `checksum_method` - The hash algorithm of the manifest. Inferred from the manifest file name
+///   (`B3SUMS`, `*.sha1`, ...) or else from the hash length if not given.
+///
+/// # Returns
+/// * `Ok(ManifestVerification)` - The matched, changed, missing, extra and unreadable files.
+/// * `Err(String)` - An error message if the directory or the manifest cannot be read.
+///
+/// # Example
+/// ```rust
+/// let result = verify_directory_manifest("/path/to/release".into(), "/path/to/release/SHA256SUMS".into(), None).await?;
+/// println!("{} files changed", result.changed.len());
+/// ```
+#[tauri::command]
+pub async fn verify_directory_manifest(
+    path: String,
+    manifest_path: String,
+    checksum_method: Option<ChecksumMethod>,
+) -> Result<ManifestVerification, String> {
+    let root = existing_directory(&path)?;
+    let text = tokio::fs::read_to_string(&manifest_path).await.map_err(|err| {
+        log_error!("Failed to read manifest {}: {}", manifest_path, err);
+        Error::new(ErrorCode::ResourceNotFound, format!("Failed to read manifest {}: {}", manifest_path, err)).to_json()
+    })?;
+    let entries = manifest::parse_checksum_list(&text)
+        .map_err(|err| Error::new(ErrorCode::InvalidInput, err).to_json())?;
+    let file_name = Path::new(&manifest_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let method = checksum_method
+        .or_else(|| checksum_file::method_from_file_name(&file_name))
+        .or_else(|| entries.first().and_then(|entry| ChecksumMethod::from_hash_length(entry.hash.len())))
+        .unwrap_or(ChecksumMethod::SHA256);
+    let excluded = vec![absolute_path(&manifest_path)];
+
+    tokio::task::spawn_blocking(move || manifest::verify_directory(&root, &entries, &method, &excluded))
+        .await
+        .map_err(|_| HashError::FileOperationError.to_string())
+}
+
+/// Verifies the files listed in a checksum file, like `sha256sum -c`.
+/// GNU coreutils lists (`SHA256SUMS`), BSD-style lists (`SHA256 (file) = hash`) and files
+/// that only contain a hash (`image.iso.sha256`) are supported, also PGP clearsigned ones,
+/// whose signature is not checked. Relative paths are resolved against the directory of
+/// the checksum file. The verification can be stopped with `cancel_hashing` and the path
+/// of the checksum file.
+///
+/// # Arguments
+/// * `path` - A string representing the absolute path to the checksum file.
+/// * `checksum_method` - The hash algorithm of the lines without one. Inferred from the file name
+///   (`MD5SUMS`, `*.sha512`, ...) or else from the hash length if not given.
+/// * `hashing` - The running hash calculations.
+///
+/// # Returns
+/// * `Ok(ChecksumFileVerification)` - The status of every listed file and whether all of them passed.
+/// * `Err(String)` - An error message if the checksum file cannot be read or parsed, or the verification was cancelled.
+///
+/// # Example
+/// ```rust
+/// let result = verify_checksum_file("/downloads/SHA256SUMS".into(), None, hashing).await?;
+/// for file in result.files {
+///     println!("{}: {:?}", file.path, file.status);
+/// }
+/// ```
+#[tauri::command]
+pub async fn verify_checksum_file(
+    path: String,
+    checksum_method: Option<ChecksumMethod>,
+    hashing: State<'_, Arc<Mutex<HashingState>>>,
+) -> Result<ChecksumFileVerification, String> {
+    verify_checksum_file_impl(path, checksum_method, hashing.inner().clone()).await
+}
+
+pub async fn verify_checksum_file_impl(
+    path: String,
+    checksum_method: Option<ChecksumMethod>,
+    hashing: Arc<Mutex<HashingState>>,
+) -> Result<ChecksumFileVerification, String> {
+    let text = tokio::fs::read_to_string(&path).await.map_err(|err| {
+        log_error!("Failed to read checksum file {}: {}", path, err);
+        Error::new(ErrorCode::ResourceNotFound, format!("Failed to read checksum file {}: {}", path, err)).to_json()
+    })?;
+    let checksum_path = Path::new(&path);
+    let file_name = checksum_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let lines = checksum_file::parse_checksum_file(&text, &file_name)
+        .map_err(|err| Error::new(ErrorCode::InvalidInput, err).to_json())?;
+    let method = checksum_method.or_else(|| checksum_file::method_from_file_name(&file_name));
+    let base_dir = checksum_path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let (id, cancel) = hashing.lock().map_err(|_| "Failed to lock hashing state")?.start(&path);
+
+    let result = tokio::task::spawn_blocking(move || {
+        checksum_file::verify_checksum_lines(&base_dir, &lines, method.as_ref(), Some(&cancel))
+    })
+    .await;
+
+    if let Ok(mut hashing) = hashing.lock() {
+        hashing.finish(id);
+    }
+    result
+        .map_err(|_| HashError::FileOperationError.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Canonicalizes a path that may not exist yet, by canonicalizing its parent.
+fn absolute_path(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    match (path.parent().and_then(|parent| parent.canonicalize().ok()), path.file_name()) {
+        (Some(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Progress of `gen_hashes`, sent through its channel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HashProgress {
+    pub path: String,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+/// A hash computed by `gen_hashes`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileHash {
+    pub method: ChecksumMethod,
+    pub hash: String,
+}
+
+/// Hashes a file with one or more methods in a single pass over the file.
+/// The file is read in chunks, so files of any size are hashed with constant memory.
+/// Progress is sent through `on_progress` at most every 100 ms, the calculation can
+/// be stopped with `cancel_hashing`.
+///
+/// # Arguments
+/// * `path` - A string representing the absolute path to the file to hash.
+/// * `methods` - The hash algorithms to compute. Defaults to the algorithm from the settings.
+/// * `on_progress` - The channel that receives `HashProgress` updates.
+/// * `state` - The application's settings state containing the default hash algorithm.
+/// * `hashing` - The running hash calculations.
+///
+/// # Returns
+/// * `Ok(Vec<FileHash>)` - The hashes in the order of `methods`.
+/// * `Err(String)` - An error message if the file cannot be read or the calculation was cancelled.
+///
+/// # Example
+/// ```rust
+/// let hashes = gen_hashes("/path/to/image.iso".into(), Some(vec![ChecksumMethod::MD5, ChecksumMethod::SHA256]), channel, state, hashing).await?;
+/// ```
+#[tauri::command]
+pub async fn gen_hashes(
+    path: String,
+    methods: Option<Vec<ChecksumMethod>>,
+    on_progress: Channel<HashProgress>,
+    state: State<'_, Arc<Mutex<SettingsState>>>,
+    hashing: State<'_, Arc<Mutex<HashingState>>>,
+) -> Result<Vec<FileHash>, String> {
+    let send_progress = move |progress| {
+        if let Err(err) = on_progress.send(progress) {
+            log_warn!("Failed to send hash progress: {}", err);
+        }
+    };
+    gen_hashes_impl(path, methods, send_progress, state.inner().clone(), hashing.inner().clone()).await
+}
+
+pub async fn gen_hashes_impl(
+    path: String,
+    methods: Option<Vec<ChecksumMethod>>,
+    mut on_progress: impl FnMut(HashProgress) + Send + 'static,
+    state: Arc<Mutex<SettingsState>>,
+    hashing: Arc<Mutex<HashingState>>,
+) -> Result<Vec<FileHash>, String> {
+    let methods = match methods {
+        Some(methods) if !methods.is_empty() => methods,
+        _ => vec![get_checksum_method(state).await.map_err(|e| e.to_string())?],
+    };
+    let bytes_total = std::fs::metadata(&path)
+        .map(|meta| meta.len())
+        .map_err(|_| HashError::FileOperationError.to_string())?;
+    let (id, cancel) = hashing.lock().map_err(|_| "Failed to lock hashing state")?.start(&path);
+
+    let hashed_path = path.clone();
+    let hashed_methods = methods.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let mut last_progress: Option<Instant> = None;
+        hash_file(Path::new(&hashed_path), &hashed_methods, Some(&cancel), |bytes_done| {
+            if last_progress.is_none_or(|last| last.elapsed() >= PROGRESS_INTERVAL) || bytes_done == bytes_total {
+                last_progress = Some(Instant::now());
+                on_progress(HashProgress { path: hashed_path.clone(), bytes_done, bytes_total });
+            }
+        })
+    })
+    .await;
+
+    if let Ok(mut hashing) = hashing.lock() {
+        hashing.finish(id);
+    }
+    let hashes = result
+        .map_err(|_| HashError::FileOperationError.to_string())?
+        .map_err(|e| e.to_string())?;
+    Ok(methods
+        .into_iter()
+        .zip(hashes)
+        .map(|(method, hash)| FileHash { method, hash })
+        .collect())
+}
+
+/// Cancels running `gen_hashes` calculations. They fail with "Hash calculation was cancelled".
+///
+/// # Arguments
+/// * `path` - The file whose calculations to cancel. Every calculation is cancelled if not given.
+/// * `hashing` - The running hash calculations.
+///
+/// # Returns
+/// * `Ok(usize)` - The number of cancelled calculations.
+/// * `Err(String)` - If the hashing state could not be accessed.
+#[tauri::command]
+pub fn cancel_hashing(path: Option<String>, hashing: State<Arc<Mutex<HashingState>>>) -> Result<usize, String> {
+    cancel_hashing_impl(path, hashing.inner().clone())
+}
+
+pub fn cancel_hashing_impl(path: Option<String>, hashing: Arc<Mutex<HashingState>>) -> Result<usize, String> {
+    let hashing = hashing.lock().map_err(|_| "Failed to lock hashing state")?;
+    Ok(hashing.cancel(path.as_deref()))
 }
 
 #[cfg(test)]
@@ -377,6 +687,9 @@ mod tests_hash_commands {
                 (ChecksumMethod::SHA384, "55bc556b0d2fe0fce582ba5fe07baafff035653638c7ac0d5494c2a64c0bea1cc57331c7c12a45cdbca7f4c34a089eeb"),
                 (ChecksumMethod::SHA512, "c1527cd893c124773d811911970c8fe6e857d6df5dc9226bd8a160614c0cd963a4ddea2b94bb7d36021ef9d865d5cea294a82dd49a0bb269f51f6e7a57f79421"),
                 (ChecksumMethod::CRC32, "ebe6c6e6"),
+                (ChecksumMethod::SHA1, "943a702d06f34599aee1f8da8ef9f7296031d699"),
+                (ChecksumMethod::BLAKE3, "ede5c0b10f2ec4979c69b52f61e42ff5b413519ce09be0f14d098dcfe5f6f98d"),
+                (ChecksumMethod::XXH3, "f3c34bf11915e869"),
             ];
 
         for (method, expected_hash) in expected_hashes {
@@ -394,6 +707,17 @@ mod tests_hash_commands {
         }
     }
 
+    #[test]
+    fn test_checksum_method_names_round_trip() {
+        for name in ["MD5", "SHA1", "SHA256", "SHA384", "SHA512", "CRC32", "BLAKE3", "XXH3"] {
+            let method = ChecksumMethod::from_str(name).unwrap();
+            assert_eq!(method.to_string(), name);
+            assert_eq!(serde_json::to_value(&method).unwrap(), json!(name));
+        }
+        assert_eq!(ChecksumMethod::from_str("blake3").unwrap(), ChecksumMethod::BLAKE3);
+        assert!(ChecksumMethod::from_str("SHA3").is_err());
+    }
+
     #[tokio::test]
     async fn test_non_existent_file() {
         let mock_state = create_test_state(ChecksumMethod::SHA256);
@@ -575,4 +899,102 @@ mod tests_hash_commands {
             "50e72a0e26442fe2552dc3938ac58658228c0cbfb1d2ca872ae435266fcd055e"
         );
     }
+
+    #[tokio::test]
+    async fn test_gen_hashes_in_one_pass() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let test_file_path = temp_dir.path().join("test_hash.txt");
+        std::fs::write(&test_file_path, b"Hello, world!").unwrap();
+        let hashing = Arc::new(Mutex::new(HashingState::new()));
+
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let collected = progress.clone();
+        let hashes = gen_hashes_impl(
+            test_file_path.to_str().unwrap().to_string(),
+            Some(vec![ChecksumMethod::SHA256, ChecksumMethod::CRC32]),
+            move |update: HashProgress| collected.lock().unwrap().push(update.bytes_done),
+            create_test_settings_state(),
+            hashing.clone(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(hashes[0].method, ChecksumMethod::SHA256);
+        assert_eq!(hashes[0].hash, "315f5bdb76d078c43b8ac0064e4a0164612b1fce77c869345bfc94c75894edd3");
+        assert_eq!(hashes[1].hash, checksum(&ChecksumMethod::CRC32, b"Hello, world!"));
+        assert_eq!(*progress.lock().unwrap(), vec![13]);
+
+        // Finished calculations are no longer running
+        assert_eq!(cancel_hashing_impl(None, hashing.clone()).unwrap(), 0);
+
+        let missing = temp_dir.path().join("missing.txt").to_str().unwrap().to_string();
+        assert!(gen_hashes_impl(missing, None, |_| {}, create_test_settings_state(), hashing).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_directory_manifest_round_trip() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        std::fs::create_dir(temp_dir.path().join("nested")).unwrap();
+        std::fs::write(temp_dir.path().join("nested/file.txt"), "Hello, world!").unwrap();
+        let root = temp_dir.path().to_str().unwrap().to_string();
+        let manifest_path = temp_dir.path().join("SHA256SUMS").to_str().unwrap().to_string();
+        let state = create_test_state(ChecksumMethod::SHA256);
+        let directory_hash = gen_hash_and_return_string_impl(root.clone(), state.clone()).await.unwrap();
+
+        // Written into the hashed directory, the manifest does not list itself
+        let manifest = gen_directory_manifest_impl(root.clone(), Some(manifest_path.clone()), None, create_test_state(ChecksumMethod::SHA256))
+            .await
+            .unwrap();
+        assert_eq!(manifest.entries.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(&manifest_path).unwrap(),
+            "315f5bdb76d078c43b8ac0064e4a0164612b1fce77c869345bfc94c75894edd3  nested/file.txt\n"
+        );
+
+        // The root hash is what the directory hashes to, and the hash of the manifest file
+        assert_eq!(directory_hash, manifest.root_hash);
+        assert!(compare_file_or_dir_with_hash_impl(manifest_path.clone(), manifest.root_hash.clone(), state).await.unwrap());
+
+        let verification = verify_directory_manifest(root.clone(), manifest_path.clone(), None).await.unwrap();
+        assert!(verification.ok);
+        std::fs::write(temp_dir.path().join("nested/file.txt"), "changed").unwrap();
+        let verification = verify_directory_manifest(root, manifest_path, None).await.unwrap();
+        assert_eq!(verification.changed, vec!["nested/file.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_verify_checksum_file() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        std::fs::write(temp_dir.path().join("image.iso"), "Hello, world!").unwrap();
+        let hash_path = temp_dir.path().join("image.iso.sha256").to_str().unwrap().to_string();
+        let state = create_test_state(ChecksumMethod::SHA256);
+        gen_hash_and_save_to_file_impl(temp_dir.path().join("image.iso").to_str().unwrap().to_string(), hash_path.clone(), state)
+            .await
+            .unwrap();
+        let hashing = Arc::new(Mutex::new(HashingState::new()));
+
+        let result = verify_checksum_file_impl(hash_path, None, hashing.clone()).await.unwrap();
+        assert!(result.ok);
+        assert_eq!(result.files[0].path, "image.iso");
+        assert_eq!(result.files[0].method, Some(ChecksumMethod::SHA256));
+
+        // A hash that does not match the listed file
+        let sums_path = temp_dir.path().join("SHA256SUMS");
+        std::fs::write(&sums_path, format!("{}  image.iso\n", "0".repeat(64))).unwrap();
+        let result = verify_checksum_file_impl(sums_path.to_str().unwrap().to_string(), None, hashing.clone()).await.unwrap();
+        assert_eq!(result.files[0].status, checksum_file::ChecksumStatus::Failed);
+        assert!(!result.ok);
+
+        // BLAKE3 hashes have the length of SHA256 ones, the file name tells them apart
+        let b3_path = temp_dir.path().join("B3SUMS");
+        std::fs::write(&b3_path, format!("{}  image.iso\n", checksum(&ChecksumMethod::BLAKE3, b"Hello, world!"))).unwrap();
+        let result = verify_checksum_file_impl(b3_path.to_str().unwrap().to_string(), None, hashing.clone()).await.unwrap();
+        assert_eq!(result.files[0].method, Some(ChecksumMethod::BLAKE3));
+        assert!(result.ok);
+
+        let missing = temp_dir.path().join("MD5SUMS").to_str().unwrap().to_string();
+        let err = verify_checksum_file_impl(missing, None, hashing).await.unwrap_err();
+        assert!(err.contains("ResourceNotFound"));
+    }
 }
@@ -1,11 +1,18 @@
+pub mod archive_commands;
 pub mod command_exec_commands;
+pub mod disk_usage_commands;
+pub mod duplicate_commands;
+pub mod file_job_commands;
 pub mod file_system_operation_commands;
+pub mod folder_size_commands;
 pub mod hash_commands;
 pub mod meta_data_commands;
 pub mod search_engine_commands;
 pub mod settings_commands;
 pub mod template_commands;
+pub mod trash_commands;
 pub mod volume_operations_commands;
 pub mod sftp_file_system_operation_commands;
+pub mod sftp_connection_commands;
 pub mod preview_commands;
 pub mod permission_commands;
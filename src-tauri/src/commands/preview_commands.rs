@@ -1,5 +1,7 @@
 use base64::Engine;
 use anyhow::Result;
+use crate::filesystem::archive::browse::{self, ArchivePath};
+use crate::filesystem::archive::ArchiveEntryKind;
 use serde::Serialize;
 use std::{fs, io::Read, path::{Path, PathBuf}};
 
@@ -22,6 +24,9 @@ pub enum PreviewPayload {
     Error { name: String, message: String },
 }
 
+/// Number of bytes read to detect the type of a file and to preview text
+const HEAD_BYTES: usize = 256 * 1024;
+
 fn filename(p: &Path) -> String {
     p.file_name().and_then(|s| s.to_str()).unwrap_or("file").to_string()
 }
@@ -56,11 +61,48 @@ fn detect_mime(path: &Path, head: &[u8]) -> Option<&'static str> {
     None
 }
 
+/// Text/binary heuristic shared by previews and the content index:
+/// a file is text if its mime type says so or its head contains no control bytes.
+pub(crate) fn looks_like_text(mime: &str, head: &[u8]) -> bool {
+    mime.starts_with("text/") || head.iter().all(|&b| b == 9 || b == 10 || b == 13 || (32..0xF5).contains(&b))
+}
+
+fn decode_text(bytes: &[u8]) -> String {
+    let mut det = chardetng::EncodingDetector::new();
+    det.feed(bytes, true);
+    let enc = det.guess(None, true);
+    let (cow, _, _) = enc.decode(bytes);
+    cow.to_string()
+}
+
+/// Reads up to `max_bytes` of a file as text, using the same heuristic as `build_preview`.
+///
+/// # Returns
+/// `None` for directories, unreadable files and files that look binary
+pub(crate) fn read_text_content(path: &Path, max_bytes: usize) -> Option<String> {
+    if !fs::metadata(path).ok()?.is_file() {
+        return None;
+    }
+    let head = read_prefix(path, max_bytes).ok()?;
+    let mime = detect_mime(path, &head).unwrap_or("application/octet-stream");
+    if !looks_like_text(mime, &head) {
+        return None;
+    }
+    Some(decode_text(&head))
+}
+
 #[tauri::command]
 pub fn build_preview(path: String) -> Result<PreviewPayload, String> {
     let p = PathBuf::from(&path);
     let name = filename(&p);
 
+    // Paths inside an archive are previewed without extracting it
+    if !p.exists() {
+        if let Some(member) = ArchivePath::parse(&path) {
+            return build_archive_preview(&member, name);
+        }
+    }
+
     // Folders: return Folder preview
     if p.is_dir() {
         // Count items (files + dirs, not recursive)
@@ -97,17 +139,35 @@ pub fn build_preview(path: String) -> Result<PreviewPayload, String> {
     // Files
     let meta = fs::metadata(&p).map_err(|e| e.to_string())?;
     // Read a small head for detection + maybe text
-    let head = read_prefix(&p, 256 * 1024).map_err(|e| e.to_string())?;
-    let mime = detect_mime(&p, &head).unwrap_or("application/octet-stream");
+    let head = read_prefix(&p, HEAD_BYTES).map_err(|e| e.to_string())?;
+    file_preview(name, &p, meta.len(), head, Some(path), || {
+        fs::read(&p).map_err(|e| e.to_string())
+    })
+}
+
+/// Builds the preview of a file from the first `HEAD_BYTES` of it.
+///
+/// # Arguments
+/// * `read_all` - Reads the whole file, for images and PDFs small enough to embed
+/// * `media_path` - Where videos and audio can be played from, they are `Unknown` without
+fn file_preview(
+    name: String,
+    p: &Path,
+    size: u64,
+    head: Vec<u8>,
+    media_path: Option<String>,
+    read_all: impl FnOnce() -> Result<Vec<u8>, String>,
+) -> Result<PreviewPayload, String> {
+    let mime = detect_mime(p, &head).unwrap_or("application/octet-stream");
 
     // Branch by mime top-level type
     if mime.starts_with("image/") {
         // Encode entire file only if small; else just the head (fast path)
         // You can raise this cap depending on your perf goals
         let cap = 6 * 1024 * 1024;
-        let bytes = meta.len() as usize;
+        let bytes = size as usize;
         let data = if bytes <= cap {
-            fs::read(&p).map_err(|e| e.to_string())?
+            read_all()?
         } else {
             head.clone()
         };
@@ -117,9 +177,9 @@ pub fn build_preview(path: String) -> Result<PreviewPayload, String> {
     if mime == "application/pdf" {
         // Encode entire file only if small; else just the head (fast path)
         let cap = 12 * 1024 * 1024; // Allow larger PDFs than images
-        let bytes = meta.len() as usize;
+        let bytes = size as usize;
         let data = if bytes <= cap {
-            fs::read(&p).map_err(|e| e.to_string())?
+            read_all()?
         } else {
             head.clone()
         };
@@ -128,21 +188,22 @@ pub fn build_preview(path: String) -> Result<PreviewPayload, String> {
     }
 
     if mime.starts_with("video/") {
-        return Ok(PreviewPayload::Video { name, path });
+        return Ok(match media_path {
+            Some(path) => PreviewPayload::Video { name, path },
+            None => PreviewPayload::Unknown { name },
+        });
     }
 
     if mime.starts_with("audio/") {
-        return Ok(PreviewPayload::Audio { name, path });
+        return Ok(match media_path {
+            Some(path) => PreviewPayload::Audio { name, path },
+            None => PreviewPayload::Unknown { name },
+        });
     }
 
     // Heuristic: treat smallish or text‑ish files as text
-    let looks_texty = mime.starts_with("text/") || head.iter().all(|&b| b == 9 || b == 10 || b == 13 || (b >= 32 && b < 0xF5));
-    if looks_texty || meta.len() <= 2 * 1024 * 1024 {
-        let mut det = chardetng::EncodingDetector::new();
-        det.feed(&head, true);
-        let enc = det.guess(None, true);
-        let (cow, _, _) = enc.decode(&head);
-        let mut text = cow.to_string();
+    if looks_like_text(mime, &head) || size <= 2 * 1024 * 1024 {
+        let mut text = decode_text(&head);
         let mut truncated = false;
         if text.len() > 200_000 {
             text.truncate(200_000);
@@ -155,6 +216,44 @@ pub fn build_preview(path: String) -> Result<PreviewPayload, String> {
     Ok(PreviewPayload::Unknown { name })
 }
 
+/// Previews a file or directory inside an archive without extracting it. Videos and
+/// audio inside archives cannot be played and are previewed as `Unknown`.
+fn build_archive_preview(member: &ArchivePath, name: String) -> Result<PreviewPayload, String> {
+    let entries = browse::archive_entries(&member.archive).map_err(|e| e.to_string())?;
+    match browse::member_kind(&entries, &member.inner) {
+        Some(ArchiveEntryKind::Directory) => {
+            let listing = browse::list_directory(&entries, &member.inner).map_err(|e| e.to_string())?;
+            let modified = listing
+                .files
+                .iter()
+                .map(|file| file.modified)
+                .chain(listing.directories.iter().map(|directory| directory.modified))
+                .flatten()
+                .max();
+            let modified_str = modified
+                .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+                .map(|time| time.with_timezone(&chrono::Local).to_rfc3339());
+            Ok(PreviewPayload::Folder {
+                name,
+                size: listing.files.iter().map(|file| file.size).sum(),
+                item_count: listing.directories.len() + listing.files.len(),
+                modified: modified_str,
+            })
+        }
+        Some(ArchiveEntryKind::File) => {
+            let (entry, head) = browse::read_member(&member.archive, &member.inner, HEAD_BYTES as u64)
+                .map_err(|e| e.to_string())?;
+            file_preview(name, Path::new(&member.inner), entry.size, head, None, || {
+                browse::read_member(&member.archive, &member.inner, entry.size)
+                    .map(|(_, data)| data)
+                    .map_err(|e| e.to_string())
+            })
+        }
+        Some(_) => Ok(PreviewPayload::Unknown { name }),
+        None => Err(format!("No such file in the archive: {}", member.inner)),
+    }
+}
+
 #[cfg(test)]
 mod preview_tests {
     use super::*;
@@ -482,4 +581,48 @@ mod preview_tests {
         
         log_info!("test_build_preview_folder_truncation completed successfully");
     }
+
+    #[test]
+    fn test_read_text_content_skips_binary_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let text_path = temp_dir.path().join("notes.txt");
+        let binary_path = temp_dir.path().join("blob.bin");
+        fs::write(&text_path, "line one\nline two").unwrap();
+        fs::write(&binary_path, [0u8, 1, 2, 3, 0xFF, 0]).unwrap();
+
+        assert_eq!(read_text_content(&text_path, 1024).as_deref(), Some("line one\nline two"));
+        assert_eq!(read_text_content(&text_path, 4).as_deref(), Some("line"));
+        assert!(read_text_content(&binary_path, 1024).is_none());
+        assert!(read_text_content(temp_dir.path(), 1024).is_none());
+    }
+
+    #[test]
+    fn test_build_preview_of_archive_members() {
+        use crate::filesystem::archive::{create_writer, ArchiveFormat, ArchiveOptions};
+
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("notes.txt");
+        fs::write(&source, "inside the archive").unwrap();
+        let archive = temp_dir.path().join("bundle.tar.bz2");
+        let mut writer = create_writer(&archive, ArchiveFormat::TarBz2, &ArchiveOptions::default()).unwrap();
+        writer.add_file("docs/notes.txt", &source, &mut fs::File::open(&source).unwrap()).unwrap();
+        writer.add_file("docs/more.txt", &source, &mut fs::File::open(&source).unwrap()).unwrap();
+        writer.finish().unwrap();
+
+        match build_preview(archive.join("docs/notes.txt").to_string_lossy().to_string()) {
+            Ok(PreviewPayload::Text { name, text, truncated }) => {
+                assert_eq!(name, "notes.txt");
+                assert_eq!(text, "inside the archive");
+                assert!(!truncated);
+            }
+            other => panic!("Expected text preview, got: {:?}", other),
+        }
+        match build_preview(archive.join("docs").to_string_lossy().to_string()) {
+            Ok(PreviewPayload::Folder { name, size, item_count, .. }) => {
+                assert_eq!((name.as_str(), size, item_count), ("docs", 36, 2));
+            }
+            other => panic!("Expected folder preview, got: {:?}", other),
+        }
+        assert!(build_preview(archive.join("docs/missing.txt").to_string_lossy().to_string()).is_err());
+    }
 }
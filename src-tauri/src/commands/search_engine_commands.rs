@@ -3,6 +3,8 @@ use std::sync::{Arc, Mutex};
 use tauri::State;
 
 use crate::{log_error, log_info};
+use crate::search_engine::content_index::ContentSearchResult;
+use crate::search_engine::pattern_search::SearchMode;
 use crate::state::searchengine_data::{IndexingProgress, SearchEngineInfo, SearchEngineState, SearchEngineStatus};
 
 // Type alias for the search result type returned by the engine
@@ -12,6 +14,7 @@ type SearchResult = Vec<(String, f32)>;
 ///
 /// # Arguments
 /// * `query` - The search query string
+/// * `mode` - How the query is interpreted: fuzzy (default), glob, regex or exact
 /// * `search_engine_state` - The state containing the search engine
 ///
 /// # Returns
@@ -20,7 +23,7 @@ type SearchResult = Vec<(String, f32)>;
 ///
 /// # Example
 /// ```rust
-/// let result = search("document".to_string(), search_engine_state).await;
+/// let result = search("document".to_string(), None, search_engine_state).await;
 /// match result {
 ///     Ok(matches) => {
 ///         for (path, score) in matches {
@@ -33,21 +36,34 @@ type SearchResult = Vec<(String, f32)>;
 #[tauri::command]
 pub fn search(
     query: String,
+    mode: Option<SearchMode>,
     search_engine_state: State<Arc<Mutex<SearchEngineState>>>,
 ) -> Result<SearchResult, String> {
-    search_impl(query, search_engine_state.inner().clone())
+    match mode {
+        Some(mode) => search_with_mode_impl(query, mode, search_engine_state.inner().clone()),
+        None => search_impl(query, search_engine_state.inner().clone()),
+    }
 }
 
 pub fn search_impl(
     query: String,
     state: Arc<Mutex<SearchEngineState>>,
+) -> Result<SearchResult, String> {
+    search_with_mode_impl(query, SearchMode::Fuzzy, state)
+}
+
+pub fn search_with_mode_impl(
+    query: String,
+    mode: SearchMode,
+    state: Arc<Mutex<SearchEngineState>>,
 ) -> Result<SearchResult, String> {
     log_info!(
-        "Search implementation called with query: {}",
-        query
+        "Search implementation called with query: {} (mode: {:?})",
+        query,
+        mode
     );
     let engine = state.lock().map_err(|_| "lock poisoned")?;
-    engine.search(&query)
+    engine.search_with_mode(&query, mode)
 }
 
 /// Searches the indexed files based on the provided query string,
@@ -100,6 +116,50 @@ pub fn search_with_extension_impl(
     engine.search_by_extension(&query, extensions)
 }
 
+/// Searches inside the text content of indexed files.
+///
+/// Requires `index_file_contents` to be enabled in the search engine configuration
+/// before the folder is indexed; otherwise no files have indexed content.
+///
+/// # Arguments
+/// * `query` - The text to look for inside files
+/// * `search_engine_state` - The state containing the search engine
+///
+/// # Returns
+/// * `Ok(Vec<ContentSearchResult>)` - Matching files with their line numbers and snippets, best first
+/// * `Err(String)` - If there was an error during the search operation
+///
+/// # Example
+/// ```rust
+/// let result = search_content("fn main".to_string(), search_engine_state).await;
+/// match result {
+///     Ok(files) => {
+///         for file in files {
+///             for m in file.matches {
+///                 println!("{}:{}: {}", file.path, m.line_number, m.snippet);
+///             }
+///         }
+///     },
+///     Err(err) => println!("Content search error: {}", err),
+/// }
+/// ```
+#[tauri::command]
+pub fn search_content(
+    query: String,
+    search_engine_state: State<Arc<Mutex<SearchEngineState>>>,
+) -> Result<Vec<ContentSearchResult>, String> {
+    search_content_impl(query, search_engine_state.inner().clone())
+}
+
+pub fn search_content_impl(
+    query: String,
+    state: Arc<Mutex<SearchEngineState>>,
+) -> Result<Vec<ContentSearchResult>, String> {
+    log_info!("Content search called with query: {}", query);
+    let engine = state.lock().map_err(|_| "Failed to acquire lock on search engine state")?;
+    engine.search_content(&query)
+}
+
 /// Recursively adds all files from a directory to the search engine index using chunked processing.
 ///
 /// Updated to use chunked indexing by default for better performance and responsiveness.
@@ -316,6 +376,11 @@ pub fn clear_search_engine_impl(state: Arc<Mutex<SearchEngineState>>) -> Result<
     let state = state.lock().map_err(|_| "Failed to acquire lock on search engine state")?;
     let mut engine = state.engine.write().map_err(|_| "Failed to acquire write lock on search engine")?;
     engine.clear();
+    drop(engine);
+
+    // A cleared engine must not come back on the next start or be refilled by the watcher
+    state.remove_persisted_index();
+    state.stop_watching();
 
     // Update state
     let mut data = state.data.lock().map_err(|_| "Failed to acquire lock on search engine data")?;
@@ -481,6 +546,72 @@ mod tests_autocomplete_commands {
         assert_eq!(results.unwrap().len(), 0);
     }
 
+    #[test]
+    fn test_search_with_mode_impl_pattern_modes() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("src")).unwrap();
+        create_temp_file(&temp_dir, "src/parser_test.rs", "");
+        create_temp_file(&temp_dir, "src/parser.rs", "");
+        create_temp_file(&temp_dir, "notes_test.txt", "");
+
+        let state = create_test_search_engine_state();
+        add_paths_recursive_impl(temp_dir.path().to_string_lossy().to_string(), state.clone()).unwrap();
+
+        let results = search_with_mode_impl("**/src/*_test.rs".to_string(), SearchMode::Glob, state.clone()).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].0.ends_with("src/parser_test.rs"));
+
+        let results = search_with_mode_impl(r"parser(_test)?\.rs$".to_string(), SearchMode::Regex, state.clone()).unwrap();
+        assert_eq!(results.len(), 2);
+
+        let results = search_with_mode_impl("NOTES_".to_string(), SearchMode::Exact, state.clone()).unwrap();
+        assert_eq!(results.len(), 1);
+
+        assert!(search_with_mode_impl("(unclosed".to_string(), SearchMode::Regex, state).is_err());
+    }
+
+    #[test]
+    fn test_search_content_impl_finds_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        create_temp_file(&temp_dir, "notes.txt", "shopping list\nremember the quarterly report\n");
+        create_temp_file(&temp_dir, "other.txt", "nothing to see here");
+        create_temp_file(&temp_dir, "report.md", "# Quarterly report\nDraft");
+
+        let state = create_test_search_engine_state();
+        {
+            let engine_state = state.lock().unwrap();
+            engine_state.data.lock().unwrap().config.index_file_contents = true;
+        }
+        add_paths_recursive_impl(temp_dir.path().to_string_lossy().to_string(), state.clone()).unwrap();
+
+        let results = search_content_impl("quarterly report".to_string(), state.clone()).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].score >= results[1].score);
+
+        let report = results.iter().find(|r| r.path.ends_with("report.md")).unwrap();
+        assert_eq!(report.matches[0].line_number, 1);
+        assert_eq!(report.matches[0].snippet, "# Quarterly report");
+
+        let notes = results.iter().find(|r| r.path.ends_with("notes.txt")).unwrap();
+        assert_eq!(notes.match_count, 1);
+        assert_eq!(notes.matches[0].line_number, 2);
+        assert_eq!(notes.matches[0].snippet, "remember the quarterly report");
+
+        assert!(search_content_impl("missing phrase".to_string(), state).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_content_impl_without_content_index() {
+        let temp_dir = TempDir::new().unwrap();
+        create_temp_file(&temp_dir, "notes.txt", "quarterly report");
+
+        let state = create_test_search_engine_state();
+        add_paths_recursive_impl(temp_dir.path().to_string_lossy().to_string(), state.clone()).unwrap();
+
+        let results = search_content_impl("quarterly".to_string(), state).unwrap();
+        assert!(results.is_empty());
+    }
+
     #[test]
     fn test_add_and_search_path() {
         let temp_dir = TempDir::new().unwrap();
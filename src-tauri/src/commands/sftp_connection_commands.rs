@@ -0,0 +1,143 @@
+use crate::state::sftp_connection_data::{SavedSftpConnection, SftpConnectionInput, SftpConnectionState};
+use crate::state::sftp_session_data::{self, SftpSessionId, SftpSessionState};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tauri::State;
+
+fn lock_connections(
+    state: &Arc<Mutex<SftpConnectionState>>,
+) -> Result<MutexGuard<'_, SftpConnectionState>, String> {
+    state.lock().map_err(|_| "SFTP connections are poisoned".to_string())
+}
+
+/// Lists the saved SFTP connections. Secrets are never returned, `has_secret` tells
+/// whether one is stored.
+///
+/// # Returns
+/// * `Ok(Vec<SavedSftpConnection>)` - The saved connections
+/// * `Err(String)` - If the registry cannot be accessed
+///
+/// # Example
+/// ```rust
+/// let connections = list_sftp_connections(state)?;
+/// ```
+#[tauri::command]
+pub fn list_sftp_connections(
+    state: State<'_, Arc<Mutex<SftpConnectionState>>>,
+) -> Result<Vec<SavedSftpConnection>, String> {
+    list_sftp_connections_impl(state.inner().clone())
+}
+
+pub fn list_sftp_connections_impl(state: Arc<Mutex<SftpConnectionState>>) -> Result<Vec<SavedSftpConnection>, String> {
+    Ok(lock_connections(&state)?.list())
+}
+
+/// Adds a connection, or updates an existing one. The password or passphrase is kept in
+/// the encrypted secret store, never in the connections file.
+///
+/// # Arguments
+/// * `connection_id` - The connection to update, or `None` to add a new one
+/// * `connection` - The settings of the connection. Its `secret` replaces the stored one,
+///   `None` keeps it and an empty string removes it.
+///
+/// # Returns
+/// * `Ok(SavedSftpConnection)` - The saved connection with its id
+/// * `Err(String)` - If the name or host is missing, the name is taken or the connection does not exist
+///
+/// # Example
+/// ```rust
+/// let saved = save_sftp_connection(None, input, state)?;
+/// let session_id = connect_sftp_connection(saved.id, connections, sessions)?;
+/// ```
+#[tauri::command]
+pub fn save_sftp_connection(
+    connection_id: Option<String>,
+    connection: SftpConnectionInput,
+    state: State<'_, Arc<Mutex<SftpConnectionState>>>,
+) -> Result<SavedSftpConnection, String> {
+    save_sftp_connection_impl(connection_id, connection, state.inner().clone())
+}
+
+pub fn save_sftp_connection_impl(
+    connection_id: Option<String>,
+    connection: SftpConnectionInput,
+    state: Arc<Mutex<SftpConnectionState>>,
+) -> Result<SavedSftpConnection, String> {
+    lock_connections(&state)?.save(connection_id, connection)
+}
+
+/// Removes a saved connection together with its secret. Open sessions are not closed.
+///
+/// # Arguments
+/// * `connection_id` - The id of the connection
+///
+/// # Returns
+/// * `Ok(())` - If the connection was removed
+/// * `Err(String)` - If there is no such connection
+#[tauri::command]
+pub fn remove_sftp_connection(
+    connection_id: String,
+    state: State<'_, Arc<Mutex<SftpConnectionState>>>,
+) -> Result<(), String> {
+    remove_sftp_connection_impl(connection_id, state.inner().clone())
+}
+
+pub fn remove_sftp_connection_impl(connection_id: String, state: Arc<Mutex<SftpConnectionState>>) -> Result<(), String> {
+    lock_connections(&state)?.remove(&connection_id)
+}
+
+/// Opens a session for a saved connection, with its stored secret. The session id is
+/// used with the SFTP file commands like one from `connect_to_sftp`.
+///
+/// # Arguments
+/// * `connection_id` - The id of the connection
+///
+/// # Returns
+/// * `Ok(SftpSessionId)` - The id of the session
+/// * `Err(String)` - If there is no such connection, the host key is not trusted or authentication fails
+#[tauri::command]
+pub fn connect_sftp_connection(
+    connection_id: String,
+    state: State<'_, Arc<Mutex<SftpConnectionState>>>,
+    sessions: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<SftpSessionId, String> {
+    connect_sftp_connection_impl(connection_id, state.inner().clone(), sessions.inner().clone())
+}
+
+pub fn connect_sftp_connection_impl(
+    connection_id: String,
+    state: Arc<Mutex<SftpConnectionState>>,
+    sessions: Arc<Mutex<SftpSessionState>>,
+) -> Result<SftpSessionId, String> {
+    // The registry is not locked while connecting
+    let (connection, auth) = lock_connections(&state)?.login(&connection_id)?;
+    sftp_session_data::connect_session(&sessions, connection.host, connection.port, connection.username, auth)
+}
+
+#[cfg(test)]
+mod tests_sftp_connection_commands {
+    use super::*;
+    use crate::state::sftp_connection_data::SftpAuthMethod;
+
+    #[test]
+    fn test_saved_connection_commands() {
+        let state = Arc::new(Mutex::new(SftpConnectionState::new()));
+        let input: SftpConnectionInput = serde_json::from_str(
+            r#"{"name":"Local","host":"127.0.0.1","port":1,"username":"explorer","secret":"explorer"}"#,
+        )
+        .unwrap();
+
+        let saved = save_sftp_connection_impl(None, input, state.clone()).unwrap();
+        assert_eq!(saved.auth_method, SftpAuthMethod::Password);
+        assert!(saved.has_secret);
+        assert_eq!(list_sftp_connections_impl(state.clone()).unwrap(), vec![saved.clone()]);
+        assert!(!serde_json::to_string(&saved).unwrap().contains("\"secret\""));
+
+        // Nothing listens on port 1
+        let sessions = Arc::new(Mutex::new(SftpSessionState::new()));
+        assert!(connect_sftp_connection_impl(saved.id.clone(), state.clone(), sessions.clone()).is_err());
+
+        remove_sftp_connection_impl(saved.id.clone(), state.clone()).unwrap();
+        let err = connect_sftp_connection_impl(saved.id.clone(), state.clone(), sessions).unwrap_err();
+        assert_eq!(err, format!("Unknown SFTP connection: {}", saved.id));
+    }
+}
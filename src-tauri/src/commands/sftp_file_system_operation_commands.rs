@@ -1,64 +1,155 @@
 use std::io::{Read, Write};
-use ssh2::{Session, Sftp};
-use std::net::TcpStream;
+use ssh2::Sftp;
 use std::path::Path;
 use std::fs;
+use std::sync::{Arc, Mutex};
+use tauri::State;
 use crate::models::SFTPDirectory;
 use crate::commands::preview_commands::PreviewPayload;
+use crate::sftp::auth::SftpAuth;
+use crate::sftp::host_keys::HostKeyInfo;
+use crate::state::sftp_session_data::{self, SftpSessionId, SftpSessionState};
 use base64::Engine;
 
-fn connect_to_sftp_via_password(
+/// Connects to an SFTP server, or reuses the open session for the same server, user and
+/// authentication. The returned session id is passed to every other SFTP command.
+/// The host key of the server must be trusted first, see `check_sftp_host_key`.
+///
+/// # Arguments
+/// * `host` - The hostname, IP address or an alias of `~/.ssh/config`
+/// * `port` - The port of the server, defaults to the one of `~/.ssh/config` or 22
+/// * `username` - The user to log in as, defaults to the one of `~/.ssh/config`
+/// * `auth` - How to log in: password, public key, ssh-agent or keyboard-interactive
+///
+/// # Returns
+/// * `Ok(SftpSessionId)` - The id of the session
+/// * `Err(String)` - If the server cannot be reached, its host key is not trusted or authentication fails
+///
+/// # Example
+/// ```rust
+/// let auth = SftpAuth::PublicKey { private_key_path: None, passphrase: None };
+/// let session_id = connect_to_sftp("nas".to_string(), None, None, auth, state)?;
+/// let listing = load_dir(session_id, ".".to_string(), state)?;
+/// ```
+#[tauri::command]
+pub fn connect_to_sftp(
     host: String,
-    port: u16,
-    username: String,
-    password: String,
-) -> Result<Sftp, String> {
-    // Create the TCP connection string
-    let connection_string = format!("{}:{}", host, port);
-    // Connect to the SSH server
-    let tcp = TcpStream::connect(connection_string).map_err(|e| e.to_string())?;
-    let mut session = Session::new().map_err(|_| "Could not initialize session".to_string())?;
-    session.set_tcp_stream(tcp);
-    session.handshake().map_err(|e| e.to_string())?;
-
-    // Generates a unique SFTP destination path by appending a number if the path already exists on the remote server.
-    // For example: "file.txt" -> "file (1).txt" -> "file (2).txt"
-    // For directories: "folder" -> "folder (1)" -> "folder (2)"
-    // Authenticate
-    session.userauth_password(&username, &password).map_err(|e| e.to_string())?;
-    
-    // Check if authentication was successful
-    if !session.authenticated() {
-        return Err("Authentication failed".to_string());
-    }
-    
-    // Open an SFTP session
-    session.sftp().map_err(|e| e.to_string()).map_err(|e| e.to_string())
+    port: Option<u16>,
+    username: Option<String>,
+    auth: SftpAuth,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<SftpSessionId, String> {
+    connect_to_sftp_impl(host, port, username, auth, state.inner().clone())
+}
+
+pub fn connect_to_sftp_impl(
+    host: String,
+    port: Option<u16>,
+    username: Option<String>,
+    auth: SftpAuth,
+    pool: Arc<Mutex<SftpSessionState>>,
+) -> Result<SftpSessionId, String> {
+    sftp_session_data::connect_session(&pool, host, port, username, auth)
 }
 
-#[allow(dead_code)]
+/// Connects to an SFTP server without logging in and compares its host key to
+/// `~/.ssh/known_hosts`. An unknown key should be shown to the user, and passed to
+/// `trust_sftp_host_key` if they accept it.
+///
+/// # Arguments
+/// * `host` - The hostname, IP address or an alias of `~/.ssh/config`
+/// * `port` - The port of the server, defaults to the one of `~/.ssh/config` or 22
+///
+/// # Returns
+/// * `Ok(HostKeyInfo)` - The key type, fingerprint and whether the key is trusted, unknown or changed
+/// * `Err(String)` - If the server cannot be reached
 #[tauri::command]
-pub fn connect_to_sftp(
+pub fn check_sftp_host_key(
     host: String,
-    port: u16,
-    username: String,
-    password: String,
-) -> Result<Sftp, String> {
-    connect_to_sftp_via_password(host, port, username, password)
+    port: Option<u16>,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<HostKeyInfo, String> {
+    check_sftp_host_key_impl(host, port, state.inner().clone())
 }
 
+pub fn check_sftp_host_key_impl(
+    host: String,
+    port: Option<u16>,
+    pool: Arc<Mutex<SftpSessionState>>,
+) -> Result<HostKeyInfo, String> {
+    sftp_session_data::check_host_key(&pool, host, port)
+}
+
+/// Adds the host key of an SFTP server to `~/.ssh/known_hosts`. The fingerprint is the one
+/// the user accepted, so a key that changed in the meantime is not trusted by accident.
+/// A changed key is never replaced, its old entry has to be removed by hand.
+///
+/// # Arguments
+/// * `host` - The hostname, IP address or an alias of `~/.ssh/config`
+/// * `port` - The port of the server, defaults to the one of `~/.ssh/config` or 22
+/// * `fingerprint` - The fingerprint returned by `check_sftp_host_key`
+///
+/// # Returns
+/// * `Ok(HostKeyInfo)` - The key, now trusted
+/// * `Err(String)` - If the server presents another key, the key changed or known_hosts cannot be written
 #[tauri::command]
-pub fn load_dir(
+pub fn trust_sftp_host_key(
+    host: String,
+    port: Option<u16>,
+    fingerprint: String,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<HostKeyInfo, String> {
+    trust_sftp_host_key_impl(host, port, fingerprint, state.inner().clone())
+}
+
+pub fn trust_sftp_host_key_impl(
     host: String,
-    port: u16,
-    username: String,
-    password: String,
+    port: Option<u16>,
+    fingerprint: String,
+    pool: Arc<Mutex<SftpSessionState>>,
+) -> Result<HostKeyInfo, String> {
+    sftp_session_data::trust_host_key(&pool, host, port, &fingerprint)
+}
+
+/// Closes an SFTP session. Commands using the session afterwards fail until
+/// `connect_to_sftp` is called again.
+///
+/// # Arguments
+/// * `session_id` - The id returned by `connect_to_sftp`
+///
+/// # Returns
+/// * `Ok(())` - If the session was closed
+/// * `Err(String)` - If there is no such session
+#[tauri::command]
+pub fn disconnect_sftp(
+    session_id: SftpSessionId,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<(), String> {
+    disconnect_sftp_impl(session_id, state.inner().clone())
+}
+
+pub fn disconnect_sftp_impl(session_id: SftpSessionId, pool: Arc<Mutex<SftpSessionState>>) -> Result<(), String> {
+    sftp_session_data::disconnect_session(&pool, session_id)
+}
+
+#[tauri::command]
+pub fn load_dir(
+    session_id: SftpSessionId,
     directory: String,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<String, String> {
+    load_dir_impl(session_id, directory, state.inner().clone())
+}
+
+pub fn load_dir_impl(
+    session_id: SftpSessionId,
+    directory: String,
+    pool: Arc<Mutex<SftpSessionState>>,
 ) -> Result<String, String> {
-    let sftp = connect_to_sftp_via_password(host, port, username, password)?;
-    
     // Read the directory entries
-    let entries = sftp.readdir(&directory).map_err(|e| e.to_string())?;
+    let entries = sftp_session_data::with_session_retrying(&pool, session_id, |sftp| {
+        sftp.readdir(&directory).map_err(|e| e.to_string())
+    })?;
     
     // Convert entries to SFTPDirectory format
     let files: Vec<String> = entries.iter()
@@ -93,177 +184,231 @@ pub fn load_dir(
 
 #[tauri::command]
 pub fn open_file_sftp(
-    host: String,
-    port: u16,
-    username: String,
-    password: String,
+    session_id: SftpSessionId,
     file_path: String,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
 ) -> Result<String, String> {
-    let sftp = connect_to_sftp_via_password(host, port, username, password)?;
-    
-    // Open the file
-    let mut file = sftp.open(&file_path).map_err(|e| e.to_string())?;
-    
-    // Read the file content
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).map_err(|e| e.to_string())?;
-    
-    Ok(contents)
+    open_file_sftp_impl(session_id, file_path, state.inner().clone())
+}
+
+pub fn open_file_sftp_impl(
+    session_id: SftpSessionId,
+    file_path: String,
+    pool: Arc<Mutex<SftpSessionState>>,
+) -> Result<String, String> {
+    sftp_session_data::with_session_retrying(&pool, session_id, |sftp| {
+        // Open the file
+        let mut file = sftp.open(&file_path).map_err(|e| e.to_string())?;
+        
+        // Read the file content
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).map_err(|e| e.to_string())?;
+        
+        Ok(contents)
+    })
 }
 
 #[tauri::command]
 pub fn create_file_sftp(
-    host: String,
-    port: u16,
-    username: String,
-    password: String,
+    session_id: SftpSessionId,
     file_path: String,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<String, String> {
+    create_file_sftp_impl(session_id, file_path, state.inner().clone())
+}
+
+pub fn create_file_sftp_impl(
+    session_id: SftpSessionId,
+    file_path: String,
+    pool: Arc<Mutex<SftpSessionState>>,
 ) -> Result<String, String> {
-    let sftp = connect_to_sftp_via_password(host, port, username, password)?;
-    
     // Create the file
-    sftp.create(file_path.as_ref()).map_err(|e| e.to_string())?;
+    sftp_session_data::with_session(&pool, session_id, |sftp| {
+        sftp.create(file_path.as_ref()).map(|_| ()).map_err(|e| e.to_string())
+    })?;
     
     Ok(format!("File created at: {}", file_path))
 }
 
 #[tauri::command]
 pub fn delete_file_sftp(
-    host: String,
-    port: u16,
-    username: String,
-    password: String,
+    session_id: SftpSessionId,
     file_path: String,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<String, String> {
+    delete_file_sftp_impl(session_id, file_path, state.inner().clone())
+}
+
+pub fn delete_file_sftp_impl(
+    session_id: SftpSessionId,
+    file_path: String,
+    pool: Arc<Mutex<SftpSessionState>>,
 ) -> Result<String, String> {
-    let sftp = connect_to_sftp_via_password(host, port, username, password)?;
-    
     // Delete the file
-    sftp.unlink(file_path.as_ref()).map_err(|e| e.to_string())?;
+    sftp_session_data::with_session(&pool, session_id, |sftp| {
+        sftp.unlink(file_path.as_ref()).map_err(|e| e.to_string())
+    })?;
     
     Ok(format!("File deleted at: {}", file_path))
 }
 
 #[tauri::command]
 pub fn rename_file_sftp(
-    host: String,
-    port: u16,
-    username: String,
-    password: String,
+    session_id: SftpSessionId,
     old_path: String,
     new_path: String,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<String, String> {
+    rename_file_sftp_impl(session_id, old_path, new_path, state.inner().clone())
+}
+
+pub fn rename_file_sftp_impl(
+    session_id: SftpSessionId,
+    old_path: String,
+    new_path: String,
+    pool: Arc<Mutex<SftpSessionState>>,
 ) -> Result<String, String> {
-    let sftp = connect_to_sftp_via_password(host, port, username, password)?;
-    
     // Rename the file
-    sftp.rename(old_path.as_ref(), new_path.as_ref(), None).map_err(|e| e.to_string())?;
+    sftp_session_data::with_session(&pool, session_id, |sftp| {
+        sftp.rename(old_path.as_ref(), new_path.as_ref(), None).map_err(|e| e.to_string())
+    })?;
     
     Ok(format!("File renamed from {} to {}", old_path, new_path))
 }
 
+/// Copies a file on the server through this machine, as SFTP has no copy operation.
+fn copy_file_on(sftp: &Sftp, source_path: &Path, destination_path: &Path) -> Result<(), String> {
+    let mut source_file = sftp.open(source_path).map_err(|e| e.to_string())?;
+    let mut destination_file = sftp.create(destination_path).map_err(|e| e.to_string())?;
+    
+    let mut buffer = Vec::new();
+    source_file.read_to_end(&mut buffer).map_err(|e| e.to_string())?;
+    destination_file.write_all(&buffer).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn copy_file_sftp(
-    host: String,
-    port: u16,
-    username: String,
-    password: String,
+    session_id: SftpSessionId,
     source_path: String,
     destination_path: String,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<String, String> {
+    copy_file_sftp_impl(session_id, source_path, destination_path, state.inner().clone())
+}
+
+pub fn copy_file_sftp_impl(
+    session_id: SftpSessionId,
+    source_path: String,
+    destination_path: String,
+    pool: Arc<Mutex<SftpSessionState>>,
 ) -> Result<String, String> {
-    let sftp = connect_to_sftp_via_password(host, port, username, password)?;
-    
     // Copy the file
-    let mut source_file = sftp.open(&source_path).map_err(|e| e.to_string())?;
-    let mut destination_file = sftp.create(destination_path.as_ref()).map_err(|e| e.to_string())?;
-    
-    let mut buffer = Vec::new();
-    source_file.read_to_end(&mut buffer).map_err(|e| e.to_string())?;
-    destination_file.write_all(&buffer).map_err(|e| e.to_string())?;
+    sftp_session_data::with_session(&pool, session_id, |sftp| {
+        copy_file_on(sftp, source_path.as_ref(), destination_path.as_ref())
+    })?;
     
     Ok(format!("File copied from {} to {}", source_path, destination_path))
 }
 
 #[tauri::command]
 pub fn move_file_sftp(
-    host: String,
-    port: u16,
-    username: String,
-    password: String,
+    session_id: SftpSessionId,
     source_path: String,
     destination_path: String,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<String, String> {
+    move_file_sftp_impl(session_id, source_path, destination_path, state.inner().clone())
+}
+
+pub fn move_file_sftp_impl(
+    session_id: SftpSessionId,
+    source_path: String,
+    destination_path: String,
+    pool: Arc<Mutex<SftpSessionState>>,
 ) -> Result<String, String> {
-    let sftp = connect_to_sftp_via_password(host, port, username, password)?;
-    
     // Move the file
-    sftp.rename(source_path.as_ref(), destination_path.as_ref(), None).map_err(|e| e.to_string())?;
+    sftp_session_data::with_session(&pool, session_id, |sftp| {
+        sftp.rename(source_path.as_ref(), destination_path.as_ref(), None).map_err(|e| e.to_string())
+    })?;
     
     Ok(format!("File moved from {} to {}", source_path, destination_path))
 }
 
 #[tauri::command]
 pub fn create_directory_sftp(
-    host: String,
-    port: u16,
-    username: String,
-    password: String,
+    session_id: SftpSessionId,
     directory_path: String,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<String, String> {
+    create_directory_sftp_impl(session_id, directory_path, state.inner().clone())
+}
+
+pub fn create_directory_sftp_impl(
+    session_id: SftpSessionId,
+    directory_path: String,
+    pool: Arc<Mutex<SftpSessionState>>,
 ) -> Result<String, String> {
-    let sftp = connect_to_sftp_via_password(host, port, username, password)?;
-    
     // Create the directory
-    sftp.mkdir(directory_path.as_ref(), 0o755).map_err(|e| e.to_string())?;
+    sftp_session_data::with_session(&pool, session_id, |sftp| {
+        sftp.mkdir(directory_path.as_ref(), 0o755).map_err(|e| e.to_string())
+    })?;
     
     Ok(format!("Directory created at: {}", directory_path))
 }
 
 #[tauri::command]
 pub fn delete_directory_sftp(
-    host: String,
-    port: u16,
-    username: String,
-    password: String,
+    session_id: SftpSessionId,
     directory_path: String,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<String, String> {
+    delete_directory_sftp_impl(session_id, directory_path, state.inner().clone())
+}
+
+pub fn delete_directory_sftp_impl(
+    session_id: SftpSessionId,
+    directory_path: String,
+    pool: Arc<Mutex<SftpSessionState>>,
 ) -> Result<String, String> {
-    let sftp = connect_to_sftp_via_password(host, port, username, password)?;
-    
     // Delete the directory
-    sftp.rmdir(directory_path.as_ref()).map_err(|e| e.to_string())?;
+    sftp_session_data::with_session(&pool, session_id, |sftp| {
+        sftp.rmdir(directory_path.as_ref()).map_err(|e| e.to_string())
+    })?;
     
     Ok(format!("Directory deleted at: {}", directory_path))
 }
 
 #[tauri::command]
 pub fn rename_directory_sftp(
-    host: String,
-    port: u16,
-    username: String,
-    password: String,
+    session_id: SftpSessionId,
     old_path: String,
     new_path: String,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<String, String> {
+    rename_directory_sftp_impl(session_id, old_path, new_path, state.inner().clone())
+}
+
+pub fn rename_directory_sftp_impl(
+    session_id: SftpSessionId,
+    old_path: String,
+    new_path: String,
+    pool: Arc<Mutex<SftpSessionState>>,
 ) -> Result<String, String> {
-    let sftp = connect_to_sftp_via_password(host, port, username, password)?;
-    
     // Rename the directory
-    sftp.rename(old_path.as_ref(), new_path.as_ref(), None).map_err(|e| e.to_string())?;
+    sftp_session_data::with_session(&pool, session_id, |sftp| {
+        sftp.rename(old_path.as_ref(), new_path.as_ref(), None).map_err(|e| e.to_string())
+    })?;
     
     Ok(format!("Directory renamed from {} to {}", old_path, new_path))
 }
 
-#[tauri::command]
-pub fn copy_directory_sftp(
-    host: String,
-    port: u16,
-    username: String,
-    password: String,
-    source_path: String,
-    destination_path: String,
-) -> Result<String, String> {
-    let sftp = connect_to_sftp_via_password(host.clone(), port, username.clone(), password.clone())?;
-    
+/// Copies a directory with everything in it, over the one session.
+fn copy_directory_on(sftp: &Sftp, source_path: &Path, destination_path: &str) -> Result<(), String> {
     // Create the destination directory
     sftp.mkdir(destination_path.as_ref(), 0o755).map_err(|e| e.to_string())?;
     
     // Read the source directory entries
-    let entries = sftp.readdir(&source_path).map_err(|e| e.to_string())?;
+    let entries = sftp.readdir(source_path).map_err(|e| e.to_string())?;
     
     for (path, stat) in entries {
         let file_name = path.file_name()
@@ -272,36 +417,57 @@ pub fn copy_directory_sftp(
         let new_path = format!("{}/{}", destination_path, file_name);
         
         if stat.is_file() {
-            // Copy file
-            let mut source_file = sftp.open(&path).map_err(|e| e.to_string())?;
-            let mut destination_file = sftp.create(new_path.as_ref()).map_err(|e| e.to_string())?;
-            
-            let mut buffer = Vec::new();
-            source_file.read_to_end(&mut buffer).map_err(|e| e.to_string())?;
-            destination_file.write_all(&buffer).map_err(|e| e.to_string())?;
+            copy_file_on(sftp, &path, new_path.as_ref())?;
         } else if stat.is_dir() {
-            // Recursively copy directory
-            let path_str = path.to_str().unwrap_or("[invalid_path]").to_string();
-            copy_directory_sftp(host.clone(), port, username.clone(), password.clone(), path_str, new_path)?;
+            copy_directory_on(sftp, &path, &new_path)?;
         }
     }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn copy_directory_sftp(
+    session_id: SftpSessionId,
+    source_path: String,
+    destination_path: String,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<String, String> {
+    copy_directory_sftp_impl(session_id, source_path, destination_path, state.inner().clone())
+}
+
+pub fn copy_directory_sftp_impl(
+    session_id: SftpSessionId,
+    source_path: String,
+    destination_path: String,
+    pool: Arc<Mutex<SftpSessionState>>,
+) -> Result<String, String> {
+    sftp_session_data::with_session(&pool, session_id, |sftp| {
+        copy_directory_on(sftp, source_path.as_ref(), &destination_path)
+    })?;
     
     Ok(format!("Directory copied from {} to {}", source_path, destination_path))
 }
 
 #[tauri::command]
 pub fn move_directory_sftp(
-    host: String,
-    port: u16,
-    username: String,
-    password: String,
+    session_id: SftpSessionId,
     source_path: String,
     destination_path: String,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<String, String> {
+    move_directory_sftp_impl(session_id, source_path, destination_path, state.inner().clone())
+}
+
+pub fn move_directory_sftp_impl(
+    session_id: SftpSessionId,
+    source_path: String,
+    destination_path: String,
+    pool: Arc<Mutex<SftpSessionState>>,
 ) -> Result<String, String> {
-    let sftp = connect_to_sftp_via_password(host, port, username, password)?;
-    
     // Move the directory
-    sftp.rename(source_path.as_ref(), destination_path.as_ref(), None).map_err(|e| e.to_string())?;
+    sftp_session_data::with_session(&pool, session_id, |sftp| {
+        sftp.rename(source_path.as_ref(), destination_path.as_ref(), None).map_err(|e| e.to_string())
+    })?;
     
     Ok(format!("Directory moved from {} to {}", source_path, destination_path))
 }
@@ -360,13 +526,22 @@ fn read_sftp_prefix(sftp: &Sftp, path: &str, max_bytes: usize) -> Result<Vec<u8>
 
 #[tauri::command]
 pub fn build_preview_sftp(
-    host: String,
-    port: u16,
-    username: String,
-    password: String,
+    session_id: SftpSessionId,
     file_path: String,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
 ) -> Result<PreviewPayload, String> {
-    let sftp = connect_to_sftp_via_password(host, port, username, password)?;
+    build_preview_sftp_impl(session_id, file_path, state.inner().clone())
+}
+
+pub fn build_preview_sftp_impl(
+    session_id: SftpSessionId,
+    file_path: String,
+    pool: Arc<Mutex<SftpSessionState>>,
+) -> Result<PreviewPayload, String> {
+    sftp_session_data::with_session_retrying(&pool, session_id, |sftp| build_preview_on(sftp, &file_path))
+}
+
+fn build_preview_on(sftp: &Sftp, file_path: &str) -> Result<PreviewPayload, String> {
     let name = filename_from_path(&file_path);
     
     // Get file stats to check if it's a directory or file
@@ -415,7 +590,7 @@ pub fn build_preview_sftp(
     // Files
     let bytes = stat.size.unwrap_or(0) as usize;
     // Read a small head for detection + maybe text
-    let head = read_sftp_prefix(&sftp, &file_path, 256 * 1024).map_err(|e| e.to_string())?;
+    let head = read_sftp_prefix(sftp, file_path, 256 * 1024).map_err(|e| e.to_string())?;
     let mime = detect_mime_sftp(&file_path, &head).unwrap_or("application/octet-stream");
     
     // Branch by mime top-level type - exactly like original
@@ -481,15 +656,20 @@ pub fn build_preview_sftp(
 
 #[tauri::command]
 pub fn download_and_open_sftp_file(
-    host: String,
-    port: u16,
-    username: String,
-    password: String,
+    session_id: SftpSessionId,
     file_path: String,
     open_file: Option<bool>,
+    state: State<'_, Arc<Mutex<SftpSessionState>>>,
+) -> Result<String, String> {
+    download_and_open_sftp_file_impl(session_id, file_path, open_file, state.inner().clone())
+}
+
+pub fn download_and_open_sftp_file_impl(
+    session_id: SftpSessionId,
+    file_path: String,
+    open_file: Option<bool>,
+    pool: Arc<Mutex<SftpSessionState>>,
 ) -> Result<String, String> {
-    let sftp = connect_to_sftp_via_password(host, port, username, password)?;
-    
     // Get the filename from the path
     let filename = filename_from_path(&file_path);
     
@@ -503,11 +683,13 @@ pub fn download_and_open_sftp_file(
     let temp_file_path = temp_dir.join(&filename);
     
     // Download the file from SFTP
-    let mut remote_file = sftp.open(Path::new(&file_path)).map_err(|e| e.to_string())?;
-    let mut local_file = fs::File::create(&temp_file_path).map_err(|e| e.to_string())?;
-    
-    // Copy the file content
-    std::io::copy(&mut remote_file, &mut local_file).map_err(|e| e.to_string())?;
+    sftp_session_data::with_session_retrying(&pool, session_id, |sftp| {
+        let mut remote_file = sftp.open(Path::new(&file_path)).map_err(|e| e.to_string())?;
+        let mut local_file = fs::File::create(&temp_file_path).map_err(|e| e.to_string())?;
+        
+        // Copy the file content
+        std::io::copy(&mut remote_file, &mut local_file).map(|_| ()).map_err(|e| e.to_string())
+    })?;
     
     // Only open the file if explicitly requested (default is true for backward compatibility)
     let should_open = open_file.unwrap_or(true);
@@ -584,6 +766,7 @@ pub fn cleanup_sftp_temp_files() -> Result<String, String> {
 #[cfg(feature = "sftp-tests")]
 mod sftp_file_system_operation_commands_tests {
     use super::*;
+    use crate::sftp::host_keys::HostKeyStatus;
 
     // Test data
     const TEST_HOST: &str = "localhost";
@@ -599,13 +782,47 @@ mod sftp_file_system_operation_commands_tests {
         "This is a test file content for SFTP operations."
     }
 
+    fn password(password: &str) -> SftpAuth {
+        SftpAuth::Password { password: password.to_string() }
+    }
+
+    /// A pool with its own known hosts, so the tests leave `~/.ssh` alone
+    fn pool() -> Arc<Mutex<SftpSessionState>> {
+        let ssh_dir = std::env::temp_dir().join("file_explorer_sftp_tests_ssh");
+        Arc::new(Mutex::new(SftpSessionState::with_ssh_dir(ssh_dir)))
+    }
+
+    /// A pool that trusts the host key of the test server
+    fn trusted_pool() -> Arc<Mutex<SftpSessionState>> {
+        let pool = pool();
+        let info = check_sftp_host_key_impl(TEST_HOST.to_string(), Some(TEST_PORT), pool.clone())
+            .expect("Should read the host key of the SFTP server");
+        trust_sftp_host_key_impl(TEST_HOST.to_string(), Some(TEST_PORT), info.fingerprint, pool.clone())
+            .expect("Should trust the host key of the SFTP server");
+        pool
+    }
+
+    fn connect() -> (Arc<Mutex<SftpSessionState>>, SftpSessionId) {
+        let pool = trusted_pool();
+        let session_id = connect_to_sftp_impl(
+            TEST_HOST.to_string(),
+            Some(TEST_PORT),
+            Some(TEST_USERNAME.to_string()),
+            password(TEST_PASSWORD),
+            pool.clone(),
+        )
+        .expect("Should connect to the SFTP server");
+        (pool, session_id)
+    }
+
     #[test]
     fn test_connect_to_sftp_via_password_success() {
-        let result = connect_to_sftp_via_password(
+        let result = connect_to_sftp_impl(
             TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+            Some(TEST_PORT),
+            Some(TEST_USERNAME.to_string()),
+            password(TEST_PASSWORD),
+            trusted_pool(),
         );
         
         assert!(result.is_ok(), "Should successfully connect to SFTP server");
@@ -613,11 +830,12 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_connect_to_sftp_via_password_failure_wrong_password() {
-        let result = connect_to_sftp_via_password(
+        let result = connect_to_sftp_impl(
             TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_WRONG_PASSWORD.to_string(),
+            Some(TEST_PORT),
+            Some(TEST_USERNAME.to_string()),
+            password(TEST_WRONG_PASSWORD),
+            trusted_pool(),
         );
         
         assert!(result.is_err(), "Should fail with wrong password");
@@ -625,11 +843,12 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_connect_to_sftp_via_password_failure_wrong_host() {
-        let result = connect_to_sftp_via_password(
+        let result = connect_to_sftp_impl(
             TEST_WRONG_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+            Some(TEST_PORT),
+            Some(TEST_USERNAME.to_string()),
+            password(TEST_PASSWORD),
+            pool(),
         );
         
         assert!(result.is_err(), "Should fail with wrong host");
@@ -637,23 +856,101 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_connect_to_sftp_success() {
-        let result = connect_to_sftp(
+        let (pool, session_id) = connect();
+        let result = connect_to_sftp_impl(
             TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+            Some(TEST_PORT),
+            Some(TEST_USERNAME.to_string()),
+            password(TEST_PASSWORD),
+            pool.clone(),
         );
         
-        assert!(result.is_ok(), "Should successfully connect to SFTP server");
+        assert_eq!(result, Ok(session_id), "Should reuse the open session");
+    }
+
+    #[test]
+    fn test_untrusted_host_key_is_refused() {
+        let ssh_dir = tempfile::tempdir().unwrap();
+        let pool = Arc::new(Mutex::new(SftpSessionState::with_ssh_dir(ssh_dir.path().to_path_buf())));
+        
+        let info = check_sftp_host_key_impl(TEST_HOST.to_string(), Some(TEST_PORT), pool.clone())
+            .expect("Should read the host key without logging in");
+        assert_eq!(info.status, HostKeyStatus::Unknown);
+        
+        let result = connect_to_sftp_impl(
+            TEST_HOST.to_string(),
+            Some(TEST_PORT),
+            Some(TEST_USERNAME.to_string()),
+            password(TEST_PASSWORD),
+            pool.clone(),
+        );
+        assert!(result.unwrap_err().contains("not trusted"), "Should refuse an unknown host key");
+        
+        let result = trust_sftp_host_key_impl(TEST_HOST.to_string(), Some(TEST_PORT), "SHA256:wrong".to_string(), pool.clone());
+        assert!(result.is_err(), "Should not trust a key with another fingerprint");
+        
+        let trusted = trust_sftp_host_key_impl(TEST_HOST.to_string(), Some(TEST_PORT), info.fingerprint.clone(), pool.clone())
+            .expect("Should trust the confirmed key");
+        assert_eq!(trusted.status, HostKeyStatus::Trusted);
+        assert_eq!(
+            check_sftp_host_key_impl(TEST_HOST.to_string(), Some(TEST_PORT), pool.clone()).unwrap().status,
+            HostKeyStatus::Trusted
+        );
+        
+        let result = connect_to_sftp_impl(
+            TEST_HOST.to_string(),
+            Some(TEST_PORT),
+            Some(TEST_USERNAME.to_string()),
+            password(TEST_PASSWORD),
+            pool.clone(),
+        );
+        assert!(result.is_ok(), "Should connect once the key is trusted");
+    }
+
+    #[test]
+    fn test_connect_to_sftp_via_ssh_config_alias() {
+        let pool = trusted_pool();
+        let ssh_dir = std::env::temp_dir().join("file_explorer_sftp_tests_ssh");
+        std::fs::write(
+            ssh_dir.join("config"),
+            format!("Host test-server\n  HostName {}\n  Port {}\n  User {}\n", TEST_HOST, TEST_PORT, TEST_USERNAME),
+        )
+        .unwrap();
+        
+        let result = connect_to_sftp_impl("test-server".to_string(), None, None, password(TEST_PASSWORD), pool.clone());
+        assert!(result.is_ok(), "Should connect to the host of the alias");
+    }
+
+    #[test]
+    fn test_connect_to_sftp_via_keyboard_interactive() {
+        let result = connect_to_sftp_impl(
+            TEST_HOST.to_string(),
+            Some(TEST_PORT),
+            Some(TEST_USERNAME.to_string()),
+            SftpAuth::KeyboardInteractive { responses: vec![TEST_WRONG_PASSWORD.to_string()] },
+            trusted_pool(),
+        );
+        
+        assert!(result.is_err(), "Should fail with a wrong response");
+    }
+
+    #[test]
+    fn test_disconnect_sftp() {
+        let (pool, session_id) = connect();
+        
+        assert!(disconnect_sftp_impl(session_id, pool.clone()).is_ok(), "Should disconnect the session");
+        assert!(load_dir_impl(session_id, ".".to_string(), pool.clone()).is_err(), "Should fail after disconnecting");
+        assert!(disconnect_sftp_impl(session_id, pool.clone()).is_err(), "Should fail for a closed session");
     }
 
     #[test]
     fn test_connect_to_sftp_failure() {
-        let result = connect_to_sftp(
+        let result = connect_to_sftp_impl(
             TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_WRONG_PASSWORD.to_string(),
+            Some(TEST_PORT),
+            Some(TEST_USERNAME.to_string()),
+            password(TEST_WRONG_PASSWORD),
+            trusted_pool(),
         );
         
         assert!(result.is_err(), "Should fail with wrong credentials");
@@ -661,12 +958,11 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_load_dir_success() {
-        let result = load_dir(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let (pool, session_id) = connect();
+        let result = load_dir_impl(
+            session_id,
             ".".to_string(),
+            pool.clone(),
         );
         
         match result {
@@ -685,12 +981,12 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_load_dir_failure_wrong_credentials() {
-        let result = load_dir(
+        let result = connect_to_sftp_impl(
             TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_WRONG_PASSWORD.to_string(),
-            ".".to_string(),
+            Some(TEST_PORT),
+            Some(TEST_USERNAME.to_string()),
+            password(TEST_WRONG_PASSWORD),
+            trusted_pool(),
         );
         
         assert!(result.is_err(), "Should fail with wrong credentials");
@@ -698,12 +994,11 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_load_dir_failure_nonexistent_directory() {
-        let result = load_dir(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let (pool, session_id) = connect();
+        let result = load_dir_impl(
+            session_id,
             "/nonexistent/directory".to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_err(), "Should fail with nonexistent directory");
@@ -711,36 +1006,33 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_create_file_sftp_success() {
+        let (pool, session_id) = connect();
         let test_file = "test_create_file.txt";
         
-        let result = create_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let result = create_file_sftp_impl(
+            session_id,
             test_file.to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_ok(), "Should successfully create file");
         
         // Clean up - delete the test file
-        let _ = delete_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let _ = delete_file_sftp_impl(
+            session_id,
             test_file.to_string(),
+            pool.clone(),
         );
     }
 
     #[test]
     fn test_create_file_sftp_failure() {
-        let result = create_file_sftp(
+        let result = connect_to_sftp_impl(
             TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_WRONG_PASSWORD.to_string(),
-            "test_file.txt".to_string(),
+            Some(TEST_PORT),
+            Some(TEST_USERNAME.to_string()),
+            password(TEST_WRONG_PASSWORD),
+            trusted_pool(),
         );
         
         assert!(result.is_err(), "Should fail with wrong credentials");
@@ -748,25 +1040,22 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_delete_file_sftp_success() {
+        let (pool, session_id) = connect();
         let test_file = "test_delete_file.txt";
         
         // First create a file
-        let create_result = create_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let create_result = create_file_sftp_impl(
+            session_id,
             test_file.to_string(),
+            pool.clone(),
         );
         assert!(create_result.is_ok(), "Should create test file first");
         
         // Then delete it
-        let result = delete_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let result = delete_file_sftp_impl(
+            session_id,
             test_file.to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_ok(), "Should successfully delete file");
@@ -774,12 +1063,11 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_delete_file_sftp_failure_nonexistent_file() {
-        let result = delete_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let (pool, session_id) = connect();
+        let result = delete_file_sftp_impl(
+            session_id,
             "nonexistent_file.txt".to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_err(), "Should fail with nonexistent file");
@@ -787,50 +1075,44 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_rename_file_sftp_success() {
+        let (pool, session_id) = connect();
         let original_file = "test_rename_original.txt";
         let renamed_file = "test_rename_new.txt";
         
         // First create a file
-        let create_result = create_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let create_result = create_file_sftp_impl(
+            session_id,
             original_file.to_string(),
+            pool.clone(),
         );
         assert!(create_result.is_ok(), "Should create test file first");
         
         // Then rename it
-        let result = rename_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let result = rename_file_sftp_impl(
+            session_id,
             original_file.to_string(),
             renamed_file.to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_ok(), "Should successfully rename file");
         
         // Clean up
-        let _ = delete_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let _ = delete_file_sftp_impl(
+            session_id,
             renamed_file.to_string(),
+            pool.clone(),
         );
     }
 
     #[test]
     fn test_rename_file_sftp_failure() {
-        let result = rename_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let (pool, session_id) = connect();
+        let result = rename_file_sftp_impl(
+            session_id,
             "nonexistent_file.txt".to_string(),
             "new_name.txt".to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_err(), "Should fail with nonexistent file");
@@ -838,57 +1120,49 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_copy_file_sftp_success() {
+        let (pool, session_id) = connect();
         let source_file = "test_copy_source.txt";
         let dest_file = "test_copy_dest.txt";
         
         // First create a source file
-        let create_result = create_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let create_result = create_file_sftp_impl(
+            session_id,
             source_file.to_string(),
+            pool.clone(),
         );
         assert!(create_result.is_ok(), "Should create source file first");
         
         // Then copy it
-        let result = copy_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let result = copy_file_sftp_impl(
+            session_id,
             source_file.to_string(),
             dest_file.to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_ok(), "Should successfully copy file");
         
         // Clean up
-        let _ = delete_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let _ = delete_file_sftp_impl(
+            session_id,
             source_file.to_string(),
+            pool.clone(),
         );
-        let _ = delete_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let _ = delete_file_sftp_impl(
+            session_id,
             dest_file.to_string(),
+            pool.clone(),
         );
     }
 
     #[test]
     fn test_copy_file_sftp_failure() {
-        let result = copy_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let (pool, session_id) = connect();
+        let result = copy_file_sftp_impl(
+            session_id,
             "nonexistent_source.txt".to_string(),
             "dest.txt".to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_err(), "Should fail with nonexistent source file");
@@ -896,50 +1170,44 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_move_file_sftp_success() {
+        let (pool, session_id) = connect();
         let source_file = "test_move_source.txt";
         let dest_file = "test_move_dest.txt";
         
         // First create a source file
-        let create_result = create_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let create_result = create_file_sftp_impl(
+            session_id,
             source_file.to_string(),
+            pool.clone(),
         );
         assert!(create_result.is_ok(), "Should create source file first");
         
         // Then move it
-        let result = move_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let result = move_file_sftp_impl(
+            session_id,
             source_file.to_string(),
             dest_file.to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_ok(), "Should successfully move file");
         
         // Clean up
-        let _ = delete_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let _ = delete_file_sftp_impl(
+            session_id,
             dest_file.to_string(),
+            pool.clone(),
         );
     }
 
     #[test]
     fn test_move_file_sftp_failure() {
-        let result = move_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let (pool, session_id) = connect();
+        let result = move_file_sftp_impl(
+            session_id,
             "nonexistent_file.txt".to_string(),
             "dest.txt".to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_err(), "Should fail with nonexistent file");
@@ -947,36 +1215,33 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_create_directory_sftp_success() {
+        let (pool, session_id) = connect();
         let test_dir = "test_create_directory";
         
-        let result = create_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let result = create_directory_sftp_impl(
+            session_id,
             test_dir.to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_ok(), "Should successfully create directory");
         
         // Clean up
-        let _ = delete_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let _ = delete_directory_sftp_impl(
+            session_id,
             test_dir.to_string(),
+            pool.clone(),
         );
     }
 
     #[test]
     fn test_create_directory_sftp_failure() {
-        let result = create_directory_sftp(
+        let result = connect_to_sftp_impl(
             TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_WRONG_PASSWORD.to_string(),
-            "test_dir".to_string(),
+            Some(TEST_PORT),
+            Some(TEST_USERNAME.to_string()),
+            password(TEST_WRONG_PASSWORD),
+            trusted_pool(),
         );
         
         assert!(result.is_err(), "Should fail with wrong credentials");
@@ -984,25 +1249,22 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_delete_directory_sftp_success() {
+        let (pool, session_id) = connect();
         let test_dir = "test_delete_directory";
         
         // First create a directory
-        let create_result = create_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let create_result = create_directory_sftp_impl(
+            session_id,
             test_dir.to_string(),
+            pool.clone(),
         );
         assert!(create_result.is_ok(), "Should create test directory first");
         
         // Then delete it
-        let result = delete_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let result = delete_directory_sftp_impl(
+            session_id,
             test_dir.to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_ok(), "Should successfully delete directory");
@@ -1010,12 +1272,11 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_delete_directory_sftp_failure() {
-        let result = delete_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let (pool, session_id) = connect();
+        let result = delete_directory_sftp_impl(
+            session_id,
             "nonexistent_directory".to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_err(), "Should fail with nonexistent directory");
@@ -1023,50 +1284,44 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_rename_directory_sftp_success() {
+        let (pool, session_id) = connect();
         let original_dir = "test_rename_dir_original";
         let renamed_dir = "test_rename_dir_new";
         
         // First create a directory
-        let create_result = create_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let create_result = create_directory_sftp_impl(
+            session_id,
             original_dir.to_string(),
+            pool.clone(),
         );
         assert!(create_result.is_ok(), "Should create test directory first");
         
         // Then rename it
-        let result = rename_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let result = rename_directory_sftp_impl(
+            session_id,
             original_dir.to_string(),
             renamed_dir.to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_ok(), "Should successfully rename directory");
         
         // Clean up
-        let _ = delete_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let _ = delete_directory_sftp_impl(
+            session_id,
             renamed_dir.to_string(),
+            pool.clone(),
         );
     }
 
     #[test]
     fn test_rename_directory_sftp_failure() {
-        let result = rename_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let (pool, session_id) = connect();
+        let result = rename_directory_sftp_impl(
+            session_id,
             "nonexistent_directory".to_string(),
             "new_name".to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_err(), "Should fail with nonexistent directory");
@@ -1074,50 +1329,44 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_move_directory_sftp_success() {
+        let (pool, session_id) = connect();
         let source_dir = "test_move_dir_source";
         let dest_dir = "test_move_dir_dest";
         
         // First create a source directory
-        let create_result = create_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let create_result = create_directory_sftp_impl(
+            session_id,
             source_dir.to_string(),
+            pool.clone(),
         );
         assert!(create_result.is_ok(), "Should create source directory first");
         
         // Then move it
-        let result = move_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let result = move_directory_sftp_impl(
+            session_id,
             source_dir.to_string(),
             dest_dir.to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_ok(), "Should successfully move directory");
         
         // Clean up
-        let _ = delete_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let _ = delete_directory_sftp_impl(
+            session_id,
             dest_dir.to_string(),
+            pool.clone(),
         );
     }
 
     #[test]
     fn test_move_directory_sftp_failure() {
-        let result = move_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let (pool, session_id) = connect();
+        let result = move_directory_sftp_impl(
+            session_id,
             "nonexistent_directory".to_string(),
             "dest_dir".to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_err(), "Should fail with nonexistent directory");
@@ -1125,57 +1374,49 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_copy_directory_sftp_success() {
+        let (pool, session_id) = connect();
         let source_dir = "test_copy_dir_source";
         let dest_dir = "test_copy_dir_dest";
         
         // First create a source directory
-        let create_result = create_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let create_result = create_directory_sftp_impl(
+            session_id,
             source_dir.to_string(),
+            pool.clone(),
         );
         assert!(create_result.is_ok(), "Should create source directory first");
         
         // Then copy it
-        let result = copy_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let result = copy_directory_sftp_impl(
+            session_id,
             source_dir.to_string(),
             dest_dir.to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_ok(), "Should successfully copy directory");
         
         // Clean up
-        let _ = delete_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let _ = delete_directory_sftp_impl(
+            session_id,
             source_dir.to_string(),
+            pool.clone(),
         );
-        let _ = delete_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let _ = delete_directory_sftp_impl(
+            session_id,
             dest_dir.to_string(),
+            pool.clone(),
         );
     }
 
     #[test]
     fn test_copy_directory_sftp_failure() {
-        let result = copy_directory_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let (pool, session_id) = connect();
+        let result = copy_directory_sftp_impl(
+            session_id,
             "nonexistent_directory".to_string(),
             "dest_dir".to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_err(), "Should fail with nonexistent directory");
@@ -1183,49 +1424,43 @@ mod sftp_file_system_operation_commands_tests {
 
     #[test]
     fn test_open_file_sftp_success() {
+        let (pool, session_id) = connect();
         // Test with an existing file - let's assume there's at least one file in the test directory
         // We'll create a file first, then read it
         let test_file = "test_read_file.txt";
         
         // First create a file
-        let create_result = create_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let create_result = create_file_sftp_impl(
+            session_id,
             test_file.to_string(),
+            pool.clone(),
         );
         assert!(create_result.is_ok(), "Should create test file first");
         
         // Then try to read it
-        let result = open_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let result = open_file_sftp_impl(
+            session_id,
             test_file.to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_ok(), "Should successfully read file");
         
         // Clean up
-        let _ = delete_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let _ = delete_file_sftp_impl(
+            session_id,
             test_file.to_string(),
+            pool.clone(),
         );
     }
 
     #[test]
     fn test_open_file_sftp_failure() {
-        let result = open_file_sftp(
-            TEST_HOST.to_string(),
-            TEST_PORT,
-            TEST_USERNAME.to_string(),
-            TEST_PASSWORD.to_string(),
+        let (pool, session_id) = connect();
+        let result = open_file_sftp_impl(
+            session_id,
             "nonexistent_file.txt".to_string(),
+            pool.clone(),
         );
         
         assert!(result.is_err(), "Should fail with nonexistent file");
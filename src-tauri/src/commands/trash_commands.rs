@@ -0,0 +1,169 @@
+use crate::filesystem::conflict_policy::ConflictPolicy;
+use crate::filesystem::trash::{self, RestoreOutcome, TrashEntry, TrashError};
+use crate::log_error;
+
+fn log_and_convert(action: &str, err: TrashError) -> String {
+    log_error!("Failed to {}: {:?}", action, err);
+    err.to_json()
+}
+
+/// Lists all items in the trash, most recently deleted first.
+/// Only supported on Linux.
+///
+/// # Returns
+/// - `Ok(Vec<TrashEntry>)` - The trashed items with their id, name, original path,
+///   deletion time (seconds since epoch) and size.
+/// - `Err(String)` - If the trash could not be read or the OS is not supported.
+///
+/// # Example
+/// ```rust
+/// let result = list_trash().await;
+/// match result {
+///     Ok(entries) => println!("{} items in the trash", entries.len()),
+///     Err(err) => println!("Error reading the trash: {}", err),
+/// }
+/// ```
+#[tauri::command]
+pub async fn list_trash() -> Result<Vec<TrashEntry>, String> {
+    trash::list_trash().map_err(|err| log_and_convert("list the trash", err))
+}
+
+/// Restores items from the trash to their original location.
+/// Missing parent directories are created again.
+///
+/// # Arguments
+/// - `ids` - The ids of the items as returned by `list_trash`.
+/// - `conflict_policy` - What to do if something exists at the original path: skip,
+///   overwrite (moves the existing item to the trash), overwrite_if_newer or rename (default).
+///   `Ask` is not supported.
+///
+/// # Returns
+/// - `Ok(Vec<RestoreOutcome>)` - One outcome per item with where it was restored to, how a
+///   conflict was resolved and the error if restoring it failed.
+/// - `Err(String)` - If an id is not in the trash, the policy is `Ask` or the OS is not supported.
+///   Nothing is restored in that case.
+///
+/// # Example
+/// ```rust
+/// let result = restore_trash_items(vec![entry.id], Some(ConflictPolicy::Skip)).await;
+/// match result {
+///     Ok(outcomes) => println!("Restored to {:?}", outcomes[0].restored_to),
+///     Err(err) => println!("Error restoring: {}", err),
+/// }
+/// ```
+#[tauri::command]
+pub async fn restore_trash_items(
+    ids: Vec<String>,
+    conflict_policy: Option<ConflictPolicy>,
+) -> Result<Vec<RestoreOutcome>, String> {
+    trash::restore_trash_items(&ids, conflict_policy.unwrap_or_default())
+        .map_err(|err| log_and_convert("restore from the trash", err))
+}
+
+/// Permanently deletes items from the trash.
+///
+/// # Arguments
+/// - `ids` - The ids of the items as returned by `list_trash`.
+///
+/// # Returns
+/// - `Ok(usize)` - The number of deleted items.
+/// - `Err(String)` - If an id is not in the trash, deleting failed or the OS is not supported.
+///   Nothing is deleted if an id is unknown.
+///
+/// # Example
+/// ```rust
+/// let result = purge_trash_items(vec![entry.id]).await;
+/// ```
+#[tauri::command]
+pub async fn purge_trash_items(ids: Vec<String>) -> Result<usize, String> {
+    trash::purge_trash_items(&ids).map_err(|err| log_and_convert("purge from the trash", err))
+}
+
+/// Permanently deletes everything in the trash.
+///
+/// # Returns
+/// - `Ok(usize)` - The number of deleted items.
+/// - `Err(String)` - If deleting failed or the OS is not supported.
+///
+/// # Example
+/// ```rust
+/// let result = empty_trash().await;
+/// ```
+#[tauri::command]
+pub async fn empty_trash() -> Result<usize, String> {
+    trash::empty_trash().map_err(|err| log_and_convert("empty the trash", err))
+}
+
+#[cfg(test)]
+#[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+mod tests_trash_commands {
+    use super::*;
+    use crate::filesystem::conflict_policy::ConflictChoice;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    /// Moves the file to the trash and returns its trash entry.
+    async fn trash_file(path: &Path) -> TrashEntry {
+        ::trash::delete(path).unwrap();
+        let original_path = path.to_string_lossy().to_string();
+        list_trash()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|entry| entry.original_path == original_path)
+            .expect("Trashed file should be listed")
+    }
+
+    #[tokio::test]
+    async fn list_and_restore_trash_items_test() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().canonicalize().unwrap().join("restore_test.txt");
+        fs::write(&file, "trashed").unwrap();
+
+        let entry = trash_file(&file).await;
+        assert_eq!(entry.name, "restore_test.txt");
+        assert!(!entry.is_dir);
+        assert_eq!(entry.size, Some(7));
+
+        // A new file took the original path in the meantime
+        fs::write(&file, "new").unwrap();
+        let skipped = restore_trash_items(vec![entry.id.clone()], Some(ConflictPolicy::Skip)).await.unwrap();
+        assert_eq!(skipped[0].conflict, Some(ConflictChoice::Skip));
+        assert_eq!(skipped[0].restored_to, None);
+
+        let renamed = restore_trash_items(vec![entry.id.clone()], None).await.unwrap();
+        let restored_to = file.with_file_name("restore_test (1).txt");
+        assert_eq!(renamed[0].conflict, Some(ConflictChoice::Rename));
+        assert_eq!(renamed[0].restored_to.as_deref(), restored_to.to_str());
+        assert_eq!(fs::read_to_string(&restored_to).unwrap(), "trashed");
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+
+        // The item left the trash
+        let result = restore_trash_items(vec![entry.id], None).await;
+        assert!(result.unwrap_err().contains("Not in the trash"));
+    }
+
+    #[tokio::test]
+    async fn restore_with_overwrite_test() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().canonicalize().unwrap().join("overwrite_test.txt");
+        fs::write(&file, "trashed").unwrap();
+        let entry = trash_file(&file).await;
+        fs::write(&file, "existing").unwrap();
+
+        let outcomes = restore_trash_items(vec![entry.id], Some(ConflictPolicy::Overwrite)).await.unwrap();
+        assert_eq!(outcomes[0].error, None);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "trashed");
+
+        // The replaced file went to the trash and can be purged from there
+        let replaced = list_trash()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|candidate| candidate.original_path == file.to_string_lossy())
+            .expect("Replaced file should be in the trash");
+        assert_eq!(purge_trash_items(vec![replaced.id.clone()]).await, Ok(1));
+        assert!(purge_trash_items(vec![replaced.id]).await.is_err());
+    }
+}
@@ -45,3 +45,26 @@ pub static SETTINGS_CONFIG_FILE_NAME: &str = "settings.json";
 pub static TEMPLATES_ABS_PATH_FOLDER: LazyLock<PathBuf> =
     LazyLock::new(|| CONFIG_PATH.join(TEMPLATES_FOLDER));
 pub static TEMPLATES_FOLDER: &str = "templates";
+
+pub static SEARCH_INDEX_ABS_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| CONFIG_PATH.join(SEARCH_INDEX_FILE_NAME));
+pub static SEARCH_INDEX_FILE_NAME: &str = "search_index.bin";
+
+pub static OPERATION_JOURNAL_ABS_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| CONFIG_PATH.join(OPERATION_JOURNAL_FILE_NAME));
+pub static OPERATION_JOURNAL_FILE_NAME: &str = "operation_journal.json";
+
+pub static SFTP_CONNECTIONS_ABS_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| CONFIG_PATH.join(SFTP_CONNECTIONS_FILE_NAME));
+pub static SFTP_CONNECTIONS_FILE_NAME: &str = "sftp_connections.json";
+
+pub static SFTP_SECRETS_ABS_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| CONFIG_PATH.join(SFTP_SECRETS_FILE_NAME));
+pub static SFTP_SECRETS_FILE_NAME: &str = "sftp_secrets.bin";
+
+pub static SFTP_SECRETS_KEY_ABS_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| CONFIG_PATH.join(SFTP_SECRETS_KEY_FILE_NAME));
+pub static SFTP_SECRETS_KEY_FILE_NAME: &str = "sftp_secrets.key";
+
+pub static SFTP_SECRETS_KEYRING_SERVICE: &str = "com.explr.app";
+pub static SFTP_SECRETS_KEYRING_USER: &str = "sftp-secrets";
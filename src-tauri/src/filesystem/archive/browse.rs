@@ -0,0 +1,439 @@
+//! Browsing archives like folders, without extracting them.
+//!
+//! A path inside an archive is written as the path of the archive followed by the path
+//! of the member, e.g. `/home/me/backup.tar.gz/photos/cat.jpg`. Listing a tar archive
+//! means decompressing it, so the entries of the last listed archive are cached until
+//! the archive changes.
+
+use super::{limit_error, open_detected, ArchiveEntry, ArchiveEntryKind, ArchiveFormat, ExtractOptions, SizeLimit};
+use crate::commands::file_system_operation_commands::generate_unique_path;
+use crate::log_warn;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::SystemTime;
+
+/// A path that points into an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivePath {
+    /// The archive file on disk
+    pub archive: PathBuf,
+    /// The member inside the archive, `/` separated, empty for the root of the archive
+    pub inner: String,
+}
+
+impl ArchivePath {
+    /// Splits a path into the archive it points into and the member inside it. The
+    /// longest part of the path that exists on disk has to be an archive file.
+    ///
+    /// # Returns
+    /// `None` if the path does not point into a supported archive
+    pub fn parse(path: &str) -> Option<Self> {
+        let path = Path::new(path);
+        let archive = path.ancestors().find(|ancestor| ancestor.exists())?;
+        if !archive.is_file() || !matches!(ArchiveFormat::detect(archive), Ok(Some(_))) {
+            return None;
+        }
+        let inner = path.strip_prefix(archive).ok()?.to_string_lossy().to_string();
+        Some(ArchivePath { archive: archive.to_path_buf(), inner: normalize(&inner) })
+    }
+
+    /// The path of a member of the same archive, as used by `parse`.
+    pub fn member_path(&self, inner: &str) -> String {
+        self.archive.join(inner).to_string_lossy().to_string()
+    }
+}
+
+/// Joins the usable components of a member path with `/`, dropping empty, `.` and `..`
+/// components. Members with unsafe paths are shown and extracted below the archive root.
+pub fn normalize(path: &str) -> String {
+    path.split(['/', '\\'])
+        .filter(|component| !component.is_empty() && *component != "." && *component != "..")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// A directory inside an archive, which may only be implied by the paths of its content.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveDirectory {
+    /// Normalized path inside the archive
+    pub path: String,
+    pub modified: Option<i64>,
+    pub mode: Option<u32>,
+    /// Number of direct subfiles
+    pub sub_file_count: usize,
+    /// Number of direct subdirectories
+    pub sub_dir_count: usize,
+}
+
+/// The direct children of a directory inside an archive, sorted by path.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArchiveListing {
+    pub directories: Vec<ArchiveDirectory>,
+    /// Files and symlinks, with normalized paths
+    pub files: Vec<ArchiveEntry>,
+}
+
+struct CachedEntries {
+    archive: PathBuf,
+    len: u64,
+    modified: Option<SystemTime>,
+    entries: Arc<Vec<ArchiveEntry>>,
+}
+
+static ENTRY_CACHE: LazyLock<Mutex<Option<CachedEntries>>> = LazyLock::new(|| Mutex::new(None));
+
+/// Lists all entries of an archive, with normalized paths. The result for the last
+/// archive is reused as long as its size and modification time stay the same.
+pub fn archive_entries(archive: &Path) -> io::Result<Arc<Vec<ArchiveEntry>>> {
+    let metadata = fs::metadata(archive)?;
+    let (len, modified) = (metadata.len(), metadata.modified().ok());
+    if let Ok(cache) = ENTRY_CACHE.lock() {
+        if let Some(cached) = cache.as_ref() {
+            if cached.archive == archive && cached.len == len && cached.modified == modified {
+                return Ok(cached.entries.clone());
+            }
+        }
+    }
+
+    let (_, mut reader) = open_detected(archive, None)?;
+    let entries: Arc<Vec<ArchiveEntry>> = Arc::new(
+        reader
+            .entries()?
+            .into_iter()
+            .map(|entry| ArchiveEntry { path: normalize(&entry.path), ..entry })
+            .filter(|entry| !entry.path.is_empty())
+            .collect(),
+    );
+    if let Ok(mut cache) = ENTRY_CACHE.lock() {
+        *cache = Some(CachedEntries { archive: archive.to_path_buf(), len, modified, entries: entries.clone() });
+    }
+    Ok(entries)
+}
+
+/// What the normalized `inner` path is in an archive. Directories count even if they
+/// have no entry of their own.
+///
+/// # Returns
+/// `None` if nothing in the archive has this path
+pub fn member_kind(entries: &[ArchiveEntry], inner: &str) -> Option<ArchiveEntryKind> {
+    if inner.is_empty() {
+        return Some(ArchiveEntryKind::Directory);
+    }
+    let prefix = format!("{}/", inner);
+    entries.iter().find_map(|entry| {
+        if entry.path == inner {
+            Some(entry.kind)
+        } else {
+            entry.path.starts_with(&prefix).then_some(ArchiveEntryKind::Directory)
+        }
+    })
+}
+
+/// Lists the direct children of the directory `inner` of an archive.
+///
+/// # Arguments
+/// * `entries` - All entries of the archive, as returned by `archive_entries`
+/// * `inner` - Normalized path of the directory, empty for the root
+///
+/// # Returns
+/// * `Ok(ArchiveListing)` - The subdirectories and files
+/// * `Err(io::Error)` - `NotFound` if there is no such directory in the archive
+pub fn list_directory(entries: &[ArchiveEntry], inner: &str) -> io::Result<ArchiveListing> {
+    if member_kind(entries, inner) != Some(ArchiveEntryKind::Directory) {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No directory '{}' in the archive", inner),
+        ));
+    }
+    let prefix = if inner.is_empty() { String::new() } else { format!("{}/", inner) };
+
+    let mut directories: BTreeMap<&str, ArchiveDirectory> = BTreeMap::new();
+    // Direct children of every subdirectory, to count them
+    let mut grandchildren: BTreeMap<&str, BTreeSet<(&str, bool)>> = BTreeMap::new();
+    let mut files = Vec::new();
+    for entry in entries {
+        let Some(rest) = entry.path.strip_prefix(&prefix) else {
+            continue;
+        };
+        let (name, below) = match rest.split_once('/') {
+            Some((name, below)) => (name, Some(below)),
+            None => (rest, None),
+        };
+        let is_directory = below.is_some() || entry.kind == ArchiveEntryKind::Directory;
+        if !is_directory {
+            if matches!(entry.kind, ArchiveEntryKind::File | ArchiveEntryKind::Symlink) {
+                files.push(entry.clone());
+            }
+            continue;
+        }
+
+        let directory = directories.entry(name).or_insert_with(|| ArchiveDirectory {
+            path: format!("{}{}", prefix, name),
+            modified: None,
+            mode: None,
+            sub_file_count: 0,
+            sub_dir_count: 0,
+        });
+        match below {
+            None => {
+                directory.modified = entry.modified;
+                directory.mode = entry.mode;
+            }
+            Some(below) => {
+                let (child, is_dir) = match below.split_once('/') {
+                    Some((child, _)) => (child, true),
+                    None => (below, entry.kind == ArchiveEntryKind::Directory),
+                };
+                grandchildren.entry(name).or_default().insert((child, is_dir));
+            }
+        }
+    }
+
+    for (name, children) in grandchildren {
+        if let Some(directory) = directories.get_mut(name) {
+            directory.sub_dir_count = children.iter().filter(|(_, is_dir)| *is_dir).count();
+            directory.sub_file_count = children.len() - directory.sub_dir_count;
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(ArchiveListing { directories: directories.into_values().collect(), files })
+}
+
+/// Reads the start of a file inside an archive.
+///
+/// # Arguments
+/// * `inner` - Normalized path of the file
+/// * `limit` - Maximum number of bytes to read
+///
+/// # Returns
+/// * `Ok((ArchiveEntry, Vec<u8>))` - The entry and up to `limit` bytes of its content
+/// * `Err(io::Error)` - `NotFound` if there is no such file in the archive
+pub fn read_member(archive: &Path, inner: &str, limit: u64) -> io::Result<(ArchiveEntry, Vec<u8>)> {
+    let (_, mut reader) = open_detected(archive, None)?;
+    let mut found = None;
+    reader.for_each_entry(&mut |entry, content| {
+        if entry.kind != ArchiveEntryKind::File || normalize(&entry.path) != inner {
+            return Ok(true);
+        }
+        let mut data = Vec::new();
+        content.take(limit).read_to_end(&mut data)?;
+        found = Some((entry.clone(), data));
+        Ok(false)
+    })?;
+    found.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("No file '{}' in the archive", inner)))
+}
+
+/// Extracts a single file or directory of an archive into `dest_dir`, under a unique name.
+/// Symlinks and special entries are skipped. If the extraction fails, everything it wrote
+/// is removed again.
+///
+/// Like `extract`, going over the limits of `options` fails the extraction. Every entry
+/// read before the member is found counts towards `max_entries`.
+///
+/// # Arguments
+/// * `inner` - Normalized path of the member, not empty
+/// * `options` - Password and limits, dry runs are not supported
+///
+/// # Returns
+/// * `Ok(PathBuf)` - The path of the extracted file or directory
+/// * `Err(io::Error)` - `NotFound` if there is no such member in the archive, `InvalidData`
+///   if it is too large
+pub fn extract_member(archive: &Path, inner: &str, dest_dir: &Path, options: &ExtractOptions) -> io::Result<PathBuf> {
+    let name = inner.rsplit('/').next().filter(|name| !name.is_empty()).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "Use unzip to extract the whole archive")
+    })?;
+    if options.dry_run {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Dry runs are only supported by unzip"));
+    }
+    let target = PathBuf::from(generate_unique_path(&dest_dir.join(name).to_string_lossy()));
+    let prefix = format!("{}/", inner);
+
+    let (_, mut reader) = open_detected(archive, options.password.as_deref())?;
+    let mut found = false;
+    let mut entry_count = 0;
+    let mut bytes = 0;
+    let result = reader.for_each_entry(&mut |entry, content| {
+        entry_count += 1;
+        if let Some(max_entries) = options.max_entries.filter(|max| entry_count > *max) {
+            return Err(limit_error(format!("The archive has more than {} entries", max_entries)));
+        }
+        let path = normalize(&entry.path);
+        let outpath = if path == inner {
+            target.clone()
+        } else if let Some(below) = path.strip_prefix(&prefix) {
+            fs::create_dir_all(&target)?;
+            target.join(below)
+        } else {
+            return Ok(true);
+        };
+        found = true;
+        match entry.kind {
+            ArchiveEntryKind::Directory => fs::create_dir_all(outpath)?,
+            ArchiveEntryKind::File => {
+                if let Some(max) = options.max_total_size.filter(|max| bytes + entry.size > *max) {
+                    return Err(limit_error(format!("The archive expands to more than {} bytes", max)));
+                }
+                if let Some(parent) = outpath.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let mut limited = SizeLimit {
+                    content,
+                    remaining: options.max_total_size.map(|max| max - bytes),
+                    limit: options.max_total_size.unwrap_or_default(),
+                };
+                bytes += io::copy(&mut limited, &mut File::create(outpath)?)?;
+                // A file has no content below it
+                return Ok(path != inner);
+            }
+            ArchiveEntryKind::Symlink | ArchiveEntryKind::Other => {
+                log_warn!("Skipping archive entry that is not a file or directory: {}", entry.path);
+            }
+        }
+        Ok(true)
+    });
+
+    let result = result.and_then(|()| {
+        if found {
+            Ok(target.clone())
+        } else {
+            Err(io::Error::new(io::ErrorKind::NotFound, format!("No member '{}' in the archive", inner)))
+        }
+    });
+    if result.is_err() && target.exists() {
+        let removed = if target.is_dir() { fs::remove_dir_all(&target) } else { fs::remove_file(&target) };
+        if let Err(err) = removed {
+            log_warn!("Failed to remove partial extraction {}: {}", target.display(), err);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests_browse {
+    use super::*;
+    use crate::filesystem::archive::{add_member, collect_members, create_writer, ArchiveOptions, MemberSelection};
+    use tempfile::TempDir;
+
+    fn create_archive(root: &Path, name: &str, format: ArchiveFormat) -> PathBuf {
+        let source = root.join("site");
+        fs::create_dir_all(source.join("img/icons")).unwrap();
+        fs::create_dir_all(source.join("empty")).unwrap();
+        fs::write(source.join("index.html"), "<html></html>").unwrap();
+        fs::write(source.join("img/logo.png"), [0x89, b'P', b'N', b'G']).unwrap();
+        fs::write(source.join("img/icons/a.svg"), "<svg/>").unwrap();
+
+        let archive = root.join(name);
+        let mut writer = create_writer(&archive, format, &ArchiveOptions::default()).unwrap();
+        for member in collect_members(&source, "site", &MemberSelection::default()).unwrap() {
+            add_member(writer.as_mut(), &member).unwrap();
+        }
+        writer.finish().unwrap();
+        archive
+    }
+
+    #[test]
+    fn test_parse_archive_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = create_archive(temp_dir.path(), "site.zip", ArchiveFormat::Zip);
+
+        let root = ArchivePath::parse(&archive.to_string_lossy()).unwrap();
+        assert_eq!(root, ArchivePath { archive: archive.clone(), inner: String::new() });
+        let member = ArchivePath::parse(&archive.join("site/img/logo.png").to_string_lossy()).unwrap();
+        assert_eq!(member.inner, "site/img/logo.png");
+        assert_eq!(ArchivePath::parse(&member.member_path(&member.inner)), Some(member));
+
+        // Neither plain directories nor missing paths point into an archive
+        assert_eq!(ArchivePath::parse(&temp_dir.path().to_string_lossy()), None);
+        assert_eq!(ArchivePath::parse(&temp_dir.path().join("site/missing").to_string_lossy()), None);
+        assert_eq!(ArchivePath::parse(&temp_dir.path().join("site/index.html/x").to_string_lossy()), None);
+        assert_eq!(normalize("./a//b/../c\\d/"), "a/b/c/d");
+    }
+
+    #[test]
+    fn test_list_directories_of_every_format() {
+        let temp_dir = TempDir::new().unwrap();
+        for (name, format) in [("site.zip", ArchiveFormat::Zip), ("site.tar.xz", ArchiveFormat::TarXz)] {
+            let archive = create_archive(temp_dir.path(), name, format);
+            let entries = archive_entries(&archive).unwrap();
+
+            let root = list_directory(&entries, "").unwrap();
+            assert_eq!(root.directories.len(), 1);
+            assert_eq!(root.directories[0].path, "site");
+            assert_eq!((root.directories[0].sub_file_count, root.directories[0].sub_dir_count), (1, 2));
+            assert!(root.files.is_empty());
+
+            // `img` has no entry of its own, only its content
+            let img = list_directory(&entries, "site/img").unwrap();
+            assert_eq!(img.directories.iter().map(|dir| dir.path.as_str()).collect::<Vec<_>>(), ["site/img/icons"]);
+            assert_eq!(img.files.iter().map(|file| file.path.as_str()).collect::<Vec<_>>(), ["site/img/logo.png"]);
+            assert_eq!(img.files[0].size, 4);
+
+            let site = list_directory(&entries, "site").unwrap();
+            assert_eq!(site.directories.iter().map(|dir| dir.path.as_str()).collect::<Vec<_>>(), ["site/empty", "site/img"]);
+            assert_eq!(member_kind(&entries, "site/index.html"), Some(ArchiveEntryKind::File));
+            assert!(list_directory(&entries, "site/index.html").is_err());
+            assert!(list_directory(&entries, "site/missing").is_err());
+        }
+    }
+
+    #[test]
+    fn test_read_and_extract_members() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = create_archive(temp_dir.path(), "site.tar.gz", ArchiveFormat::TarGz);
+
+        let (entry, head) = read_member(&archive, "site/index.html", 6).unwrap();
+        assert_eq!((entry.size, head.as_slice()), (13, b"<html>".as_slice()));
+        assert_eq!(read_member(&archive, "site/nope", 6).unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        let dest = temp_dir.path().join("out");
+        fs::create_dir(&dest).unwrap();
+        let options = ExtractOptions::default();
+        let file = extract_member(&archive, "site/index.html", &dest, &options).unwrap();
+        assert_eq!(file, dest.join("index.html"));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "<html></html>");
+
+        // Existing names are kept, the member gets a unique name
+        let dir = extract_member(&archive, "site/img", &dest, &options).unwrap();
+        assert_eq!(dir, dest.join("img"));
+        let again = extract_member(&archive, "site/img", &dest, &options).unwrap();
+        assert_eq!(again, dest.join("img (1)"));
+        assert_eq!(fs::read_to_string(again.join("icons/a.svg")).unwrap(), "<svg/>");
+        assert!(!again.join("index.html").exists());
+
+        assert_eq!(extract_member(&archive, "site/nope", &dest, &options).unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert!(!dest.join("nope").exists());
+        assert!(archive.exists(), "The archive is left in place");
+    }
+
+    #[test]
+    fn test_extract_member_limits_and_password() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = create_archive(temp_dir.path(), "site.tar.gz", ArchiveFormat::TarGz);
+        let dest = temp_dir.path().join("out");
+        fs::create_dir(&dest).unwrap();
+
+        // img holds 4 + 6 bytes
+        let small = ExtractOptions { max_total_size: Some(8), ..ExtractOptions::default() };
+        let err = extract_member(&archive, "site/img", &dest, &small).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(!dest.join("img").exists(), "Partial output is removed");
+        let few = ExtractOptions { max_entries: Some(2), ..ExtractOptions::default() };
+        assert_eq!(extract_member(&archive, "site/img", &dest, &few).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let dry_run = ExtractOptions { dry_run: true, ..ExtractOptions::default() };
+        assert!(extract_member(&archive, "site/img", &dest, &dry_run).is_err());
+
+        let encrypted = temp_dir.path().join("secret.zip");
+        let source = temp_dir.path().join("site/index.html");
+        let archive_options = ArchiveOptions { password: Some("hunter2".to_string()), ..ArchiveOptions::default() };
+        let mut writer = create_writer(&encrypted, ArchiveFormat::Zip, &archive_options).unwrap();
+        writer.add_file("index.html", &source, &mut File::open(&source).unwrap()).unwrap();
+        writer.finish().unwrap();
+        assert!(extract_member(&encrypted, "index.html", &dest, &ExtractOptions::default()).is_err());
+        let with_password = ExtractOptions { password: Some("hunter2".to_string()), ..ExtractOptions::default() };
+        let file = extract_member(&encrypted, "index.html", &dest, &with_password).unwrap();
+        assert_eq!(fs::read_to_string(file).unwrap(), "<html></html>");
+    }
+}
@@ -0,0 +1,913 @@
+//! Creating and extracting archives in several formats.
+//!
+//! Every format implements `ArchiveReader` and, if it can be written, `ArchiveWriter`, so
+//! the commands and queued jobs handle all formats the same way. The format of an existing
+//! archive is detected from its magic bytes, the format of a new one from its file name.
+
+pub mod browse;
+mod options;
+mod seven_zip_archive;
+mod tar_archive;
+#[cfg(test)]
+pub(crate) mod test_corpus;
+mod zip_archive;
+
+use crate::commands::file_system_operation_commands::generate_unique_path;
+use crate::log_warn;
+pub use options::{ArchiveOptions, CompressionMethod, ExtractOptions, MemberSelection};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Number of bytes read to detect a format, enough for the tar header
+const HEADER_BYTES: u64 = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarBz2,
+    TarXz,
+    TarZst,
+    /// Can only be read
+    SevenZip,
+}
+
+impl ArchiveFormat {
+    /// The usual file extension, without the leading dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::TarBz2 => "tar.bz2",
+            ArchiveFormat::TarXz => "tar.xz",
+            ArchiveFormat::TarZst => "tar.zst",
+            ArchiveFormat::SevenZip => "7z",
+        }
+    }
+
+    pub fn can_write(self) -> bool {
+        self != ArchiveFormat::SevenZip
+    }
+
+    /// Whether the entries can be listed without reading through the whole archive.
+    pub fn has_index(self) -> bool {
+        matches!(self, ArchiveFormat::Zip | ArchiveFormat::SevenZip)
+    }
+
+    /// The format of a new archive at `path`, from its extension. Zip if the extension is
+    /// not an archive extension.
+    ///
+    /// # Returns
+    /// * `Ok(ArchiveFormat)` - The format to write
+    /// * `Err(String)` - If the extension is of a format that can only be read
+    pub fn for_new_archive(path: &Path) -> Result<Self, String> {
+        let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        match ArchiveFormat::from_file_name(&name) {
+            Some(format) if !format.can_write() => {
+                Err(format!("{} archives can only be extracted", format.extension()))
+            }
+            Some(format) => Ok(format),
+            None => Ok(ArchiveFormat::Zip),
+        }
+    }
+
+    /// Picks the format from the extension of a file name, e.g. for a new archive.
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        matching_extension(name).map(|(_, format)| format)
+    }
+
+    /// Detects the format of an existing archive from its magic bytes. For compressed
+    /// files the start of the decompressed data is checked for a tar header.
+    ///
+    /// # Returns
+    /// * `Ok(Some(ArchiveFormat))` - The detected format
+    /// * `Ok(None)` - If the file is not an archive in a supported format
+    /// * `Err(io::Error)` - If the file could not be read
+    pub fn detect(path: &Path) -> io::Result<Option<Self>> {
+        let mut head = Vec::new();
+        File::open(path)?.take(HEADER_BYTES).read_to_end(&mut head)?;
+        let compressed = if infer::archive::is_zip(&head) {
+            return Ok(Some(ArchiveFormat::Zip));
+        } else if infer::archive::is_7z(&head) {
+            return Ok(Some(ArchiveFormat::SevenZip));
+        } else if infer::archive::is_tar(&head) {
+            return Ok(Some(ArchiveFormat::Tar));
+        } else if infer::archive::is_gz(&head) {
+            ArchiveFormat::TarGz
+        } else if infer::archive::is_bz2(&head) {
+            ArchiveFormat::TarBz2
+        } else if infer::archive::is_xz(&head) {
+            ArchiveFormat::TarXz
+        } else if infer::archive::is_zst(&head) {
+            ArchiveFormat::TarZst
+        } else {
+            return Ok(None);
+        };
+
+        let mut inner = Vec::new();
+        // A corrupt stream is reported when the archive is read
+        let _ = tar_archive::decompress(File::open(path)?, compressed)?
+            .take(HEADER_BYTES)
+            .read_to_end(&mut inner);
+        Ok(infer::archive::is_tar(&inner).then_some(compressed))
+    }
+}
+
+/// File name suffixes of the formats, longer ones first
+const EXTENSIONS: [(&str, ArchiveFormat); 11] = [
+    (".tar.gz", ArchiveFormat::TarGz),
+    (".tgz", ArchiveFormat::TarGz),
+    (".tar.bz2", ArchiveFormat::TarBz2),
+    (".tbz2", ArchiveFormat::TarBz2),
+    (".tar.xz", ArchiveFormat::TarXz),
+    (".txz", ArchiveFormat::TarXz),
+    (".tar.zst", ArchiveFormat::TarZst),
+    (".tzst", ArchiveFormat::TarZst),
+    (".tar", ArchiveFormat::Tar),
+    (".zip", ArchiveFormat::Zip),
+    (".7z", ArchiveFormat::SevenZip),
+];
+
+fn matching_extension(name: &str) -> Option<(&'static str, ArchiveFormat)> {
+    let name = name.to_lowercase();
+    EXTENSIONS.into_iter().find(|(extension, _)| name.ends_with(extension))
+}
+
+/// The file name of an archive without its archive extension, e.g. `backup` for
+/// `backup.tar.gz`.
+pub fn archive_stem(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let stem = match matching_extension(&name) {
+        Some((extension, _)) => name.get(..name.len() - extension.len())?.to_string(),
+        None => Path::new(&name).file_stem()?.to_string_lossy().to_string(),
+    };
+    (!stem.is_empty()).then_some(stem)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveEntryKind {
+    File,
+    Directory,
+    Symlink,
+    /// Hard links, devices and other special entries
+    Other,
+}
+
+/// An entry of an archive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// The path as stored in the archive, with `/` as separator and without a trailing slash
+    pub path: String,
+    pub kind: ArchiveEntryKind,
+    /// Uncompressed size in bytes
+    pub size: u64,
+    /// Unix timestamp in seconds
+    pub modified: Option<i64>,
+    /// Unix permission bits
+    pub mode: Option<u32>,
+    /// The target of a symlink
+    pub link_target: Option<String>,
+}
+
+impl ArchiveEntry {
+    /// The path relative to the extraction directory, without `.` components.
+    ///
+    /// # Returns
+    /// * `Ok(Some(PathBuf))` - The relative path
+    /// * `Ok(None)` - If nothing of the path is left, e.g. for `./`
+    /// * `Err(io::Error)` - If the path is absolute or contains `..`, so it could point
+    ///   outside of the extraction directory
+    pub fn relative_path(&self) -> io::Result<Option<PathBuf>> {
+        let normalized = self.path.replace('\\', "/");
+        let mut path = PathBuf::new();
+        for component in Path::new(&normalized).components() {
+            match component {
+                Component::Normal(name) => path.push(name),
+                Component::CurDir => {}
+                Component::RootDir | Component::Prefix(_) | Component::ParentDir => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Unsafe path in archive: {}", self.path),
+                    ));
+                }
+            }
+        }
+        Ok((!path.as_os_str().is_empty()).then_some(path))
+    }
+}
+
+/// Longest symlink target that is read, like `PATH_MAX` on Linux
+const MAX_LINK_TARGET: u64 = 4096;
+
+/// Reads the target of a symlink, which archives store as its content. Longer targets
+/// fail instead of being read into memory, they are read before any limit applies.
+fn read_link_target(content: &mut dyn Read) -> io::Result<String> {
+    let mut target = Vec::new();
+    content.take(MAX_LINK_TARGET + 1).read_to_end(&mut target)?;
+    if target.len() as u64 > MAX_LINK_TARGET {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Symlink target in archive is longer than {} bytes", MAX_LINK_TARGET),
+        ));
+    }
+    String::from_utf8(target).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Reads the entries of an archive.
+pub trait ArchiveReader {
+    /// Calls `visit` for every entry in archive order, with a reader for its content.
+    /// Stops early when `visit` returns `Ok(false)` and fails with the first error.
+    fn for_each_entry(
+        &mut self,
+        visit: &mut dyn FnMut(&ArchiveEntry, &mut dyn Read) -> io::Result<bool>,
+    ) -> io::Result<()>;
+
+    /// Lists all entries without extracting anything. Formats with an index of their
+    /// entries override this, the others have to read through the whole archive.
+    fn entries(&mut self) -> io::Result<Vec<ArchiveEntry>> {
+        let mut entries = Vec::new();
+        self.for_each_entry(&mut |entry, _| {
+            entries.push(entry.clone());
+            Ok(true)
+        })?;
+        Ok(entries)
+    }
+}
+
+/// Writes a new archive.
+pub trait ArchiveWriter {
+    /// Adds an empty directory with the metadata of `source`.
+    fn add_directory(&mut self, name: &str, source: &Path) -> io::Result<()>;
+
+    /// Adds a file with the metadata of `source` and the data read from `content`.
+    fn add_file(&mut self, name: &str, source: &Path, content: &mut dyn Read) -> io::Result<()>;
+
+    /// Adds a symlink to `target` with the metadata of the link `source`.
+    fn add_symlink(&mut self, name: &str, source: &Path, target: &str) -> io::Result<()>;
+
+    /// Writes the end of the archive. An archive that is not finished is incomplete.
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// Opens an existing archive for reading.
+///
+/// # Arguments
+/// * `password` - Decrypts the files of encrypted zip archives, other formats ignore it
+pub fn open_reader(
+    path: &Path,
+    format: ArchiveFormat,
+    password: Option<&str>,
+) -> io::Result<Box<dyn ArchiveReader>> {
+    match format {
+        ArchiveFormat::Zip => Ok(Box::new(zip_archive::ZipArchiveReader::open(path, password)?)),
+        ArchiveFormat::SevenZip => Ok(Box::new(seven_zip_archive::SevenZipArchiveReader::open(path)?)),
+        format => Ok(Box::new(tar_archive::TarArchiveReader::open(path, format)?)),
+    }
+}
+
+/// Creates a new archive, replacing an existing file at `path`. The options are expected
+/// to be checked with `ArchiveOptions::validate` before.
+pub fn create_writer(
+    path: &Path,
+    format: ArchiveFormat,
+    options: &ArchiveOptions,
+) -> io::Result<Box<dyn ArchiveWriter>> {
+    match format {
+        ArchiveFormat::Zip => Ok(Box::new(zip_archive::ZipArchiveWriter::create(path, options)?)),
+        ArchiveFormat::SevenZip => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "7z archives can only be extracted",
+        )),
+        format => Ok(Box::new(tar_archive::TarArchiveWriter::create(path, format, options.level)?)),
+    }
+}
+
+/// Detects the format of an archive and opens it, see `ArchiveFormat::detect`.
+pub fn open_detected(
+    path: &Path,
+    password: Option<&str>,
+) -> io::Result<(ArchiveFormat, Box<dyn ArchiveReader>)> {
+    let format = ArchiveFormat::detect(path)?.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "Unsupported or unrecognized archive format")
+    })?;
+    Ok((format, open_reader(path, format, password)?))
+}
+
+/// Something to add to a new archive.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArchiveMember {
+    File { source: PathBuf, name: String },
+    /// Directories are only added when empty, the others are implied by their files
+    EmptyDirectory { source: PathBuf, name: String },
+    /// Only with `MemberSelection::store_symlinks`, otherwise links are followed
+    Symlink { source: PathBuf, name: String, target: String },
+}
+
+/// Collects what to add to an archive for a file or directory tree, named below `base_name`.
+/// A symlink given as `source` itself is always followed.
+pub fn collect_members(
+    source: &Path,
+    base_name: &str,
+    selection: &MemberSelection,
+) -> io::Result<Vec<ArchiveMember>> {
+    let mut members = Vec::new();
+    let mut walker = WalkDir::new(source).follow_links(!selection.store_symlinks).into_iter();
+    while let Some(entry) = walker.next() {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(source).map_err(io::Error::other)?;
+        let name = if relative.as_os_str().is_empty() {
+            base_name.to_string()
+        } else {
+            format!("{}/{}", base_name, relative.to_string_lossy().replace('\\', "/"))
+        };
+
+        if selection.excludes(&name) {
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            continue;
+        }
+        if !selection.includes(&name) && !entry.file_type().is_dir() {
+            continue;
+        }
+        if entry.file_type().is_symlink() {
+            let target = fs::read_link(entry.path())?.to_string_lossy().to_string();
+            members.push(ArchiveMember::Symlink { source: entry.into_path(), name, target });
+        } else if entry.file_type().is_file() {
+            members.push(ArchiveMember::File { source: entry.into_path(), name });
+        } else if entry.file_type().is_dir()
+            && selection.includes(&name)
+            && fs::read_dir(entry.path())?.next().is_none()
+        {
+            members.push(ArchiveMember::EmptyDirectory { source: entry.into_path(), name });
+        }
+    }
+    Ok(members)
+}
+
+/// Adds a member to an archive, reading files from disk.
+pub fn add_member(writer: &mut dyn ArchiveWriter, member: &ArchiveMember) -> io::Result<()> {
+    match member {
+        ArchiveMember::File { source, name } => writer.add_file(name, source, &mut File::open(source)?),
+        ArchiveMember::EmptyDirectory { source, name } => writer.add_directory(name, source),
+        ArchiveMember::Symlink { source, name, target } => writer.add_symlink(name, source, target),
+    }
+}
+
+/// A file or directory that a dry run of `extract` would write.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlannedEntry {
+    pub path: PathBuf,
+    pub kind: ArchiveEntryKind,
+    /// The size stored in the archive
+    pub size: u64,
+}
+
+/// Result of `extract`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Extraction {
+    /// The directory the content was extracted into
+    pub path: PathBuf,
+    pub files: u64,
+    pub bytes: u64,
+    /// What a dry run would write, empty otherwise
+    pub planned: Vec<PlannedEntry>,
+}
+
+fn limit_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Fails once more than `remaining` bytes are read, whatever the archive claims the
+/// size of the content is.
+struct SizeLimit<'a> {
+    content: &'a mut dyn Read,
+    remaining: Option<u64>,
+    limit: u64,
+}
+
+impl Read for SizeLimit<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.content.read(buf)?;
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.checked_sub(read as u64).ok_or_else(|| {
+                limit_error(format!("The archive expands to more than {} bytes", self.limit))
+            })?;
+        }
+        Ok(read)
+    }
+}
+
+/// Extracts an archive of any supported format. An archive that only contains a single
+/// file is extracted directly into `dest_dir`, anything else into a new subdirectory
+/// named after the archive. Names that already exist get a unique name.
+///
+/// Entries with absolute paths or `..` components fail the extraction, as does going
+/// over the limits of `options`. Symlinks and special entries are skipped. If the
+/// extraction fails, everything it wrote is removed again.
+///
+/// # Arguments
+/// * `options` - Password and limits, a dry run lists the entries without writing anything
+/// * `write` - Copies the content of one file to the created output file and returns the
+///   number of bytes written, this is where callers report progress or cancel
+///
+/// # Returns
+/// * `Ok(Extraction)` - Where the content went, and the number of files and bytes written
+/// * `Err(io::Error)` - If the archive could not be read, is unsafe or too large, or a
+///   file could not be written
+pub fn extract(
+    archive: &Path,
+    dest_dir: &Path,
+    options: &ExtractOptions,
+    write: &mut dyn FnMut(&Path, &mut dyn Read, &mut File) -> io::Result<u64>,
+) -> io::Result<Extraction> {
+    let (_, mut reader) = open_detected(archive, options.password.as_deref())?;
+    let name = archive_stem(archive).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid archive filename"))?;
+    let extract_path = PathBuf::from(generate_unique_path(&dest_dir.join(name).to_string_lossy()));
+    if !options.dry_run {
+        fs::create_dir_all(&extract_path)?;
+    }
+
+    let mut entry_count = 0;
+    let (mut files, mut bytes) = (0, 0);
+    let mut planned = Vec::new();
+    let mut last_file = None;
+    let result = reader.for_each_entry(&mut |entry, content| {
+        entry_count += 1;
+        if let Some(max_entries) = options.max_entries.filter(|max| entry_count > *max) {
+            return Err(limit_error(format!("The archive has more than {} entries", max_entries)));
+        }
+        let Some(relative) = entry.relative_path()? else {
+            log_warn!("Skipping archive entry without a usable path: {}", entry.path);
+            return Ok(true);
+        };
+        // The stored size is checked first, so honest archives fail before anything is written
+        let too_large = |size: u64| {
+            options.max_total_size.filter(|max| bytes + size > *max).map(|max| {
+                limit_error(format!("The archive expands to more than {} bytes", max))
+            })
+        };
+        match entry.kind {
+            ArchiveEntryKind::Directory if options.dry_run => {
+                let path = extract_path.join(relative);
+                planned.push(PlannedEntry { path, kind: entry.kind, size: 0 });
+            }
+            ArchiveEntryKind::Directory => fs::create_dir_all(extract_path.join(relative))?,
+            ArchiveEntryKind::File => {
+                if let Some(err) = too_large(entry.size) {
+                    return Err(err);
+                }
+                if options.dry_run {
+                    let path = extract_path.join(relative);
+                    planned.push(PlannedEntry { path: path.clone(), kind: entry.kind, size: entry.size });
+                    files += 1;
+                    bytes += entry.size;
+                    last_file = Some(path);
+                    return Ok(true);
+                }
+                let outpath = PathBuf::from(generate_unique_path(&extract_path.join(relative).to_string_lossy()));
+                if let Some(parent) = outpath.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let mut outfile = File::create(&outpath)?;
+                let mut limited = SizeLimit {
+                    content,
+                    remaining: options.max_total_size.map(|max| max - bytes),
+                    limit: options.max_total_size.unwrap_or_default(),
+                };
+                bytes += write(&outpath, &mut limited, &mut outfile)?;
+                files += 1;
+                last_file = Some(outpath);
+            }
+            ArchiveEntryKind::Symlink | ArchiveEntryKind::Other => {
+                log_warn!("Skipping archive entry that is not a file or directory: {}", entry.path);
+            }
+        }
+        Ok(true)
+    });
+    if let Err(err) = result {
+        if !options.dry_run {
+            if let Err(remove_err) = fs::remove_dir_all(&extract_path) {
+                log_warn!("Failed to remove partial extraction {}: {}", extract_path.display(), remove_err);
+            }
+        }
+        return Err(err);
+    }
+
+    // A single file is moved up next to where the subdirectory would be
+    if let (1, Some(file)) = (entry_count, last_file) {
+        let file_name = file.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+        let target = PathBuf::from(generate_unique_path(&dest_dir.join(file_name).to_string_lossy()));
+        if options.dry_run {
+            planned[0].path = target;
+        } else {
+            fs::rename(&file, target)?;
+            fs::remove_dir_all(&extract_path)?;
+        }
+        return Ok(Extraction { path: dest_dir.to_path_buf(), files, bytes, planned });
+    }
+    Ok(Extraction { path: extract_path, files, bytes, planned })
+}
+
+#[cfg(test)]
+mod tests_archive {
+    use super::*;
+    use tempfile::TempDir;
+
+    const WRITABLE_FORMATS: [ArchiveFormat; 6] = [
+        ArchiveFormat::Zip,
+        ArchiveFormat::Tar,
+        ArchiveFormat::TarGz,
+        ArchiveFormat::TarBz2,
+        ArchiveFormat::TarXz,
+        ArchiveFormat::TarZst,
+    ];
+
+    fn create_tree(root: &Path) -> PathBuf {
+        let source = root.join("project");
+        fs::create_dir_all(source.join("src/empty")).unwrap();
+        fs::write(source.join("README.md"), "readme").unwrap();
+        fs::write(source.join("src/main.rs"), "fn main() {}").unwrap();
+        source
+    }
+
+    fn write_archive(path: &Path, format: ArchiveFormat, source: &Path) {
+        let mut writer = create_writer(path, format, &ArchiveOptions::default()).unwrap();
+        for member in collect_members(source, "project", &MemberSelection::default()).unwrap() {
+            add_member(writer.as_mut(), &member).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    fn copy(_: &Path, content: &mut dyn Read, file: &mut File) -> io::Result<u64> {
+        io::copy(content, file)
+    }
+
+    #[test]
+    fn test_round_trip_in_every_writable_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = create_tree(temp_dir.path());
+
+        for format in WRITABLE_FORMATS {
+            // The content decides the format, not the name
+            let archive = temp_dir.path().join(format!("archive-{:?}.bin", format));
+            write_archive(&archive, format, &source);
+            assert_eq!(ArchiveFormat::detect(&archive).unwrap(), Some(format));
+
+            let (_, mut reader) = open_detected(&archive, None).unwrap();
+            let mut paths: Vec<(String, ArchiveEntryKind)> = reader.entries()
+                .unwrap()
+                .into_iter()
+                .map(|entry| (entry.path, entry.kind))
+                .collect();
+            paths.sort();
+            assert_eq!(
+                paths,
+                vec![
+                    ("project/README.md".to_string(), ArchiveEntryKind::File),
+                    ("project/src/empty".to_string(), ArchiveEntryKind::Directory),
+                    ("project/src/main.rs".to_string(), ArchiveEntryKind::File),
+                ],
+                "{:?}",
+                format
+            );
+
+            let dest = temp_dir.path().join(format!("out-{:?}", format));
+            let extraction = extract(&archive, &dest, &ExtractOptions::default(), &mut copy).unwrap();
+            assert_eq!(extraction.path, dest.join(format!("archive-{:?}", format)));
+            assert_eq!((extraction.files, extraction.bytes), (2, 18));
+            let extracted = extraction.path.join("project");
+            assert_eq!(fs::read_to_string(extracted.join("src/main.rs")).unwrap(), "fn main() {}");
+            assert!(extracted.join("src/empty").is_dir());
+        }
+    }
+
+    #[test]
+    fn test_single_file_is_extracted_next_to_the_archive() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("notes.txt");
+        fs::write(&source, "notes").unwrap();
+        let archive = temp_dir.path().join("notes.tar.zst");
+        let mut writer = create_writer(&archive, ArchiveFormat::TarZst, &ArchiveOptions::default()).unwrap();
+        writer.add_file("notes.txt", &source, &mut File::open(&source).unwrap()).unwrap();
+        writer.finish().unwrap();
+
+        let extraction = extract(&archive, temp_dir.path(), &ExtractOptions::default(), &mut copy).unwrap();
+        assert_eq!(extraction.path, temp_dir.path());
+        // The original file exists, so the extracted one gets a unique name
+        assert_eq!(fs::read_to_string(temp_dir.path().join("notes (1).txt")).unwrap(), "notes");
+        assert!(!temp_dir.path().join("notes").exists());
+    }
+
+    #[test]
+    fn test_zip_methods_levels_and_encryption() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = create_tree(temp_dir.path());
+        let cases = [
+            (CompressionMethod::Store, None, zip::CompressionMethod::Stored),
+            (CompressionMethod::Deflate, Some(9), zip::CompressionMethod::Deflated),
+            (CompressionMethod::Zstd, Some(-3), zip::CompressionMethod::Zstd),
+            (CompressionMethod::Bzip2, Some(1), zip::CompressionMethod::Bzip2),
+        ];
+        for (method, level, expected) in cases {
+            let path = temp_dir.path().join(format!("{:?}.zip", method));
+            let options = ArchiveOptions { method: Some(method), level, ..ArchiveOptions::default() };
+            let mut writer = create_writer(&path, ArchiveFormat::Zip, &options).unwrap();
+            add_member(writer.as_mut(), &ArchiveMember::File { source: source.join("README.md"), name: "README.md".to_string() }).unwrap();
+            writer.finish().unwrap();
+
+            let mut zip = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+            let mut file = zip.by_name("README.md").unwrap();
+            assert_eq!(file.compression(), expected);
+            let mut content = String::new();
+            file.read_to_string(&mut content).unwrap();
+            assert_eq!(content, "readme");
+        }
+
+        let path = temp_dir.path().join("secret.zip");
+        let options = ArchiveOptions { password: Some("hunter2".to_string()), ..ArchiveOptions::default() };
+        let mut writer = create_writer(&path, ArchiveFormat::Zip, &options).unwrap();
+        add_member(writer.as_mut(), &ArchiveMember::File { source: source.join("README.md"), name: "README.md".to_string() }).unwrap();
+        writer.finish().unwrap();
+
+        let mut zip = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        assert!(zip.by_name("README.md").is_err());
+        assert!(zip.by_name_decrypt("README.md", b"wrong").is_err());
+        let mut content = String::new();
+        zip.by_name_decrypt("README.md", b"hunter2").unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "readme");
+    }
+
+    #[test]
+    fn test_include_and_exclude_filters() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = create_tree(temp_dir.path());
+        fs::create_dir_all(source.join("target/debug")).unwrap();
+        fs::write(source.join("target/debug/app"), "binary").unwrap();
+        fs::write(source.join("src/lib.rs"), "").unwrap();
+
+        let options = ArchiveOptions {
+            include: vec!["*.rs".to_string()],
+            exclude: vec!["/project/target".to_string(), "lib.rs".to_string()],
+            ..ArchiveOptions::default()
+        };
+        let selection = options.validate(ArchiveFormat::Zip).unwrap();
+        let names: Vec<String> = collect_members(&source, "project", &selection)
+            .unwrap()
+            .into_iter()
+            .map(|member| match member {
+                ArchiveMember::File { name, .. }
+                | ArchiveMember::EmptyDirectory { name, .. }
+                | ArchiveMember::Symlink { name, .. } => name,
+            })
+            .collect();
+        assert_eq!(names, vec!["project/src/main.rs".to_string()]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_stored_as_links() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = create_tree(temp_dir.path());
+        std::os::unix::fs::symlink("src/main.rs", source.join("link.rs")).unwrap();
+
+        for format in [ArchiveFormat::Zip, ArchiveFormat::TarGz] {
+            let path = temp_dir.path().join(format!("links.{}", format.extension()));
+            let options = ArchiveOptions { store_symlinks: true, ..ArchiveOptions::default() };
+            let mut writer = create_writer(&path, format, &options).unwrap();
+            for member in collect_members(&source, "project", &options.validate(format).unwrap()).unwrap() {
+                add_member(writer.as_mut(), &member).unwrap();
+            }
+            writer.finish().unwrap();
+
+            let (_, mut reader) = open_detected(&path, None).unwrap();
+            let link = reader.entries().unwrap().into_iter().find(|entry| entry.path == "project/link.rs").unwrap();
+            assert_eq!(link.kind, ArchiveEntryKind::Symlink, "{:?}", format);
+            assert_eq!(link.link_target.as_deref(), Some("src/main.rs"), "{:?}", format);
+        }
+
+        // By default the link is followed and its target stored as a file
+        let path = temp_dir.path().join("followed.zip");
+        write_archive(&path, ArchiveFormat::Zip, &source);
+        let (_, mut reader) = open_detected(&path, None).unwrap();
+        let link = reader.entries().unwrap().into_iter().find(|entry| entry.path == "project/link.rs").unwrap();
+        assert_eq!((link.kind, link.size), (ArchiveEntryKind::File, 12));
+    }
+
+    /// Lists everything below `dir`, to check that nothing was written.
+    fn files_below(dir: &Path) -> Vec<PathBuf> {
+        walkdir::WalkDir::new(dir).min_depth(1).into_iter().map(|entry| entry.unwrap().into_path()).collect()
+    }
+
+    #[test]
+    fn test_huge_symlink_targets_are_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = test_corpus::symlink_bomb(temp_dir.path().join("link.zip"), 1024 * 1024);
+
+        let (_, mut reader) = open_detected(&archive, None).unwrap();
+        let err = reader.for_each_entry(&mut |_, _| Ok(true)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let dest = temp_dir.path().join("out");
+        fs::create_dir(&dest).unwrap();
+        let mut copy = |_: &Path, content: &mut dyn Read, file: &mut File| io::copy(content, file);
+        assert!(extract(&archive, &dest, &ExtractOptions::default(), &mut copy).is_err());
+        assert!(files_below(&dest).is_empty());
+    }
+
+    #[test]
+    fn test_unsafe_paths_are_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let corpus = temp_dir.path().join("corpus");
+        fs::create_dir(&corpus).unwrap();
+
+        for archive in test_corpus::path_traversal(&corpus) {
+            let dest = temp_dir.path().join("out").join(archive.file_name().unwrap());
+            fs::create_dir_all(&dest).unwrap();
+            for dry_run in [false, true] {
+                let options = ExtractOptions { dry_run, ..ExtractOptions::default() };
+                let err = extract(&archive, &dest, &options, &mut copy).unwrap_err();
+                assert!(err.to_string().contains("Unsafe path in archive"), "{}: {}", archive.display(), err);
+            }
+            assert_eq!(files_below(&dest), Vec::<PathBuf>::new(), "{}", archive.display());
+            assert!(!temp_dir.path().join("out/evil.txt").exists());
+            assert!(!corpus.join("evil.txt").exists());
+        }
+    }
+
+    #[test]
+    fn test_size_and_entry_limits() {
+        let temp_dir = TempDir::new().unwrap();
+        let bomb = test_corpus::zip_bomb(temp_dir.path().join("bomb.zip"), 8 * 1024 * 1024);
+        assert!(fs::metadata(&bomb).unwrap().len() < 64 * 1024);
+        let crowded = test_corpus::many_entries(temp_dir.path().join("crowded.zip"), 200);
+        let dest = temp_dir.path().join("out");
+        fs::create_dir(&dest).unwrap();
+
+        let options = ExtractOptions { max_total_size: Some(1024 * 1024), ..ExtractOptions::default() };
+        let err = extract(&bomb, &dest, &options, &mut copy).unwrap_err();
+        assert_eq!(err.to_string(), "The archive expands to more than 1048576 bytes");
+        let options = ExtractOptions { max_entries: Some(100), ..ExtractOptions::default() };
+        let err = extract(&crowded, &dest, &options, &mut copy).unwrap_err();
+        assert_eq!(err.to_string(), "The archive has more than 100 entries");
+        assert_eq!(files_below(&dest), Vec::<PathBuf>::new());
+
+        // Content that is larger than the archive claims is cut off while reading
+        let mut content: &[u8] = &[0; 10];
+        let mut limited = SizeLimit { content: &mut content, remaining: Some(5), limit: 5 };
+        assert!(io::copy(&mut limited, &mut io::sink()).is_err());
+
+        let unlimited = ExtractOptions { max_total_size: None, max_entries: None, ..ExtractOptions::default() };
+        assert_eq!(extract(&bomb, &dest, &unlimited, &mut copy).unwrap().bytes, 8 * 1024 * 1024);
+        assert_eq!(extract(&crowded, &dest, &unlimited, &mut copy).unwrap().files, 200);
+    }
+
+    #[test]
+    fn test_encrypted_zip_needs_the_password() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = create_tree(temp_dir.path());
+        let archive = temp_dir.path().join("secret.zip");
+        let options = ArchiveOptions { password: Some("hunter2".to_string()), ..ArchiveOptions::default() };
+        let mut writer = create_writer(&archive, ArchiveFormat::Zip, &options).unwrap();
+        for member in collect_members(&source, "project", &MemberSelection::default()).unwrap() {
+            add_member(writer.as_mut(), &member).unwrap();
+        }
+        writer.finish().unwrap();
+        let dest = temp_dir.path().join("out");
+        fs::create_dir(&dest).unwrap();
+
+        let err = extract(&archive, &dest, &ExtractOptions::default(), &mut copy).unwrap_err();
+        assert_eq!(err.to_string(), "The archive is encrypted, a password is required");
+        let wrong = ExtractOptions { password: Some("wrong".to_string()), ..ExtractOptions::default() };
+        assert_eq!(extract(&archive, &dest, &wrong, &mut copy).unwrap_err().to_string(), "Wrong password for the archive");
+        assert_eq!(files_below(&dest), Vec::<PathBuf>::new());
+
+        let right = ExtractOptions { password: Some("hunter2".to_string()), ..ExtractOptions::default() };
+        let extraction = extract(&archive, &dest, &right, &mut copy).unwrap();
+        assert_eq!(fs::read_to_string(extraction.path.join("project/README.md")).unwrap(), "readme");
+    }
+
+    #[test]
+    fn test_encrypted_zip_is_listed_without_the_password() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = create_tree(temp_dir.path());
+        let archive = temp_dir.path().join("secret.zip");
+        let options = ArchiveOptions { password: Some("hunter2".to_string()), ..ArchiveOptions::default() };
+        let mut writer = create_writer(&archive, ArchiveFormat::Zip, &options).unwrap();
+        for member in collect_members(&source, "project", &MemberSelection::default()).unwrap() {
+            add_member(writer.as_mut(), &member).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let (_, mut reader) = open_detected(&archive, None).unwrap();
+        let readme = reader.entries().unwrap().into_iter().find(|entry| entry.path == "project/README.md").unwrap();
+        assert_eq!((readme.kind, readme.size), (ArchiveEntryKind::File, 6));
+        assert!(browse::archive_entries(&archive).unwrap().iter().any(|entry| entry.path == "project/README.md"));
+    }
+
+    #[test]
+    fn test_tar_files_keep_the_size_of_their_header() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("log.txt");
+        fs::write(&source, "0123456789").unwrap();
+        let path = temp_dir.path().join("log.tar");
+
+        // Content that grew after the metadata was read is cut to the recorded size
+        let mut writer = create_writer(&path, ArchiveFormat::Tar, &ArchiveOptions::default()).unwrap();
+        writer.add_file("log.txt", &source, &mut "0123456789 and more".as_bytes()).unwrap();
+        writer.finish().unwrap();
+        let (_, mut reader) = open_detected(&path, None).unwrap();
+        let mut contents = Vec::new();
+        reader
+            .for_each_entry(&mut |_, content| {
+                let mut text = String::new();
+                content.read_to_string(&mut text)?;
+                contents.push(text);
+                Ok(true)
+            })
+            .unwrap();
+        assert_eq!(contents, vec!["0123456789".to_string()]);
+
+        // Content that shrank fails instead of writing a corrupt archive
+        let mut writer = create_writer(&path, ArchiveFormat::Tar, &ArchiveOptions::default()).unwrap();
+        let err = writer.add_file("log.txt", &source, &mut "01234".as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_dry_run_writes_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = create_tree(temp_dir.path());
+        let archive = temp_dir.path().join("project.tar.gz");
+        write_archive(&archive, ArchiveFormat::TarGz, &source);
+        let dest = temp_dir.path().join("out");
+        fs::create_dir(&dest).unwrap();
+
+        let options = ExtractOptions { dry_run: true, ..ExtractOptions::default() };
+        let extraction = extract(&archive, &dest, &options, &mut copy).unwrap();
+        assert_eq!(extraction.path, dest.join("project"));
+        assert_eq!((extraction.files, extraction.bytes), (2, 18));
+        let mut planned: Vec<(PathBuf, ArchiveEntryKind)> =
+            extraction.planned.into_iter().map(|entry| (entry.path, entry.kind)).collect();
+        planned.sort();
+        assert_eq!(
+            planned,
+            vec![
+                (dest.join("project/project/README.md"), ArchiveEntryKind::File),
+                (dest.join("project/project/src/empty"), ArchiveEntryKind::Directory),
+                (dest.join("project/project/src/main.rs"), ArchiveEntryKind::File),
+            ]
+        );
+        assert_eq!(files_below(&dest), Vec::<PathBuf>::new());
+
+        // A single file would be written next to the archive, under a unique name
+        let single = test_corpus::zip_with(temp_dir.path().join("single.zip"), &[("project", b"x")]);
+        let extraction = extract(&single, temp_dir.path(), &options, &mut copy).unwrap();
+        assert_eq!(extraction.path, temp_dir.path());
+        assert_eq!(extraction.planned[0].path, temp_dir.path().join("project (1)"));
+    }
+
+    #[test]
+    fn test_format_names_and_detection() {
+        assert_eq!(ArchiveFormat::from_file_name("Backup.TGZ"), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::from_file_name("a.tar.zst"), Some(ArchiveFormat::TarZst));
+        assert_eq!(ArchiveFormat::from_file_name("a.txt"), None);
+        assert_eq!(archive_stem(Path::new("/x/release-1.0.tar.gz")), Some("release-1.0".to_string()));
+        assert_eq!(archive_stem(Path::new("/x/data.tgz")), Some("data".to_string()));
+        assert_eq!(archive_stem(Path::new("/x/image.iso")), Some("image".to_string()));
+
+        let temp_dir = TempDir::new().unwrap();
+        let plain = temp_dir.path().join("plain.txt");
+        fs::write(&plain, "not an archive").unwrap();
+        assert_eq!(ArchiveFormat::detect(&plain).unwrap(), None);
+        assert!(extract(&plain, temp_dir.path(), &ExtractOptions::default(), &mut copy).is_err());
+
+        // A compressed file that is not a tar archive
+        let gz = temp_dir.path().join("plain.txt.gz");
+        let mut encoder = flate2::write::GzEncoder::new(File::create(&gz).unwrap(), flate2::Compression::default());
+        io::Write::write_all(&mut encoder, b"not a tar archive").unwrap();
+        encoder.finish().unwrap();
+        assert_eq!(ArchiveFormat::detect(&gz).unwrap(), None);
+
+        let entry = |path: &str| ArchiveEntry {
+            path: path.to_string(),
+            kind: ArchiveEntryKind::File,
+            size: 0,
+            modified: None,
+            mode: None,
+            link_target: None,
+        };
+        assert_eq!(entry("./docs/./a.txt").relative_path().unwrap(), Some(PathBuf::from("docs/a.txt")));
+        assert_eq!(entry("docs\\a.txt").relative_path().unwrap(), Some(PathBuf::from("docs/a.txt")));
+        assert_eq!(entry("./").relative_path().unwrap(), None);
+        for unsafe_path in ["../../etc/passwd", "docs/../../x", "/etc/passwd", "..\\x"] {
+            assert!(entry(unsafe_path).relative_path().is_err(), "{}", unsafe_path);
+        }
+    }
+}
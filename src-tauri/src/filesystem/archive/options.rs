@@ -0,0 +1,261 @@
+//! Options for creating and extracting archives.
+
+use super::ArchiveFormat;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// How the files of a zip archive are compressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressionMethod {
+    /// No compression
+    Store,
+    #[default]
+    Deflate,
+    Zstd,
+    Bzip2,
+}
+
+/// How a new archive is written. The defaults give the archives `zip` always created.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveOptions {
+    /// Compression of zip archives. Tar archives are compressed as their extension says.
+    pub method: Option<CompressionMethod>,
+    /// Compression level, `None` for the default of the method. The range depends on
+    /// the method: deflate, gzip and xz 0-9, bzip2 1-9, zstd -7-22. Not supported for
+    /// `store` and uncompressed tar archives.
+    pub level: Option<i64>,
+    /// Encrypts every file of a zip archive with AES-256
+    pub password: Option<String>,
+    /// Adds symlinks as links instead of the files they point to
+    pub store_symlinks: bool,
+    /// Globs of what to add, everything if empty
+    pub include: Vec<String>,
+    /// Globs of what to leave out, excluded directories are left out completely
+    pub exclude: Vec<String>,
+}
+
+/// Keeps the password out of logs.
+impl fmt::Debug for ArchiveOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArchiveOptions")
+            .field("method", &self.method)
+            .field("level", &self.level)
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .field("store_symlinks", &self.store_symlinks)
+            .field("include", &self.include)
+            .field("exclude", &self.exclude)
+            .finish()
+    }
+}
+
+/// Default cap on the bytes one archive may expand to
+pub const DEFAULT_MAX_TOTAL_SIZE: u64 = 16 * 1024 * 1024 * 1024;
+/// Default cap on the entries of one archive
+pub const DEFAULT_MAX_ENTRIES: u64 = 500_000;
+
+/// How archives are extracted. The limits stop archives that expand to far more than
+/// they take up, like zip bombs.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtractOptions {
+    /// Password of encrypted zip archives
+    pub password: Option<String>,
+    /// Fails the extraction once the files would add up to more bytes, `None` for no limit
+    pub max_total_size: Option<u64>,
+    /// Fails the extraction if the archive has more entries, `None` for no limit
+    pub max_entries: Option<u64>,
+    /// Only lists what would be written, without writing anything
+    pub dry_run: bool,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            password: None,
+            max_total_size: Some(DEFAULT_MAX_TOTAL_SIZE),
+            max_entries: Some(DEFAULT_MAX_ENTRIES),
+            dry_run: false,
+        }
+    }
+}
+
+/// Keeps the password out of logs.
+impl fmt::Debug for ExtractOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtractOptions")
+            .field("password", &self.password.as_ref().map(|_| "***"))
+            .field("max_total_size", &self.max_total_size)
+            .field("max_entries", &self.max_entries)
+            .field("dry_run", &self.dry_run)
+            .finish()
+    }
+}
+
+/// The valid compression levels of a format and method, `None` if there are none.
+fn level_range(format: ArchiveFormat, method: CompressionMethod) -> Option<RangeInclusive<i64>> {
+    match (format, method) {
+        (ArchiveFormat::Zip, CompressionMethod::Store) | (ArchiveFormat::Tar, _) | (ArchiveFormat::SevenZip, _) => None,
+        (ArchiveFormat::Zip, CompressionMethod::Deflate) | (ArchiveFormat::TarGz, _) | (ArchiveFormat::TarXz, _) => Some(0..=9),
+        (ArchiveFormat::Zip, CompressionMethod::Bzip2) | (ArchiveFormat::TarBz2, _) => Some(1..=9),
+        (ArchiveFormat::Zip, CompressionMethod::Zstd) | (ArchiveFormat::TarZst, _) => Some(-7..=22),
+    }
+}
+
+impl ArchiveOptions {
+    /// Checks that the options can be used for an archive of `format`.
+    ///
+    /// # Returns
+    /// * `Ok(MemberSelection)` - What to add to the archive, from the filters
+    /// * `Err(String)` - If an option does not apply to the format, the level is out of
+    ///   range or a glob is invalid
+    pub fn validate(&self, format: ArchiveFormat) -> Result<MemberSelection, String> {
+        if format != ArchiveFormat::Zip {
+            if self.password.is_some() {
+                return Err("Only zip archives can be encrypted".to_string());
+            }
+            if self.method.is_some() {
+                return Err(format!(
+                    "The compression of {} archives follows from the file extension",
+                    format.extension()
+                ));
+            }
+        }
+        if self.password.as_deref() == Some("") {
+            return Err("Password must not be empty".to_string());
+        }
+        if let Some(level) = self.level {
+            let method = self.method.unwrap_or_default();
+            match level_range(format, method) {
+                None => {
+                    return Err(format!("No compression level can be set for {} archives", self.describe(format)))
+                }
+                Some(range) if !range.contains(&level) => {
+                    return Err(format!(
+                        "Compression level {} is out of range {}..={} for {} archives",
+                        level,
+                        range.start(),
+                        range.end(),
+                        self.describe(format)
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+
+        Ok(MemberSelection {
+            include: compile_globs(&self.include)?,
+            exclude: compile_globs(&self.exclude)?,
+            store_symlinks: self.store_symlinks,
+        })
+    }
+
+    /// The format with the compression method of zip archives, for error messages.
+    fn describe(&self, format: ArchiveFormat) -> String {
+        match format {
+            ArchiveFormat::Zip => format!("{:?} zip", self.method.unwrap_or_default()).to_lowercase(),
+            format => format.extension().to_string(),
+        }
+    }
+}
+
+/// Globs match the `/` separated path in the archive, e.g. `project/src/main.rs`. `*`
+/// stays within one component and `**` spans any number of them. A glob matches at any
+/// depth unless it starts with `/`, so `*.log` leaves out every log file.
+fn compile_globs(globs: &[String]) -> Result<Option<GlobSet>, String> {
+    if globs.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        let pattern = glob.trim().replace('\\', "/");
+        let pattern = match pattern.strip_prefix('/') {
+            Some(anchored) => anchored.to_string(),
+            None => format!("**/{}", pattern),
+        };
+        let compiled = GlobBuilder::new(&pattern)
+            .literal_separator(true)
+            .backslash_escape(false)
+            .build()
+            .map_err(|e| format!("Invalid glob '{}': {}", glob, e))?;
+        builder.add(compiled);
+    }
+    builder.build().map(Some).map_err(|e| e.to_string())
+}
+
+/// What `collect_members` adds to an archive. The default adds everything and follows
+/// symlinks.
+#[derive(Debug, Clone, Default)]
+pub struct MemberSelection {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    pub store_symlinks: bool,
+}
+
+impl MemberSelection {
+    /// Whether a file, symlink or empty directory with this archive name is added.
+    pub fn includes(&self, name: &str) -> bool {
+        self.include.as_ref().is_none_or(|include| include.is_match(name)) && !self.excludes(name)
+    }
+
+    /// Whether an entry with this archive name is left out, with everything below it.
+    pub fn excludes(&self, name: &str) -> bool {
+        self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(name))
+    }
+}
+
+#[cfg(test)]
+mod tests_options {
+    use super::*;
+
+    #[test]
+    fn test_validate_options() {
+        let defaults = ArchiveOptions::default();
+        assert!(defaults.validate(ArchiveFormat::Zip).is_ok());
+        assert!(defaults.validate(ArchiveFormat::TarXz).is_ok());
+
+        let encrypted = ArchiveOptions { password: Some("secret".to_string()), ..ArchiveOptions::default() };
+        assert!(encrypted.validate(ArchiveFormat::Zip).is_ok());
+        assert_eq!(encrypted.validate(ArchiveFormat::TarGz).unwrap_err(), "Only zip archives can be encrypted");
+        assert!(!format!("{:?}", encrypted).contains("secret"));
+
+        let store = ArchiveOptions { method: Some(CompressionMethod::Store), level: Some(1), ..ArchiveOptions::default() };
+        assert_eq!(store.validate(ArchiveFormat::Zip).unwrap_err(), "No compression level can be set for store zip archives");
+        assert!(store.validate(ArchiveFormat::Tar).is_err());
+
+        let zstd = ArchiveOptions { method: Some(CompressionMethod::Zstd), level: Some(22), ..ArchiveOptions::default() };
+        assert!(zstd.validate(ArchiveFormat::Zip).is_ok());
+        let too_high = ArchiveOptions { level: Some(10), ..ArchiveOptions::default() };
+        assert_eq!(
+            too_high.validate(ArchiveFormat::Zip).unwrap_err(),
+            "Compression level 10 is out of range 0..=9 for deflate zip archives"
+        );
+        assert!(too_high.validate(ArchiveFormat::TarZst).is_ok());
+
+        let invalid_glob = ArchiveOptions { include: vec!["src/[".to_string()], ..ArchiveOptions::default() };
+        assert!(invalid_glob.validate(ArchiveFormat::Zip).unwrap_err().starts_with("Invalid glob 'src/['"));
+    }
+
+    #[test]
+    fn test_member_selection() {
+        let options = ArchiveOptions {
+            include: vec!["*.rs".to_string(), "/project/README.md".to_string()],
+            exclude: vec!["target".to_string(), "**/generated_*.rs".to_string()],
+            ..ArchiveOptions::default()
+        };
+        let selection = options.validate(ArchiveFormat::Zip).unwrap();
+        assert!(selection.includes("project/src/main.rs"));
+        assert!(selection.includes("project/README.md"));
+        assert!(!selection.includes("project/docs/README.md"));
+        assert!(!selection.includes("project/src/generated_api.rs"));
+        assert!(selection.excludes("project/target"));
+        assert!(!selection.excludes("project/src"));
+
+        let everything = MemberSelection::default();
+        assert!(everything.includes("anything/at/all"));
+        assert!(!everything.excludes("anything"));
+    }
+}
@@ -0,0 +1,83 @@
+//! 7z archives, which can only be read.
+
+use super::{read_link_target, ArchiveEntry, ArchiveEntryKind, ArchiveReader};
+use sevenz_rust::{Password, SevenZArchiveEntry, SevenZReader};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Set in the Windows attributes when the high 16 bits hold unix permissions
+const UNIX_EXTENSION: u32 = 0x8000;
+const UNIX_FILE_TYPE: u32 = 0o170000;
+const UNIX_SYMLINK: u32 = 0o120000;
+
+fn to_io_error(err: sevenz_rust::Error) -> io::Error {
+    match err {
+        sevenz_rust::Error::Io(err, _) | sevenz_rust::Error::FileOpen(err, _) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err.to_string()),
+    }
+}
+
+pub(super) struct SevenZipArchiveReader {
+    reader: SevenZReader<File>,
+}
+
+impl SevenZipArchiveReader {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self { reader: SevenZReader::open(path, Password::empty()).map_err(to_io_error)? })
+    }
+}
+
+/// Converts the metadata of an entry, without the target of symlinks.
+fn entry_of(entry: &SevenZArchiveEntry) -> ArchiveEntry {
+    let mode = (entry.has_windows_attributes && entry.windows_attributes & UNIX_EXTENSION != 0)
+        .then_some(entry.windows_attributes >> 16);
+    let kind = if entry.is_anti_item() {
+        ArchiveEntryKind::Other
+    } else if entry.is_directory() {
+        ArchiveEntryKind::Directory
+    } else if mode.is_some_and(|mode| mode & UNIX_FILE_TYPE == UNIX_SYMLINK) {
+        ArchiveEntryKind::Symlink
+    } else {
+        ArchiveEntryKind::File
+    };
+    ArchiveEntry {
+        path: entry.name().replace('\\', "/").trim_end_matches('/').to_string(),
+        kind,
+        size: entry.size(),
+        modified: entry
+            .has_last_modified_date
+            .then(|| entry.last_modified_date().to_unix_time()),
+        mode: mode.map(|mode| mode & 0o7777),
+        link_target: None,
+    }
+}
+
+impl ArchiveReader for SevenZipArchiveReader {
+    fn for_each_entry(
+        &mut self,
+        visit: &mut dyn FnMut(&ArchiveEntry, &mut dyn Read) -> io::Result<bool>,
+    ) -> io::Result<()> {
+        // Returning `false` only stops the current solid block, later ones are skipped here
+        let mut stopped = false;
+        self.reader
+            .for_each_entries(|entry, content| {
+                if stopped {
+                    return Ok(false);
+                }
+                let mut entry = entry_of(entry);
+                // The target of a symlink is stored as its content
+                if entry.kind == ArchiveEntryKind::Symlink {
+                    entry.link_target = Some(read_link_target(content)?);
+                }
+                stopped = !visit(&entry, content)?;
+                Ok(!stopped)
+            })
+            .map_err(to_io_error)
+    }
+
+    fn entries(&mut self) -> io::Result<Vec<ArchiveEntry>> {
+        // Read from the header, without decompressing anything
+        Ok(self.reader.archive().files.iter().map(entry_of).collect())
+    }
+}
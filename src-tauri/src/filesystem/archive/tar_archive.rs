@@ -0,0 +1,190 @@
+//! Tar archives, uncompressed or compressed with gzip, bzip2, xz or zstd.
+
+use super::{ArchiveEntry, ArchiveEntryKind, ArchiveFormat, ArchiveReader, ArchiveWriter};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+use tar::{EntryType, Header};
+
+/// Wraps `file` in the decompressor of a tar format.
+pub(super) fn decompress(file: File, format: ArchiveFormat) -> io::Result<Box<dyn Read>> {
+    let file = BufReader::new(file);
+    Ok(match format {
+        ArchiveFormat::Tar => Box::new(file),
+        // Concatenated streams, as written by parallel compressors, are read completely
+        ArchiveFormat::TarGz => Box::new(flate2::bufread::MultiGzDecoder::new(file)),
+        ArchiveFormat::TarBz2 => Box::new(bzip2::bufread::MultiBzDecoder::new(file)),
+        ArchiveFormat::TarXz => Box::new(xz2::bufread::XzDecoder::new_multi_decoder(file)),
+        ArchiveFormat::TarZst => Box::new(zstd::stream::read::Decoder::with_buffer(file)?),
+        ArchiveFormat::Zip | ArchiveFormat::SevenZip => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Not a tar format"))
+        }
+    })
+}
+
+pub(super) struct TarArchiveReader {
+    archive: tar::Archive<Box<dyn Read>>,
+}
+
+impl TarArchiveReader {
+    pub fn open(path: &Path, format: ArchiveFormat) -> io::Result<Self> {
+        Ok(Self { archive: tar::Archive::new(decompress(File::open(path)?, format)?) })
+    }
+}
+
+impl ArchiveReader for TarArchiveReader {
+    fn for_each_entry(
+        &mut self,
+        visit: &mut dyn FnMut(&ArchiveEntry, &mut dyn Read) -> io::Result<bool>,
+    ) -> io::Result<()> {
+        for entry in self.archive.entries()? {
+            let mut entry = entry?;
+            let header = entry.header();
+            let kind = match header.entry_type() {
+                EntryType::Regular | EntryType::Continuous => ArchiveEntryKind::File,
+                EntryType::Directory => ArchiveEntryKind::Directory,
+                EntryType::Symlink => ArchiveEntryKind::Symlink,
+                // Global pax headers carry no entry of their own
+                EntryType::XGlobalHeader => continue,
+                _ => ArchiveEntryKind::Other,
+            };
+            let archive_entry = ArchiveEntry {
+                path: String::from_utf8_lossy(&entry.path_bytes()).trim_end_matches('/').to_string(),
+                kind,
+                size: entry.size(),
+                modified: header.mtime().ok().map(|mtime| mtime as i64),
+                mode: header.mode().ok(),
+                link_target: entry
+                    .link_name_bytes()
+                    .map(|target| String::from_utf8_lossy(&target).to_string()),
+            };
+            if !visit(&archive_entry, &mut entry)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The compressor of a tar format, finished after the tar data is complete.
+enum Compressor {
+    Plain(File),
+    Gzip(flate2::write::GzEncoder<File>),
+    Bzip2(bzip2::write::BzEncoder<File>),
+    Xz(xz2::write::XzEncoder<File>),
+    Zstd(zstd::stream::write::Encoder<'static, File>),
+}
+
+impl Compressor {
+    /// Levels out of range are rejected by `ArchiveOptions::validate` before.
+    fn new(file: File, format: ArchiveFormat, level: Option<i64>) -> io::Result<Self> {
+        Ok(match format {
+            ArchiveFormat::Tar => Compressor::Plain(file),
+            ArchiveFormat::TarGz => {
+                let level = level.map_or(flate2::Compression::default(), |level| flate2::Compression::new(level as u32));
+                Compressor::Gzip(flate2::write::GzEncoder::new(file, level))
+            }
+            ArchiveFormat::TarBz2 => {
+                let level = level.map_or(bzip2::Compression::default(), |level| bzip2::Compression::new(level as u32));
+                Compressor::Bzip2(bzip2::write::BzEncoder::new(file, level))
+            }
+            ArchiveFormat::TarXz => Compressor::Xz(xz2::write::XzEncoder::new(file, level.unwrap_or(6) as u32)),
+            // Level 0 is the default of zstd
+            ArchiveFormat::TarZst => Compressor::Zstd(zstd::stream::write::Encoder::new(file, level.unwrap_or(0) as i32)?),
+            ArchiveFormat::Zip | ArchiveFormat::SevenZip => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "Not a tar format"))
+            }
+        })
+    }
+
+    fn finish(self) -> io::Result<File> {
+        match self {
+            Compressor::Plain(file) => Ok(file),
+            Compressor::Gzip(encoder) => encoder.finish(),
+            Compressor::Bzip2(encoder) => encoder.finish(),
+            Compressor::Xz(encoder) => encoder.finish(),
+            Compressor::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl Write for Compressor {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Compressor::Plain(file) => file.write(buf),
+            Compressor::Gzip(encoder) => encoder.write(buf),
+            Compressor::Bzip2(encoder) => encoder.write(buf),
+            Compressor::Xz(encoder) => encoder.write(buf),
+            Compressor::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Compressor::Plain(file) => file.flush(),
+            Compressor::Gzip(encoder) => encoder.flush(),
+            Compressor::Bzip2(encoder) => encoder.flush(),
+            Compressor::Xz(encoder) => encoder.flush(),
+            Compressor::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Reads exactly `remaining` bytes and fails if the content ends earlier.
+struct ExactSize<R> {
+    content: R,
+    remaining: u64,
+}
+
+impl<R: Read> Read for ExactSize<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.content.read(buf)?;
+        if read == 0 && self.remaining > 0 && !buf.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "The file became shorter while it was archived"));
+        }
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+pub(super) struct TarArchiveWriter {
+    builder: tar::Builder<Compressor>,
+}
+
+impl TarArchiveWriter {
+    pub fn create(path: &Path, format: ArchiveFormat, level: Option<i64>) -> io::Result<Self> {
+        Ok(Self { builder: tar::Builder::new(Compressor::new(File::create(path)?, format, level)?) })
+    }
+}
+
+impl ArchiveWriter for TarArchiveWriter {
+    fn add_directory(&mut self, name: &str, source: &Path) -> io::Result<()> {
+        let mut header = Header::new_gnu();
+        header.set_metadata(&fs::metadata(source)?);
+        header.set_entry_type(EntryType::Directory);
+        header.set_size(0);
+        self.builder.append_data(&mut header, name, io::empty())
+    }
+
+    fn add_file(&mut self, name: &str, source: &Path, content: &mut dyn Read) -> io::Result<()> {
+        let mut header = Header::new_gnu();
+        header.set_metadata(&fs::metadata(source)?);
+        header.set_entry_type(EntryType::Regular);
+        // The size is written before the data, so a file that changed since must not
+        // add more or fewer bytes than announced
+        let size = header.size()?;
+        self.builder.append_data(&mut header, name, ExactSize { content: content.take(size), remaining: size })
+    }
+
+    fn add_symlink(&mut self, name: &str, source: &Path, target: &str) -> io::Result<()> {
+        let mut header = Header::new_gnu();
+        header.set_metadata(&fs::symlink_metadata(source)?);
+        header.set_entry_type(EntryType::Symlink);
+        header.set_size(0);
+        self.builder.append_link(&mut header, name, target)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.builder.into_inner()?.finish()?.sync_all()
+    }
+}
@@ -0,0 +1,80 @@
+//! Malicious archives for the tests of extraction. The writers of this crate refuse to
+//! create such archives, so they are built here with raw names and headers.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+/// Writes a zip archive with entries named exactly as given.
+pub fn zip_with(path: PathBuf, entries: &[(&str, &[u8])]) -> PathBuf {
+    let mut zip = ZipWriter::new(File::create(&path).unwrap());
+    for (name, content) in entries {
+        zip.start_file(*name, FileOptions::<()>::default()).unwrap();
+        zip.write_all(content).unwrap();
+    }
+    zip.finish().unwrap();
+    path
+}
+
+/// Writes a tar archive with entries named exactly as given, which `tar::Builder` would
+/// refuse for `..` components.
+pub fn tar_with(path: PathBuf, entries: &[(&str, &[u8])]) -> PathBuf {
+    let mut builder = tar::Builder::new(File::create(&path).unwrap());
+    for (name, content) in entries {
+        let mut header = tar::Header::new_ustar();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, *content).unwrap();
+    }
+    builder.into_inner().unwrap().sync_all().unwrap();
+    path
+}
+
+/// Archives with entries that point outside of the directory they are extracted into.
+/// The absolute paths point into `dir`, so a broken check cannot write elsewhere.
+pub fn path_traversal(dir: &Path) -> Vec<PathBuf> {
+    let absolute = dir.join("evil.txt").to_string_lossy().to_string();
+    vec![
+        zip_with(dir.join("parent.zip"), &[("safe.txt", b"safe"), ("../evil.txt", b"evil")]),
+        zip_with(dir.join("nested-parent.zip"), &[("docs/a.txt", b"a"), ("docs/../../evil.txt", b"evil")]),
+        zip_with(dir.join("backslash.zip"), &[("docs/a.txt", b"a"), ("..\\evil.txt", b"evil")]),
+        zip_with(dir.join("absolute.zip"), &[("docs/a.txt", b"a"), (&absolute, b"evil")]),
+        tar_with(dir.join("parent.tar"), &[("docs/a.txt", b"a"), ("../evil.txt", b"evil")]),
+        tar_with(dir.join("absolute.tar"), &[("docs/a.txt", b"a"), (&absolute, b"evil")]),
+    ]
+}
+
+/// A zip archive with a single file of `size` zeros, which compresses to almost nothing.
+pub fn zip_bomb(path: PathBuf, size: usize) -> PathBuf {
+    let mut zip = ZipWriter::new(File::create(&path).unwrap());
+    zip.start_file("zeros.bin", FileOptions::<()>::default().compression_level(Some(9))).unwrap();
+    let block = vec![0u8; 64 * 1024];
+    for _ in 0..size / block.len() {
+        zip.write_all(&block).unwrap();
+    }
+    zip.finish().unwrap();
+    path
+}
+
+/// A zip archive with `count` empty files.
+pub fn many_entries(path: PathBuf, count: usize) -> PathBuf {
+    let mut zip = ZipWriter::new(File::create(&path).unwrap());
+    for index in 0..count {
+        zip.start_file(format!("files/{}.txt", index), FileOptions::<()>::default()).unwrap();
+    }
+    zip.finish().unwrap();
+    path
+}
+
+/// A zip archive with a symlink whose target is `size` bytes long.
+pub fn symlink_bomb(path: PathBuf, size: usize) -> PathBuf {
+    let mut zip = ZipWriter::new(File::create(&path).unwrap());
+    zip.add_symlink("link", "a".repeat(size), FileOptions::<()>::default()).unwrap();
+    zip.finish().unwrap();
+    path
+}
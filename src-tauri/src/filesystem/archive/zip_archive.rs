@@ -0,0 +1,173 @@
+//! Zip archives.
+
+use super::{
+    read_link_target, ArchiveEntry, ArchiveEntryKind, ArchiveOptions, ArchiveReader, ArchiveWriter, CompressionMethod,
+};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use zip::write::FileOptions;
+use zip::AesMode;
+use zip::read::ZipFile;
+use zip::{ZipArchive, ZipWriter};
+
+fn to_io_error(err: zip::result::ZipError) -> io::Error {
+    match err {
+        zip::result::ZipError::Io(err) => err,
+        zip::result::ZipError::UnsupportedArchive(zip::result::ZipError::PASSWORD_REQUIRED) => {
+            io::Error::new(io::ErrorKind::InvalidInput, "The archive is encrypted, a password is required")
+        }
+        zip::result::ZipError::InvalidPassword => {
+            io::Error::new(io::ErrorKind::InvalidInput, "Wrong password for the archive")
+        }
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+/// Zip stores local time without a time zone, it is taken as UTC.
+fn unix_timestamp(time: zip::DateTime) -> Option<i64> {
+    let date = chrono::NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?;
+    let time = date.and_hms_opt(time.hour().into(), time.minute().into(), time.second().into())?;
+    Some(time.and_utc().timestamp())
+}
+
+/// Converts the metadata of an entry, without the target of symlinks.
+fn entry_of<R: Read>(file: &ZipFile<'_, R>) -> ArchiveEntry {
+    let kind = if file.is_dir() {
+        ArchiveEntryKind::Directory
+    } else if file.is_symlink() {
+        ArchiveEntryKind::Symlink
+    } else {
+        ArchiveEntryKind::File
+    };
+    ArchiveEntry {
+        path: file.name().trim_end_matches('/').to_string(),
+        kind,
+        size: file.size(),
+        modified: file.last_modified().and_then(unix_timestamp),
+        mode: file.unix_mode(),
+        link_target: None,
+    }
+}
+
+pub(super) struct ZipArchiveReader {
+    archive: ZipArchive<File>,
+    password: Option<Vec<u8>>,
+}
+
+impl ZipArchiveReader {
+    pub fn open(path: &Path, password: Option<&str>) -> io::Result<Self> {
+        Ok(Self {
+            archive: ZipArchive::new(File::open(path)?).map_err(to_io_error)?,
+            password: password.map(|password| password.as_bytes().to_vec()),
+        })
+    }
+}
+
+impl ArchiveReader for ZipArchiveReader {
+    fn for_each_entry(
+        &mut self,
+        visit: &mut dyn FnMut(&ArchiveEntry, &mut dyn Read) -> io::Result<bool>,
+    ) -> io::Result<()> {
+        for index in 0..self.archive.len() {
+            // Files that are not encrypted are read as they are, even with a password
+            let file = match &self.password {
+                Some(password) => self.archive.by_index_decrypt(index, password),
+                None => self.archive.by_index(index),
+            };
+            let mut file = file.map_err(to_io_error)?;
+            let entry = entry_of(&file);
+            // The target of a symlink is stored as its content
+            let entry = if entry.kind == ArchiveEntryKind::Symlink {
+                ArchiveEntry { link_target: Some(read_link_target(&mut file)?), ..entry }
+            } else {
+                entry
+            };
+            if !visit(&entry, &mut file)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn entries(&mut self) -> io::Result<Vec<ArchiveEntry>> {
+        // Read from the central directory, so encrypted archives can be listed without a password
+        let mut entries = Vec::with_capacity(self.archive.len());
+        for index in 0..self.archive.len() {
+            let file = self.archive.by_index_raw(index).map_err(to_io_error)?;
+            let mut entry = entry_of(&file);
+            let encrypted = file.encrypted();
+            drop(file);
+            // The target of a symlink is its content, which stays unknown without the password
+            if entry.kind == ArchiveEntryKind::Symlink && (!encrypted || self.password.is_some()) {
+                let file = match &self.password {
+                    Some(password) => self.archive.by_index_decrypt(index, password),
+                    None => self.archive.by_index(index),
+                };
+                entry.link_target = Some(read_link_target(&mut file.map_err(to_io_error)?)?);
+            }
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+}
+
+/// The settings every file of a new archive is written with.
+struct ZipSettings {
+    method: zip::CompressionMethod,
+    level: Option<i64>,
+    password: Option<String>,
+}
+
+fn file_options(settings: &ZipSettings) -> FileOptions<'_, ()> {
+    let options = FileOptions::default()
+        .compression_method(settings.method)
+        .compression_level(settings.level)
+        .unix_permissions(0o755);
+    match &settings.password {
+        Some(password) => options.with_aes_encryption(AesMode::Aes256, password),
+        None => options,
+    }
+}
+
+pub(super) struct ZipArchiveWriter {
+    zip: ZipWriter<File>,
+    settings: ZipSettings,
+}
+
+impl ZipArchiveWriter {
+    pub fn create(path: &Path, options: &ArchiveOptions) -> io::Result<Self> {
+        let method = match options.method.unwrap_or_default() {
+            CompressionMethod::Store => zip::CompressionMethod::Stored,
+            CompressionMethod::Deflate => zip::CompressionMethod::Deflated,
+            CompressionMethod::Zstd => zip::CompressionMethod::Zstd,
+            CompressionMethod::Bzip2 => zip::CompressionMethod::Bzip2,
+        };
+        Ok(Self {
+            zip: ZipWriter::new(File::create(path)?),
+            settings: ZipSettings { method, level: options.level, password: options.password.clone() },
+        })
+    }
+}
+
+impl ArchiveWriter for ZipArchiveWriter {
+    fn add_directory(&mut self, name: &str, _source: &Path) -> io::Result<()> {
+        // Directories have no content to encrypt
+        let options = FileOptions::<()>::default().unix_permissions(0o755);
+        self.zip.add_directory(name, options).map_err(to_io_error)
+    }
+
+    fn add_file(&mut self, name: &str, _source: &Path, content: &mut dyn Read) -> io::Result<()> {
+        self.zip.start_file(name, file_options(&self.settings)).map_err(to_io_error)?;
+        io::copy(content, &mut self.zip)?;
+        Ok(())
+    }
+
+    fn add_symlink(&mut self, name: &str, _source: &Path, target: &str) -> io::Result<()> {
+        self.zip.add_symlink(name, target, file_options(&self.settings)).map_err(to_io_error)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        self.zip.finish().map_err(to_io_error)?.sync_all()
+    }
+}
@@ -0,0 +1,323 @@
+//! Reading and verifying checksum files as they are published next to downloads.
+//!
+//! Three formats are understood:
+//! * GNU coreutils (`<hash>  <file>` or `<hash> *<file>`), written by `sha256sum` and friends,
+//!   `b3sum` and `xxhsum`, which prefixes XXH3 hashes with `XXH3_`
+//! * BSD (`SHA256 (<file>) = <hash>`), written by BSD `sha256`, `shasum --tag` and `sha256sum --tag`
+//! * A single hash without a file name, e.g. `image.iso.sha256` or the output of
+//!   `gen_hash_and_save_to_file`, which refers to the file named like the checksum file
+//!   without its extension
+//!
+//! PGP clearsigned files are read as well, but the signature is not checked.
+
+use crate::commands::hash_commands::{ChecksumMethod, HashError};
+use crate::filesystem::hashing::hash_file;
+use crate::filesystem::manifest;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::AtomicBool;
+
+/// One line of a checksum file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChecksumLine {
+    /// As written in the checksum file, relative paths are relative to the checksum file
+    pub path: String,
+    pub hash: String,
+    /// The algorithm named on a BSD-style line or by the `XXH3_` prefix of `xxhsum`
+    pub algorithm: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumStatus {
+    Passed,
+    Failed,
+    /// The file does not exist
+    Missing,
+    /// The file exists but could not be read
+    Unreadable,
+    /// The algorithm of the line is not supported or could not be determined
+    UnknownMethod,
+}
+
+/// Result for one file referenced by a checksum file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChecksumFileResult {
+    /// As written in the checksum file
+    pub path: String,
+    pub method: Option<ChecksumMethod>,
+    pub expected_hash: String,
+    pub actual_hash: Option<String>,
+    pub status: ChecksumStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChecksumFileVerification {
+    /// In the order of the checksum file
+    pub files: Vec<ChecksumFileResult>,
+    pub passed: usize,
+    /// Every file that did not pass
+    pub failed: usize,
+    /// Whether the checksum file lists at least one file and all of them passed
+    pub ok: bool,
+}
+
+/// Maps an algorithm name like `SHA256`, `sha-256`, `SHA2-256` or `b3` to a checksum method.
+pub fn method_from_name(name: &str) -> Option<ChecksumMethod> {
+    let name = name.to_uppercase().replace(['-', '_'], "");
+    let name = match name.as_str() {
+        "SHA2256" => "SHA256",
+        "SHA2384" => "SHA384",
+        "SHA2512" => "SHA512",
+        "B3" => "BLAKE3",
+        "XXH364" => "XXH3",
+        name => name,
+    };
+    ChecksumMethod::from_str(name).ok()
+}
+
+/// Infers the checksum method from the name of a checksum file, e.g. `SHA256SUMS`,
+/// `MD5SUMS.txt`, `B3SUMS` or `image.iso.sha512`. Later parts of the name win.
+pub fn method_from_file_name(file_name: &str) -> Option<ChecksumMethod> {
+    file_name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .rev()
+        .find_map(|part| {
+            let part = part.to_uppercase();
+            let name = part
+                .strip_suffix("SUMS")
+                .or_else(|| part.strip_suffix("SUM"))
+                .unwrap_or(&part);
+            method_from_name(name)
+        })
+}
+
+/// Parses a BSD-style line like `SHA256 (file.iso) = <hash>`.
+fn parse_bsd_line(line: &str) -> Option<ChecksumLine> {
+    let (algorithm, rest) = line.split_once(" (")?;
+    let (path, hash) = rest.rsplit_once(") = ")?;
+    if algorithm.is_empty()
+        || algorithm.contains(' ')
+        || path.is_empty()
+        || hash.is_empty()
+        || !hash.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return None;
+    }
+    Some(ChecksumLine {
+        path: path.to_string(),
+        hash: hash.to_lowercase(),
+        algorithm: Some(algorithm.to_string()),
+    })
+}
+
+/// Returns the numbered lines that can contain checksums. Empty lines, `#` comments and
+/// the armor of PGP clearsigned files are left out.
+fn content_lines(text: &str) -> Vec<(usize, &str)> {
+    let mut lines = Vec::new();
+    let mut in_pgp_header = false;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line == "-----BEGIN PGP SIGNED MESSAGE-----" {
+            in_pgp_header = true;
+            continue;
+        }
+        if in_pgp_header {
+            // The armor headers end with an empty line
+            in_pgp_header = !line.is_empty();
+            continue;
+        }
+        if line == "-----BEGIN PGP SIGNATURE-----" {
+            break;
+        }
+        // Clearsigned text escapes lines starting with a dash, no checksum line does
+        let line = line.strip_prefix("- ").unwrap_or(line);
+        if !line.trim().is_empty() && !line.starts_with('#') {
+            lines.push((index + 1, line));
+        }
+    }
+    lines
+}
+
+/// Parses a checksum file in any of the supported formats.
+///
+/// # Arguments
+/// * `text` - The content of the checksum file
+/// * `file_name` - The name of the checksum file, used for files that only contain a hash
+///
+/// # Returns
+/// * `Ok(Vec<ChecksumLine>)` - The lines in file order
+/// * `Err(String)` - The first line that is not in a supported format
+pub fn parse_checksum_file(text: &str, file_name: &str) -> Result<Vec<ChecksumLine>, String> {
+    let lines = content_lines(text);
+    if let [(_, line)] = lines.as_slice() {
+        let hash = line.trim();
+        if hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            let path = Path::new(file_name)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            return Ok(vec![ChecksumLine { path, hash: hash.to_lowercase(), algorithm: None }]);
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|(number, line)| {
+            parse_bsd_line(line)
+                .or_else(|| match line.strip_prefix("XXH3_") {
+                    Some(line) => manifest::parse_line(line)
+                        .map(|(hash, path)| ChecksumLine { path, hash, algorithm: Some("XXH3".to_string()) }),
+                    None => manifest::parse_line(line).map(|(hash, path)| ChecksumLine { path, hash, algorithm: None }),
+                })
+                .ok_or_else(|| format!("Line {} is not a checksum line: {}", number, line))
+        })
+        .collect()
+}
+
+/// Hashes every file referenced by a checksum file and compares it with the listed hash.
+///
+/// The method of a BSD-style line is taken from the line itself. For the other lines
+/// `default_method` is used, or the method is guessed from the length of the hash.
+///
+/// # Arguments
+/// * `base_dir` - The directory relative paths are resolved against, usually the one of the checksum file
+/// * `cancel` - Checked while hashing, a cancelled verification returns `HashError::Cancelled`
+///
+/// # Returns
+/// * `Ok(ChecksumFileVerification)` - The result of every line
+/// * `Err(HashError)` - If the verification was cancelled
+pub fn verify_checksum_lines(
+    base_dir: &Path,
+    lines: &[ChecksumLine],
+    default_method: Option<&ChecksumMethod>,
+    cancel: Option<&AtomicBool>,
+) -> Result<ChecksumFileVerification, HashError> {
+    let mut files = Vec::with_capacity(lines.len());
+    for line in lines {
+        let method = match &line.algorithm {
+            Some(algorithm) => method_from_name(algorithm),
+            None => default_method
+                .cloned()
+                .or_else(|| ChecksumMethod::from_hash_length(line.hash.len())),
+        };
+        let mut result = ChecksumFileResult {
+            path: line.path.clone(),
+            method: method.clone(),
+            expected_hash: line.hash.clone(),
+            actual_hash: None,
+            status: ChecksumStatus::UnknownMethod,
+        };
+        if let Some(method) = method {
+            let path = base_dir.join(&line.path);
+            result.status = if !path.is_file() {
+                ChecksumStatus::Missing
+            } else {
+                match hash_file(&path, std::slice::from_ref(&method), cancel, |_| {}) {
+                    Ok(mut hashes) => {
+                        let actual = hashes.remove(0);
+                        let status = if actual.eq_ignore_ascii_case(&line.hash) {
+                            ChecksumStatus::Passed
+                        } else {
+                            ChecksumStatus::Failed
+                        };
+                        result.actual_hash = Some(actual);
+                        status
+                    }
+                    Err(HashError::Cancelled) => return Err(HashError::Cancelled),
+                    Err(_) => ChecksumStatus::Unreadable,
+                }
+            };
+        }
+        files.push(result);
+    }
+
+    let passed = files.iter().filter(|file| file.status == ChecksumStatus::Passed).count();
+    Ok(ChecksumFileVerification {
+        failed: files.len() - passed,
+        ok: !files.is_empty() && passed == files.len(),
+        passed,
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests_checksum_file {
+    use super::*;
+    use crate::commands::hash_commands::checksum;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_gnu_bsd_and_hash_only_files() {
+        let gnu = "d41d8cd98f00b204e9800998ecf8427e  empty.txt\r\nABCDEF12 *bin/data.bin\n\n";
+        let lines = parse_checksum_file(gnu, "MD5SUMS").unwrap();
+        assert_eq!(lines[0], ChecksumLine { path: "empty.txt".into(), hash: "d41d8cd98f00b204e9800998ecf8427e".into(), algorithm: None });
+        assert_eq!(lines[1].path, "bin/data.bin");
+        assert_eq!(lines[1].hash, "abcdef12");
+
+        let signed = "-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA256\n\n\
+            # image.iso: 4 bytes\n\
+            SHA256 (image (1).iso) = 00ff\n\
+            -----BEGIN PGP SIGNATURE-----\nabc\n-----END PGP SIGNATURE-----\n";
+        let lines = parse_checksum_file(signed, "CHECKSUM").unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].path, "image (1).iso");
+        assert_eq!(lines[0].algorithm.as_deref(), Some("SHA256"));
+
+        let lines = parse_checksum_file("00FF\n", "image.iso.sha256").unwrap();
+        assert_eq!(lines[0].path, "image.iso");
+        assert_eq!(lines[0].hash, "00ff");
+
+        let lines = parse_checksum_file("XXH3_f3c34bf11915e869  hello.txt\n", "hello.xxh").unwrap();
+        assert_eq!(lines[0].algorithm.as_deref(), Some("XXH3"));
+        assert_eq!(lines[0].hash, "f3c34bf11915e869");
+
+        assert!(parse_checksum_file("00ff  a\nnot a checksum\n", "SHA256SUMS").is_err());
+    }
+
+    #[test]
+    fn test_method_inference() {
+        assert_eq!(method_from_file_name("SHA256SUMS"), Some(ChecksumMethod::SHA256));
+        assert_eq!(method_from_file_name("MD5SUMS.txt"), Some(ChecksumMethod::MD5));
+        assert_eq!(method_from_file_name("tool-sha512.tar.gz.md5"), Some(ChecksumMethod::MD5));
+        assert_eq!(method_from_file_name("sha384sum.txt"), Some(ChecksumMethod::SHA384));
+        assert_eq!(method_from_file_name("CHECKSUM"), None);
+        assert_eq!(method_from_file_name("B3SUMS"), Some(ChecksumMethod::BLAKE3));
+        assert_eq!(method_from_file_name("repo.tar.sha1"), Some(ChecksumMethod::SHA1));
+        assert_eq!(method_from_file_name("artifact.xxh3"), Some(ChecksumMethod::XXH3));
+        assert_eq!(method_from_name("SHA2-512"), Some(ChecksumMethod::SHA512));
+        assert_eq!(method_from_name("WHIRLPOOL"), None);
+    }
+
+    #[test]
+    fn test_verify_reports_each_file() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("good.txt"), "good").unwrap();
+        fs::write(temp_dir.path().join("bad.txt"), "bad").unwrap();
+        let text = format!(
+            "{}  good.txt\nMD5 (bad.txt) = {}\n{}  missing.txt\nWHIRLPOOL (good.txt) = 00\n",
+            checksum(&ChecksumMethod::SHA256, b"good"),
+            checksum(&ChecksumMethod::MD5, b"not bad"),
+            checksum(&ChecksumMethod::SHA256, b""),
+        );
+        let lines = parse_checksum_file(&text, "CHECKSUMS").unwrap();
+
+        let verification = verify_checksum_lines(temp_dir.path(), &lines, None, None).unwrap();
+        let statuses: Vec<ChecksumStatus> = verification.files.iter().map(|file| file.status).collect();
+        assert_eq!(
+            statuses,
+            vec![ChecksumStatus::Passed, ChecksumStatus::Failed, ChecksumStatus::Missing, ChecksumStatus::UnknownMethod]
+        );
+        assert_eq!(verification.files[0].method, Some(ChecksumMethod::SHA256));
+        assert_eq!(verification.files[1].actual_hash, Some(checksum(&ChecksumMethod::MD5, b"bad")));
+        assert_eq!((verification.passed, verification.failed, verification.ok), (1, 3, false));
+
+        let cancelled = AtomicBool::new(true);
+        assert!(matches!(
+            verify_checksum_lines(temp_dir.path(), &lines[..1], None, Some(&cancelled)),
+            Err(HashError::Cancelled)
+        ));
+    }
+}
@@ -0,0 +1,213 @@
+//! Conflict handling for operations that write to a path that already exists.
+
+use crate::commands::file_system_operation_commands::generate_unique_path;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// What to do when the destination of a copy already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Keep the existing destination and leave the source out
+    Skip,
+    /// Replace existing files, merge into existing directories
+    Overwrite,
+    /// Like `Overwrite`, but existing files are only replaced by newer ones
+    OverwriteIfNewer,
+    /// Write to a free name such as `file (1).txt` (the default)
+    #[default]
+    Rename,
+    /// Ask the user through a `file-conflict` event for every conflict
+    Ask,
+}
+
+/// The policies of `ConflictPolicy` that need no answer from the user, for commands that
+/// return their result directly. Asking needs the event round-trip of a queued job.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectConflictPolicy {
+    Skip,
+    Overwrite,
+    OverwriteIfNewer,
+    #[default]
+    Rename,
+}
+
+impl From<DirectConflictPolicy> for ConflictPolicy {
+    fn from(policy: DirectConflictPolicy) -> Self {
+        match policy {
+            DirectConflictPolicy::Skip => ConflictPolicy::Skip,
+            DirectConflictPolicy::Overwrite => ConflictPolicy::Overwrite,
+            DirectConflictPolicy::OverwriteIfNewer => ConflictPolicy::OverwriteIfNewer,
+            DirectConflictPolicy::Rename => ConflictPolicy::Rename,
+        }
+    }
+}
+
+/// How a single conflict was resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictChoice {
+    Skip,
+    /// Replaces a file, merges a directory into the existing one
+    Overwrite,
+    Rename,
+}
+
+/// An answer to a `file-conflict` event.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ConflictDecision {
+    pub choice: ConflictChoice,
+    /// Use the same choice for all further conflicts of the operation
+    pub apply_to_all: bool,
+}
+
+/// A conflict that needs a decision, sent as `file-conflict` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileConflict {
+    pub job_id: u64,
+    pub conflict_id: u64,
+    pub source: String,
+    pub destination: String,
+    pub source_is_dir: bool,
+    pub destination_is_dir: bool,
+    pub source_size: u64,
+    pub destination_size: u64,
+    pub source_modified: Option<u64>,      // as milliseconds since epoch
+    pub destination_modified: Option<u64>, // as milliseconds since epoch
+}
+
+impl FileConflict {
+    pub fn new(job_id: u64, conflict_id: u64, source: &Path, destination: &Path) -> Self {
+        let source_meta = fs::metadata(source).ok();
+        let destination_meta = fs::metadata(destination).ok();
+        Self {
+            job_id,
+            conflict_id,
+            source: source.to_string_lossy().to_string(),
+            destination: destination.to_string_lossy().to_string(),
+            source_is_dir: source_meta.as_ref().is_some_and(|meta| meta.is_dir()),
+            destination_is_dir: destination_meta.as_ref().is_some_and(|meta| meta.is_dir()),
+            source_size: source_meta.as_ref().map(|meta| meta.len()).unwrap_or(0),
+            destination_size: destination_meta.as_ref().map(|meta| meta.len()).unwrap_or(0),
+            source_modified: source_meta.as_ref().and_then(modified_millis),
+            destination_modified: destination_meta.as_ref().and_then(modified_millis),
+        }
+    }
+}
+
+/// A resolved conflict, reported in the result of the operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConflictRecord {
+    pub source: String,
+    /// The path that already existed
+    pub destination: String,
+    pub choice: ConflictChoice,
+    /// Where the source was written to, `None` if it was skipped
+    pub written_to: Option<String>,
+    /// Whether the choice was made by the user rather than by the policy
+    pub asked: bool,
+}
+
+pub(crate) fn modified_millis(meta: &fs::Metadata) -> Option<u64> {
+    let modified = meta.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+/// Resolves a conflict from the policy alone.
+///
+/// `OverwriteIfNewer` overwrites files whose source was modified after the
+/// destination and merges directories, so their files are compared one by one.
+///
+/// # Returns
+/// The choice, or `None` for `ConflictPolicy::Ask`
+pub fn decide(policy: ConflictPolicy, source: &Path, destination: &Path) -> Option<ConflictChoice> {
+    match policy {
+        ConflictPolicy::Skip => Some(ConflictChoice::Skip),
+        ConflictPolicy::Overwrite => Some(ConflictChoice::Overwrite),
+        ConflictPolicy::Rename => Some(ConflictChoice::Rename),
+        ConflictPolicy::Ask => None,
+        ConflictPolicy::OverwriteIfNewer => {
+            if source.is_dir() && destination.is_dir() {
+                return Some(ConflictChoice::Overwrite);
+            }
+            let modified = |path: &Path| fs::metadata(path).ok().and_then(|meta| modified_millis(&meta));
+            match (modified(source), modified(destination)) {
+                (Some(source), Some(destination)) if source > destination => Some(ConflictChoice::Overwrite),
+                _ => Some(ConflictChoice::Skip),
+            }
+        }
+    }
+}
+
+/// The path a source is written to after a conflict was resolved.
+///
+/// # Returns
+/// `None` for `ConflictChoice::Skip`
+pub fn target_for(choice: ConflictChoice, destination: &Path) -> Option<PathBuf> {
+    match choice {
+        ConflictChoice::Skip => None,
+        ConflictChoice::Overwrite => Some(destination.to_path_buf()),
+        ConflictChoice::Rename => Some(PathBuf::from(generate_unique_path(&destination.to_string_lossy()))),
+    }
+}
+
+/// Removes an existing destination that cannot be merged with the source
+/// (a file replaced by a directory or the other way around).
+pub fn clear_for_overwrite(source: &Path, destination: &Path) -> std::io::Result<()> {
+    let destination_is_dir = fs::symlink_metadata(destination).map(|meta| meta.is_dir());
+    match destination_is_dir {
+        Ok(true) if !source.is_dir() => fs::remove_dir_all(destination),
+        Ok(false) if source.is_dir() => fs::remove_file(destination),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests_conflict_policy {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_overwrite_if_newer_compares_modification_times() {
+        let temp_dir = TempDir::new().unwrap();
+        let old = temp_dir.path().join("old.txt");
+        let new = temp_dir.path().join("new.txt");
+        fs::write(&old, "old").unwrap();
+        fs::write(&new, "new").unwrap();
+        let past = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options().write(true).open(&old).unwrap().set_modified(past).unwrap();
+
+        assert_eq!(decide(ConflictPolicy::OverwriteIfNewer, &new, &old), Some(ConflictChoice::Overwrite));
+        assert_eq!(decide(ConflictPolicy::OverwriteIfNewer, &old, &new), Some(ConflictChoice::Skip));
+        assert_eq!(decide(ConflictPolicy::Ask, &old, &new), None);
+    }
+
+    #[test]
+    fn test_target_for_choice() {
+        let temp_dir = TempDir::new().unwrap();
+        let existing = temp_dir.path().join("report.txt");
+        fs::write(&existing, "data").unwrap();
+
+        assert_eq!(target_for(ConflictChoice::Skip, &existing), None);
+        assert_eq!(target_for(ConflictChoice::Overwrite, &existing), Some(existing.clone()));
+        assert_eq!(
+            target_for(ConflictChoice::Rename, &existing),
+            Some(temp_dir.path().join("report (1).txt"))
+        );
+    }
+
+    #[test]
+    fn test_policy_serialization() {
+        assert_eq!(serde_json::to_string(&ConflictPolicy::OverwriteIfNewer).unwrap(), "\"overwrite_if_newer\"");
+        assert_eq!(serde_json::from_str::<ConflictPolicy>("\"ask\"").unwrap(), ConflictPolicy::Ask);
+        assert_eq!(
+            ConflictPolicy::from(serde_json::from_str::<DirectConflictPolicy>("\"overwrite_if_newer\"").unwrap()),
+            ConflictPolicy::OverwriteIfNewer
+        );
+        assert!(serde_json::from_str::<DirectConflictPolicy>("\"ask\"").is_err());
+    }
+}
@@ -0,0 +1,399 @@
+//! Sorted directory listings that are delivered in chunks, for folders too large to
+//! send as a single response.
+
+use crate::filesystem::folder_size::{cached_folder_size, FolderSizeCache};
+use crate::models::{
+    count_subfiles_and_subdirectories, format_system_time, get_access_permission_number,
+    get_access_permission_string, File,
+};
+use crate::state::{SortBy, SortDirection};
+#[allow(unused_imports)]
+use crate::{log_error, log_info, log_warn};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs::{self, Permissions};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Number of entries per chunk if the caller does not choose one
+pub const DEFAULT_CHUNK_SIZE: usize = 500;
+
+/// A directory as returned by `open_directory`, except that the child counts are only
+/// filled in when requested, since counting needs one extra directory read per child.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListedDirectory {
+    pub name: String,
+    pub path: String,
+    pub is_symlink: bool,
+    pub access_rights_as_string: String,
+    pub access_rights_as_number: u32,
+    pub size_in_bytes: u64,
+    pub sub_file_count: Option<usize>,
+    pub sub_dir_count: Option<usize>,
+    pub created: String,
+    pub last_modified: String,
+    pub accessed: String,
+}
+
+/// A part of a sorted listing. Directories come before files, so a chunk only contains
+/// both if it is where the directories end.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryChunk {
+    /// Position of the first entry of this chunk in the whole listing
+    pub offset: usize,
+    /// Number of entries in the whole listing
+    pub total: usize,
+    pub directories: Vec<ListedDirectory>,
+    pub files: Vec<File>,
+    /// Set on the last chunk
+    pub done: bool,
+}
+
+/// The child counts of a directory, see `child_counts`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChildCounts {
+    pub path: String,
+    pub sub_file_count: usize,
+    pub sub_dir_count: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListingOptions {
+    pub sort_by: SortBy,
+    pub sort_direction: SortDirection,
+    pub chunk_size: usize,
+    /// Count the children of every listed directory
+    pub child_counts: bool,
+}
+
+/// What is kept of every entry until it is sent, so a huge listing stays small in memory.
+struct ListedEntry {
+    name: String,
+    sort_name: String,
+    extension: String,
+    path: PathBuf,
+    is_symlink: bool,
+    size: u64,
+    permissions: Permissions,
+    created: Option<SystemTime>,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+}
+
+impl ListedEntry {
+    fn compare(&self, other: &Self, sort_by: SortBy) -> Ordering {
+        let primary = match sort_by {
+            SortBy::Name => Ordering::Equal,
+            SortBy::Size => self.size.cmp(&other.size),
+            SortBy::Date => self.modified.cmp(&other.modified),
+            SortBy::Type => self.extension.cmp(&other.extension),
+        };
+        primary
+            .then_with(|| self.sort_name.cmp(&other.sort_name))
+            .then_with(|| self.name.cmp(&other.name))
+    }
+}
+
+fn format_time(time: Option<SystemTime>) -> String {
+    time.map_or("1970-01-01 00:00:00".to_string(), format_system_time)
+}
+
+/// Reads a directory, sorts it and passes it to `send` in chunks of `options.chunk_size`.
+///
+/// Entries that cannot be read are skipped. Symlinks are listed as what they point to.
+/// Directories get their size from `folder_sizes` if it is still valid, 0 otherwise.
+/// At least one chunk is sent, even for an empty directory.
+///
+/// # Returns
+/// * `Ok(usize)` - The number of listed entries
+/// * `Err(String)` - If the directory could not be read or `send` failed
+pub fn stream_directory(
+    path: &Path,
+    options: ListingOptions,
+    folder_sizes: &FolderSizeCache,
+    mut send: impl FnMut(DirectoryChunk) -> Result<(), String>,
+) -> Result<usize, String> {
+    let mut directories = Vec::new();
+    let mut files = Vec::new();
+
+    let read = fs::read_dir(path).map_err(|err| format!("Failed to read directory: {}", err))?;
+    for entry in read {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                log_warn!("Skipping unreadable entry in {}: {}", path.display(), err);
+                continue;
+            }
+        };
+        let entry_path = entry.path();
+        let is_symlink = entry.file_type().is_ok_and(|file_type| file_type.is_symlink());
+        let metadata = match fs::metadata(&entry_path).or_else(|_| fs::symlink_metadata(&entry_path)) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                log_warn!("Skipping {}: {}", entry_path.display(), err);
+                continue;
+            }
+        };
+
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_dir = metadata.is_dir();
+        let size = if is_dir {
+            cached_folder_size(&entry_path, folder_sizes).map_or(0, |size| size.size_in_bytes)
+        } else {
+            metadata.len()
+        };
+        let listed = ListedEntry {
+            sort_name: name.to_lowercase(),
+            extension: if is_dir {
+                String::new()
+            } else {
+                entry_path
+                    .extension()
+                    .map(|extension| extension.to_string_lossy().to_lowercase())
+                    .unwrap_or_default()
+            },
+            name,
+            path: entry_path,
+            is_symlink,
+            size,
+            permissions: metadata.permissions(),
+            created: metadata.created().ok(),
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+        };
+        if is_dir {
+            directories.push(listed);
+        } else {
+            files.push(listed);
+        }
+    }
+
+    for entries in [&mut directories, &mut files] {
+        entries.sort_by(|a, b| {
+            let ordering = a.compare(b, options.sort_by);
+            match options.sort_direction {
+                SortDirection::Acscending => ordering,
+                SortDirection::Descending => ordering.reverse(),
+            }
+        });
+    }
+
+    let total = directories.len() + files.len();
+    let chunk_size = options.chunk_size.max(1);
+    let mut offset = 0;
+    let mut directories = directories.into_iter();
+    let mut files = files.into_iter();
+    loop {
+        let mut chunk = DirectoryChunk {
+            offset,
+            total,
+            directories: Vec::new(),
+            files: Vec::new(),
+            done: false,
+        };
+        while chunk.directories.len() < chunk_size {
+            match directories.next() {
+                Some(entry) => chunk.directories.push(to_directory(entry, options.child_counts)),
+                None => break,
+            }
+        }
+        while chunk.directories.len() + chunk.files.len() < chunk_size {
+            match files.next() {
+                Some(entry) => chunk.files.push(to_file(entry)),
+                None => break,
+            }
+        }
+
+        offset += chunk.directories.len() + chunk.files.len();
+        chunk.done = offset == total;
+        let done = chunk.done;
+        send(chunk)?;
+        if done {
+            return Ok(total);
+        }
+    }
+}
+
+fn to_directory(entry: ListedEntry, child_counts: bool) -> ListedDirectory {
+    let path = entry.path.to_string_lossy().to_string();
+    let (sub_file_count, sub_dir_count) = if child_counts {
+        let (files, directories) = count_subfiles_and_subdirectories(&path);
+        (Some(files), Some(directories))
+    } else {
+        (None, None)
+    };
+    ListedDirectory {
+        name: entry.name,
+        path,
+        is_symlink: entry.is_symlink,
+        access_rights_as_string: get_access_permission_string(entry.permissions.clone(), true),
+        access_rights_as_number: get_access_permission_number(entry.permissions, true),
+        size_in_bytes: entry.size,
+        sub_file_count,
+        sub_dir_count,
+        created: format_time(entry.created),
+        last_modified: format_time(entry.modified),
+        accessed: format_time(entry.accessed),
+    }
+}
+
+fn to_file(entry: ListedEntry) -> File {
+    File {
+        name: entry.name,
+        path: entry.path.to_string_lossy().to_string(),
+        is_symlink: entry.is_symlink,
+        access_rights_as_string: get_access_permission_string(entry.permissions.clone(), false),
+        access_rights_as_number: get_access_permission_number(entry.permissions, false),
+        size_in_bytes: entry.size,
+        created: format_time(entry.created),
+        last_modified: format_time(entry.modified),
+        accessed: format_time(entry.accessed),
+    }
+}
+
+/// Counts the files and directories directly inside each of the given directories.
+/// Paths that cannot be read get zero counts.
+pub fn child_counts(paths: &[String]) -> Vec<ChildCounts> {
+    paths
+        .iter()
+        .map(|path| {
+            let (sub_file_count, sub_dir_count) = count_subfiles_and_subdirectories(path);
+            ChildCounts {
+                path: path.clone(),
+                sub_file_count,
+                sub_dir_count,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests_directory_listing {
+    use super::*;
+    use crate::filesystem::folder_size::measure_folder;
+    use std::fs::File as FsFile;
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn options(sort_by: SortBy, sort_direction: SortDirection, chunk_size: usize) -> ListingOptions {
+        ListingOptions {
+            sort_by,
+            sort_direction,
+            chunk_size,
+            child_counts: false,
+        }
+    }
+
+    fn collect(path: &Path, options: ListingOptions) -> Vec<DirectoryChunk> {
+        collect_with_sizes(path, options, &FolderSizeCache::new())
+    }
+
+    fn collect_with_sizes(path: &Path, options: ListingOptions, folder_sizes: &FolderSizeCache) -> Vec<DirectoryChunk> {
+        let mut chunks = Vec::new();
+        stream_directory(path, options, folder_sizes, |chunk| {
+            chunks.push(chunk);
+            Ok(())
+        })
+        .unwrap();
+        chunks
+    }
+
+    fn names(chunks: &[DirectoryChunk]) -> Vec<String> {
+        chunks
+            .iter()
+            .flat_map(|chunk| {
+                let directories = chunk.directories.iter().map(|directory| directory.name.clone());
+                directories.chain(chunk.files.iter().map(|file| file.name.clone())).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chunks_cover_sorted_listing() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["b.txt", "A.md", "c.rs"] {
+            fs::write(temp_dir.path().join(name), "").unwrap();
+        }
+        fs::create_dir(temp_dir.path().join("zeta")).unwrap();
+        fs::create_dir(temp_dir.path().join("Alpha")).unwrap();
+
+        let chunks = collect(temp_dir.path(), options(SortBy::Name, SortDirection::Acscending, 2));
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(names(&chunks), ["Alpha", "zeta", "A.md", "b.txt", "c.rs"]);
+        assert_eq!(chunks.iter().map(|chunk| chunk.offset).collect::<Vec<_>>(), [0, 2, 4]);
+        assert!(chunks.iter().all(|chunk| chunk.total == 5));
+        assert_eq!(chunks.iter().filter(|chunk| chunk.done).count(), 1);
+        assert!(chunks[2].done);
+        assert_eq!(chunks[0].directories[0].sub_file_count, None);
+    }
+
+    #[test]
+    fn test_sort_by_size_date_and_type() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("small.txt"), "1").unwrap();
+        fs::write(temp_dir.path().join("large.md"), "1234567890").unwrap();
+        fs::write(temp_dir.path().join("medium.rs"), "12345").unwrap();
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        FsFile::options()
+            .write(true)
+            .open(temp_dir.path().join("large.md"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        let by_size = collect(temp_dir.path(), options(SortBy::Size, SortDirection::Descending, 10));
+        assert_eq!(names(&by_size), ["large.md", "medium.rs", "small.txt"]);
+
+        let by_date = collect(temp_dir.path(), options(SortBy::Date, SortDirection::Acscending, 10));
+        assert_eq!(names(&by_date)[0], "large.md");
+
+        let by_type = collect(temp_dir.path(), options(SortBy::Type, SortDirection::Acscending, 10));
+        assert_eq!(names(&by_type), ["large.md", "medium.rs", "small.txt"]);
+    }
+
+    #[test]
+    fn test_measured_folders_are_listed_with_their_size() {
+        let temp_dir = TempDir::new().unwrap();
+        for (name, content) in [("small", "1"), ("large", "1234567890"), ("unmeasured", "12345")] {
+            fs::create_dir_all(temp_dir.path().join(name).join("nested")).unwrap();
+            fs::write(temp_dir.path().join(name).join("nested/file.txt"), content).unwrap();
+        }
+        let folder_sizes = FolderSizeCache::new();
+        for name in ["small", "large"] {
+            measure_folder(&temp_dir.path().join(name), &folder_sizes, &AtomicBool::new(false), |_| {}).unwrap();
+        }
+
+        let chunks = collect_with_sizes(temp_dir.path(), options(SortBy::Size, SortDirection::Descending, 10), &folder_sizes);
+        assert_eq!(names(&chunks), ["large", "small", "unmeasured"]);
+        let sizes: Vec<u64> = chunks[0].directories.iter().map(|directory| directory.size_in_bytes).collect();
+        assert_eq!(sizes, [10, 1, 0]);
+
+        // A changed folder is 0 again until it is measured again
+        fs::write(temp_dir.path().join("large/nested/more.txt"), "1").unwrap();
+        let chunks = collect_with_sizes(temp_dir.path(), options(SortBy::Name, SortDirection::Acscending, 10), &folder_sizes);
+        assert_eq!(chunks[0].directories[0].size_in_bytes, 0);
+    }
+
+    #[test]
+    fn test_empty_directory_and_child_counts() {
+        let temp_dir = TempDir::new().unwrap();
+        let chunks = collect(temp_dir.path(), options(SortBy::Name, SortDirection::Acscending, 10));
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].done);
+        assert_eq!(chunks[0].total, 0);
+
+        let child = temp_dir.path().join("child");
+        fs::create_dir_all(child.join("nested")).unwrap();
+        fs::write(child.join("file.txt"), "").unwrap();
+        let mut with_counts = options(SortBy::Name, SortDirection::Acscending, 10);
+        with_counts.child_counts = true;
+        let chunks = collect(temp_dir.path(), with_counts);
+        assert_eq!(chunks[0].directories[0].sub_file_count, Some(1));
+        assert_eq!(chunks[0].directories[0].sub_dir_count, Some(1));
+
+        let counts = child_counts(&[child.to_string_lossy().to_string()]);
+        assert_eq!((counts[0].sub_file_count, counts[0].sub_dir_count), (1, 1));
+    }
+}
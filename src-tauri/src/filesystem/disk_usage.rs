@@ -0,0 +1,434 @@
+//! Disk usage analysis that builds a size tree of a folder or volume.
+//!
+//! The scan keeps every directory with the total size of the files directly inside it, but
+//! remembers only the largest of these files by name. A snapshot of the scan turns this into a
+//! tree with the largest children of every directory down to a maximum depth, everything
+//! else of a directory is combined into one "other" node. Snapshots can be taken while the
+//! scan is still running, so a partial tree can be shown early.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Name of the node that combines the children left out of the tree
+pub const OTHER_NODE_NAME: &str = "Other";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DiskUsageOptions {
+    /// Largest children kept per directory, and largest file types
+    pub top_n: usize,
+    /// Levels below the root that get children in the tree
+    pub max_depth: usize,
+    /// Whether to skip directories on other filesystems, e.g. when analyzing `/`
+    pub same_file_system: bool,
+}
+
+impl Default for DiskUsageOptions {
+    fn default() -> Self {
+        Self {
+            top_n: 20,
+            max_depth: 4,
+            same_file_system: true,
+        }
+    }
+}
+
+/// A file, a directory or an "other" bucket in the size tree.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageNode {
+    pub name: String,
+    /// Empty for "other" buckets
+    pub path: String,
+    pub size_in_bytes: u64,
+    /// Files inside the node, 1 for a file
+    pub file_count: u64,
+    pub is_dir: bool,
+    /// Whether the node combines the smaller children of its parent
+    pub is_other: bool,
+    /// Largest first. Empty for files, and for directories deeper than `max_depth`
+    pub children: Vec<UsageNode>,
+}
+
+/// Total size of all files with the same extension.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileTypeUsage {
+    /// Lowercase extension without the dot, empty for files without extension,
+    /// `OTHER_NODE_NAME` for the combined remaining extensions
+    pub extension: String,
+    pub size_in_bytes: u64,
+    pub file_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiskUsageTree {
+    pub root: UsageNode,
+    /// Largest first, the remaining extensions combined into the last entry
+    pub file_types: Vec<FileTypeUsage>,
+    pub scanned_files: u64,
+    pub scanned_dirs: u64,
+    /// Directories that could not be read
+    pub skipped_dirs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiskUsageError {
+    Cancelled,
+    Failed(String),
+}
+
+struct ScannedDirectory {
+    name: String,
+    path: PathBuf,
+    file_bytes: u64,
+    file_count: u64,
+    /// The largest files directly inside the directory, largest first
+    largest_files: Vec<(String, u64)>,
+    subdirectories: Vec<usize>,
+}
+
+/// State of a running analysis, see `DiskUsageScan::snapshot`.
+pub struct DiskUsageScan {
+    options: DiskUsageOptions,
+    /// Every directory is stored after its parent
+    directories: Vec<ScannedDirectory>,
+    file_types: HashMap<String, (u64, u64)>,
+    scanned_files: u64,
+    skipped_dirs: u64,
+    /// Files with several hard links, so they are only counted once
+    seen_inodes: HashSet<(u64, u64)>,
+}
+
+impl DiskUsageScan {
+    fn new(root: &Path, options: DiskUsageOptions) -> Self {
+        let name = root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| root.to_string_lossy().to_string());
+        Self {
+            options,
+            directories: vec![ScannedDirectory::new(name, root.to_path_buf())],
+            file_types: HashMap::new(),
+            scanned_files: 0,
+            skipped_dirs: 0,
+            seen_inodes: HashSet::new(),
+        }
+    }
+
+    /// Builds the size tree of everything scanned so far.
+    pub fn snapshot(&self) -> DiskUsageTree {
+        // Children are always stored after their parent, so one backwards pass sums up the tree
+        let mut totals: Vec<(u64, u64)> = self
+            .directories
+            .iter()
+            .map(|dir| (dir.file_bytes, dir.file_count))
+            .collect();
+        for index in (0..self.directories.len()).rev() {
+            for &child in &self.directories[index].subdirectories {
+                let (child_bytes, child_files) = totals[child];
+                totals[index] = (totals[index].0 + child_bytes, totals[index].1 + child_files);
+            }
+        }
+
+        DiskUsageTree {
+            root: self.build_node(0, 0, &totals),
+            file_types: self.largest_file_types(),
+            scanned_files: self.scanned_files,
+            scanned_dirs: self.directories.len() as u64,
+            skipped_dirs: self.skipped_dirs,
+        }
+    }
+
+    fn build_node(&self, index: usize, depth: usize, totals: &[(u64, u64)]) -> UsageNode {
+        let dir = &self.directories[index];
+        let (size_in_bytes, file_count) = totals[index];
+        let mut node = UsageNode {
+            name: dir.name.clone(),
+            path: dir.path.to_string_lossy().to_string(),
+            size_in_bytes,
+            file_count,
+            is_dir: true,
+            is_other: false,
+            children: Vec::new(),
+        };
+        if depth >= self.options.max_depth {
+            return node;
+        }
+
+        // Directories and files compete for the top places by size
+        let mut candidates: Vec<(u64, Option<usize>, usize)> = dir
+            .subdirectories
+            .iter()
+            .map(|&child| (totals[child].0, Some(child), 0))
+            .chain(dir.largest_files.iter().enumerate().map(|(file, (_, size))| (*size, None, file)))
+            .collect();
+        candidates.sort_by_key(|candidate| Reverse(candidate.0));
+
+        let mut other_bytes = size_in_bytes;
+        let mut other_files = file_count;
+        for (size, child, file) in candidates.into_iter().take(self.options.top_n) {
+            let child_node = match child {
+                Some(child) => self.build_node(child, depth + 1, totals),
+                None => {
+                    let (name, _) = &dir.largest_files[file];
+                    UsageNode {
+                        name: name.clone(),
+                        path: dir.path.join(name).to_string_lossy().to_string(),
+                        size_in_bytes: size,
+                        file_count: 1,
+                        is_dir: false,
+                        is_other: false,
+                        children: Vec::new(),
+                    }
+                }
+            };
+            other_bytes -= child_node.size_in_bytes;
+            other_files -= child_node.file_count;
+            node.children.push(child_node);
+        }
+
+        if other_bytes > 0 || other_files > 0 {
+            node.children.push(UsageNode {
+                name: OTHER_NODE_NAME.to_string(),
+                path: String::new(),
+                size_in_bytes: other_bytes,
+                file_count: other_files,
+                is_dir: false,
+                is_other: true,
+                children: Vec::new(),
+            });
+        }
+        node
+    }
+
+    fn largest_file_types(&self) -> Vec<FileTypeUsage> {
+        let mut types: Vec<FileTypeUsage> = self
+            .file_types
+            .iter()
+            .map(|(extension, (size_in_bytes, file_count))| FileTypeUsage {
+                extension: extension.clone(),
+                size_in_bytes: *size_in_bytes,
+                file_count: *file_count,
+            })
+            .collect();
+        types.sort_by(|a, b| b.size_in_bytes.cmp(&a.size_in_bytes).then_with(|| a.extension.cmp(&b.extension)));
+
+        if types.len() > self.options.top_n {
+            let rest = types.split_off(self.options.top_n);
+            types.push(FileTypeUsage {
+                extension: OTHER_NODE_NAME.to_string(),
+                size_in_bytes: rest.iter().map(|usage| usage.size_in_bytes).sum(),
+                file_count: rest.iter().map(|usage| usage.file_count).sum(),
+            });
+        }
+        types
+    }
+
+    fn add_file(&mut self, index: usize, name: String, meta: &fs::Metadata) {
+        if !self.first_link(meta) {
+            return;
+        }
+        let size = meta.len();
+        let extension = Path::new(&name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let usage = self.file_types.entry(extension).or_default();
+        usage.0 += size;
+        usage.1 += 1;
+        self.scanned_files += 1;
+
+        let top_n = self.options.top_n;
+        let dir = &mut self.directories[index];
+        dir.file_bytes += size;
+        dir.file_count += 1;
+        if dir.largest_files.len() < top_n || dir.largest_files.last().is_some_and(|(_, smallest)| size > *smallest) {
+            let position = dir.largest_files.partition_point(|(_, other)| *other >= size);
+            dir.largest_files.insert(position, (name, size));
+            dir.largest_files.truncate(top_n);
+        }
+    }
+
+    #[cfg(unix)]
+    fn first_link(&mut self, meta: &fs::Metadata) -> bool {
+        use std::os::unix::fs::MetadataExt;
+        meta.nlink() <= 1 || self.seen_inodes.insert((meta.dev(), meta.ino()))
+    }
+
+    #[cfg(not(unix))]
+    fn first_link(&mut self, _meta: &fs::Metadata) -> bool {
+        true
+    }
+}
+
+impl ScannedDirectory {
+    fn new(name: String, path: PathBuf) -> Self {
+        Self {
+            name,
+            path,
+            file_bytes: 0,
+            file_count: 0,
+            largest_files: Vec::new(),
+            subdirectories: Vec::new(),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn device_of(meta: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(meta.dev())
+}
+
+#[cfg(not(unix))]
+fn device_of(_meta: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Scans everything below `root` and builds its size tree.
+///
+/// Symlinks are counted with their own size and never followed, files with several hard
+/// links are counted once. Directories that cannot be read are skipped and counted in
+/// `skipped_dirs`. `on_progress` is called after every directory with the scan so far,
+/// `cancel` is checked before every directory.
+///
+/// # Returns
+/// * `Ok(DiskUsageTree)` - The size tree of `root`
+/// * `Err(DiskUsageError)` - If `root` is not a readable directory or the scan was cancelled
+pub fn analyze_disk_usage(
+    root: &Path,
+    options: DiskUsageOptions,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(&DiskUsageScan),
+) -> Result<DiskUsageTree, DiskUsageError> {
+    let root_meta = fs::metadata(root)
+        .map_err(|err| DiskUsageError::Failed(format!("Cannot access '{}': {}", root.display(), err)))?;
+    if !root_meta.is_dir() {
+        return Err(DiskUsageError::Failed(format!("'{}' is not a directory", root.display())));
+    }
+    let root_device = device_of(&root_meta);
+
+    let mut scan = DiskUsageScan::new(root, options);
+    let mut pending = vec![0];
+    while let Some(index) = pending.pop() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(DiskUsageError::Cancelled);
+        }
+
+        let entries = match fs::read_dir(&scan.directories[index].path) {
+            Ok(entries) => entries,
+            Err(err) if index == 0 => {
+                return Err(DiskUsageError::Failed(format!("Cannot read '{}': {}", root.display(), err)));
+            }
+            Err(_) => {
+                scan.skipped_dirs += 1;
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !meta.is_dir() {
+                scan.add_file(index, name, &meta);
+                continue;
+            }
+            if options.same_file_system && device_of(&meta) != root_device {
+                continue;
+            }
+            let child = scan.directories.len();
+            scan.directories.push(ScannedDirectory::new(name, entry.path()));
+            scan.directories[index].subdirectories.push(child);
+            pending.push(child);
+        }
+        on_progress(&scan);
+    }
+    Ok(scan.snapshot())
+}
+
+#[cfg(test)]
+mod tests_disk_usage {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn analyze(root: &Path, options: DiskUsageOptions) -> DiskUsageTree {
+        analyze_disk_usage(root, options, &AtomicBool::new(false), |_| {}).unwrap()
+    }
+
+    fn child<'a>(node: &'a UsageNode, name: &str) -> &'a UsageNode {
+        node.children.iter().find(|child| child.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_tree_keeps_largest_children_and_other_bucket() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("build/cache")).unwrap();
+        fs::write(root.join("build/cache/blob.bin"), vec![0u8; 1000]).unwrap();
+        fs::write(root.join("build/log.txt"), vec![0u8; 100]).unwrap();
+        fs::write(root.join("video.mp4"), vec![0u8; 500]).unwrap();
+        fs::write(root.join("a.txt"), vec![0u8; 10]).unwrap();
+        fs::write(root.join("b.txt"), vec![0u8; 20]).unwrap();
+
+        let options = DiskUsageOptions { top_n: 2, max_depth: 1, same_file_system: true };
+        let tree = analyze(root, options);
+
+        assert_eq!(tree.root.size_in_bytes, 1630);
+        assert_eq!(tree.root.file_count, 5);
+        assert_eq!(tree.scanned_files, 5);
+        assert_eq!(tree.scanned_dirs, 3);
+        let names: Vec<&str> = tree.root.children.iter().map(|child| child.name.as_str()).collect();
+        assert_eq!(names, vec!["build", "video.mp4", OTHER_NODE_NAME]);
+
+        let other = child(&tree.root, OTHER_NODE_NAME);
+        assert!(other.is_other);
+        assert_eq!((other.size_in_bytes, other.file_count), (30, 2));
+
+        // Below `max_depth` only the totals are known
+        let build = child(&tree.root, "build");
+        assert_eq!((build.size_in_bytes, build.file_count), (1100, 2));
+        assert!(build.children.is_empty());
+
+        assert_eq!(tree.file_types[0], FileTypeUsage { extension: "bin".into(), size_in_bytes: 1000, file_count: 1 });
+        assert_eq!(tree.file_types[1].extension, "mp4");
+        assert_eq!(tree.file_types[2], FileTypeUsage { extension: OTHER_NODE_NAME.into(), size_in_bytes: 130, file_count: 3 });
+    }
+
+    #[test]
+    fn test_nested_levels_and_hard_links() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/b/data"), vec![0u8; 64]).unwrap();
+        fs::hard_link(root.join("a/b/data"), root.join("a/link")).unwrap();
+
+        let tree = analyze(root, DiskUsageOptions::default());
+        assert_eq!(tree.root.size_in_bytes, 64);
+        // `a` is read before `a/b`, so the link in `a` is the one that is counted
+        let a = child(&tree.root, "a");
+        assert_eq!(child(a, "link").size_in_bytes, 64);
+        assert_eq!(child(a, "b").size_in_bytes, 0);
+        assert_eq!(tree.file_types, vec![FileTypeUsage { extension: String::new(), size_in_bytes: 64, file_count: 1 }]);
+    }
+
+    #[test]
+    fn test_cancel_and_partial_snapshots() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join("sub")).unwrap();
+        fs::write(temp_dir.path().join("sub/file"), "data").unwrap();
+
+        let mut partial_sizes = Vec::new();
+        analyze_disk_usage(temp_dir.path(), DiskUsageOptions::default(), &AtomicBool::new(false), |scan| {
+            partial_sizes.push(scan.snapshot().root.size_in_bytes)
+        })
+        .unwrap();
+        assert_eq!(partial_sizes, vec![0, 4]);
+
+        let result = analyze_disk_usage(temp_dir.path(), DiskUsageOptions::default(), &AtomicBool::new(true), |_| {});
+        assert_eq!(result, Err(DiskUsageError::Cancelled));
+        assert!(analyze_disk_usage(&temp_dir.path().join("sub/file"), DiskUsageOptions::default(), &AtomicBool::new(false), |_| {}).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+//! Finding files with identical content and removing the extra copies.
+//!
+//! Files are grouped by size first, then by a hash of their first bytes and only the
+//! files that still share a group are hashed completely. Most files therefore never
+//! have to be read in full.
+
+use crate::commands::hash_commands::{ChecksumMethod, HashError};
+use crate::filesystem::hashing::hash_reader;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Number of bytes hashed to tell apart files of the same size
+const PARTIAL_HASH_BYTES: u64 = 64 * 1024;
+
+/// Files with the same content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    /// The full hash of the content
+    pub hash: String,
+    /// Size of each file in the group
+    pub size_in_bytes: u64,
+    /// Sorted paths of all copies
+    pub paths: Vec<String>,
+    /// Space freed by keeping only one copy
+    pub reclaimable_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    pub method: ChecksumMethod,
+    /// Largest `reclaimable_bytes` first
+    pub groups: Vec<DuplicateGroup>,
+    pub total_reclaimable_bytes: u64,
+    pub scanned_files: u64,
+    /// Files that could not be read while hashing
+    pub failed_files: u64,
+}
+
+/// What to do with the extra copies of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateAction {
+    /// Move the copies to the trash
+    Trash,
+    /// Replace the copies with hard links to the kept file
+    Hardlink,
+}
+
+/// Result of the action for a single copy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateActionOutcome {
+    pub path: String,
+    pub error: Option<String>,
+}
+
+/// Finds files with identical content below the given roots.
+///
+/// Symlinks are not followed, empty files and files smaller than `min_size` are ignored.
+/// Paths that are hard links to the same file are only considered once, since removing
+/// them would not free any space. Roots that overlap are only scanned once.
+///
+/// # Returns
+/// The duplicate groups, every group with at least two files
+pub fn find_duplicates(roots: &[PathBuf], method: ChecksumMethod, min_size: u64) -> DuplicateReport {
+    let mut seen = HashSet::new();
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    let mut scanned_files = 0;
+    for root in roots {
+        for entry in WalkDir::new(root).into_iter().filter_map(|entry| entry.ok()) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if !seen.insert(file_identity(entry.path(), &meta)) {
+                continue;
+            }
+            scanned_files += 1;
+            if meta.len() > 0 && meta.len() >= min_size {
+                by_size.entry(meta.len()).or_default().push(entry.into_path());
+            }
+        }
+    }
+
+    let mut failed_files = 0;
+    let mut groups = Vec::new();
+    for (size, paths) in by_size.into_iter().filter(|(_, paths)| paths.len() > 1) {
+        let partial_groups = group_by_hash(paths, &mut failed_files, |path| hash_prefix(path, &method, PARTIAL_HASH_BYTES));
+        for (partial_hash, paths) in partial_groups {
+            // For small files the partial hash already covers the whole content
+            let full_groups = if size <= PARTIAL_HASH_BYTES {
+                vec![(partial_hash, paths)]
+            } else {
+                group_by_hash(paths, &mut failed_files, |path| hash_prefix(path, &method, u64::MAX))
+            };
+            for (hash, paths) in full_groups {
+                let mut paths: Vec<String> = paths.iter().map(|path| path.to_string_lossy().to_string()).collect();
+                paths.sort();
+                groups.push(DuplicateGroup {
+                    hash,
+                    size_in_bytes: size,
+                    reclaimable_bytes: size * (paths.len() as u64 - 1),
+                    paths,
+                });
+            }
+        }
+    }
+    groups.sort_by(|a, b| b.reclaimable_bytes.cmp(&a.reclaimable_bytes).then_with(|| a.paths.cmp(&b.paths)));
+
+    DuplicateReport {
+        method,
+        total_reclaimable_bytes: groups.iter().map(|group| group.reclaimable_bytes).sum(),
+        groups,
+        scanned_files,
+        failed_files,
+    }
+}
+
+/// Splits `paths` by their hash and keeps the groups with at least two files.
+fn group_by_hash(
+    paths: Vec<PathBuf>,
+    failed_files: &mut u64,
+    hash: impl Fn(&Path) -> Result<String, HashError>,
+) -> Vec<(String, Vec<PathBuf>)> {
+    let mut by_hash: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for path in paths {
+        match hash(&path) {
+            Ok(hash) => by_hash.entry(hash).or_default().push(path),
+            Err(_) => *failed_files += 1,
+        }
+    }
+    by_hash.into_iter().filter(|(_, paths)| paths.len() > 1).collect()
+}
+
+/// Hashes the first `limit` bytes of a file.
+fn hash_prefix(path: &Path, method: &ChecksumMethod, limit: u64) -> Result<String, HashError> {
+    let file = File::open(path).map_err(|_| HashError::FileOperationError)?;
+    let mut hashes = hash_reader(file.take(limit), std::slice::from_ref(method), None, |_| {})?;
+    Ok(hashes.remove(0))
+}
+
+#[cfg(unix)]
+fn file_identity(_path: &Path, meta: &fs::Metadata) -> (u64, u64, PathBuf) {
+    use std::os::unix::fs::MetadataExt;
+    (meta.dev(), meta.ino(), PathBuf::new())
+}
+
+#[cfg(not(unix))]
+fn file_identity(path: &Path, _meta: &fs::Metadata) -> (u64, u64, PathBuf) {
+    (0, 0, fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()))
+}
+
+/// Checks that `copy` still has exactly the same content as `keep`.
+///
+/// The grouping is based on hashes and the files may have changed since, so this is
+/// done right before a copy is removed.
+pub fn same_content(keep: &Path, copy: &Path) -> io::Result<bool> {
+    if fs::metadata(keep)?.len() != fs::metadata(copy)?.len() {
+        return Ok(false);
+    }
+    let mut keep = File::open(keep)?;
+    let mut copy = File::open(copy)?;
+    let mut keep_buffer = vec![0u8; 64 * 1024];
+    let mut copy_buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = read_full(&mut keep, &mut keep_buffer)?;
+        if read != read_full(&mut copy, &mut copy_buffer)? || keep_buffer[..read] != copy_buffer[..read] {
+            return Ok(false);
+        }
+        if read == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Reads until `buffer` is full or the end of the file is reached.
+fn read_full(file: &mut File, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.read(&mut buffer[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+/// Replaces `copy` with a hard link to `keep`.
+///
+/// The link is created next to `copy` first and then renamed over it, so `copy` is never
+/// missing if linking fails, e.g. because both are on different filesystems. A `copy` that
+/// already is a hard link of `keep` is left as it is.
+pub fn replace_with_hardlink(keep: &Path, copy: &Path) -> io::Result<()> {
+    // Renaming a link over another link of the same file does nothing and would leave the
+    // temporary link behind
+    if file_identity(keep, &fs::metadata(keep)?) == file_identity(copy, &fs::metadata(copy)?) {
+        return Ok(());
+    }
+
+    let parent = copy.parent().unwrap_or_else(|| Path::new("."));
+    let file_name = copy.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let temp_link = (0..)
+        .map(|attempt| parent.join(format!(".{}.link-{}", file_name, attempt)))
+        .find(|candidate| fs::symlink_metadata(candidate).is_err())
+        .expect("an unused name exists");
+
+    fs::hard_link(keep, &temp_link)?;
+    fs::rename(&temp_link, copy).inspect_err(|_| {
+        let _ = fs::remove_file(&temp_link);
+    })
+}
+
+#[cfg(test)]
+mod tests_duplicates {
+    use super::*;
+    use crate::commands::hash_commands::checksum;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_duplicates_groups_by_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("one.txt"), "same content").unwrap();
+        fs::write(root.join("a/two.txt"), "same content").unwrap();
+        fs::write(root.join("a/b/three.txt"), "same content").unwrap();
+        // Same size, different content
+        fs::write(root.join("a/other.txt"), "diff content").unwrap();
+        fs::write(root.join("empty1"), "").unwrap();
+        fs::write(root.join("empty2"), "").unwrap();
+        // Large files that only differ after the partially hashed bytes
+        let mut large = vec![1u8; PARTIAL_HASH_BYTES as usize + 10];
+        fs::write(root.join("large1.bin"), &large).unwrap();
+        fs::write(root.join("large2.bin"), &large).unwrap();
+        *large.last_mut().unwrap() = 2;
+        fs::write(root.join("a/large3.bin"), &large).unwrap();
+
+        // Overlapping roots are scanned once
+        let report = find_duplicates(&[root.to_path_buf(), root.join("a")], ChecksumMethod::SHA256, 1);
+
+        assert_eq!(report.scanned_files, 9);
+        assert_eq!(report.groups.len(), 2);
+        let large_group = &report.groups[0];
+        assert_eq!(large_group.paths.len(), 2);
+        assert!(large_group.paths[0].ends_with("large1.bin"));
+        assert_eq!(large_group.reclaimable_bytes, PARTIAL_HASH_BYTES + 10);
+        let text_group = &report.groups[1];
+        assert_eq!(text_group.paths.len(), 3);
+        assert_eq!(text_group.reclaimable_bytes, 24);
+        assert_eq!(text_group.hash, checksum(&ChecksumMethod::SHA256, b"same content"));
+        assert_eq!(report.total_reclaimable_bytes, PARTIAL_HASH_BYTES + 34);
+
+        let report = find_duplicates(&[root.to_path_buf()], ChecksumMethod::CRC32, 1000);
+        assert_eq!(report.groups.len(), 1);
+    }
+
+    #[test]
+    fn test_hardlink_replaces_copy() {
+        let temp_dir = TempDir::new().unwrap();
+        let keep = temp_dir.path().join("keep.txt");
+        let copy = temp_dir.path().join("copy.txt");
+        fs::write(&keep, "data").unwrap();
+        fs::write(&copy, "data").unwrap();
+
+        assert!(same_content(&keep, &copy).unwrap());
+        replace_with_hardlink(&keep, &copy).unwrap();
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+
+        // Linking an existing link again leaves no temporary link behind
+        replace_with_hardlink(&keep, &copy).unwrap();
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+
+        // Hard links of the same file are not reported as duplicates
+        let report = find_duplicates(&[temp_dir.path().to_path_buf()], ChecksumMethod::MD5, 1);
+        assert!(report.groups.is_empty());
+
+        fs::write(temp_dir.path().join("changed.txt"), "date").unwrap();
+        assert!(!same_content(&keep, &temp_dir.path().join("changed.txt")).unwrap());
+    }
+}
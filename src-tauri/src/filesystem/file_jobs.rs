@@ -0,0 +1,613 @@
+//! Implementations of the queued file operations run by `state::file_job_data`.
+//!
+//! Each function processes its top level items one after another, reports
+//! progress through the `JobContext` and stops at the next chunk once the job
+//! is cancelled. Partially written output of a cancelled or failed item is
+//! removed again, so every item is either fully done or not done at all.
+
+use crate::filesystem::archive::{
+    self, ArchiveEntryKind, ArchiveFormat, ArchiveMember, ArchiveOptions, ArchiveWriter, ExtractOptions,
+    MemberSelection,
+};
+use crate::filesystem::conflict_policy::{
+    clear_for_overwrite, decide, target_for, ConflictChoice, ConflictPolicy, ConflictRecord,
+};
+use crate::log_warn;
+use crate::state::file_job_data::{JobCancelled, JobContext, JobItemResult, JobItemStatus};
+use serde::Serialize;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Size of the chunks files are copied in, progress and cancellation are checked per chunk
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Why a single item stopped before completion
+enum ItemError {
+    Cancelled,
+    Failed(String),
+}
+
+impl From<JobCancelled> for ItemError {
+    fn from(_: JobCancelled) -> Self {
+        ItemError::Cancelled
+    }
+}
+
+impl From<std::io::Error> for ItemError {
+    fn from(err: std::io::Error) -> Self {
+        ItemError::Failed(err.to_string())
+    }
+}
+
+/// Builds the item result for an item that stopped early.
+fn stopped_item(source: &str, error: ItemError) -> JobItemResult {
+    match error {
+        ItemError::Cancelled => JobItemResult::new(source, JobItemStatus::Cancelled),
+        ItemError::Failed(message) => JobItemResult {
+            error: Some(message),
+            ..JobItemResult::new(source, JobItemStatus::Failed)
+        },
+    }
+}
+
+/// Marks all items from `start` on as cancelled.
+fn cancel_remaining(items: &mut Vec<JobItemResult>, all: &[String], start: usize) {
+    for source in &all[start..] {
+        items.push(JobItemResult::new(source, JobItemStatus::Cancelled));
+    }
+}
+
+/// Counts the regular files below `path` (or `path` itself) and their total size.
+fn scan(path: &Path) -> (u64, u64) {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .fold((0, 0), |(files, bytes), entry| {
+            let size = entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+            (files + 1, bytes + size)
+        })
+}
+
+/// Streams `reader` into `writer` chunk by chunk, reporting every chunk.
+///
+/// # Returns
+/// The number of bytes written
+fn stream(ctx: &JobContext, reader: &mut impl Read, writer: &mut impl Write) -> Result<u64, ItemError> {
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut written = 0;
+    loop {
+        ctx.checkpoint()?;
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(written);
+        }
+        writer.write_all(&buffer[..read])?;
+        written += read as u64;
+        ctx.add_bytes(read as u64);
+    }
+}
+
+/// Result of copying a file or directory with a conflict policy.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CopyOutcome {
+    /// Where the source was copied to, `None` if it was skipped
+    pub written_to: Option<String>,
+    pub files: u64,
+    pub bytes: u64,
+    /// Every existing destination that was found and how it was resolved
+    pub conflicts: Vec<ConflictRecord>,
+}
+
+/// State of a single copy, tracking what was created so a failed or
+/// cancelled copy can be removed again.
+struct CopyRun<'a> {
+    ctx: &'a JobContext,
+    policy: ConflictPolicy,
+    outcome: CopyOutcome,
+    /// Directories and files created by this copy, in creation order
+    created: Vec<PathBuf>,
+}
+
+impl CopyRun<'_> {
+    /// Resolves a possible conflict at `destination`.
+    ///
+    /// # Returns
+    /// The path to write to, or `None` if the source is skipped
+    fn resolve(&mut self, source: &Path, destination: &Path) -> Result<Option<PathBuf>, ItemError> {
+        if fs::symlink_metadata(destination).is_err() {
+            return Ok(Some(destination.to_path_buf()));
+        }
+
+        let (choice, asked) = match decide(self.policy, source, destination) {
+            Some(choice) => (choice, false),
+            None => (self.ctx.ask_conflict(source, destination)?, true),
+        };
+        if choice == ConflictChoice::Overwrite {
+            clear_for_overwrite(source, destination)?;
+        }
+
+        let target = target_for(choice, destination);
+        self.outcome.conflicts.push(ConflictRecord {
+            source: source.to_string_lossy().to_string(),
+            destination: destination.to_string_lossy().to_string(),
+            choice,
+            written_to: target.as_ref().map(|path| path.to_string_lossy().to_string()),
+            asked,
+        });
+        Ok(target)
+    }
+
+    /// Copies `source` to `destination`, merging into existing directories
+    /// when a conflict is resolved with `ConflictChoice::Overwrite`.
+    ///
+    /// # Returns
+    /// The path written to, `None` if the source was skipped
+    fn copy(&mut self, source: &Path, destination: &Path) -> Result<Option<PathBuf>, ItemError> {
+        let Some(target) = self.resolve(source, destination)? else {
+            let (files, bytes) = scan(source);
+            self.ctx.skip(files, bytes);
+            return Ok(None);
+        };
+
+        if source.is_dir() {
+            if !target.is_dir() {
+                fs::create_dir(&target)?;
+                self.created.push(target.clone());
+            }
+            for entry in fs::read_dir(source)? {
+                let path = entry?.path();
+                // Broken symlinks and special files are left out, like in `copy_file_or_dir`
+                if path.is_dir() || path.is_file() {
+                    let name = path.file_name().unwrap_or_default();
+                    self.copy(&path, &target.join(name))?;
+                }
+            }
+        } else {
+            self.copy_file(source, &target)?;
+        }
+        Ok(Some(target))
+    }
+
+    /// Copies a single file and its permissions. The content is written to a
+    /// temporary file next to the target first, so an interrupted copy never
+    /// leaves a truncated file behind and an overwritten file stays intact until
+    /// the copy is complete.
+    fn copy_file(&mut self, from: &Path, to: &Path) -> Result<(), ItemError> {
+        self.ctx.set_current_path(&from.to_string_lossy());
+        let mut reader = fs::File::open(from)?;
+        let mut temp = tempfile::NamedTempFile::new_in(to.parent().unwrap_or(Path::new(".")))?;
+        let bytes = stream(self.ctx, &mut reader, temp.as_file_mut())?;
+        temp.as_file_mut().flush()?;
+
+        let existed = to.exists();
+        temp.persist(to).map_err(|err| ItemError::Failed(err.to_string()))?;
+        if !existed {
+            self.created.push(to.to_path_buf());
+        }
+        if let Ok(metadata) = fs::metadata(from) {
+            let _ = fs::set_permissions(to, metadata.permissions());
+        }
+
+        self.outcome.files += 1;
+        self.outcome.bytes += bytes;
+        self.ctx.file_done();
+        Ok(())
+    }
+
+    /// Removes everything this copy created, newest first. Files that were
+    /// overwritten cannot be restored and are kept with their new content.
+    fn roll_back(&self) {
+        for path in self.created.iter().rev() {
+            remove_partial(path);
+        }
+    }
+}
+
+/// Copies a file or directory to `destination`, resolving conflicts with `policy`.
+/// If the copy fails or is cancelled, everything it created is removed again.
+fn copy_item(
+    ctx: &JobContext,
+    source: &Path,
+    destination: &Path,
+    policy: ConflictPolicy,
+) -> Result<CopyOutcome, (ItemError, Vec<ConflictRecord>)> {
+    if source.is_dir() && destination.starts_with(source) {
+        return Err((
+            ItemError::Failed(format!("Cannot copy {} into itself", source.display())),
+            Vec::new(),
+        ));
+    }
+
+    let mut run = CopyRun {
+        ctx,
+        policy,
+        outcome: CopyOutcome::default(),
+        created: Vec::new(),
+    };
+    match run.copy(source, destination) {
+        Ok(written_to) => {
+            run.outcome.written_to = written_to.map(|path| path.to_string_lossy().to_string());
+            Ok(run.outcome)
+        }
+        Err(err) => {
+            run.roll_back();
+            Err((err, run.outcome.conflicts))
+        }
+    }
+}
+
+/// Copies a file or directory outside of a job, for `copy_file_or_dir`.
+///
+/// # Arguments
+/// * `source` - The file or directory to copy
+/// * `destination` - The path to copy to
+/// * `policy` - How to handle existing destinations, must not be `ConflictPolicy::Ask`
+///
+/// # Returns
+/// * `Ok(CopyOutcome)` - What was copied and how conflicts were resolved
+/// * `Err(String)` - If the copy failed, nothing it created is left behind
+pub fn copy_with_policy(source: &Path, destination: &Path, policy: ConflictPolicy) -> Result<CopyOutcome, String> {
+    copy_item(&JobContext::detached(), source, destination, policy).map_err(|(err, _)| match err {
+        ItemError::Cancelled => "Copy was cancelled".to_string(),
+        ItemError::Failed(message) => message,
+    })
+}
+
+/// Removes a partially written file or directory, logging instead of failing.
+fn remove_partial(path: &Path) {
+    let result = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else if path.exists() {
+        fs::remove_file(path)
+    } else {
+        Ok(())
+    };
+    if let Err(err) = result {
+        log_warn!("Failed to remove partial output {}: {}", path.display(), err);
+    }
+}
+
+/// Copies every source into `destination_dir`, which is created if needed.
+/// Existing names are handled according to `policy`; with `ConflictPolicy::Ask`
+/// the job waits for a decision on every conflict.
+///
+/// # Returns
+/// One result per source, in order. Skipped sources are reported as `Skipped`.
+pub fn run_copy(
+    ctx: &JobContext,
+    sources: &[String],
+    destination_dir: &str,
+    policy: ConflictPolicy,
+) -> Vec<JobItemResult> {
+    let (files, bytes) = sources
+        .iter()
+        .map(|source| scan(Path::new(source)))
+        .fold((0, 0), |(f, b), (files, bytes)| (f + files, b + bytes));
+    ctx.set_totals(files, bytes);
+
+    let mut items = Vec::with_capacity(sources.len());
+    if let Err(err) = fs::create_dir_all(destination_dir) {
+        let message = format!("Failed to create destination directory: {}", err);
+        return sources
+            .iter()
+            .map(|source| stopped_item(source, ItemError::Failed(message.clone())))
+            .collect();
+    }
+
+    for (index, source) in sources.iter().enumerate() {
+        if ctx.checkpoint().is_err() {
+            cancel_remaining(&mut items, sources, index);
+            break;
+        }
+
+        let source_path = Path::new(source);
+        let Some(name) = source_path.file_name() else {
+            items.push(stopped_item(source, ItemError::Failed("Invalid source path".to_string())));
+            continue;
+        };
+        if !source_path.exists() {
+            items.push(stopped_item(
+                source,
+                ItemError::Failed(format!("Source path does not exist: {}", source)),
+            ));
+            continue;
+        }
+
+        match copy_item(ctx, source_path, &Path::new(destination_dir).join(name), policy) {
+            Ok(outcome) => {
+                let status = if outcome.written_to.is_some() {
+                    JobItemStatus::Completed
+                } else {
+                    JobItemStatus::Skipped
+                };
+                items.push(JobItemResult {
+                    destination: outcome.written_to,
+                    files: outcome.files,
+                    bytes: outcome.bytes,
+                    conflicts: outcome.conflicts,
+                    ..JobItemResult::new(source, status)
+                });
+            }
+            Err((err, conflicts)) => {
+                let cancelled = matches!(err, ItemError::Cancelled);
+                items.push(JobItemResult {
+                    conflicts,
+                    ..stopped_item(source, err)
+                });
+                if cancelled {
+                    cancel_remaining(&mut items, sources, index + 1);
+                    break;
+                }
+            }
+        }
+    }
+
+    items
+}
+
+/// Reports the bytes read through it and fails once the job is cancelled, for
+/// archive writers that read the content themselves.
+struct JobReader<'a, R> {
+    ctx: &'a JobContext,
+    inner: R,
+    read: u64,
+}
+
+impl<R: Read> Read for JobReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.ctx.checkpoint().map_err(|_| io::Error::other("Job cancelled"))?;
+        let read = self.inner.read(buf)?;
+        self.read += read as u64;
+        self.ctx.add_bytes(read as u64);
+        Ok(read)
+    }
+}
+
+/// Maps an error of the archive code, which also reports cancellation as an `io::Error`.
+fn archive_error(ctx: &JobContext, err: io::Error) -> ItemError {
+    if ctx.is_cancelled() {
+        ItemError::Cancelled
+    } else {
+        ItemError::Failed(err.to_string())
+    }
+}
+
+/// Adds a file or directory tree to the archive under `base_name`.
+fn zip_item(
+    ctx: &JobContext,
+    writer: &mut dyn ArchiveWriter,
+    source: &Path,
+    base_name: &str,
+    selection: &MemberSelection,
+) -> Result<(u64, u64), ItemError> {
+    let (mut files, mut bytes) = (0, 0);
+    for member in archive::collect_members(source, base_name, selection)? {
+        ctx.checkpoint()?;
+        match member {
+            ArchiveMember::File { source, name } => {
+                ctx.set_current_path(&source.to_string_lossy());
+                let mut reader = JobReader { ctx, inner: fs::File::open(&source)?, read: 0 };
+                writer.add_file(&name, &source, &mut reader).map_err(|err| match archive_error(ctx, err) {
+                    ItemError::Failed(err) => ItemError::Failed(format!("Error adding file to zip: {}", err)),
+                    cancelled => cancelled,
+                })?;
+                bytes += reader.read;
+                files += 1;
+                ctx.file_done();
+            }
+            member => archive::add_member(writer, &member)
+                .map_err(|err| ItemError::Failed(format!("Error adding file to zip: {}", err)))?,
+        }
+    }
+    Ok((files, bytes))
+}
+
+/// Archives the sources, with the same naming and format rules and options as `zip`.
+/// If the job is cancelled the incomplete archive is removed.
+///
+/// # Returns
+/// One result per source, in order
+pub fn run_zip(
+    ctx: &JobContext,
+    sources: &[String],
+    destination: Option<&str>,
+    options: &ArchiveOptions,
+) -> Vec<JobItemResult> {
+    let fail_all = |error: String| -> Vec<JobItemResult> {
+        sources
+            .iter()
+            .map(|source| stopped_item(source, ItemError::Failed(error.clone())))
+            .collect()
+    };
+    let zip_path = match (sources, destination) {
+        (_, Some(dest)) => PathBuf::from(dest),
+        ([single], None) => Path::new(single).with_extension("zip"),
+        _ => return fail_all("Destination path required for multiple sources".to_string()),
+    };
+    let (format, selection) = match ArchiveFormat::for_new_archive(&zip_path)
+        .and_then(|format| Ok((format, options.validate(format)?)))
+    {
+        Ok(checked) => checked,
+        Err(error) => return fail_all(error),
+    };
+
+    let (files, bytes) = sources
+        .iter()
+        .map(|source| scan(Path::new(source)))
+        .fold((0, 0), |(f, b), (files, bytes)| (f + files, b + bytes));
+    ctx.set_totals(files, bytes);
+
+    let mut writer = match archive::create_writer(&zip_path, format, options) {
+        Ok(writer) => writer,
+        Err(err) => return fail_all(format!("Failed to create zip file: {}", err)),
+    };
+    let destination = Some(zip_path.to_string_lossy().to_string());
+
+    let mut items = Vec::with_capacity(sources.len());
+    for source in sources {
+        let source_path = Path::new(source);
+        let base_name = source_path.file_name().map(|name| name.to_string_lossy().to_string());
+        let result = match base_name {
+            _ if !source_path.exists() => Err(ItemError::Failed(format!("Source path does not exist: {}", source))),
+            None => Err(ItemError::Failed("Invalid source name".to_string())),
+            Some(base_name) => zip_item(ctx, writer.as_mut(), source_path, &base_name, &selection),
+        };
+
+        match result {
+            Ok((files, bytes)) => items.push(JobItemResult {
+                destination: destination.clone(),
+                files,
+                bytes,
+                ..JobItemResult::new(source, JobItemStatus::Completed)
+            }),
+            Err(ItemError::Cancelled) => {
+                // The archive is discarded, so nothing of this job remains
+                drop(writer);
+                remove_partial(&zip_path);
+                items.clear();
+                cancel_remaining(&mut items, sources, 0);
+                return items;
+            }
+            Err(err) => items.push(stopped_item(source, err)),
+        }
+    }
+
+    if let Err(err) = writer.finish() {
+        remove_partial(&zip_path);
+        return fail_all(format!("Error finalizing zip file: {}", err));
+    }
+    items
+}
+
+/// Extracts one archive with the same layout rules as `unzip`, see `archive::extract`.
+///
+/// # Returns
+/// Where the content was extracted to, and the number of files and bytes written
+fn extract_archive(
+    ctx: &JobContext,
+    zip_path: &Path,
+    dest_dir: &Path,
+    options: &ExtractOptions,
+) -> Result<(PathBuf, u64, u64), ItemError> {
+    let extraction = archive::extract(zip_path, dest_dir, options, &mut |outpath, mut content, outfile| {
+        ctx.set_current_path(&outpath.to_string_lossy());
+        let written = stream(ctx, &mut content, outfile).map_err(|err| match err {
+            ItemError::Cancelled => io::Error::other("Job cancelled"),
+            ItemError::Failed(message) => io::Error::other(message),
+        })?;
+        ctx.file_done();
+        Ok(written)
+    })
+    .map_err(|err| archive_error(ctx, err))?;
+    Ok((extraction.path, extraction.files, extraction.bytes))
+}
+
+/// Counts the files of an archive and their size, for the formats that can be listed
+/// without reading through the whole archive.
+fn archive_totals(path: &Path, password: Option<&str>) -> Option<(u64, u64)> {
+    let format = ArchiveFormat::detect(path).ok()??;
+    if !format.has_index() {
+        return None;
+    }
+    let entries = archive::open_reader(path, format, password).ok()?.entries().ok()?;
+    let sizes = entries.iter().filter(|entry| entry.kind == ArchiveEntryKind::File).map(|entry| entry.size);
+    Some(sizes.fold((0, 0), |(files, bytes), size| (files + 1, bytes + size)))
+}
+
+/// Extracts every archive, with the same destination rules and limits as `unzip`.
+/// Like `unzip`, an archive is deleted after it was extracted successfully.
+/// Dry runs are refused when the job is queued.
+///
+/// # Returns
+/// One result per archive, in order
+pub fn run_unzip(
+    ctx: &JobContext,
+    archives: &[String],
+    destination: Option<&str>,
+    options: &ExtractOptions,
+) -> Vec<JobItemResult> {
+    let (files, bytes) = archives
+        .iter()
+        .filter_map(|path| archive_totals(Path::new(path), options.password.as_deref()))
+        .fold((0, 0), |(f, b), (files, bytes)| (f + files, b + bytes));
+    ctx.set_totals(files, bytes);
+
+    let mut items = Vec::with_capacity(archives.len());
+    for (index, archive) in archives.iter().enumerate() {
+        if ctx.checkpoint().is_err() {
+            cancel_remaining(&mut items, archives, index);
+            break;
+        }
+
+        let zip_path = Path::new(archive);
+        if !zip_path.exists() {
+            items.push(stopped_item(
+                archive,
+                ItemError::Failed(format!("Zip file does not exist: {}", archive)),
+            ));
+            continue;
+        }
+        let dest_dir = match destination {
+            Some(dest) => PathBuf::from(dest),
+            None => zip_path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+        };
+
+        match extract_archive(ctx, zip_path, &dest_dir, options) {
+            Ok((extract_path, files, bytes)) => {
+                if let Err(err) = fs::remove_file(zip_path) {
+                    log_warn!("Failed to remove zip file after extraction: {}", err);
+                }
+                items.push(JobItemResult {
+                    destination: Some(extract_path.to_string_lossy().to_string()),
+                    files,
+                    bytes,
+                    ..JobItemResult::new(archive, JobItemStatus::Completed)
+                });
+            }
+            Err(err) => {
+                let cancelled = matches!(err, ItemError::Cancelled);
+                items.push(stopped_item(archive, err));
+                if cancelled {
+                    cancel_remaining(&mut items, archives, index + 1);
+                    break;
+                }
+            }
+        }
+    }
+
+    items
+}
+
+/// Moves every path to the trash, reporting one file per path.
+///
+/// # Returns
+/// One result per path, in order
+pub fn run_trash(ctx: &JobContext, paths: &[String]) -> Vec<JobItemResult> {
+    ctx.set_totals(paths.len() as u64, 0);
+
+    let mut items = Vec::with_capacity(paths.len());
+    for (index, path) in paths.iter().enumerate() {
+        if ctx.checkpoint().is_err() {
+            cancel_remaining(&mut items, paths, index);
+            break;
+        }
+
+        ctx.set_current_path(path);
+        match trash::delete(path) {
+            Ok(_) => items.push(JobItemResult {
+                files: 1,
+                ..JobItemResult::new(path, JobItemStatus::Completed)
+            }),
+            Err(err) => items.push(stopped_item(
+                path,
+                ItemError::Failed(format!("Failed to move file or directory to trash: {}", err)),
+            )),
+        }
+        ctx.file_done();
+    }
+
+    items
+}
@@ -0,0 +1,266 @@
+//! Moving files and directories, also across mount points.
+
+use std::fs::{self, File, FileTimes};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// How a move was carried out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveMethod {
+    /// Atomic rename on the same filesystem
+    Renamed,
+    /// Copied to another filesystem, then the source was deleted
+    Copied,
+}
+
+/// Moves a file or directory to `destination`, which must not exist yet.
+///
+/// Tries an atomic rename first. If source and destination are on different filesystems
+/// the source is copied, every file is verified against a checksum of the source, and the
+/// source is deleted afterwards. Timestamps and permissions are kept. If the copy fails,
+/// everything it created is removed again and the source is left untouched.
+///
+/// # Returns
+/// * `Ok(MoveMethod)` - How the move was carried out
+/// * `Err(String)` - If the move failed
+pub fn move_path(source: &Path, destination: &Path) -> Result<MoveMethod, String> {
+    if fs::symlink_metadata(source).is_err() {
+        return Err(format!("'{}' does not exist", source.display()));
+    }
+    if fs::symlink_metadata(destination).is_ok() {
+        return Err(format!("'{}' already exists", destination.display()));
+    }
+
+    match fs::rename(source, destination) {
+        Ok(_) => Ok(MoveMethod::Renamed),
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            copy_then_remove(source, destination).map(|_| MoveMethod::Copied)
+        }
+        Err(err) => Err(format!(
+            "Failed to move '{}' to '{}': {}",
+            source.display(),
+            destination.display(),
+            err
+        )),
+    }
+}
+
+/// The cross-device part of `move_path`.
+pub(crate) fn copy_then_remove(source: &Path, destination: &Path) -> Result<(), String> {
+    if let Err(err) = copy_tree(source, destination) {
+        remove_partial_copy(destination);
+        return Err(format!(
+            "Failed to copy '{}' to '{}': {}",
+            source.display(),
+            destination.display(),
+            err
+        ));
+    }
+
+    // From here on the destination is complete and must be kept, even if the source is only
+    // partially removed
+    let removed = if fs::symlink_metadata(source).is_ok_and(|meta| meta.is_dir()) {
+        fs::remove_dir_all(source)
+    } else {
+        fs::remove_file(source)
+    };
+    removed.map_err(|err| {
+        format!(
+            "Copied '{}' to '{}' but failed to remove the source: {}",
+            source.display(),
+            destination.display(),
+            err
+        )
+    })
+}
+
+fn copy_tree(source: &Path, destination: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(source)?;
+
+    #[cfg(unix)]
+    if meta.file_type().is_symlink() {
+        return std::os::unix::fs::symlink(fs::read_link(source)?, destination);
+    }
+
+    if meta.is_dir() {
+        fs::create_dir(destination)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &destination.join(entry.file_name()))?;
+        }
+        // Adding the entries changed the modification time, so the times are set last
+        if let Err(err) = File::open(destination).and_then(|dir| dir.set_times(file_times(&meta))) {
+            // Directories cannot be opened like this on every platform, the content is what matters
+            crate::log_warn!("Failed to keep timestamps of '{}': {}", destination.display(), err);
+        }
+        fs::set_permissions(destination, meta.permissions())
+    } else {
+        copy_file_verified(source, destination, &meta)
+    }
+}
+
+/// Copies a file and checks that the written data matches what was read.
+fn copy_file_verified(source: &Path, destination: &Path, meta: &fs::Metadata) -> io::Result<()> {
+    let mut reader = File::open(source)?;
+    let mut writer = File::create_new(destination)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        writer.write_all(&buffer[..read])?;
+    }
+    writer.sync_all()?;
+
+    if checksum(destination)? != hasher.finalize() {
+        return Err(io::Error::other(format!(
+            "Verification of '{}' failed, the copy differs from the source",
+            destination.display()
+        )));
+    }
+
+    writer.set_times(file_times(meta))?;
+    drop(writer);
+    fs::set_permissions(destination, meta.permissions())
+}
+
+fn checksum(path: &Path) -> io::Result<u32> {
+    let mut file = File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hasher.finalize());
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+fn file_times(meta: &fs::Metadata) -> FileTimes {
+    let mut times = FileTimes::new();
+    if let Ok(accessed) = meta.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    if let Ok(modified) = meta.modified() {
+        times = times.set_modified(modified);
+    }
+    times
+}
+
+fn remove_partial_copy(destination: &Path) {
+    let removed = match fs::symlink_metadata(destination) {
+        Ok(meta) if meta.is_dir() => {
+            // Read-only directories inside the copy would block the removal
+            make_writable(destination);
+            fs::remove_dir_all(destination)
+        }
+        Ok(_) => fs::remove_file(destination),
+        Err(_) => Ok(()),
+    };
+    if let Err(err) = removed {
+        crate::log_error!("Failed to remove partial copy '{}': {}", destination.display(), err);
+    }
+}
+
+fn make_writable(dir: &Path) {
+    if let Ok(meta) = fs::symlink_metadata(dir) {
+        let mut permissions = meta.permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        let _ = fs::set_permissions(dir, permissions);
+    }
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                make_writable(&entry.path());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_file_move {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_move_renames_on_same_filesystem() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source.txt");
+        let destination = temp_dir.path().join("destination.txt");
+        fs::write(&source, "data").unwrap();
+
+        assert_eq!(move_path(&source, &destination), Ok(MoveMethod::Renamed));
+        assert!(!source.exists());
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "data");
+
+        // Never replaces an existing destination
+        fs::write(&source, "other").unwrap();
+        assert!(move_path(&source, &destination).is_err());
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "data");
+    }
+
+    #[test]
+    fn test_copy_then_remove_keeps_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let destination = temp_dir.path().join("destination");
+        fs::create_dir_all(source.join("nested")).unwrap();
+        fs::write(source.join("nested").join("file.txt"), "content").unwrap();
+        let past = SystemTime::now() - Duration::from_secs(3600);
+        File::options()
+            .write(true)
+            .open(source.join("nested").join("file.txt"))
+            .unwrap()
+            .set_modified(past)
+            .unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(source.join("nested").join("file.txt"), fs::Permissions::from_mode(0o640)).unwrap();
+        }
+
+        copy_then_remove(&source, &destination).unwrap();
+
+        assert!(!source.exists());
+        let moved = destination.join("nested").join("file.txt");
+        assert_eq!(fs::read_to_string(&moved).unwrap(), "content");
+        assert_eq!(fs::metadata(&moved).unwrap().modified().unwrap(), past);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&moved).unwrap().permissions().mode() & 0o777, 0o640);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failed_copy_leaves_source_and_no_partial_copy() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let destination = temp_dir.path().join("destination");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("a.txt"), "a").unwrap();
+        let unreadable = source.join("b.txt");
+        fs::write(&unreadable, "b").unwrap();
+        fs::set_permissions(&unreadable, fs::Permissions::from_mode(0o000)).unwrap();
+        if File::open(&unreadable).is_ok() {
+            // Running as root, permissions cannot make the copy fail
+            return;
+        }
+
+        assert!(copy_then_remove(&source, &destination).is_err());
+        assert!(!destination.exists());
+        assert!(source.join("a.txt").exists());
+        fs::set_permissions(&unreadable, fs::Permissions::from_mode(0o644)).unwrap();
+    }
+}
@@ -0,0 +1,257 @@
+//! Recursive folder size calculation backed by a cache of directory listings.
+//!
+//! The cache stores, per directory, the total size of the files directly inside it and the
+//! list of its subdirectories, together with the directory's modification time. Adding,
+//! removing or renaming an entry changes the modification time of its parent, so a cached
+//! directory only has to be read again if its modification time changed. Measuring a tree
+//! whose directories are all cached therefore only needs one `stat` per directory.
+//!
+//! Files that grow or shrink in place do not change the modification time of their parent
+//! directory, their new size is picked up once that directory changes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Number of cached directories after which the cache is cleared and filled up again
+const MAX_CACHED_DIRECTORIES: usize = 500_000;
+
+/// Number of directories `cached_folder_size` checks before it treats a folder as not cached,
+/// so listing a folder with huge cached trees stays fast
+const MAX_LOOKUP_DIRECTORIES: usize = 10_000;
+
+/// The recursive totals of a folder.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FolderSize {
+    pub size_in_bytes: u64,
+    /// Files anywhere below the folder
+    pub file_count: u64,
+    /// Directories anywhere below the folder, not counting the folder itself
+    pub dir_count: u64,
+}
+
+/// Why a folder could not be measured.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FolderSizeError {
+    Cancelled,
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+struct CachedDirectory {
+    modified: SystemTime,
+    file_bytes: u64,
+    file_count: u64,
+    subdirectories: Vec<PathBuf>,
+}
+
+/// Directory listings shared between all size calculations.
+#[derive(Debug, Default)]
+pub struct FolderSizeCache {
+    directories: Mutex<HashMap<PathBuf, CachedDirectory>>,
+}
+
+impl FolderSizeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get(&self, path: &Path, modified: SystemTime) -> Option<CachedDirectory> {
+        let directories = self.directories.lock().ok()?;
+        directories.get(path).filter(|cached| cached.modified == modified).cloned()
+    }
+
+    fn insert(&self, path: PathBuf, cached: CachedDirectory) {
+        if let Ok(mut directories) = self.directories.lock() {
+            if directories.len() >= MAX_CACHED_DIRECTORIES {
+                directories.clear();
+            }
+            directories.insert(path, cached);
+        }
+    }
+}
+
+/// Calculates the total size of all files below `root`.
+///
+/// Symlinks are counted with their own size and never followed. Subdirectories that cannot
+/// be read are skipped. `on_progress` is called with the running totals after every
+/// directory, `cancel` is checked before every directory.
+///
+/// # Returns
+/// * `Ok(FolderSize)` - The totals of the folder
+/// * `Err(FolderSizeError)` - If `root` is not a readable directory or the calculation was cancelled
+pub fn measure_folder(
+    root: &Path,
+    cache: &FolderSizeCache,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(&FolderSize),
+) -> Result<FolderSize, FolderSizeError> {
+    let meta = fs::symlink_metadata(root)
+        .map_err(|err| FolderSizeError::Failed(format!("Cannot access '{}': {}", root.display(), err)))?;
+    if !meta.is_dir() {
+        return Err(FolderSizeError::Failed(format!("'{}' is not a directory", root.display())));
+    }
+
+    let mut totals = FolderSize::default();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(FolderSizeError::Cancelled);
+        }
+
+        let listing = match read_directory(&dir, cache) {
+            Ok(listing) => listing,
+            Err(err) if dir == root => return Err(FolderSizeError::Failed(err)),
+            Err(_) => continue,
+        };
+        totals.size_in_bytes += listing.file_bytes;
+        totals.file_count += listing.file_count;
+        totals.dir_count += listing.subdirectories.len() as u64;
+        pending.extend(listing.subdirectories);
+        on_progress(&totals);
+    }
+    Ok(totals)
+}
+
+/// The totals of `root` from the cache alone, without reading any directory.
+///
+/// # Returns
+/// The totals, or `None` if a directory below `root` is not cached, was modified since it
+/// was cached, or there are too many directories to check them quickly
+pub fn cached_folder_size(root: &Path, cache: &FolderSizeCache) -> Option<FolderSize> {
+    let mut totals = FolderSize::default();
+    let mut pending = vec![root.to_path_buf()];
+    let mut checked = 0;
+    while let Some(dir) = pending.pop() {
+        checked += 1;
+        if checked > MAX_LOOKUP_DIRECTORIES {
+            return None;
+        }
+        let modified = fs::symlink_metadata(&dir).and_then(|meta| meta.modified()).ok()?;
+        let Some(listing) = cache.get(&dir, modified) else {
+            // Unreadable subdirectories are never cached, `measure_folder` skips them as well
+            if dir != root && fs::read_dir(&dir).is_err() {
+                continue;
+            }
+            return None;
+        };
+        totals.size_in_bytes += listing.file_bytes;
+        totals.file_count += listing.file_count;
+        totals.dir_count += listing.subdirectories.len() as u64;
+        pending.extend(listing.subdirectories);
+    }
+    Some(totals)
+}
+
+/// Returns the listing of `dir`, from the cache if the directory was not modified since.
+fn read_directory(dir: &Path, cache: &FolderSizeCache) -> Result<CachedDirectory, String> {
+    let modified = fs::symlink_metadata(dir)
+        .and_then(|meta| meta.modified())
+        .map_err(|err| format!("Cannot access '{}': {}", dir.display(), err))?;
+    if let Some(cached) = cache.get(dir, modified) {
+        return Ok(cached);
+    }
+
+    let entries = fs::read_dir(dir).map_err(|err| format!("Cannot read '{}': {}", dir.display(), err))?;
+    let mut listing = CachedDirectory {
+        modified,
+        file_bytes: 0,
+        file_count: 0,
+        subdirectories: Vec::new(),
+    };
+    for entry in entries.flatten() {
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if meta.is_dir() {
+            listing.subdirectories.push(entry.path());
+        } else {
+            listing.file_bytes += meta.len();
+            listing.file_count += 1;
+        }
+    }
+    cache.insert(dir.to_path_buf(), listing.clone());
+    Ok(listing)
+}
+
+#[cfg(test)]
+mod tests_folder_size {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn measure(root: &Path, cache: &FolderSizeCache) -> FolderSize {
+        measure_folder(root, cache, &AtomicBool::new(false), |_| {}).unwrap()
+    }
+
+    #[test]
+    fn test_measure_nested_folders() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("a/b")).unwrap();
+        fs::write(temp_dir.path().join("top.txt"), "12345").unwrap();
+        fs::write(temp_dir.path().join("a/one.txt"), "1").unwrap();
+        fs::write(temp_dir.path().join("a/b/two.txt"), "22").unwrap();
+
+        let cache = FolderSizeCache::new();
+        let mut updates = 0;
+        let size = measure_folder(temp_dir.path(), &cache, &AtomicBool::new(false), |_| updates += 1).unwrap();
+
+        assert_eq!(size, FolderSize { size_in_bytes: 8, file_count: 3, dir_count: 2 });
+        assert_eq!(updates, 3);
+        assert_eq!(cache.directories.lock().unwrap().len(), 3);
+        assert!(measure_folder(&temp_dir.path().join("top.txt"), &cache, &AtomicBool::new(false), |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_cache_notices_changes_in_nested_folders() {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("a/b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("one.txt"), "1").unwrap();
+
+        let cache = FolderSizeCache::new();
+        assert_eq!(measure(temp_dir.path(), &cache).size_in_bytes, 1);
+
+        // Only the modification time of `a/b` changes, the root is still cached
+        fs::write(nested.join("two.txt"), "22").unwrap();
+        let size = measure(temp_dir.path(), &cache);
+        assert_eq!(size.size_in_bytes, 3);
+        assert_eq!(size.file_count, 2);
+
+        fs::remove_dir_all(temp_dir.path().join("a")).unwrap();
+        assert_eq!(measure(temp_dir.path(), &cache), FolderSize::default());
+    }
+
+    #[test]
+    fn test_cached_folder_size_needs_every_directory_unchanged() {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("a/b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("one.txt"), "1").unwrap();
+
+        let cache = FolderSizeCache::new();
+        assert_eq!(cached_folder_size(temp_dir.path(), &cache), None);
+        let size = measure(temp_dir.path(), &cache);
+        assert_eq!(cached_folder_size(temp_dir.path(), &cache), Some(size));
+        assert_eq!(cached_folder_size(&temp_dir.path().join("a"), &cache), Some(FolderSize { dir_count: 1, ..size }));
+
+        // A change deep down invalidates the folder until it is measured again
+        fs::write(nested.join("two.txt"), "22").unwrap();
+        assert_eq!(cached_folder_size(temp_dir.path(), &cache), None);
+        measure(temp_dir.path(), &cache);
+        assert_eq!(cached_folder_size(temp_dir.path(), &cache).unwrap().size_in_bytes, 3);
+    }
+
+    #[test]
+    fn test_cancelled_measurement() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("file.txt"), "data").unwrap();
+
+        let cache = FolderSizeCache::new();
+        let result = measure_folder(temp_dir.path(), &cache, &AtomicBool::new(true), |_| {});
+        assert_eq!(result, Err(FolderSizeError::Cancelled));
+    }
+}
@@ -0,0 +1,180 @@
+//! Incremental hashing of files and streams.
+//!
+//! Data is read in fixed size chunks and fed into one hasher per requested method, so files
+//! of any size are hashed with constant memory and several hashes cost a single read.
+
+use crate::commands::hash_commands::{ChecksumMethod, HashError};
+use md5::{Digest as Md5Digest, Md5 as Md5Hasher};
+use sha1::Sha1;
+use sha2::{Digest as Sha2Digest, Sha256, Sha384, Sha512};
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Size of the chunks data is hashed in, progress and cancellation are checked per chunk
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The running state of one checksum method.
+pub enum StreamingHasher {
+    Md5(Md5Hasher),
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+    Crc32(crc32fast::Hasher),
+    Blake3(Box<blake3::Hasher>),
+    Xxh3(Box<xxhash_rust::xxh3::Xxh3>),
+}
+
+impl StreamingHasher {
+    pub fn new(method: &ChecksumMethod) -> Self {
+        match method {
+            ChecksumMethod::MD5 => StreamingHasher::Md5(Md5Hasher::new()),
+            ChecksumMethod::SHA1 => StreamingHasher::Sha1(Sha1::new()),
+            ChecksumMethod::SHA256 => StreamingHasher::Sha256(Sha256::new()),
+            ChecksumMethod::SHA384 => StreamingHasher::Sha384(Sha384::new()),
+            ChecksumMethod::SHA512 => StreamingHasher::Sha512(Sha512::new()),
+            ChecksumMethod::CRC32 => StreamingHasher::Crc32(crc32fast::Hasher::new()),
+            ChecksumMethod::BLAKE3 => StreamingHasher::Blake3(Box::new(blake3::Hasher::new())),
+            ChecksumMethod::XXH3 => StreamingHasher::Xxh3(Box::new(xxhash_rust::xxh3::Xxh3::new())),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            StreamingHasher::Md5(hasher) => Md5Digest::update(hasher, data),
+            StreamingHasher::Sha1(hasher) => Sha2Digest::update(hasher, data),
+            StreamingHasher::Sha256(hasher) => Sha2Digest::update(hasher, data),
+            StreamingHasher::Sha384(hasher) => Sha2Digest::update(hasher, data),
+            StreamingHasher::Sha512(hasher) => Sha2Digest::update(hasher, data),
+            StreamingHasher::Crc32(hasher) => hasher.update(data),
+            StreamingHasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            StreamingHasher::Xxh3(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Returns the hex encoded hash, in the same format as `hash_commands::checksum`.
+    pub fn finalize(self) -> String {
+        match self {
+            StreamingHasher::Md5(hasher) => hex::encode(hasher.finalize()),
+            StreamingHasher::Sha1(hasher) => format!("{:x}", hasher.finalize()),
+            StreamingHasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            StreamingHasher::Sha384(hasher) => format!("{:x}", hasher.finalize()),
+            StreamingHasher::Sha512(hasher) => format!("{:x}", hasher.finalize()),
+            StreamingHasher::Crc32(hasher) => format!("{:08x}", hasher.finalize()),
+            StreamingHasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+            // Printed big endian like `xxhsum`
+            StreamingHasher::Xxh3(hasher) => format!("{:016x}", hasher.digest()),
+        }
+    }
+}
+
+/// Hashes everything `reader` returns with each of `methods` in a single pass.
+///
+/// `on_progress` is called with the number of bytes hashed so far after every chunk.
+/// `cancel` is checked before every chunk.
+///
+/// # Returns
+/// * `Ok(Vec<String>)` - The hashes in the order of `methods`
+/// * `Err(HashError)` - If reading failed or the hashing was cancelled
+pub fn hash_reader(
+    mut reader: impl Read,
+    methods: &[ChecksumMethod],
+    cancel: Option<&AtomicBool>,
+    mut on_progress: impl FnMut(u64),
+) -> Result<Vec<String>, HashError> {
+    let mut hashers: Vec<StreamingHasher> = methods.iter().map(StreamingHasher::new).collect();
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut done = 0;
+    loop {
+        if cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+            return Err(HashError::Cancelled);
+        }
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(_) => return Err(HashError::FileOperationError),
+        };
+        for hasher in &mut hashers {
+            hasher.update(&buffer[..read]);
+        }
+        done += read as u64;
+        on_progress(done);
+    }
+    Ok(hashers.into_iter().map(StreamingHasher::finalize).collect())
+}
+
+/// Hashes a file with each of `methods` in a single pass, see `hash_reader`.
+///
+/// # Returns
+/// * `Ok(Vec<String>)` - The hashes in the order of `methods`
+/// * `Err(HashError)` - If the path is not a readable file or the hashing was cancelled
+pub fn hash_file(
+    path: &Path,
+    methods: &[ChecksumMethod],
+    cancel: Option<&AtomicBool>,
+    on_progress: impl FnMut(u64),
+) -> Result<Vec<String>, HashError> {
+    if path.is_dir() {
+        return Err(HashError::FileOperationError);
+    }
+    let file = File::open(path).map_err(|_| HashError::FileOperationError)?;
+    hash_reader(file, methods, cancel, on_progress)
+}
+
+#[cfg(test)]
+mod tests_hashing {
+    use super::*;
+    use crate::commands::hash_commands::checksum;
+    use std::io::Cursor;
+    use tempfile::TempDir;
+
+    const ALL_METHODS: [ChecksumMethod; 8] = [
+        ChecksumMethod::MD5,
+        ChecksumMethod::SHA1,
+        ChecksumMethod::SHA256,
+        ChecksumMethod::SHA384,
+        ChecksumMethod::SHA512,
+        ChecksumMethod::CRC32,
+        ChecksumMethod::BLAKE3,
+        ChecksumMethod::XXH3,
+    ];
+
+    #[test]
+    fn test_streaming_matches_one_shot_hashes() {
+        // Spans several chunks, with a partial chunk at the end
+        let data: Vec<u8> = (0..CHUNK_SIZE * 2 + 123).map(|i| (i % 251) as u8).collect();
+
+        let mut progress = Vec::new();
+        let hashes = hash_reader(Cursor::new(&data), &ALL_METHODS, None, |done| progress.push(done)).unwrap();
+
+        for (method, hash) in ALL_METHODS.iter().zip(&hashes) {
+            assert_eq!(hash, &checksum(method, &data), "{:?} differs", method);
+        }
+        // One-shot implementations of the crates
+        assert_eq!(hashes[6], blake3::hash(&data).to_hex().to_string());
+        assert_eq!(hashes[7], format!("{:016x}", xxhash_rust::xxh3::xxh3_64(&data)));
+        assert_eq!(progress, vec![CHUNK_SIZE as u64, CHUNK_SIZE as u64 * 2, data.len() as u64]);
+    }
+
+    #[test]
+    fn test_hash_file_and_cancel() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("file.txt");
+        std::fs::write(&path, "Hello, world!").unwrap();
+
+        let hashes = hash_file(&path, &[ChecksumMethod::SHA256], None, |_| {}).unwrap();
+        assert_eq!(hashes, vec!["315f5bdb76d078c43b8ac0064e4a0164612b1fce77c869345bfc94c75894edd3"]);
+
+        let cancelled = AtomicBool::new(true);
+        assert!(matches!(
+            hash_file(&path, &[ChecksumMethod::MD5], Some(&cancelled), |_| {}),
+            Err(HashError::Cancelled)
+        ));
+        assert!(hash_file(temp_dir.path(), &[ChecksumMethod::MD5], None, |_| {}).is_err());
+    }
+}
@@ -0,0 +1,274 @@
+//! Hash manifests of directory trees.
+//!
+//! A manifest lists every regular file below a directory with its hash, sorted by the
+//! relative path with `/` as separator. It is written in the format of `sha256sum` and
+//! friends (`<hash>  <path>` per line), so it can also be checked with `sha256sum -c`.
+//! The root hash of a directory is the hash of its manifest text, which makes it equal to
+//! hashing the written manifest file.
+
+use crate::commands::hash_commands::{checksum, ChecksumMethod, HashError};
+use crate::filesystem::hashing::hash_file;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use walkdir::WalkDir;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Relative to the hashed directory, with `/` as separator
+    pub path: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryManifest {
+    pub method: ChecksumMethod,
+    /// Sorted by path
+    pub entries: Vec<ManifestEntry>,
+    /// The hash of the manifest text
+    pub root_hash: String,
+}
+
+impl DirectoryManifest {
+    pub fn new(method: ChecksumMethod, mut entries: Vec<ManifestEntry>) -> Self {
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        let root_hash = checksum(&method, to_checksum_list(&entries).as_bytes());
+        Self { method, entries, root_hash }
+    }
+
+    /// The manifest in `sha256sum` format.
+    pub fn to_checksum_list(&self) -> String {
+        to_checksum_list(&self.entries)
+    }
+}
+
+/// Comparison of a directory with a manifest.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ManifestVerification {
+    /// Files with the hash from the manifest
+    pub matched: Vec<String>,
+    /// Files whose hash differs from the manifest
+    pub changed: Vec<String>,
+    /// Files in the manifest that do not exist
+    pub missing: Vec<String>,
+    /// Files that are not in the manifest
+    pub extra: Vec<String>,
+    /// Files that exist but could not be read
+    pub unreadable: Vec<String>,
+    /// Whether the directory matches the manifest exactly
+    pub ok: bool,
+}
+
+fn to_checksum_list(entries: &[ManifestEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| format_line(&entry.hash, &entry.path))
+        .collect()
+}
+
+/// Formats one line like `sha256sum`. Paths containing a backslash or line break are
+/// escaped and the line is prefixed with a backslash.
+pub fn format_line(hash: &str, path: &str) -> String {
+    if path.contains(['\\', '\n', '\r']) {
+        let escaped = path.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r");
+        format!("\\{}  {}\n", hash, escaped)
+    } else {
+        format!("{}  {}\n", hash, path)
+    }
+}
+
+/// Parses one line written by `sha256sum` or `format_line`.
+///
+/// # Returns
+/// * `Some((hash, path))` - The hash and the unescaped path
+/// * `None` - If the line is not in this format
+pub fn parse_line(line: &str) -> Option<(String, String)> {
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let (hash, path) = line.split_once(' ')?;
+    // Text mode uses a second space, binary mode a `*` in front of the path
+    let path = path.strip_prefix([' ', '*'])?;
+    if hash.is_empty() || path.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let path = if escaped { unescape(path)? } else { path.to_string() };
+    Some((hash.to_lowercase(), path))
+}
+
+fn unescape(path: &str) -> Option<String> {
+    let mut result = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => result.push('\\'),
+            'n' => result.push('\n'),
+            'r' => result.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(result)
+}
+
+/// Parses a manifest in `sha256sum` format. Empty lines are skipped.
+///
+/// # Returns
+/// * `Ok(Vec<ManifestEntry>)` - The entries in file order
+/// * `Err(String)` - The first line that is not in the expected format
+pub fn parse_checksum_list(text: &str) -> Result<Vec<ManifestEntry>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            parse_line(line)
+                .map(|(hash, path)| ManifestEntry { hash, path })
+                .ok_or_else(|| format!("Line {} is not a checksum line: {}", index + 1, line))
+        })
+        .collect()
+}
+
+/// Lists the regular files below `root` by their relative path. Symlinks are not followed
+/// and files in `excluded` are left out.
+fn list_files(root: &Path, excluded: &[PathBuf]) -> BTreeMap<String, PathBuf> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file() && !excluded.iter().any(|path| path == entry.path()))
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(root).ok()?;
+            let relative = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            Some((relative, entry.into_path()))
+        })
+        .collect()
+}
+
+/// Hashes every regular file below `root` and builds the manifest.
+///
+/// # Arguments
+/// * `excluded` - Files to leave out, e.g. the manifest file itself if it is written into `root`
+///
+/// # Returns
+/// * `Ok(DirectoryManifest)` - The manifest of the directory
+/// * `Err(HashError)` - If a file could not be read or the hashing was cancelled
+pub fn hash_directory(
+    root: &Path,
+    method: ChecksumMethod,
+    excluded: &[PathBuf],
+    cancel: Option<&AtomicBool>,
+) -> Result<DirectoryManifest, HashError> {
+    let mut entries = Vec::new();
+    for (path, absolute) in list_files(root, excluded) {
+        let mut hashes = hash_file(&absolute, std::slice::from_ref(&method), cancel, |_| {})?;
+        entries.push(ManifestEntry { path, hash: hashes.remove(0) });
+    }
+    Ok(DirectoryManifest::new(method, entries))
+}
+
+/// Compares the files below `root` with the entries of a manifest.
+///
+/// # Arguments
+/// * `excluded` - Files that are not reported as extra, e.g. the manifest file itself
+pub fn verify_directory(
+    root: &Path,
+    entries: &[ManifestEntry],
+    method: &ChecksumMethod,
+    excluded: &[PathBuf],
+) -> ManifestVerification {
+    let mut files = list_files(root, excluded);
+    let mut verification = ManifestVerification::default();
+    for entry in entries {
+        let Some(absolute) = files.remove(&entry.path) else {
+            verification.missing.push(entry.path.clone());
+            continue;
+        };
+        match hash_file(&absolute, std::slice::from_ref(method), None, |_| {}) {
+            Ok(hashes) if hashes[0].eq_ignore_ascii_case(&entry.hash) => verification.matched.push(entry.path.clone()),
+            Ok(_) => verification.changed.push(entry.path.clone()),
+            Err(_) => verification.unreadable.push(entry.path.clone()),
+        }
+    }
+    verification.extra = files.into_keys().collect();
+    verification.ok = verification.changed.is_empty()
+        && verification.missing.is_empty()
+        && verification.extra.is_empty()
+        && verification.unreadable.is_empty();
+    verification
+}
+
+#[cfg(test)]
+mod tests_manifest {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn create_tree() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("sub/deeper")).unwrap();
+        fs::write(temp_dir.path().join("b.txt"), "b").unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "a").unwrap();
+        fs::write(temp_dir.path().join("sub/deeper/c.txt"), "c").unwrap();
+        temp_dir
+    }
+
+    #[test]
+    fn test_manifest_is_sorted_and_deterministic() {
+        let temp_dir = create_tree();
+        let manifest = hash_directory(temp_dir.path(), ChecksumMethod::SHA256, &[], None).unwrap();
+
+        let paths: Vec<&str> = manifest.entries.iter().map(|entry| entry.path.as_str()).collect();
+        assert_eq!(paths, vec!["a.txt", "b.txt", "sub/deeper/c.txt"]);
+        let text = manifest.to_checksum_list();
+        assert!(text.starts_with("ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb  a.txt\n"));
+        assert_eq!(manifest.root_hash, checksum(&ChecksumMethod::SHA256, text.as_bytes()));
+
+        // The same content elsewhere gives the same root hash
+        let copy = create_tree();
+        assert_eq!(hash_directory(copy.path(), ChecksumMethod::SHA256, &[], None).unwrap().root_hash, manifest.root_hash);
+        fs::write(copy.path().join("sub/deeper/c.txt"), "changed").unwrap();
+        assert_ne!(hash_directory(copy.path(), ChecksumMethod::SHA256, &[], None).unwrap().root_hash, manifest.root_hash);
+    }
+
+    #[test]
+    fn test_checksum_list_round_trip() {
+        let entries = vec![
+            ManifestEntry { path: "plain name.txt".into(), hash: "0a".into() },
+            ManifestEntry { path: "new\nline\\slash".into(), hash: "1b".into() },
+        ];
+        let text = to_checksum_list(&entries);
+        assert_eq!(text, "0a  plain name.txt\n\\1b  new\\nline\\\\slash\n");
+        assert_eq!(parse_checksum_list(&text).unwrap(), entries);
+
+        // Binary mode and uppercase hashes as written by other tools
+        assert_eq!(parse_line("ABCD *file.bin"), Some(("abcd".to_string(), "file.bin".to_string())));
+        assert!(parse_checksum_list("not a checksum line").is_err());
+    }
+
+    #[test]
+    fn test_verify_reports_differences() {
+        let temp_dir = create_tree();
+        let manifest = hash_directory(temp_dir.path(), ChecksumMethod::MD5, &[], None).unwrap();
+
+        fs::write(temp_dir.path().join("a.txt"), "changed").unwrap();
+        fs::remove_file(temp_dir.path().join("sub/deeper/c.txt")).unwrap();
+        fs::write(temp_dir.path().join("new.txt"), "new").unwrap();
+        let manifest_file = temp_dir.path().join("MD5SUMS");
+        fs::write(&manifest_file, manifest.to_checksum_list()).unwrap();
+
+        let verification = verify_directory(temp_dir.path(), &manifest.entries, &ChecksumMethod::MD5, &[manifest_file]);
+        assert_eq!(verification.matched, vec!["b.txt"]);
+        assert_eq!(verification.changed, vec!["a.txt"]);
+        assert_eq!(verification.missing, vec!["sub/deeper/c.txt"]);
+        assert_eq!(verification.extra, vec!["new.txt"]);
+        assert!(!verification.ok);
+    }
+}
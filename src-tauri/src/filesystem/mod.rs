@@ -1 +1,14 @@
 pub(crate) mod fs_utils;
+pub(crate) mod archive;
+pub(crate) mod checksum_file;
+pub(crate) mod conflict_policy;
+pub(crate) mod directory_listing;
+pub(crate) mod disk_usage;
+pub(crate) mod duplicates;
+pub(crate) mod file_jobs;
+pub(crate) mod file_move;
+pub(crate) mod folder_size;
+pub(crate) mod hashing;
+pub(crate) mod manifest;
+pub(crate) mod operation_journal;
+pub(crate) mod trash;
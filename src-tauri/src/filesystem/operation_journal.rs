@@ -0,0 +1,309 @@
+//! Filesystem operations that are recorded in the operation journal and can be
+//! undone and redone.
+
+use crate::filesystem::conflict_policy::ConflictPolicy;
+use crate::filesystem::file_jobs::copy_with_policy;
+use crate::filesystem::file_move;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// A reversible operation with everything needed to undo and redo it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JournalOperation {
+    Rename { old_path: String, new_path: String },
+    /// Like `Rename`, but may cross filesystems
+    Move { old_path: String, new_path: String },
+    CreateFile { path: String },
+    CreateDirectory { path: String },
+    /// `destination_path` is the path the copy was written to, `manifest` what was
+    /// written there. Copies recorded without a manifest cannot be undone.
+    Copy {
+        source_path: String,
+        destination_path: String,
+        #[serde(default)]
+        manifest: Vec<CopiedEntry>,
+    },
+    /// `path` is the canonical path the item had before it was trashed
+    Trash { path: String },
+}
+
+/// A file, directory or symlink written by a copy. Undoing the copy compares these to
+/// the destination, so files changed since the copy are never deleted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CopiedEntry {
+    /// Relative to the destination, empty for the destination itself
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    /// Nanoseconds since the Unix epoch, not recorded for directories
+    pub modified: Option<u64>,
+}
+
+/// Lists everything at `path`, sorted by relative path. Symlinks are not followed.
+pub fn copy_manifest(path: &Path) -> io::Result<Vec<CopiedEntry>> {
+    let mut manifest = Vec::new();
+    collect_manifest(path, "", &mut manifest)?;
+    manifest.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(manifest)
+}
+
+fn collect_manifest(path: &Path, relative: &str, manifest: &mut Vec<CopiedEntry>) -> io::Result<()> {
+    let meta = fs::symlink_metadata(path)?;
+    let is_dir = meta.is_dir();
+    manifest.push(CopiedEntry {
+        path: relative.to_string(),
+        is_dir,
+        size: if is_dir { 0 } else { meta.len() },
+        // A directory's mtime changes with its entries, which are compared one by one
+        modified: if is_dir {
+            None
+        } else {
+            meta.modified().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_nanos() as u64)
+        },
+    });
+    if is_dir {
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let child = if relative.is_empty() { name } else { format!("{}/{}", relative, name) };
+            collect_manifest(&entry.path(), &child, manifest)?;
+        }
+    }
+    Ok(())
+}
+
+impl JournalOperation {
+    /// Reverts the operation.
+    ///
+    /// Nothing is reverted if the filesystem no longer matches the state right after
+    /// the operation, e.g. a created file that has been written to since is not deleted.
+    ///
+    /// # Returns
+    /// * `Ok(())` - The operation was reverted
+    /// * `Err(String)` - The operation could not be reverted
+    pub fn undo(&self) -> Result<(), String> {
+        match self {
+            JournalOperation::Rename { old_path, new_path } => move_path(new_path, old_path),
+            JournalOperation::Move { old_path, new_path } => {
+                file_move::move_path(Path::new(new_path), Path::new(old_path)).map(|_| ())
+            }
+            JournalOperation::CreateFile { path } => {
+                let meta = fs::symlink_metadata(path).map_err(|err| format!("Cannot access '{}': {}", path, err))?;
+                if !meta.is_file() || meta.len() > 0 {
+                    return Err(format!("'{}' has been changed since it was created", path));
+                }
+                fs::remove_file(path).map_err(|err| format!("Failed to remove '{}': {}", path, err))
+            }
+            JournalOperation::CreateDirectory { path } => {
+                let mut entries = fs::read_dir(path).map_err(|err| format!("Cannot access '{}': {}", path, err))?;
+                if entries.next().is_some() {
+                    return Err(format!("'{}' is no longer empty", path));
+                }
+                fs::remove_dir(path).map_err(|err| format!("Failed to remove '{}': {}", path, err))
+            }
+            JournalOperation::Copy { destination_path, manifest, .. } => {
+                let current = copy_manifest(Path::new(destination_path))
+                    .map_err(|err| format!("Cannot access '{}': {}", destination_path, err))?;
+                if manifest.is_empty() || current != *manifest {
+                    return Err(format!("'{}' has been changed since it was copied", destination_path));
+                }
+                let result = if current[0].is_dir {
+                    fs::remove_dir_all(destination_path)
+                } else {
+                    fs::remove_file(destination_path)
+                };
+                result.map_err(|err| format!("Failed to remove '{}': {}", destination_path, err))
+            }
+            JournalOperation::Trash { path } => restore_from_trash(path),
+        }
+    }
+
+    /// Performs the operation again after it was undone. A copy records the manifest of
+    /// the new copy.
+    ///
+    /// # Returns
+    /// * `Ok(())` - The operation was performed
+    /// * `Err(String)` - The operation could not be performed
+    pub fn redo(&mut self) -> Result<(), String> {
+        match self {
+            JournalOperation::Rename { old_path, new_path } => move_path(old_path, new_path),
+            JournalOperation::Move { old_path, new_path } => {
+                file_move::move_path(Path::new(old_path), Path::new(new_path)).map(|_| ())
+            }
+            JournalOperation::CreateFile { path } => fs::File::create_new(&path)
+                .map(|_| ())
+                .map_err(|err| format!("Failed to create '{}': {}", path, err)),
+            JournalOperation::CreateDirectory { path } => {
+                fs::create_dir(&path).map_err(|err| format!("Failed to create '{}': {}", path, err))
+            }
+            JournalOperation::Copy { source_path, destination_path, manifest } => {
+                if Path::new(destination_path).exists() {
+                    return Err(format!("'{}' already exists", destination_path));
+                }
+                copy_with_policy(Path::new(source_path), Path::new(destination_path), ConflictPolicy::Skip)
+                    .map_err(|err| format!("Failed to copy '{}': {}", source_path, err))?;
+                *manifest = copy_manifest(Path::new(destination_path))
+                    .map_err(|err| format!("Cannot access '{}': {}", destination_path, err))?;
+                Ok(())
+            }
+            JournalOperation::Trash { path } => {
+                trash::delete(&path).map_err(|err| format!("Failed to move '{}' to trash: {}", path, err))
+            }
+        }
+    }
+}
+
+/// Renames `from` to `to` without replacing anything at `to`.
+fn move_path(from: &str, to: &str) -> Result<(), String> {
+    if fs::symlink_metadata(from).is_err() {
+        return Err(format!("'{}' does not exist", from));
+    }
+    if fs::symlink_metadata(to).is_ok() {
+        return Err(format!("'{}' already exists", to));
+    }
+    fs::rename(from, to).map_err(|err| format!("Failed to rename '{}' to '{}': {}", from, to, err))
+}
+
+/// Restores the most recently trashed item that was located at `path`.
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+fn restore_from_trash(path: &str) -> Result<(), String> {
+    if fs::symlink_metadata(path).is_ok() {
+        return Err(format!("'{}' already exists", path));
+    }
+    let items = trash::os_limited::list().map_err(|err| format!("Failed to list trash: {}", err))?;
+    let item = items
+        .into_iter()
+        .filter(|item| item.original_path() == Path::new(path))
+        .max_by_key(|item| item.time_deleted)
+        .ok_or_else(|| format!("'{}' is no longer in the trash", path))?;
+    trash::os_limited::restore_all([item]).map_err(|err| format!("Failed to restore '{}': {}", path, err))
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+)))]
+fn restore_from_trash(path: &str) -> Result<(), String> {
+    Err(format!("Restoring '{}' from the trash is not supported on this OS", path))
+}
+
+#[cfg(test)]
+mod tests_operation_journal {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn path_str(path: &Path) -> String {
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_undo_and_redo_rename() {
+        let temp_dir = TempDir::new().unwrap();
+        let old = temp_dir.path().join("old.txt");
+        let new = temp_dir.path().join("new.txt");
+        fs::write(&new, "data").unwrap();
+        let mut operation = JournalOperation::Rename { old_path: path_str(&old), new_path: path_str(&new) };
+
+        operation.undo().unwrap();
+        assert!(old.exists() && !new.exists());
+
+        operation.redo().unwrap();
+        assert!(!old.exists() && new.exists());
+
+        // Neither direction replaces an existing path
+        fs::write(&old, "other").unwrap();
+        assert!(operation.undo().is_err());
+        assert_eq!(fs::read_to_string(&old).unwrap(), "other");
+    }
+
+    #[test]
+    fn test_undo_create_keeps_changed_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("notes.txt");
+        let dir = temp_dir.path().join("folder");
+        fs::write(&file, "written later").unwrap();
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("inner.txt"), "").unwrap();
+
+        assert!(JournalOperation::CreateFile { path: path_str(&file) }.undo().is_err());
+        assert!(JournalOperation::CreateDirectory { path: path_str(&dir) }.undo().is_err());
+        assert!(file.exists() && dir.join("inner.txt").exists());
+
+        fs::remove_file(dir.join("inner.txt")).unwrap();
+        JournalOperation::CreateDirectory { path: path_str(&dir) }.undo().unwrap();
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_undo_and_redo_copy() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let destination = temp_dir.path().join("destination");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("a.txt"), "a").unwrap();
+        copy_with_policy(&source, &destination, ConflictPolicy::Rename).unwrap();
+        let mut operation = JournalOperation::Copy {
+            source_path: path_str(&source),
+            destination_path: path_str(&destination),
+            manifest: copy_manifest(&destination).unwrap(),
+        };
+
+        operation.undo().unwrap();
+        assert!(!destination.exists());
+        assert!(source.join("a.txt").exists());
+
+        operation.redo().unwrap();
+        assert_eq!(fs::read_to_string(destination.join("a.txt")).unwrap(), "a");
+        // The manifest follows the new copy, so it can be undone again
+        operation.undo().unwrap();
+        assert!(!destination.exists());
+    }
+
+    #[test]
+    fn test_undo_copy_keeps_edited_copies() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let destination = temp_dir.path().join("destination");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("a.txt"), "a").unwrap();
+        copy_with_policy(&source, &destination, ConflictPolicy::Rename).unwrap();
+        let operation = JournalOperation::Copy {
+            source_path: path_str(&source),
+            destination_path: path_str(&destination),
+            manifest: copy_manifest(&destination).unwrap(),
+        };
+
+        fs::write(destination.join("a.txt"), "edited after the copy").unwrap();
+        assert!(operation.undo().unwrap_err().contains("changed since it was copied"));
+        assert_eq!(fs::read_to_string(destination.join("a.txt")).unwrap(), "edited after the copy");
+
+        // Added files also keep the copy
+        fs::write(destination.join("a.txt"), "a").unwrap();
+        let operation = JournalOperation::Copy {
+            source_path: path_str(&source),
+            destination_path: path_str(&destination),
+            manifest: copy_manifest(&destination).unwrap(),
+        };
+        fs::write(destination.join("new.txt"), "new").unwrap();
+        assert!(operation.undo().is_err());
+        assert!(destination.join("new.txt").exists());
+
+        // Without a manifest nothing can be verified
+        fs::remove_file(destination.join("new.txt")).unwrap();
+        let legacy: JournalOperation = serde_json::from_value(serde_json::json!({
+            "type": "copy",
+            "source_path": path_str(&source),
+            "destination_path": path_str(&destination),
+        }))
+        .unwrap();
+        assert!(legacy.undo().is_err());
+        assert!(destination.exists());
+    }
+}
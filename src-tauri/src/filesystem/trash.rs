@@ -0,0 +1,356 @@
+//! Browsing, restoring and purging the system trash.
+//!
+//! Built on the `os_limited` API of the `trash` crate and the freedesktop.org trash
+//! layout, so it is only available on Linux. Other platforms get `TrashError::Unsupported`.
+
+use crate::error_handling::{Error, ErrorCode};
+use crate::filesystem::conflict_policy::{ConflictChoice, ConflictPolicy};
+use serde::{Deserialize, Serialize};
+
+/// An item in the trash.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashEntry {
+    /// Identifies the item for restoring and purging (the path of its `.trashinfo` file)
+    pub id: String,
+    pub name: String,
+    pub original_path: String,
+    pub deleted_at: i64, // as seconds since epoch
+    pub is_dir: bool,
+    /// Size in bytes of a file, `None` for directories
+    pub size: Option<u64>,
+    /// Number of entries directly inside a directory, `None` for files
+    pub entries: Option<usize>,
+}
+
+/// The result of restoring a single item.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestoreOutcome {
+    pub id: String,
+    pub original_path: String,
+    /// Where the item was restored to, `None` if it was skipped or failed
+    pub restored_to: Option<String>,
+    /// How an existing item at the original path was handled, `None` if there was none
+    pub conflict: Option<ConflictChoice>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TrashError {
+    /// Only constructed on platforms without a freedesktop.org trash
+    #[cfg_attr(
+        all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")),
+        allow(dead_code)
+    )]
+    Unsupported,
+    /// The given ids are not in the trash (anymore)
+    NotInTrash(Vec<String>),
+    /// `ConflictPolicy::Ask` needs a round-trip to the user that restoring does not support
+    AskNotSupported,
+    Failed(String),
+}
+
+impl TrashError {
+    pub fn to_json(&self) -> String {
+        match self {
+            TrashError::Unsupported => Error::new(
+                ErrorCode::NotImplementedForOS,
+                "Browsing the trash is only supported on Linux".to_string(),
+            ),
+            TrashError::NotInTrash(ids) => Error::new(
+                ErrorCode::ResourceNotFound,
+                format!("Not in the trash: {}", ids.join(", ")),
+            ),
+            TrashError::AskNotSupported => Error::new(
+                ErrorCode::InvalidInput,
+                "The ask conflict policy is not supported for restoring from the trash".to_string(),
+            ),
+            TrashError::Failed(message) => Error::new(ErrorCode::InternalError, message.clone()),
+        }
+        .to_json()
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+pub use freedesktop::{empty_trash, list_trash, purge_trash_items, restore_trash_items};
+
+#[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+mod freedesktop {
+    use super::*;
+    use crate::commands::file_system_operation_commands::generate_unique_path;
+    use crate::filesystem::conflict_policy::modified_millis;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use trash::os_limited;
+    use trash::{TrashItem, TrashItemSize};
+
+    fn failed(err: trash::Error) -> TrashError {
+        TrashError::Failed(err.to_string())
+    }
+
+    fn id_of(item: &TrashItem) -> String {
+        item.id.to_string_lossy().to_string()
+    }
+
+    /// The trashed file or directory itself, stored under `files/` next to the `info/` folder.
+    pub(super) fn file_in_trash(item: &TrashItem) -> PathBuf {
+        let info_file = Path::new(&item.id);
+        let trash_folder = info_file.parent().and_then(Path::parent).unwrap_or(Path::new(""));
+        trash_folder.join("files").join(info_file.file_stem().unwrap_or_default())
+    }
+
+    fn to_entry(item: &TrashItem) -> TrashEntry {
+        let (size, entries) = match os_limited::metadata(item).map(|metadata| metadata.size) {
+            Ok(TrashItemSize::Bytes(bytes)) => (Some(bytes), None),
+            Ok(TrashItemSize::Entries(entries)) => (None, Some(entries)),
+            Err(_) => (None, None),
+        };
+        TrashEntry {
+            id: id_of(item),
+            name: item.name.to_string_lossy().to_string(),
+            original_path: item.original_path().to_string_lossy().to_string(),
+            deleted_at: item.time_deleted,
+            is_dir: fs::symlink_metadata(file_in_trash(item)).is_ok_and(|meta| meta.is_dir()),
+            size,
+            entries,
+        }
+    }
+
+    /// Looks up the trash items with the given ids.
+    fn find_items(ids: &[String]) -> Result<Vec<TrashItem>, TrashError> {
+        select_items(os_limited::list().map_err(failed)?, ids)
+    }
+
+    /// Keeps the items with the given ids, failing with the ids that are not among them.
+    pub(super) fn select_items(mut items: Vec<TrashItem>, ids: &[String]) -> Result<Vec<TrashItem>, TrashError> {
+        items.retain(|item| ids.contains(&id_of(item)));
+
+        let missing: Vec<String> = ids
+            .iter()
+            .filter(|id| !items.iter().any(|item| &id_of(item) == *id))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            return Err(TrashError::NotInTrash(missing));
+        }
+        Ok(items)
+    }
+
+    /// Lists all items in the trash, most recently deleted first.
+    pub fn list_trash() -> Result<Vec<TrashEntry>, TrashError> {
+        let mut entries: Vec<TrashEntry> = os_limited::list().map_err(failed)?.iter().map(to_entry).collect();
+        entries.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+        Ok(entries)
+    }
+
+    /// Restores the items to their original location.
+    ///
+    /// If something exists at the original path, the policy decides: `Skip` leaves the item
+    /// in the trash, `Overwrite` moves the existing item to the trash first, `OverwriteIfNewer`
+    /// does so only if the trashed item was modified later, and `Rename` restores under a
+    /// free name such as `file (1).txt`.
+    ///
+    /// # Returns
+    /// One outcome per item in the order of `ids`. A failing item does not stop the others.
+    pub fn restore_trash_items(ids: &[String], policy: ConflictPolicy) -> Result<Vec<RestoreOutcome>, TrashError> {
+        if policy == ConflictPolicy::Ask {
+            return Err(TrashError::AskNotSupported);
+        }
+        let mut items = find_items(ids)?;
+
+        let mut outcomes = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(position) = items.iter().position(|item| &id_of(item) == id) {
+                outcomes.push(restore_item(items.swap_remove(position), policy));
+            }
+        }
+        Ok(outcomes)
+    }
+
+    fn restore_item(item: TrashItem, policy: ConflictPolicy) -> RestoreOutcome {
+        let original_path = item.original_path();
+        let mut outcome = RestoreOutcome {
+            id: id_of(&item),
+            original_path: original_path.to_string_lossy().to_string(),
+            restored_to: None,
+            conflict: None,
+            error: None,
+        };
+
+        let result = if fs::symlink_metadata(&original_path).is_err() {
+            os_limited::restore_all([item]).map(|_| Some(original_path)).map_err(|err| err.to_string())
+        } else {
+            let choice = choose(policy, &file_in_trash(&item), &original_path);
+            outcome.conflict = Some(choice);
+            match choice {
+                ConflictChoice::Skip => Ok(None),
+                ConflictChoice::Overwrite => trash::delete(&original_path)
+                    .and_then(|_| os_limited::restore_all([item]))
+                    .map(|_| Some(original_path))
+                    .map_err(|err| err.to_string()),
+                ConflictChoice::Rename => {
+                    let target = PathBuf::from(generate_unique_path(&original_path.to_string_lossy()));
+                    move_out_of_trash(&item, &target).map(|_| Some(target))
+                }
+            }
+        };
+
+        match result {
+            Ok(restored_to) => outcome.restored_to = restored_to.map(|path| path.to_string_lossy().to_string()),
+            Err(err) => outcome.error = Some(err),
+        }
+        outcome
+    }
+
+    fn choose(policy: ConflictPolicy, trashed: &Path, existing: &Path) -> ConflictChoice {
+        match policy {
+            ConflictPolicy::Skip | ConflictPolicy::Ask => ConflictChoice::Skip,
+            ConflictPolicy::Overwrite => ConflictChoice::Overwrite,
+            ConflictPolicy::Rename => ConflictChoice::Rename,
+            ConflictPolicy::OverwriteIfNewer => {
+                let modified = |path: &Path| fs::symlink_metadata(path).ok().and_then(|meta| modified_millis(&meta));
+                match (modified(trashed), modified(existing)) {
+                    (Some(trashed), Some(existing)) if trashed > existing => ConflictChoice::Overwrite,
+                    _ => ConflictChoice::Skip,
+                }
+            }
+        }
+    }
+
+    /// Restores an item to another path than its original one, which the `trash` crate cannot do.
+    pub(super) fn move_out_of_trash(item: &TrashItem, target: &Path) -> Result<(), String> {
+        let file = file_in_trash(item);
+        fs::create_dir_all(&item.original_parent)
+            .map_err(|err| format!("Failed to create '{}': {}", item.original_parent.display(), err))?;
+        fs::rename(&file, target)
+            .map_err(|err| format!("Failed to move '{}' to '{}': {}", file.display(), target.display(), err))?;
+        fs::remove_file(&item.id).map_err(|err| format!("Failed to remove trash info of '{}': {}", target.display(), err))
+    }
+
+    /// Permanently deletes the items from the trash.
+    ///
+    /// # Returns
+    /// The number of purged items
+    pub fn purge_trash_items(ids: &[String]) -> Result<usize, TrashError> {
+        let items = find_items(ids)?;
+        os_limited::purge_all(&items).map_err(failed)?;
+        Ok(items.len())
+    }
+
+    /// Permanently deletes everything in the trash.
+    ///
+    /// # Returns
+    /// The number of purged items
+    pub fn empty_trash() -> Result<usize, TrashError> {
+        let items = os_limited::list().map_err(failed)?;
+        os_limited::purge_all(&items).map_err(failed)?;
+        Ok(items.len())
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))))]
+pub use unsupported::{empty_trash, list_trash, purge_trash_items, restore_trash_items};
+
+#[cfg(not(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))))]
+mod unsupported {
+    use super::*;
+
+    pub fn list_trash() -> Result<Vec<TrashEntry>, TrashError> {
+        Err(TrashError::Unsupported)
+    }
+
+    pub fn restore_trash_items(_ids: &[String], _policy: ConflictPolicy) -> Result<Vec<RestoreOutcome>, TrashError> {
+        Err(TrashError::Unsupported)
+    }
+
+    pub fn purge_trash_items(_ids: &[String]) -> Result<usize, TrashError> {
+        Err(TrashError::Unsupported)
+    }
+
+    pub fn empty_trash() -> Result<usize, TrashError> {
+        Err(TrashError::Unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests_trash {
+    use super::*;
+
+    #[test]
+    fn test_errors_to_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&TrashError::NotInTrash(vec!["a".to_string(), "b".to_string()]).to_json()).unwrap();
+        assert_eq!(json["message_from_code"], "ResourceNotFound");
+        assert_eq!(json["custom_message"], "Not in the trash: a, b");
+        let json: serde_json::Value = serde_json::from_str(&TrashError::AskNotSupported.to_json()).unwrap();
+        assert_eq!(json["message_from_code"], "InvalidInput");
+    }
+
+    #[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+    mod freedesktop {
+        use super::super::freedesktop::{file_in_trash, move_out_of_trash, select_items};
+        use super::super::*;
+        use std::fs;
+        use std::path::Path;
+        use tempfile::TempDir;
+        use trash::TrashItem;
+
+        /// An item of a trash folder at `trash`, which is not the trash of the system.
+        fn item(trash: &Path, name: &str, original_parent: &Path) -> TrashItem {
+            TrashItem {
+                id: trash.join("info").join(format!("{}.trashinfo", name)).into_os_string(),
+                name: name.into(),
+                original_parent: original_parent.to_path_buf(),
+                time_deleted: 1_700_000_000,
+            }
+        }
+
+        fn ids(items: &[TrashItem]) -> Vec<String> {
+            items.iter().map(|item| item.id.to_string_lossy().to_string()).collect()
+        }
+
+        #[test]
+        fn test_select_items_by_id() {
+            let trash = Path::new("/data/.Trash-1000");
+            let items = vec![item(trash, "a.txt", trash), item(trash, "b.txt", trash), item(trash, "c.txt", trash)];
+            let wanted = vec![ids(&items)[2].clone(), ids(&items)[0].clone()];
+
+            let selected = select_items(items.clone(), &wanted).unwrap();
+            assert_eq!(ids(&selected), vec![ids(&items)[0].clone(), ids(&items)[2].clone()]);
+
+            // Only the ids that are missing are reported, a name alone is not an id
+            let unknown = vec![ids(&items)[1].clone(), "b.txt".to_string(), "/gone.trashinfo".to_string()];
+            assert_eq!(
+                select_items(items, &unknown).unwrap_err(),
+                TrashError::NotInTrash(vec!["b.txt".to_string(), "/gone.trashinfo".to_string()])
+            );
+        }
+
+        #[test]
+        fn test_ask_policy_is_refused() {
+            let ids = vec!["/data/.Trash-1000/info/a.txt.trashinfo".to_string()];
+            assert_eq!(restore_trash_items(&ids, ConflictPolicy::Ask).unwrap_err(), TrashError::AskNotSupported);
+        }
+
+        #[test]
+        fn test_move_out_of_trash() {
+            let temp_dir = TempDir::new().unwrap();
+            let trash = temp_dir.path().join("Trash");
+            fs::create_dir_all(trash.join("info")).unwrap();
+            fs::create_dir_all(trash.join("files")).unwrap();
+            let home = temp_dir.path().join("home");
+            let item = item(&trash, "report.txt", &home);
+            fs::write(&item.id, "[Trash Info]").unwrap();
+            fs::write(trash.join("files/report.txt"), "report").unwrap();
+            assert_eq!(file_in_trash(&item), trash.join("files/report.txt"));
+
+            // The original folder is created again if it was deleted as well
+            let target = home.join("report (1).txt");
+            move_out_of_trash(&item, &target).unwrap();
+            assert_eq!(fs::read_to_string(&target).unwrap(), "report");
+            assert!(!Path::new(&item.id).exists());
+
+            let err = move_out_of_trash(&item, &home.join("again.txt")).unwrap_err();
+            assert!(err.starts_with("Failed to move"), "{}", err);
+        }
+    }
+}
@@ -6,11 +6,12 @@ mod error_handling;
 mod filesystem;
 pub mod models;
 mod search_engine;
+mod sftp;
 mod state;
 
 use crate::commands::{
-    command_exec_commands, file_system_operation_commands, hash_commands, meta_data_commands,
-    search_engine_commands, settings_commands, template_commands, volume_operations_commands, sftp_file_system_operation_commands, preview_commands, permission_commands
+    archive_commands, command_exec_commands, disk_usage_commands, duplicate_commands, file_job_commands, file_system_operation_commands, folder_size_commands, hash_commands, meta_data_commands,
+    search_engine_commands, settings_commands, template_commands, trash_commands, volume_operations_commands, sftp_file_system_operation_commands, sftp_connection_commands, preview_commands, permission_commands
 };
 use tauri::ipc::Invoke;
 use tauri::Manager;
@@ -20,14 +21,42 @@ fn all_commands() -> fn(Invoke) -> bool {
         // Filesystem commands
         //file_system_operation_commands::open_file,
         file_system_operation_commands::open_directory,
+        file_system_operation_commands::stream_directory,
+        file_system_operation_commands::get_child_counts,
         file_system_operation_commands::open_in_default_app,
         file_system_operation_commands::create_file,
         file_system_operation_commands::create_directory,
         file_system_operation_commands::rename,
+        file_system_operation_commands::move_file_or_dir,
         file_system_operation_commands::move_to_trash,
         file_system_operation_commands::copy_file_or_dir,
         file_system_operation_commands::zip,
         file_system_operation_commands::unzip,
+        file_system_operation_commands::undo_last_operation,
+        file_system_operation_commands::redo_operation,
+        // Trash commands
+        trash_commands::list_trash,
+        trash_commands::restore_trash_items,
+        trash_commands::purge_trash_items,
+        trash_commands::empty_trash,
+        // Folder size commands
+        folder_size_commands::calculate_folder_sizes,
+        folder_size_commands::cancel_folder_sizes,
+        // Disk usage commands
+        disk_usage_commands::analyze_disk_usage,
+        disk_usage_commands::cancel_disk_usage_analysis,
+        // File job commands
+        file_job_commands::queue_copy_job,
+        file_job_commands::queue_zip_job,
+        file_job_commands::queue_unzip_job,
+        file_job_commands::queue_trash_job,
+        file_job_commands::pause_file_job,
+        file_job_commands::resume_file_job,
+        file_job_commands::cancel_file_job,
+        file_job_commands::resolve_file_conflict,
+        file_job_commands::get_file_jobs,
+        file_job_commands::get_file_job,
+        file_job_commands::clear_finished_file_jobs,
         // Command execution commands
         command_exec_commands::execute_command,
         command_exec_commands::execute_command_improved,
@@ -48,6 +77,14 @@ fn all_commands() -> fn(Invoke) -> bool {
         hash_commands::gen_hash_and_return_string,
         hash_commands::gen_hash_and_save_to_file,
         hash_commands::compare_file_or_dir_with_hash,
+        hash_commands::gen_hashes,
+        hash_commands::cancel_hashing,
+        hash_commands::gen_directory_manifest,
+        hash_commands::verify_directory_manifest,
+        hash_commands::verify_checksum_file,
+        // Duplicate commands
+        duplicate_commands::find_duplicates,
+        duplicate_commands::remove_duplicates,
         // Template commands
         template_commands::get_template_paths_as_json,
         template_commands::add_template,
@@ -56,6 +93,7 @@ fn all_commands() -> fn(Invoke) -> bool {
         // Autocomplete commands
         search_engine_commands::search,
         search_engine_commands::search_with_extension,
+        search_engine_commands::search_content,
         search_engine_commands::add_paths_recursive,
         search_engine_commands::add_path,
         search_engine_commands::remove_path,
@@ -71,7 +109,15 @@ fn all_commands() -> fn(Invoke) -> bool {
         // Preview commands
         preview_commands::build_preview,
 
+        // Archive commands
+        archive_commands::open_archive_directory,
+        archive_commands::extract_archive_member,
+
         //sftp commands
+        sftp_file_system_operation_commands::check_sftp_host_key,
+        sftp_file_system_operation_commands::trust_sftp_host_key,
+        sftp_file_system_operation_commands::connect_to_sftp,
+        sftp_file_system_operation_commands::disconnect_sftp,
         sftp_file_system_operation_commands::load_dir,
         sftp_file_system_operation_commands::open_file_sftp,
         sftp_file_system_operation_commands::create_file_sftp,
@@ -87,6 +133,11 @@ fn all_commands() -> fn(Invoke) -> bool {
         sftp_file_system_operation_commands::build_preview_sftp,
         sftp_file_system_operation_commands::download_and_open_sftp_file,
         sftp_file_system_operation_commands::cleanup_sftp_temp_files,
+        //saved sftp connections
+        sftp_connection_commands::list_sftp_connections,
+        sftp_connection_commands::save_sftp_connection,
+        sftp_connection_commands::remove_sftp_connection,
+        sftp_connection_commands::connect_sftp_connection,
 
         // Permission commands
         permission_commands::request_full_disk_access,
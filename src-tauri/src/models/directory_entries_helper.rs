@@ -157,8 +157,19 @@ pub fn access_permission_string_windows(permission: Permissions, is_directory: b
 /// ```
 #[cfg(unix)]
 pub fn access_rights_to_string_unix(permissions: Permissions) -> String {
+    access_rights_to_string_from_mode(permissions.mode())
+}
+
+/// This function converts unix permission bits into a human-readable string, e.g. for
+/// entries of an archive, which carry unix permissions on every platform.
+///
+/// # Parameters
+/// - `mode`: The unix permission bits.
+///
+/// # Returns
+/// A string like `rwxr-xr-x`.
+pub fn access_rights_to_string_from_mode(mode: u32) -> String {
     let mut result = String::new();
-    let mode = permissions.mode();
 
     // User permissions
     result.push(if mode & 0o400 != 0 { 'r' } else { '-' });
@@ -254,7 +265,6 @@ pub fn get_directory_size_in_bytes(path: &str) -> u64 {
 ///  let (file_count, dir_count) = count_subfiles_and_directories(&path);
 ///  println!("Files: {}, Directories: {}", file_count, dir_count);
 /// }
-#[allow(dead_code)]
 pub fn count_subfiles_and_subdirectories(path: &str) -> (usize, usize) {
     let mut file_count = 0;
     let mut dir_count = 0;
@@ -16,11 +16,15 @@ pub struct SearchEngineConfig {
     pub ranking_config: RankingConfig,
     pub prefer_directories: bool,
     pub cache_ttl: Option<Duration>,
+    /// Also index the text content of files so they can be found with `search_content`
+    #[serde(default)]
+    pub index_file_contents: bool,
+    /// Time budget in milliseconds for glob, regex and exact searches (5 seconds if unset)
+    pub search_timeout_ms: Option<u64>,
     // To be implemented
     //pub collect_usage_stats: bool,
     //pub indexing_logging_enabled: bool,
     //pub search_logging_enabled: bool,
-    //pub search_timeout_ms: Option<u64>,
     //pub result_score_threshold: Option<f32>,
     //pub min_query_length: Option<usize>,
     //pub max_indexed_files: Option<usize>,
@@ -69,10 +73,11 @@ impl Default for SearchEngineConfig {
             
             cache_ttl: Duration::from_secs(300).into(), // 5 minutes
             prefer_directories: false,
+            index_file_contents: false,
+            search_timeout_ms: Some(5000), // 5 seconds
             //collect_usage_stats: true,
             //indexing_logging_enabled: false,
             //search_logging_enabled: false,
-            //search_timeout_ms: Some(5000), // 5 seconds
             //result_score_threshold: Some(0.1),
             //min_query_length: None,           
             //max_indexed_files: None, 
@@ -1632,6 +1632,54 @@ impl ART {
         results
     }
 
+    /// Checks whether an exact path is stored in the trie.
+    /// Normalizes the path before the lookup to ensure consistency.
+    ///
+    /// # Arguments
+    /// * `path` - A string slice containing the path to look up.
+    ///
+    /// # Returns
+    /// * `true` if the path is stored as a complete entry.
+    /// * `false` if the path is missing or only a prefix of other entries.
+    ///
+    /// # Example
+    /// ```rust
+    /// let mut trie = ART::new(10);
+    /// trie.insert("/home/user/file.txt", 1.0);
+    /// assert!(trie.contains("/home/user/file.txt"));
+    /// assert!(!trie.contains("/home/user"));
+    /// ```
+    pub fn contains(&self, path: &str) -> bool {
+        let normalized = self.normalize_path(path);
+        let path_bytes = normalized.as_bytes();
+
+        let mut node = match &self.root {
+            Some(root) => root.as_ref(),
+            None => return false,
+        };
+        let mut depth = 0;
+
+        loop {
+            let (match_len, exact_match) = node.check_prefix(path_bytes, depth);
+            if !exact_match {
+                return false;
+            }
+            depth += match_len;
+
+            if depth == path_bytes.len() {
+                return node.is_terminal();
+            }
+
+            match node.find_child(path_bytes[depth]) {
+                Some(child) => {
+                    node = child.as_ref();
+                    depth += 1;
+                }
+                None => return false,
+            }
+        }
+    }
+
     /// Removes a path from the trie.
     /// Normalizes the path before removal to ensure consistency.
     ///
@@ -1836,6 +1884,49 @@ impl ART {
         self.path_count = 0;
     }
 
+    /// Returns every path stored in the trie together with its score.
+    /// Used to serialize the trie; re-inserting the entries rebuilds an equivalent trie.
+    ///
+    /// Unlike `collect_all_paths`, keys are accumulated as raw bytes and decoded once
+    /// per path, so multi-byte UTF-8 characters survive the round trip.
+    ///
+    /// # Returns
+    /// * A vector of (path, score) tuples in no particular order.
+    ///
+    /// # Example
+    /// ```rust
+    /// let mut trie = ART::new(10);
+    /// trie.insert("/home/user/file.txt", 1.0);
+    /// let entries = trie.export_entries();
+    /// assert_eq!(entries, vec![("/home/user/file.txt".to_string(), 1.0)]);
+    /// ```
+    pub fn export_entries(&self) -> Vec<(String, f32)> {
+        let mut results = Vec::with_capacity(self.path_count);
+        let root = match &self.root {
+            Some(root) => root,
+            None => return results,
+        };
+
+        let mut stack: Vec<(&ARTNode, Vec<KeyType>)> = vec![(root.as_ref(), Vec::new())];
+        while let Some((node, mut key)) = stack.pop() {
+            key.extend_from_slice(node.get_prefix());
+
+            if node.is_terminal() {
+                if let Some(score) = node.get_score() {
+                    results.push((String::from_utf8_lossy(&key).into_owned(), score));
+                }
+            }
+
+            for (child_key, child) in node.iter_children() {
+                let mut child_path = key.clone();
+                child_path.push(child_key);
+                stack.push((child.as_ref(), child_path));
+            }
+        }
+
+        results
+    }
+
     /// Sorts and deduplicates a collection of search results.
     /// Results are sorted by score in descending order (highest first).
     ///
@@ -3593,4 +3684,50 @@ mod tests_art_v5 {
             );
         }
     }
+
+    #[test]
+    fn test_export_entries_roundtrip() {
+        let mut trie = ART::new(10);
+        let paths = [
+            ("/home/user/documents/report.pdf", 1.0),
+            ("/home/user/documents/reports", 1.2),
+            ("/home/user/música/canción.mp3", 0.8),
+        ];
+        for (path, score) in &paths {
+            trie.insert(path, *score);
+        }
+
+        let mut exported = trie.export_entries();
+        assert_eq!(exported.len(), paths.len());
+
+        // Rebuilding from the exported entries must yield the same content
+        let mut rebuilt = ART::new(10);
+        for (path, score) in &exported {
+            rebuilt.insert(path, *score);
+        }
+        let mut reexported = rebuilt.export_entries();
+
+        exported.sort_by(|a, b| a.0.cmp(&b.0));
+        reexported.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(exported, reexported);
+        assert!(exported.iter().any(|(p, _)| p == "/home/user/música/canción.mp3"));
+    }
+
+    #[test]
+    fn test_contains() {
+        let mut trie = ART::new(10);
+        trie.insert("/home/user/file.txt", 1.0);
+        trie.insert("/home/user/files", 1.0);
+
+        assert!(trie.contains("/home/user/file.txt"));
+        assert!(trie.contains("/home/user/files"));
+        assert!(trie.contains("/home/user/files/"));
+        assert!(!trie.contains("/home/user/file"));
+        assert!(!trie.contains("/home/user"));
+        assert!(!trie.contains("/home/user/file.txt.bak"));
+
+        trie.remove("/home/user/file.txt");
+        assert!(!trie.contains("/home/user/file.txt"));
+        assert!(!ART::new(10).contains("/anything"));
+    }
 }
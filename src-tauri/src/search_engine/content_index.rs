@@ -0,0 +1,447 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Number of leading bytes of each file that go into the content index
+pub const MAX_CONTENT_INDEX_BYTES: usize = 1024 * 1024;
+
+/// Words shorter than this are not indexed and are ignored in queries.
+const MIN_TOKEN_LEN: usize = 2;
+
+/// Longer words (hashes, base64 blobs, minified code) are not indexed.
+const MAX_TOKEN_LEN: usize = 64;
+
+/// Maximum number of matching lines reported per file.
+pub const MAX_MATCHES_PER_FILE: usize = 5;
+
+/// Snippets longer than this many characters are cut down around the match.
+const MAX_SNIPPET_CHARS: usize = 160;
+
+/// A single line of a file that matched a content query.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ContentMatch {
+    /// 1-based line number inside the file
+    pub line_number: usize,
+    /// The matching line, trimmed and shortened around the match
+    pub snippet: String,
+}
+
+/// A file whose contents matched a content query.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContentSearchResult {
+    pub path: String,
+    /// Relevance after ranking, between 0 and 1
+    pub score: f32,
+    /// Total number of matching lines in the file
+    pub match_count: usize,
+    /// The first `MAX_MATCHES_PER_FILE` matching lines
+    pub matches: Vec<ContentMatch>,
+}
+
+/// Serializable form of a `ContentIndex`, with the removed files left out.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentIndexSnapshot {
+    /// Indexed file paths, the position is the id used in `postings`
+    pub files: Vec<String>,
+    /// Word and the ascending ids of the files containing it
+    pub postings: Vec<(String, Vec<u32>)>,
+}
+
+/// Inverted word index over the text content of indexed files.
+///
+/// The index only narrows a query down to the files that contain every query
+/// word (as a word prefix); the caller then reads those files to find the exact
+/// matching lines. This keeps memory usage proportional to the vocabulary instead
+/// of the total amount of text.
+///
+/// # Example
+/// ```rust
+/// let mut index = ContentIndex::new();
+/// index.add_file("/notes/todo.txt", "buy milk\ncall the plumber");
+/// assert_eq!(index.candidates("plumb"), vec!["/notes/todo.txt".to_string()]);
+/// ```
+#[derive(Debug, Default)]
+pub struct ContentIndex {
+    /// Indexed file paths by id, `None` for files that have been removed
+    files: Vec<Option<String>>,
+    /// Reverse lookup from path to id for live files
+    file_ids: HashMap<String, u32>,
+    /// Word → ascending ids of the files containing it.
+    /// Kept sorted so prefix lookups are a range scan.
+    postings: BTreeMap<String, Vec<u32>>,
+    /// Number of `None` slots in `files`
+    removed: usize,
+}
+
+impl ContentIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of files currently in the index.
+    pub fn len(&self) -> usize {
+        self.file_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.file_ids.is_empty()
+    }
+
+    /// Adds or replaces the indexed content of a file.
+    ///
+    /// # Arguments
+    /// * `path` - The normalized path of the file
+    /// * `text` - The decoded text content of the file
+    ///
+    /// # Performance
+    /// O(w log v) where w is the number of words in `text` and v the vocabulary size
+    pub fn add_file(&mut self, path: &str, text: &str) {
+        self.remove_file(path);
+
+        let id = self.files.len() as u32;
+        self.files.push(Some(path.to_string()));
+        self.file_ids.insert(path.to_string(), id);
+
+        let mut words = tokenize(text);
+        words.sort_unstable();
+        words.dedup();
+        for word in words {
+            self.postings.entry(word).or_default().push(id);
+        }
+    }
+
+    /// Removes a file from the index. Does nothing if the file is not indexed.
+    pub fn remove_file(&mut self, path: &str) {
+        if let Some(id) = self.file_ids.remove(path) {
+            self.files[id as usize] = None;
+            self.removed += 1;
+            self.compact_if_needed();
+        }
+    }
+
+    /// Removes every file at or below `root`.
+    ///
+    /// # Returns
+    /// The number of files removed
+    pub fn remove_files_under(&mut self, root: &str) -> usize {
+        let trimmed = root.trim_end_matches('/');
+        let prefix = format!("{}/", trimmed);
+        let doomed: Vec<String> = self
+            .file_ids
+            .keys()
+            .filter(|path| trimmed.is_empty() || path.as_str() == trimmed || path.starts_with(&prefix))
+            .cloned()
+            .collect();
+
+        for path in &doomed {
+            if let Some(id) = self.file_ids.remove(path) {
+                self.files[id as usize] = None;
+                self.removed += 1;
+            }
+        }
+        self.compact_if_needed();
+
+        doomed.len()
+    }
+
+    /// Creates a snapshot of the index for persistence.
+    ///
+    /// # Performance
+    /// O(p) where p is the total number of postings
+    pub fn export(&self) -> ContentIndexSnapshot {
+        let mut remap = vec![u32::MAX; self.files.len()];
+        let mut files = Vec::with_capacity(self.file_ids.len());
+        for (old_id, slot) in self.files.iter().enumerate() {
+            if let Some(path) = slot {
+                remap[old_id] = files.len() as u32;
+                files.push(path.clone());
+            }
+        }
+        let postings = self
+            .postings
+            .iter()
+            .filter_map(|(word, ids)| {
+                let ids: Vec<u32> = ids.iter().map(|id| remap[*id as usize]).filter(|id| *id != u32::MAX).collect();
+                (!ids.is_empty()).then(|| (word.clone(), ids))
+            })
+            .collect();
+        ContentIndexSnapshot { files, postings }
+    }
+
+    /// Rebuilds an index from a snapshot created by `export`.
+    ///
+    /// # Returns
+    /// * `Ok(ContentIndex)` - The restored index
+    /// * `Err(String)` - A posting refers to a file that is not in the snapshot
+    pub fn from_snapshot(snapshot: ContentIndexSnapshot) -> Result<Self, String> {
+        let file_count = snapshot.files.len() as u32;
+        let mut postings = BTreeMap::new();
+        for (word, mut ids) in snapshot.postings {
+            if ids.iter().any(|id| *id >= file_count) {
+                return Err(format!("Content index entry '{}' refers to a missing file", word));
+            }
+            ids.sort_unstable();
+            ids.dedup();
+            postings.insert(word, ids);
+        }
+        let file_ids = snapshot.files.iter().enumerate().map(|(id, path)| (path.clone(), id as u32)).collect();
+        Ok(Self { files: snapshot.files.into_iter().map(Some).collect(), file_ids, postings, removed: 0 })
+    }
+
+    /// Drops all indexed content.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns the files that contain every word of `query` as a word prefix.
+    ///
+    /// Query words shorter than two characters are ignored. A query without any
+    /// usable word yields no candidates.
+    ///
+    /// # Arguments
+    /// * `query` - The raw content query
+    ///
+    /// # Returns
+    /// Candidate file paths in indexing order
+    pub fn candidates(&self, query: &str) -> Vec<String> {
+        let mut words = tokenize(query);
+        words.sort_unstable();
+        words.dedup();
+        if words.is_empty() || self.is_empty() {
+            return Vec::new();
+        }
+
+        let mut matching: Option<Vec<u32>> = None;
+        for word in &words {
+            let mut ids: Vec<u32> = self
+                .postings
+                .range(word.clone()..)
+                .take_while(|(key, _)| key.starts_with(word.as_str()))
+                .flat_map(|(_, ids)| ids.iter().copied())
+                .collect();
+            ids.sort_unstable();
+            ids.dedup();
+
+            matching = Some(match matching {
+                None => ids,
+                Some(previous) => intersect_sorted(&previous, &ids),
+            });
+
+            if matching.as_ref().is_some_and(|ids| ids.is_empty()) {
+                return Vec::new();
+            }
+        }
+
+        matching
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|id| self.files[id as usize].clone())
+            .collect()
+    }
+
+    /// Renumbers the live files once more than half of the slots are stale,
+    /// so repeated updates do not grow the postings without bound.
+    fn compact_if_needed(&mut self) {
+        if self.removed < 1024 || self.removed * 2 < self.files.len() {
+            return;
+        }
+
+        let mut remap = vec![u32::MAX; self.files.len()];
+        let mut files = Vec::with_capacity(self.file_ids.len());
+        for (old_id, slot) in self.files.drain(..).enumerate() {
+            if let Some(path) = slot {
+                remap[old_id] = files.len() as u32;
+                files.push(Some(path));
+            }
+        }
+
+        self.postings.retain(|_, ids| {
+            ids.retain_mut(|id| {
+                *id = remap[*id as usize];
+                *id != u32::MAX
+            });
+            !ids.is_empty()
+        });
+
+        self.file_ids = files
+            .iter()
+            .enumerate()
+            .filter_map(|(id, path)| path.clone().map(|path| (path, id as u32)))
+            .collect();
+        self.files = files;
+        self.removed = 0;
+    }
+}
+
+/// Finds the lines of `text` containing `query`, ignoring case.
+///
+/// # Arguments
+/// * `text` - The file content to scan
+/// * `query` - The content query, matched as a whole string
+///
+/// # Returns
+/// The total number of matching lines and the first `MAX_MATCHES_PER_FILE` of them
+pub fn find_matching_lines(text: &str, query: &str) -> (usize, Vec<ContentMatch>) {
+    let query_lc = query.trim().to_lowercase();
+    if query_lc.is_empty() {
+        return (0, Vec::new());
+    }
+
+    let mut total = 0;
+    let mut matches = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_lc = line.to_lowercase();
+        if let Some(byte_pos) = line_lc.find(&query_lc) {
+            total += 1;
+            if matches.len() < MAX_MATCHES_PER_FILE {
+                let char_pos = line_lc[..byte_pos].chars().count();
+                matches.push(ContentMatch {
+                    line_number: index + 1,
+                    snippet: make_snippet(line, char_pos, query_lc.chars().count()),
+                });
+            }
+        }
+    }
+
+    (total, matches)
+}
+
+/// Trims a line and cuts it down to `MAX_SNIPPET_CHARS` characters around the match.
+fn make_snippet(line: &str, match_char_pos: usize, match_chars: usize) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.len() <= MAX_SNIPPET_CHARS {
+        return line.trim().to_string();
+    }
+
+    let context = MAX_SNIPPET_CHARS.saturating_sub(match_chars) / 2;
+    let start = match_char_pos.saturating_sub(context);
+    let end = (start + MAX_SNIPPET_CHARS).min(chars.len());
+    let start = end.saturating_sub(MAX_SNIPPET_CHARS);
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    snippet.extend(&chars[start..end]);
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet.trim().to_string()
+}
+
+/// Splits text into lowercase words made of alphanumeric characters and underscores.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| {
+            let len = word.chars().count();
+            (MIN_TOKEN_LEN..=MAX_TOKEN_LEN).contains(&len)
+        })
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+fn intersect_sorted(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().min(b.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                result.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests_content_index {
+    use super::*;
+
+    #[test]
+    fn test_candidates_require_all_words() {
+        let mut index = ContentIndex::new();
+        index.add_file("/docs/a.txt", "The quick brown fox");
+        index.add_file("/docs/b.txt", "A quick test of the index");
+
+        assert_eq!(index.candidates("quick").len(), 2);
+        assert_eq!(index.candidates("quick fox"), vec!["/docs/a.txt".to_string()]);
+        assert_eq!(index.candidates("QUICK te"), vec!["/docs/b.txt".to_string()]);
+        assert!(index.candidates("quick zebra").is_empty());
+        assert!(index.candidates("a").is_empty());
+    }
+
+    #[test]
+    fn test_replace_and_remove_files() {
+        let mut index = ContentIndex::new();
+        index.add_file("/docs/a.txt", "alpha");
+        index.add_file("/docs/a.txt", "beta");
+        index.add_file("/docs/sub/b.txt", "beta");
+        index.add_file("/docs2/c.txt", "beta");
+
+        assert!(index.candidates("alpha").is_empty());
+        assert_eq!(index.candidates("beta").len(), 3);
+
+        assert_eq!(index.remove_files_under("/docs"), 2);
+        assert_eq!(index.candidates("beta"), vec!["/docs2/c.txt".to_string()]);
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut index = ContentIndex::new();
+        index.add_file("/docs/a.txt", "alpha beta");
+        index.add_file("/docs/b.txt", "beta gamma");
+        index.remove_file("/docs/a.txt");
+
+        let snapshot = index.export();
+        assert_eq!(snapshot.files, vec!["/docs/b.txt".to_string()]);
+        let restored = ContentIndex::from_snapshot(snapshot).unwrap();
+        assert_eq!(restored.candidates("beta"), vec!["/docs/b.txt".to_string()]);
+        assert!(restored.candidates("alpha").is_empty());
+        assert_eq!(restored.len(), 1);
+
+        let broken = ContentIndexSnapshot { files: vec![], postings: vec![("word".to_string(), vec![0])] };
+        assert!(ContentIndex::from_snapshot(broken).is_err());
+    }
+
+    #[test]
+    fn test_compaction_keeps_live_files() {
+        let mut index = ContentIndex::new();
+        for i in 0..3000 {
+            index.add_file(&format!("/f/{}.txt", i), "shared word");
+        }
+        for i in 0..2500 {
+            index.remove_file(&format!("/f/{}.txt", i));
+        }
+
+        assert_eq!(index.len(), 500);
+        assert!(index.files.len() < 3000, "stale slots should have been compacted");
+        let candidates = index.candidates("shared");
+        assert_eq!(candidates.len(), 500);
+        assert!(candidates.contains(&"/f/2999.txt".to_string()));
+    }
+
+    #[test]
+    fn test_find_matching_lines() {
+        let text = "first line\nHello World\nnothing\nhello again\n";
+        let (total, matches) = find_matching_lines(text, "hello");
+
+        assert_eq!(total, 2);
+        assert_eq!(matches[0], ContentMatch { line_number: 2, snippet: "Hello World".to_string() });
+        assert_eq!(matches[1].line_number, 4);
+    }
+
+    #[test]
+    fn test_long_lines_are_cut_around_the_match() {
+        let line = format!("{}needle{}", "x".repeat(500), "y".repeat(500));
+        let (_, matches) = find_matching_lines(&line, "needle");
+
+        let snippet = &matches[0].snippet;
+        assert!(snippet.contains("needle"));
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert!(snippet.chars().count() <= MAX_SNIPPET_CHARS + 2);
+    }
+}
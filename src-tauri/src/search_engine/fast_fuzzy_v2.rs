@@ -206,6 +206,137 @@ impl PathMatcher {
         }
     }
 
+    /// Removes a path and every path below it in a single pass.
+    /// Much cheaper than calling `remove_path` for each entry when a whole
+    /// directory disappears, because the trigram index is rewritten only once.
+    ///
+    /// # Arguments
+    /// * `root` - The path whose entry and descendants should be removed.
+    ///
+    /// # Returns
+    /// * The removed paths.
+    ///
+    /// # Example
+    /// ```rust
+    /// let mut matcher = PathMatcher::new();
+    /// matcher.add_path("/home/user/docs");
+    /// matcher.add_path("/home/user/docs/report.pdf");
+    /// matcher.add_path("/home/user/docs2");
+    /// let removed = matcher.remove_paths_under("/home/user/docs");
+    /// assert_eq!(removed.len(), 2);
+    /// ```
+    ///
+    /// # Time Complexity
+    /// * O(N + T) where N is the number of paths and T the number of trigram entries
+    pub fn remove_paths_under(&mut self, root: &str) -> Vec<String> {
+        let root_trimmed = root.trim_end_matches('/');
+        let is_below = |path: &str| {
+            if root_trimmed.is_empty() {
+                // The filesystem root contains everything
+                return true;
+            }
+            path == root_trimmed
+                || (path.starts_with(root_trimmed)
+                    && path.as_bytes().get(root_trimmed.len()) == Some(&b'/'))
+        };
+
+        // Map old path indices to new ones, u32::MAX marks removed paths
+        let mut remap = Vec::with_capacity(self.paths.len());
+        let mut removed = Vec::new();
+        let mut kept = Vec::with_capacity(self.paths.len());
+        for path in self.paths.drain(..) {
+            if is_below(&path) {
+                remap.push(u32::MAX);
+                removed.push(path);
+            } else {
+                remap.push(kept.len() as u32);
+                kept.push(path);
+            }
+        }
+        self.paths = kept;
+
+        if removed.is_empty() {
+            return removed;
+        }
+
+        for values in self.trigram_index.values_mut() {
+            values.retain(|idx| remap[*idx as usize] != u32::MAX);
+            for idx in values.iter_mut() {
+                *idx = remap[*idx as usize];
+            }
+        }
+        self.trigram_index.retain(|_, values| !values.is_empty());
+
+        removed
+    }
+
+    /// Exports the indexed paths and the trigram index in a serializable form.
+    ///
+    /// # Returns
+    /// * A tuple of the paths (in index order) and the trigram index as
+    ///   `(trigram, path indices)` pairs.
+    ///
+    /// # Example
+    /// ```rust
+    /// let mut matcher = PathMatcher::new();
+    /// matcher.add_path("/home/user/file.txt");
+    /// let (paths, index) = matcher.export_index();
+    /// let restored = PathMatcher::from_index(paths, index).unwrap();
+    /// assert_eq!(restored.search("file", 10).len(), 1);
+    /// ```
+    ///
+    /// # Time Complexity
+    /// * O(N + T) where N is the number of paths and T the number of trigram entries
+    pub fn export_index(&self) -> (Vec<String>, Vec<(u32, Vec<u32>)>) {
+        let index = self
+            .trigram_index
+            .iter()
+            .map(|(trigram, indices)| (*trigram, indices.to_vec()))
+            .collect();
+        (self.paths.clone(), index)
+    }
+
+    /// Returns all indexed paths in index order.
+    ///
+    /// # Returns
+    /// * A slice of every path known to the matcher.
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Rebuilds a PathMatcher from data produced by `export_index`.
+    /// The trigram index is validated so a damaged file can never produce
+    /// out of bounds path indices during search.
+    ///
+    /// # Arguments
+    /// * `paths` - The paths in index order.
+    /// * `index` - The trigram index as `(trigram, path indices)` pairs.
+    ///
+    /// # Returns
+    /// * `Ok(PathMatcher)` - The restored matcher.
+    /// * `Err(String)` - The index references a path that does not exist.
+    ///
+    /// # Time Complexity
+    /// * O(N + T) where N is the number of paths and T the number of trigram entries
+    pub fn from_index(paths: Vec<String>, index: Vec<(u32, Vec<u32>)>) -> Result<Self, String> {
+        let mut matcher = Self::new();
+        let path_count = paths.len() as u32;
+
+        matcher.trigram_index.reserve(index.len());
+        for (trigram, indices) in index {
+            if indices.iter().any(|idx| *idx >= path_count) {
+                return Err(format!(
+                    "Trigram index references path beyond {} indexed paths",
+                    path_count
+                ));
+            }
+            matcher.trigram_index.insert(trigram, SmallVec::from_vec(indices));
+        }
+        matcher.paths = paths;
+
+        Ok(matcher)
+    }
+
     /// Extracts trigrams from a text string and indexes them for the given path.
     /// Trigrams are 3-character sequences that serve as the basis for fuzzy matching.
     /// The path is padded with spaces to ensure edge characters are properly indexed.
@@ -873,6 +1004,50 @@ mod tests_fast_fuzzy_v2 {
         assert_eq!(matcher.paths.len(), 2);
     }
 
+    #[test]
+    fn test_remove_paths_under() {
+        let mut matcher = PathMatcher::new();
+        matcher.add_path("/home/user/docs");
+        matcher.add_path("/home/user/docs/report.pdf");
+        matcher.add_path("/home/user/docs2/notes.txt");
+        matcher.add_path("/home/user/music/song.mp3");
+
+        let mut removed = matcher.remove_paths_under("/home/user/docs/");
+        removed.sort();
+        assert_eq!(removed, vec!["/home/user/docs", "/home/user/docs/report.pdf"]);
+        assert_eq!(matcher.paths.len(), 2);
+
+        // Remaining paths are still searchable with correct indices
+        let results = matcher.search("song", 10);
+        assert_eq!(results[0].0, "/home/user/music/song.mp3");
+        let results = matcher.search("notes", 10);
+        assert_eq!(results[0].0, "/home/user/docs2/notes.txt");
+        assert!(matcher.search("report", 10).iter().all(|(p, _)| !p.contains("report")));
+    }
+
+    #[test]
+    fn test_export_and_restore_index() {
+        let mut matcher = PathMatcher::new();
+        matcher.add_path("/home/user/file.txt");
+        matcher.add_path("/var/log/system.log");
+
+        let (paths, index) = matcher.export_index();
+        let restored = PathMatcher::from_index(paths, index).unwrap();
+
+        assert_eq!(restored.paths, matcher.paths);
+        for query in ["file", "systm"] {
+            let mut expected = matcher.search(query, 10);
+            let mut actual = restored.search(query, 10);
+            expected.sort_by(|a, b| a.0.cmp(&b.0));
+            actual.sort_by(|a, b| a.0.cmp(&b.0));
+            assert_eq!(actual, expected, "Results differ for query '{}'", query);
+        }
+
+        // Indices pointing past the path list must be rejected
+        let bad = PathMatcher::from_index(vec!["/a".to_string()], vec![(1, vec![5])]);
+        assert!(bad.is_err());
+    }
+
     #[test]
     fn test_basic_search() {
         let mut matcher = PathMatcher::new();
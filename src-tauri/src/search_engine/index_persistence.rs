@@ -0,0 +1,247 @@
+use crate::search_engine::content_index::ContentIndexSnapshot;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Magic bytes at the start of every persisted index file.
+const INDEX_FILE_MAGIC: &[u8; 6] = b"FEIDX\0";
+
+/// Version of the on-disk index layout.
+///
+/// Bump this whenever the serialized structures change shape. Files written
+/// with a different version are rejected and the index is rebuilt from scratch.
+pub const INDEX_FORMAT_VERSION: u32 = 3;
+
+/// Size of the fixed header: magic + version (u32 LE) + crc32 of payload (u32 LE).
+const HEADER_LEN: usize = INDEX_FILE_MAGIC.len() + 4 + 4;
+
+/// Serializable snapshot of everything the search core needs to answer queries
+/// without walking the filesystem again.
+///
+/// Recency information is stored as unix timestamps in milliseconds because
+/// `Instant` has no stable representation across process restarts.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IndexSnapshot {
+    /// The root folder that produced this index, if known
+    pub index_folder: Option<String>,
+    /// All (path, score) pairs stored in the ART
+    pub trie_entries: Vec<(String, f32)>,
+    /// Paths known to the fuzzy matcher, in index order
+    pub fuzzy_paths: Vec<String>,
+    /// Trigram index of the fuzzy matcher (trigram -> path indices)
+    pub trigram_index: Vec<(u32, Vec<u32>)>,
+    /// Usage counts per path
+    pub frequency_map: HashMap<String, u32>,
+    /// Last usage per path as unix timestamp in milliseconds
+    pub recency_map: HashMap<String, u64>,
+    /// Words of the file contents, empty unless `index_file_contents` is enabled
+    pub content_index: ContentIndexSnapshot,
+}
+
+/// Writes an index snapshot to disk.
+///
+/// The file is written to a temporary sibling first and then renamed into place,
+/// so a crash during the write never leaves a half written index behind.
+///
+/// # Arguments
+/// * `path` - Destination file path
+/// * `snapshot` - The snapshot to persist
+///
+/// # Returns
+/// * `Ok(())` - The snapshot was written successfully
+/// * `Err(String)` - Serialization or IO failed
+///
+/// # Example
+/// ```rust
+/// let snapshot = engine.export_snapshot();
+/// save_index_snapshot(&SEARCH_INDEX_ABS_PATH, &snapshot)?;
+/// ```
+pub fn save_index_snapshot(path: &Path, snapshot: &IndexSnapshot) -> Result<(), String> {
+    let payload = serde_json::to_vec(snapshot)
+        .map_err(|e| format!("Failed to serialize search index: {}", e))?;
+    let checksum = crc32fast::hash(&payload);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create search index directory: {}", e))?;
+    }
+
+    let tmp_path = path.with_extension("tmp");
+    {
+        let mut file = fs::File::create(&tmp_path)
+            .map_err(|e| format!("Failed to create search index file: {}", e))?;
+        file.write_all(INDEX_FILE_MAGIC)
+            .and_then(|_| file.write_all(&INDEX_FORMAT_VERSION.to_le_bytes()))
+            .and_then(|_| file.write_all(&checksum.to_le_bytes()))
+            .and_then(|_| file.write_all(&payload))
+            .and_then(|_| file.sync_all())
+            .map_err(|e| format!("Failed to write search index file: {}", e))?;
+    }
+
+    fs::rename(&tmp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        format!("Failed to move search index into place: {}", e)
+    })
+}
+
+/// Reads an index snapshot from disk.
+///
+/// # Arguments
+/// * `path` - The index file to read
+///
+/// # Returns
+/// * `Ok(Some(IndexSnapshot))` - A valid snapshot was loaded
+/// * `Ok(None)` - No index file exists yet
+/// * `Err(String)` - The file exists but is corrupt or was written by another format version
+///
+/// # Example
+/// ```rust
+/// match load_index_snapshot(&SEARCH_INDEX_ABS_PATH) {
+///     Ok(Some(snapshot)) => engine.restore_snapshot(snapshot)?,
+///     Ok(None) => {}, // first start
+///     Err(_) => remove_index_snapshot(&SEARCH_INDEX_ABS_PATH),
+/// }
+/// ```
+pub fn load_index_snapshot(path: &Path) -> Result<Option<IndexSnapshot>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read search index file: {}", e))?;
+
+    if bytes.len() < HEADER_LEN || &bytes[..INDEX_FILE_MAGIC.len()] != INDEX_FILE_MAGIC {
+        return Err("Search index file has an invalid header".to_string());
+    }
+
+    let mut offset = INDEX_FILE_MAGIC.len();
+    let version = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap_or_default());
+    offset += 4;
+    if version != INDEX_FORMAT_VERSION {
+        return Err(format!(
+            "Search index file version {} does not match expected version {}",
+            version, INDEX_FORMAT_VERSION
+        ));
+    }
+
+    let checksum = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap_or_default());
+    offset += 4;
+    let payload = &bytes[offset..];
+    if crc32fast::hash(payload) != checksum {
+        return Err("Search index file checksum mismatch".to_string());
+    }
+
+    serde_json::from_slice(payload)
+        .map(Some)
+        .map_err(|e| format!("Failed to deserialize search index: {}", e))
+}
+
+/// Reads the indexed folder from an index file that `load_index_snapshot` rejected,
+/// so the index can be rebuilt.
+///
+/// # Returns
+/// The folder, if the file has a valid header and its payload still parses, even when
+/// it was written by another format version or fails the checksum
+pub fn read_index_folder(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    if bytes.len() < HEADER_LEN || &bytes[..INDEX_FILE_MAGIC.len()] != INDEX_FILE_MAGIC {
+        return None;
+    }
+    let payload: serde_json::Value = serde_json::from_slice(&bytes[HEADER_LEN..]).ok()?;
+    payload.get("index_folder")?.as_str().map(|folder| folder.to_string())
+}
+
+/// Removes a persisted index file if it exists.
+///
+/// Errors are ignored on purpose: a missing file is the desired end state and
+/// any other failure will surface again on the next save.
+pub fn remove_index_snapshot(path: &Path) {
+    let _ = fs::remove_file(path);
+}
+
+#[cfg(test)]
+mod tests_index_persistence {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn sample_snapshot() -> IndexSnapshot {
+        let mut snapshot = IndexSnapshot::default();
+        snapshot.index_folder = Some("/home/user".to_string());
+        snapshot.trie_entries = vec![
+            ("/home/user/a.txt".to_string(), 1.0),
+            ("/home/user/ünïcode.md".to_string(), 1.5),
+        ];
+        snapshot.fuzzy_paths = vec!["/home/user/a.txt".to_string()];
+        snapshot.trigram_index = vec![(42, vec![0])];
+        snapshot.frequency_map.insert("/home/user/a.txt".to_string(), 3);
+        snapshot.recency_map.insert("/home/user/a.txt".to_string(), 1_700_000_000_000);
+        snapshot.content_index = ContentIndexSnapshot {
+            files: vec!["/home/user/a.txt".to_string()],
+            postings: vec![("hello".to_string(), vec![0])],
+        };
+        snapshot
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("search_index.bin");
+
+        let snapshot = sample_snapshot();
+        save_index_snapshot(&path, &snapshot).unwrap();
+
+        let loaded = load_index_snapshot(&path).unwrap();
+        assert_eq!(loaded, Some(snapshot));
+    }
+
+    #[test]
+    fn test_load_missing_file_returns_none() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("does_not_exist.bin");
+
+        assert_eq!(load_index_snapshot(&path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_load_rejects_corrupt_payload() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("search_index.bin");
+        save_index_snapshot(&path, &sample_snapshot()).unwrap();
+
+        // Flip a byte in the payload so the checksum no longer matches
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 2;
+        bytes[last] ^= 0xFF;
+        fs::write(&path, bytes).unwrap();
+
+        assert!(load_index_snapshot(&path).is_err());
+    }
+
+    #[test]
+    fn test_load_rejects_version_mismatch() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("search_index.bin");
+        save_index_snapshot(&path, &sample_snapshot()).unwrap();
+
+        let mut bytes = fs::read(&path).unwrap();
+        let version_offset = INDEX_FILE_MAGIC.len();
+        bytes[version_offset..version_offset + 4]
+            .copy_from_slice(&(INDEX_FORMAT_VERSION + 1).to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+
+        let err = load_index_snapshot(&path).unwrap_err();
+        assert!(err.contains("version"));
+        assert_eq!(read_index_folder(&path).as_deref(), Some("/home/user"));
+    }
+
+    #[test]
+    fn test_load_rejects_garbage() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("search_index.bin");
+        fs::write(&path, b"definitely not an index").unwrap();
+
+        assert!(load_index_snapshot(&path).is_err());
+        assert_eq!(read_index_folder(&path), None);
+    }
+}
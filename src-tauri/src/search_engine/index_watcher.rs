@@ -0,0 +1,706 @@
+use crate::commands::preview_commands::read_text_content;
+use crate::search_engine::content_index::MAX_CONTENT_INDEX_BYTES;
+use crate::search_engine::search_core::SearchCore;
+#[cfg(feature = "index-progress-logging")]
+use crate::log_info;
+use crate::{log_error, log_warn};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Quiet period after the last event before a burst is applied to the index
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Upper bound on how long a continuous burst is held back before it is applied
+const MAX_BATCH_WINDOW: Duration = Duration::from_secs(2);
+
+/// How long applied changes wait before the save callback runs, so a busy folder
+/// does not write the whole index after every burst
+const SAVE_DELAY: Duration = Duration::from_secs(10);
+
+/// Called on the watcher thread to save the index after changes were applied.
+pub type SaveCallback = Arc<dyn Fn() + Send + Sync>;
+
+/// What should happen to a path once the current burst of events settles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PendingChange {
+    /// The path (and its contents, if it is a directory) should be indexed
+    Upsert,
+    /// The path and everything below it should leave the index
+    Remove,
+    /// The content or metadata of an indexed path changed
+    Refresh,
+}
+
+/// Coalesced set of changes collected from a burst of filesystem events.
+///
+/// Only the last change per path is kept, so a file that is created, modified
+/// and deleted within one burst never touches the index at all.
+#[derive(Debug, Default)]
+struct PendingChanges {
+    changes: HashMap<PathBuf, PendingChange>,
+    needs_rescan: bool,
+}
+
+impl PendingChanges {
+    /// Folds a single notify event into the pending set.
+    fn record(&mut self, event: Event) {
+        if event.need_rescan() {
+            self.needs_rescan = true;
+            return;
+        }
+
+        match event.kind {
+            EventKind::Create(_) => self.mark_all(event.paths, PendingChange::Upsert),
+            EventKind::Remove(_) => self.mark_all(event.paths, PendingChange::Remove),
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                self.mark_all(event.paths, PendingChange::Remove)
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                self.mark_all(event.paths, PendingChange::Upsert)
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                // notify reports [from, to] for a completed rename
+                let mut paths = event.paths.into_iter();
+                if let Some(from) = paths.next() {
+                    self.mark(from, PendingChange::Remove);
+                }
+                if let Some(to) = paths.next() {
+                    self.mark(to, PendingChange::Upsert);
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(_)) => {
+                // Backends that cannot tell the direction of a rename; the filesystem is
+                // checked again when the change is applied, so Upsert covers both cases
+                self.mark_all(event.paths, PendingChange::Upsert)
+            }
+            EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Metadata(_) | ModifyKind::Any) => {
+                // Size and modification time feed the structured query filters
+                for path in event.paths {
+                    self.changes.entry(path).or_insert(PendingChange::Refresh);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn mark(&mut self, path: PathBuf, change: PendingChange) {
+        self.changes.insert(path, change);
+    }
+
+    fn mark_all(&mut self, paths: Vec<PathBuf>, change: PendingChange) {
+        for path in paths {
+            self.mark(path, change);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.changes.is_empty() && !self.needs_rescan
+    }
+}
+
+/// Keeps the search index in sync with the filesystem for every indexed folder.
+///
+/// The watcher uses the platform's native notification API through the `notify`
+/// crate (inotify on Linux). Events are collected on a background thread, coalesced
+/// into bursts and then applied to the shared `SearchCore`, which updates both the
+/// ART and the fuzzy matcher. Paths matching the excluded patterns are never added.
+/// When file contents are indexed, new and changed files are read again so content
+/// search keeps matching what is on disk.
+///
+/// The OS watcher is only created when the first folder is watched, so idle
+/// instances cost nothing. Dropping the `IndexWatcher` stops the background thread.
+///
+/// # Example
+/// ```rust
+/// let mut watcher = IndexWatcher::new(engine.clone());
+/// watcher.watch(Path::new("/home/user/projects"), vec!["node_modules".to_string()], false)?;
+/// // files created under /home/user/projects now show up in search results
+/// ```
+pub struct IndexWatcher {
+    engine: Arc<RwLock<SearchCore>>,
+    watcher: Option<RecommendedWatcher>,
+    roots: Arc<Mutex<HashSet<PathBuf>>>,
+    excluded_patterns: Arc<Mutex<Vec<String>>>,
+    index_contents: Arc<AtomicBool>,
+    on_save: Arc<Mutex<Option<SaveCallback>>>,
+}
+
+impl IndexWatcher {
+    /// Creates a watcher for the given search engine without watching anything yet.
+    ///
+    /// # Arguments
+    /// * `engine` - The search core that should receive filesystem changes
+    ///
+    /// # Returns
+    /// A new idle IndexWatcher
+    pub fn new(engine: Arc<RwLock<SearchCore>>) -> Self {
+        Self {
+            engine,
+            watcher: None,
+            roots: Arc::new(Mutex::new(HashSet::new())),
+            excluded_patterns: Arc::new(Mutex::new(Vec::new())),
+            index_contents: Arc::new(AtomicBool::new(false)),
+            on_save: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Sets what is called to save the index once applied changes have settled.
+    ///
+    /// The callback runs at most once per `SAVE_DELAY` while changes keep coming in,
+    /// and once more when the watcher stops with unsaved changes.
+    pub fn set_save_callback(&self, callback: SaveCallback) {
+        if let Ok(mut on_save) = self.on_save.lock() {
+            *on_save = Some(callback);
+        }
+    }
+
+    /// Starts watching a folder recursively.
+    ///
+    /// # Arguments
+    /// * `root` - The indexed folder to watch
+    /// * `excluded_patterns` - Patterns of paths that must not be added to the index
+    /// * `index_contents` - Whether the contents of new and changed files are indexed
+    ///
+    /// # Returns
+    /// * `Ok(())` - The folder is being watched
+    /// * `Err(String)` - The OS watcher could not be created or the folder could not be watched
+    pub fn watch(&mut self, root: &Path, excluded_patterns: Vec<String>, index_contents: bool) -> Result<(), String> {
+        if let Ok(mut patterns) = self.excluded_patterns.lock() {
+            *patterns = excluded_patterns;
+        }
+        self.index_contents.store(index_contents, Ordering::Relaxed);
+
+        if self.watcher.is_none() {
+            self.watcher = Some(self.spawn_watcher()?);
+        }
+
+        let watcher = self.watcher.as_mut().ok_or("Filesystem watcher is not available")?;
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch '{}': {}", root.display(), e))?;
+
+        self.roots
+            .lock()
+            .map_err(|_| "Failed to lock watched roots")?
+            .insert(root.to_path_buf());
+
+        #[cfg(feature = "index-progress-logging")]
+        log_info!("Watching '{}' for index updates", root.display());
+
+        Ok(())
+    }
+
+    /// Stops watching a previously watched folder.
+    ///
+    /// # Arguments
+    /// * `root` - The folder to stop watching
+    ///
+    /// # Returns
+    /// * `Ok(())` - The folder is no longer watched
+    /// * `Err(String)` - The folder was not watched or could not be unwatched
+    pub fn unwatch(&mut self, root: &Path) -> Result<(), String> {
+        let removed = self
+            .roots
+            .lock()
+            .map_err(|_| "Failed to lock watched roots")?
+            .remove(root);
+        if !removed {
+            return Err(format!("'{}' is not being watched", root.display()));
+        }
+
+        if let Some(watcher) = self.watcher.as_mut() {
+            watcher
+                .unwatch(root)
+                .map_err(|e| format!("Failed to unwatch '{}': {}", root.display(), e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Stops watching all folders. The background thread stays idle until the next `watch`.
+    pub fn unwatch_all(&mut self) {
+        for root in self.watched_roots() {
+            if let Err(err) = self.unwatch(&root) {
+                log_warn!("{}", err);
+            }
+        }
+    }
+
+    /// Returns the folders currently being watched.
+    pub fn watched_roots(&self) -> Vec<PathBuf> {
+        self.roots
+            .lock()
+            .map(|roots| roots.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Creates the OS watcher and the thread that applies its events to the index.
+    fn spawn_watcher(&self) -> Result<RecommendedWatcher, String> {
+        let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+        let watcher = notify::recommended_watcher(tx)
+            .map_err(|e| format!("Failed to create filesystem watcher: {}", e))?;
+
+        let engine = Arc::clone(&self.engine);
+        let roots = Arc::clone(&self.roots);
+        let excluded_patterns = Arc::clone(&self.excluded_patterns);
+        let index_contents = Arc::clone(&self.index_contents);
+        let on_save = Arc::clone(&self.on_save);
+
+        thread::Builder::new()
+            .name("index-watcher".to_string())
+            .spawn(move || Self::run_event_loop(rx, engine, roots, excluded_patterns, index_contents, on_save))
+            .map_err(|e| format!("Failed to spawn filesystem watcher thread: {}", e))?;
+
+        Ok(watcher)
+    }
+
+    /// Receives events until the watcher is dropped, applying them in coalesced bursts.
+    fn run_event_loop(
+        rx: Receiver<notify::Result<Event>>,
+        engine: Arc<RwLock<SearchCore>>,
+        roots: Arc<Mutex<HashSet<PathBuf>>>,
+        excluded_patterns: Arc<Mutex<Vec<String>>>,
+        index_contents: Arc<AtomicBool>,
+        on_save: Arc<Mutex<Option<SaveCallback>>>,
+    ) {
+        let save = || {
+            let callback = on_save.lock().ok().and_then(|on_save| on_save.clone());
+            if let Some(callback) = callback {
+                callback();
+            }
+        };
+        // When the first change that is not saved yet was applied
+        let mut unsaved_since: Option<Instant> = None;
+
+        loop {
+            let first = match unsaved_since {
+                Some(since) => match rx.recv_timeout(SAVE_DELAY.saturating_sub(since.elapsed())) {
+                    Ok(result) => result,
+                    Err(RecvTimeoutError::Timeout) => {
+                        save();
+                        unsaved_since = None;
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        save();
+                        break;
+                    }
+                },
+                None => match rx.recv() {
+                    Ok(result) => result,
+                    Err(_) => break,
+                },
+            };
+            let mut pending = PendingChanges::default();
+            Self::record_result(&mut pending, first);
+
+            // Keep collecting until the burst goes quiet or the batch window is exhausted
+            let window_end = Instant::now() + MAX_BATCH_WINDOW;
+            let mut disconnected = false;
+            loop {
+                let remaining = window_end.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+                match rx.recv_timeout(DEBOUNCE_INTERVAL.min(remaining)) {
+                    Ok(result) => Self::record_result(&mut pending, result),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        disconnected = true;
+                        break;
+                    }
+                }
+            }
+
+            if !pending.is_empty() {
+                let patterns = excluded_patterns.lock().map(|p| p.clone()).unwrap_or_default();
+                let roots: Vec<PathBuf> = roots
+                    .lock()
+                    .map(|r| r.iter().cloned().collect())
+                    .unwrap_or_default();
+                let index_contents = index_contents.load(Ordering::Relaxed);
+                apply_changes(&engine, pending, &roots, &patterns, index_contents);
+                unsaved_since.get_or_insert_with(Instant::now);
+            }
+
+            if unsaved_since.is_some_and(|since| since.elapsed() >= SAVE_DELAY) {
+                save();
+                unsaved_since = None;
+            }
+            if disconnected {
+                if unsaved_since.is_some() {
+                    save();
+                }
+                break;
+            }
+        }
+    }
+
+    fn record_result(pending: &mut PendingChanges, result: notify::Result<Event>) {
+        match result {
+            Ok(event) => pending.record(event),
+            Err(err) => log_error!("Filesystem watcher error: {}", err),
+        }
+    }
+}
+
+/// Checks a path against the excluded patterns using the same rules as the indexer.
+fn is_excluded(path: &Path, excluded_patterns: &[String]) -> bool {
+    let path_str = path.to_string_lossy();
+    let file_name = path.file_name().map(|name| name.to_string_lossy());
+    excluded_patterns.iter().any(|pattern| {
+        path_str.contains(pattern.as_str())
+            || file_name
+                .as_ref()
+                .map(|name| name.contains(pattern.as_str()))
+                .unwrap_or(false)
+    })
+}
+
+/// Applies a coalesced burst of changes to the search core.
+///
+/// Removals are applied first so that a directory replaced within one burst ends up
+/// with its new contents. Every upsert re-checks the filesystem, so paths that vanished
+/// again before the burst settled are treated as removals.
+///
+/// With `index_contents`, added and changed files are read after the write lock is
+/// released and their content is indexed again. Files that are no longer text lose
+/// their content entry; removed paths lose it together with the path.
+fn apply_changes(
+    engine: &Arc<RwLock<SearchCore>>,
+    pending: PendingChanges,
+    roots: &[PathBuf],
+    excluded_patterns: &[String],
+    index_contents: bool,
+) {
+    let mut removals = Vec::new();
+    let mut upserts = Vec::new();
+    let mut refreshes = Vec::new();
+    for (path, change) in pending.changes {
+        match change {
+            PendingChange::Upsert if path.exists() => upserts.push(path),
+            PendingChange::Refresh => refreshes.push(path),
+            _ => removals.push(path),
+        }
+    }
+
+    if pending.needs_rescan {
+        // The OS dropped events, so rebuild the watched folders from disk
+        log_warn!("Filesystem watcher lost events, rescanning {} watched folders", roots.len());
+        for root in roots {
+            removals.push(root.clone());
+            upserts.push(root.clone());
+        }
+    }
+
+    let mut changed_files = Vec::new();
+    {
+        let mut engine = match engine.write() {
+            Ok(engine) => engine,
+            Err(_) => {
+                log_error!("Failed to acquire write lock on search engine for watcher update");
+                return;
+            }
+        };
+
+        for path in &removals {
+            engine.remove_path_tree(&path.to_string_lossy());
+        }
+
+        let _added: usize = upserts
+            .iter()
+            .map(|path| index_path_tree(&mut engine, path, excluded_patterns, &mut changed_files))
+            .sum();
+
+        for path in &refreshes {
+            if let Some(path_str) = path.to_str() {
+                if engine.contains_path(path_str) && path.is_file() {
+                    changed_files.push(path_str.to_string());
+                }
+            }
+        }
+
+        #[cfg(feature = "index-progress-logging")]
+        log_info!(
+            "Watcher applied {} removals and {} new index entries",
+            removals.len(),
+            _added
+        );
+    } // Release write lock before reading file contents
+
+    if index_contents && !changed_files.is_empty() {
+        update_file_contents(engine, changed_files);
+    }
+}
+
+/// Reads the given files and replaces their entries in the content index.
+fn update_file_contents(engine: &Arc<RwLock<SearchCore>>, files: Vec<String>) {
+    let contents: Vec<(String, Option<String>)> = files
+        .into_iter()
+        .map(|path| {
+            let text = read_text_content(Path::new(&path), MAX_CONTENT_INDEX_BYTES);
+            (path, text)
+        })
+        .collect();
+
+    let mut engine = match engine.write() {
+        Ok(engine) => engine,
+        Err(_) => {
+            log_error!("Failed to acquire write lock on search engine for content update");
+            return;
+        }
+    };
+
+    for (path, text) in contents {
+        // The path may have been removed by a later burst while the file was read
+        if !engine.contains_path(&path) {
+            continue;
+        }
+        match text {
+            Some(text) => engine.add_file_content(&path, &text),
+            None => engine.remove_file_content(&path),
+        }
+    }
+}
+
+/// Adds a path and, for directories, everything below it that is not excluded.
+/// The files that were walked are appended to `files` so their content can be read.
+///
+/// # Returns
+/// The number of paths that were newly added to the index
+fn index_path_tree(
+    engine: &mut SearchCore,
+    root: &Path,
+    excluded_patterns: &[String],
+    files: &mut Vec<String>,
+) -> usize {
+    let mut added = 0;
+    let walker = walkdir::WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|entry| !is_excluded(entry.path(), excluded_patterns));
+
+    for entry in walker.filter_map(Result::ok) {
+        if let Some(path_str) = entry.path().to_str() {
+            if !engine.contains_path(path_str) {
+                engine.add_path(path_str);
+                added += 1;
+            }
+            if entry.file_type().is_file() {
+                files.push(path_str.to_string());
+            }
+        }
+    }
+
+    added
+}
+
+#[cfg(test)]
+mod tests_index_watcher {
+    use super::*;
+    use crate::models::ranking_config::RankingConfig;
+    use crate::search_engine::query_parser::parse_query;
+    use notify::event::{CreateKind, DataChange, RemoveKind};
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn create_engine() -> Arc<RwLock<SearchCore>> {
+        Arc::new(RwLock::new(SearchCore::new(
+            100,
+            20,
+            Duration::from_secs(300),
+            RankingConfig::default(),
+        )))
+    }
+
+    fn contains(engine: &Arc<RwLock<SearchCore>>, path: &Path) -> bool {
+        engine.write().unwrap().contains_path(&path.to_string_lossy())
+    }
+
+    /// Polls until the condition holds or a generous timeout passes
+    fn wait_for(condition: impl Fn() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            if condition() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        condition()
+    }
+
+    #[test]
+    fn test_pending_changes_keep_last_change_per_path() {
+        let mut pending = PendingChanges::default();
+        let path = PathBuf::from("/tmp/file.txt");
+
+        pending.record(Event::new(EventKind::Create(CreateKind::File)).add_path(path.clone()));
+        pending.record(Event::new(EventKind::Remove(RemoveKind::File)).add_path(path.clone()));
+
+        assert_eq!(pending.changes.len(), 1);
+        assert_eq!(pending.changes.get(&path), Some(&PendingChange::Remove));
+    }
+
+    #[test]
+    fn test_pending_changes_rename_both() {
+        let mut pending = PendingChanges::default();
+        let from = PathBuf::from("/tmp/old.txt");
+        let to = PathBuf::from("/tmp/new.txt");
+
+        pending.record(
+            Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+                .add_path(from.clone())
+                .add_path(to.clone()),
+        );
+
+        assert_eq!(pending.changes.get(&from), Some(&PendingChange::Remove));
+        assert_eq!(pending.changes.get(&to), Some(&PendingChange::Upsert));
+    }
+
+    #[test]
+    fn test_apply_changes_honours_excluded_patterns() {
+        let dir = tempdir().unwrap();
+        let kept = dir.path().join("src");
+        let excluded = dir.path().join("node_modules");
+        fs::create_dir_all(&kept).unwrap();
+        fs::create_dir_all(&excluded).unwrap();
+        fs::write(kept.join("main.rs"), "fn main() {}").unwrap();
+        fs::write(excluded.join("lib.js"), "").unwrap();
+
+        let engine = create_engine();
+        let mut pending = PendingChanges::default();
+        pending.mark(dir.path().to_path_buf(), PendingChange::Upsert);
+        apply_changes(&engine, pending, &[], &["node_modules".to_string()], false);
+
+        assert!(contains(&engine, &kept.join("main.rs")));
+        assert!(!contains(&engine, &excluded));
+        assert!(!contains(&engine, &excluded.join("lib.js")));
+    }
+
+    #[test]
+    fn test_modified_files_match_new_metadata() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("log.txt");
+        fs::write(&file, "short").unwrap();
+
+        let engine = create_engine();
+        engine.write().unwrap().add_path(&file.to_string_lossy());
+        let big_files = parse_query("size:>1k").unwrap();
+        assert!(engine.write().unwrap().search_with_query(&big_files, Duration::from_secs(5)).is_empty());
+
+        fs::write(&file, "x".repeat(4096)).unwrap();
+        let mut pending = PendingChanges::default();
+        pending.record(
+            Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content))).add_path(file.clone()),
+        );
+        assert_eq!(pending.changes.get(&file), Some(&PendingChange::Refresh));
+        apply_changes(&engine, pending, &[], &[], false);
+
+        let results = engine.write().unwrap().search_with_query(&big_files, Duration::from_secs(5));
+        assert_eq!(results.len(), 1);
+
+        // A modification never downgrades a pending create
+        let mut pending = PendingChanges::default();
+        pending.record(Event::new(EventKind::Create(CreateKind::File)).add_path(file.clone()));
+        pending.record(
+            Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content))).add_path(file.clone()),
+        );
+        assert_eq!(pending.changes.get(&file), Some(&PendingChange::Upsert));
+    }
+
+    #[test]
+    fn test_apply_changes_updates_file_contents() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("notes.txt");
+        fs::write(&file, "first draft").unwrap();
+        let file_str = file.to_string_lossy().to_string();
+
+        let engine = create_engine();
+        let mut pending = PendingChanges::default();
+        pending.mark(dir.path().to_path_buf(), PendingChange::Upsert);
+        apply_changes(&engine, pending, &[], &[], true);
+        assert_eq!(engine.read().unwrap().content_candidates("draft"), vec![file_str.clone()]);
+
+        fs::write(&file, "final version").unwrap();
+        let mut pending = PendingChanges::default();
+        pending.mark(file.clone(), PendingChange::Refresh);
+        apply_changes(&engine, pending, &[], &[], true);
+        assert!(engine.read().unwrap().content_candidates("draft").is_empty());
+        assert_eq!(engine.read().unwrap().content_candidates("final"), vec![file_str.clone()]);
+
+        // A file that is no longer text loses its content entry
+        fs::write(&file, [0u8, 159, 146, 150]).unwrap();
+        let mut pending = PendingChanges::default();
+        pending.mark(file.clone(), PendingChange::Refresh);
+        apply_changes(&engine, pending, &[], &[], true);
+        assert!(engine.read().unwrap().content_candidates("final").is_empty());
+        assert!(contains(&engine, &file));
+
+        fs::remove_file(&file).unwrap();
+        let mut pending = PendingChanges::default();
+        pending.mark(file.clone(), PendingChange::Remove);
+        apply_changes(&engine, pending, &[], &[], true);
+        assert_eq!(engine.read().unwrap().get_stats().content_index_size, 0);
+    }
+
+    #[test]
+    fn test_watcher_tracks_create_rename_and_delete() {
+        let dir = tempdir().unwrap();
+        let engine = create_engine();
+        let mut watcher = IndexWatcher::new(Arc::clone(&engine));
+        watcher.watch(dir.path(), vec![".git".to_string()], false).unwrap();
+        assert_eq!(watcher.watched_roots(), vec![dir.path().to_path_buf()]);
+
+        let created = dir.path().join("created.txt");
+        fs::write(&created, "hello").unwrap();
+        assert!(wait_for(|| contains(&engine, &created)), "Created file should be indexed");
+
+        let renamed = dir.path().join("renamed.txt");
+        fs::rename(&created, &renamed).unwrap();
+        assert!(
+            wait_for(|| contains(&engine, &renamed) && !contains(&engine, &created)),
+            "Rename should move the index entry"
+        );
+
+        fs::remove_file(&renamed).unwrap();
+        assert!(wait_for(|| !contains(&engine, &renamed)), "Deleted file should leave the index");
+
+        // Excluded paths are ignored
+        let git_dir = dir.path().join(".git");
+        fs::create_dir_all(&git_dir).unwrap();
+        let marker = dir.path().join("marker.txt");
+        fs::write(&marker, "").unwrap();
+        assert!(wait_for(|| contains(&engine, &marker)));
+        assert!(!contains(&engine, &git_dir));
+
+        watcher.unwatch_all();
+        assert!(watcher.watched_roots().is_empty());
+    }
+
+    #[test]
+    fn test_applied_changes_are_saved_when_the_watcher_stops() {
+        let dir = tempdir().unwrap();
+        let engine = create_engine();
+        let saves = Arc::new(Mutex::new(0));
+        let mut watcher = IndexWatcher::new(Arc::clone(&engine));
+        let counter = Arc::clone(&saves);
+        watcher.set_save_callback(Arc::new(move || *counter.lock().unwrap() += 1));
+        watcher.watch(dir.path(), Vec::new(), false).unwrap();
+
+        let created = dir.path().join("created.txt");
+        fs::write(&created, "hello").unwrap();
+        assert!(wait_for(|| contains(&engine, &created)));
+        // Saving waits for the save delay, but unsaved changes are not lost on shutdown
+        assert_eq!(*saves.lock().unwrap(), 0);
+
+        drop(watcher);
+        assert!(wait_for(|| *saves.lock().unwrap() == 1), "Changes should be saved when the watcher stops");
+    }
+}
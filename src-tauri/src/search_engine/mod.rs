@@ -1,7 +1,12 @@
 mod art_v5;
+pub mod content_index;
 mod fast_fuzzy_v2;
+pub mod index_persistence;
+pub mod index_watcher;
 mod lru_cache_v2;
 mod path_cache_wrapper;
+pub mod pattern_search;
+pub mod query_parser;
 pub mod search_core;
 
 #[cfg(test)]
@@ -0,0 +1,218 @@
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Upper bound on the compiled size of user supplied regexes and globs.
+const MAX_PATTERN_SIZE: usize = 1 << 20;
+
+/// How many paths are checked between two timeout checks.
+const TIMEOUT_CHECK_INTERVAL: usize = 256;
+
+/// How the `search` query is interpreted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Prefix and fuzzy matching with structured filters (the default)
+    #[default]
+    Fuzzy,
+    /// Shell style glob over the full path, e.g. `**/src/**/*_test.rs`
+    Glob,
+    /// Regular expression over the full path
+    Regex,
+    /// The query appears literally in the path, ignoring case
+    Exact,
+}
+
+/// A compiled glob, regex or literal matched against whole indexed paths.
+#[derive(Debug, Clone)]
+pub enum PathPattern {
+    Glob(GlobMatcher),
+    Regex(Regex),
+    /// Lowercase literal
+    Exact(String),
+}
+
+impl PathPattern {
+    /// Compiles a query for the given mode.
+    ///
+    /// Globs use `/` as separator: `*` and `?` stay within one path component,
+    /// `**` spans any number of them and `{a,b}` lists alternatives. Globs that do
+    /// not start at the root (`/`, `**` or a drive letter) may match at any depth,
+    /// so `*.rs` finds every Rust file. Globs and regexes are case-sensitive;
+    /// use `(?i)` in a regex to ignore case.
+    ///
+    /// # Arguments
+    /// * `query` - The glob, regex or literal text
+    /// * `mode` - How to interpret the query
+    ///
+    /// # Returns
+    /// * `Ok(Some(PathPattern))` - The compiled pattern
+    /// * `Ok(None)` - For `SearchMode::Fuzzy`, which does not scan paths
+    /// * `Err(String)` - The query is empty or not a valid glob/regex
+    pub fn compile(query: &str, mode: SearchMode) -> Result<Option<Self>, String> {
+        if mode == SearchMode::Fuzzy {
+            return Ok(None);
+        }
+
+        let query = query.trim();
+        if query.is_empty() {
+            return Err("Search pattern must not be empty".to_string());
+        }
+
+        let pattern = match mode {
+            SearchMode::Glob => {
+                let anchored = query.starts_with('/')
+                    || query.starts_with("**")
+                    || query.as_bytes().get(1) == Some(&b':');
+                let glob = if anchored {
+                    query.replace('\\', "/")
+                } else {
+                    format!("**/{}", query.replace('\\', "/"))
+                };
+                let matcher = GlobBuilder::new(&glob)
+                    .literal_separator(true)
+                    .backslash_escape(false)
+                    .build()
+                    .map_err(|e| format!("Invalid glob '{}': {}", query, e))?
+                    .compile_matcher();
+                PathPattern::Glob(matcher)
+            }
+            SearchMode::Regex => PathPattern::Regex(
+                RegexBuilder::new(query)
+                    .size_limit(MAX_PATTERN_SIZE)
+                    .build()
+                    .map_err(|e| format!("Invalid regex '{}': {}", query, e))?,
+            ),
+            _ => PathPattern::Exact(query.to_lowercase()),
+        };
+
+        Ok(Some(pattern))
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        match self {
+            PathPattern::Glob(matcher) => matcher.is_match(path),
+            PathPattern::Regex(regex) => regex.is_match(path),
+            PathPattern::Exact(literal) => path.to_lowercase().contains(literal.as_str()),
+        }
+    }
+}
+
+/// Paths matched by `scan_paths` and whether the scan stopped early.
+#[derive(Debug, Default)]
+pub struct PatternScan {
+    pub matches: Vec<String>,
+    /// The result cap was reached before all paths were checked
+    pub capped: bool,
+    /// The time budget ran out before all paths were checked
+    pub timed_out: bool,
+}
+
+/// Checks paths against a pattern until all are checked, `cap` matches are
+/// found or `timeout` has passed.
+///
+/// # Arguments
+/// * `paths` - The indexed paths to check
+/// * `pattern` - The compiled pattern
+/// * `cap` - Maximum number of matches to collect
+/// * `timeout` - Time budget for the scan
+///
+/// # Returns
+/// The matches found so far, with flags telling why the scan stopped early
+///
+/// # Performance
+/// O(n) pattern checks for n paths, bounded by `cap` and `timeout`
+pub fn scan_paths<'a>(
+    paths: impl IntoIterator<Item = &'a String>,
+    pattern: &PathPattern,
+    cap: usize,
+    timeout: Duration,
+) -> PatternScan {
+    let start = Instant::now();
+    let mut scan = PatternScan::default();
+
+    for (checked, path) in paths.into_iter().enumerate() {
+        if checked % TIMEOUT_CHECK_INTERVAL == 0 && checked > 0 && start.elapsed() >= timeout {
+            scan.timed_out = true;
+            break;
+        }
+        if pattern.is_match(path) {
+            if scan.matches.len() >= cap {
+                scan.capped = true;
+                break;
+            }
+            scan.matches.push(path.clone());
+        }
+    }
+
+    scan
+}
+
+#[cfg(test)]
+mod tests_pattern_search {
+    use super::*;
+
+    fn paths() -> Vec<String> {
+        [
+            "/repo/src/lib.rs",
+            "/repo/src/engine/search_test.rs",
+            "/repo/src/engine/search.rs",
+            "/repo/tests/api_test.rs",
+            "/repo/README.md",
+        ]
+        .iter()
+        .map(|p| p.to_string())
+        .collect()
+    }
+
+    fn matching(query: &str, mode: SearchMode) -> Vec<String> {
+        let pattern = PathPattern::compile(query, mode).unwrap().unwrap();
+        scan_paths(&paths(), &pattern, 100, Duration::from_secs(5)).matches
+    }
+
+    #[test]
+    fn test_glob_mode() {
+        assert_eq!(matching("**/src/**/*_test.rs", SearchMode::Glob), vec!["/repo/src/engine/search_test.rs"]);
+        assert_eq!(matching("*_test.rs", SearchMode::Glob).len(), 2);
+        assert_eq!(matching("/repo/*.md", SearchMode::Glob), vec!["/repo/README.md"]);
+        assert_eq!(matching("/repo/*.rs", SearchMode::Glob), Vec::<String>::new());
+        assert_eq!(matching("*.{md,toml}", SearchMode::Glob), vec!["/repo/README.md"]);
+    }
+
+    #[test]
+    fn test_regex_and_exact_modes() {
+        assert_eq!(matching(r"engine/\w+\.rs$", SearchMode::Regex).len(), 2);
+        assert_eq!(matching("(?i)readme", SearchMode::Regex), vec!["/repo/README.md"]);
+        assert_eq!(matching("readme", SearchMode::Regex), Vec::<String>::new());
+        assert_eq!(matching("README", SearchMode::Exact), vec!["/repo/README.md"]);
+        assert_eq!(matching("engine/search", SearchMode::Exact).len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert!(PathPattern::compile("(unclosed", SearchMode::Regex).is_err());
+        assert!(PathPattern::compile("{a,b", SearchMode::Glob).is_err());
+        assert!(PathPattern::compile("  ", SearchMode::Glob).is_err());
+        assert!(PathPattern::compile("anything", SearchMode::Fuzzy).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_scan_stops_at_cap_and_timeout() {
+        let pattern = PathPattern::compile(".rs", SearchMode::Exact).unwrap().unwrap();
+        let scan = scan_paths(&paths(), &pattern, 2, Duration::from_secs(5));
+        assert_eq!(scan.matches.len(), 2);
+        assert!(scan.capped && !scan.timed_out);
+
+        let many: Vec<String> = (0..10_000).map(|i| format!("/data/{}.rs", i)).collect();
+        let scan = scan_paths(&many, &pattern, usize::MAX, Duration::ZERO);
+        assert!(scan.timed_out);
+        assert_eq!(scan.matches.len(), TIMEOUT_CHECK_INTERVAL);
+    }
+
+    #[test]
+    fn test_mode_serialization() {
+        assert_eq!(serde_json::to_string(&SearchMode::Glob).unwrap(), "\"glob\"");
+        assert_eq!(serde_json::from_str::<SearchMode>("\"regex\"").unwrap(), SearchMode::Regex);
+    }
+}
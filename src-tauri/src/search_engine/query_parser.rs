@@ -0,0 +1,584 @@
+use chrono::{Local, NaiveDate, TimeZone};
+use std::path::Path;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// Filesystem metadata of a path, used to evaluate `size:`, `modified:` and `type:` filters.
+///
+/// It is read when a query needs it rather than stored in the index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathMetadata {
+    /// Size in bytes (0 for most directories)
+    pub size: u64,
+    /// Last modification as unix timestamp in seconds, if the platform reports one
+    pub modified: Option<u64>,
+    pub is_dir: bool,
+}
+
+impl PathMetadata {
+    /// Reads the metadata of a path, following symlinks.
+    ///
+    /// Broken symlinks fall back to the metadata of the link itself.
+    ///
+    /// # Returns
+    /// `None` if the path does not exist or cannot be accessed
+    pub fn read(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path)
+            .or_else(|_| std::fs::symlink_metadata(path))
+            .ok()?;
+
+        Some(Self {
+            size: metadata.len(),
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|age| age.as_secs()),
+            is_dir: metadata.is_dir(),
+        })
+    }
+}
+
+/// Comparison operator of a `size:` filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn matches(self, actual: u64, expected: u64) -> bool {
+        match self {
+            Comparison::Less => actual < expected,
+            Comparison::LessOrEqual => actual <= expected,
+            Comparison::Equal => actual == expected,
+            Comparison::GreaterOrEqual => actual >= expected,
+            Comparison::Greater => actual > expected,
+        }
+    }
+}
+
+/// Kind of entry selected by a `type:` filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryType {
+    File,
+    Directory,
+}
+
+/// A single structured condition of a query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterKind {
+    /// `ext:rs` or `ext:rs,md` - lowercase extensions without the leading dot
+    Extension(Vec<String>),
+    /// `size:>10MB` - size in bytes
+    Size(Comparison, u64),
+    /// `modified:<7d` or `modified:>=2024-01-31` - modification time within
+    /// `[from, to)` as unix timestamps in seconds, open ends are `None`
+    Modified { from: Option<u64>, to: Option<u64> },
+    /// `type:dir` or `type:file`
+    Type(EntryType),
+    /// `in:/home/x` - an absolute folder prefix, or a folder name anywhere in the path
+    InFolder(String),
+    /// `-word` - lowercase text the path must not contain
+    Text(String),
+}
+
+/// A filter together with its negation flag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryFilter {
+    pub kind: FilterKind,
+    /// Set for filters written with a leading `-`
+    pub negated: bool,
+}
+
+/// A search query split into free text for `SearchCore::search` and structured filters.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedQuery {
+    /// The remaining search text, words joined by single spaces
+    pub text: String,
+    pub filters: Vec<QueryFilter>,
+}
+
+impl ParsedQuery {
+    pub fn has_filters(&self) -> bool {
+        !self.filters.is_empty()
+    }
+
+    /// Whether evaluating the filters requires filesystem metadata.
+    pub fn needs_metadata(&self) -> bool {
+        self.filters.iter().any(|filter| {
+            matches!(
+                filter.kind,
+                FilterKind::Size(..) | FilterKind::Modified { .. } | FilterKind::Type(_)
+            )
+        })
+    }
+
+    /// Checks a path against every filter.
+    ///
+    /// # Arguments
+    /// * `path` - The normalized path (forward slashes)
+    /// * `metadata` - Recorded metadata of the path, if known
+    ///
+    /// # Returns
+    /// `true` if all filters accept the path. Paths without metadata never pass
+    /// a metadata filter, negated or not.
+    pub fn matches(&self, path: &str, metadata: Option<&PathMetadata>) -> bool {
+        self.filters.iter().all(|filter| {
+            match evaluate(&filter.kind, path, metadata) {
+                Some(result) => result != filter.negated,
+                None => false,
+            }
+        })
+    }
+
+    /// Checks a path against the filters that need no metadata, the others are skipped.
+    pub fn matches_path(&self, path: &str) -> bool {
+        self.filters.iter().all(|filter| {
+            match evaluate(&filter.kind, path, None) {
+                Some(result) => result != filter.negated,
+                None => true,
+            }
+        })
+    }
+}
+
+/// Candidates kept by `scan_metadata` and whether the scan stopped early.
+#[derive(Debug, Default)]
+pub struct MetadataScan {
+    pub matches: Vec<(String, f32)>,
+    /// The result cap was reached before all candidates were checked
+    pub capped: bool,
+    /// The time budget ran out before all candidates were checked
+    pub timed_out: bool,
+}
+
+/// Reads the metadata of candidates and keeps those that pass every filter, until all
+/// are checked, `cap` matches are found or `timeout` has passed.
+///
+/// This touches the filesystem once per candidate, so it must not run under a lock
+/// of the search engine.
+///
+/// # Arguments
+/// * `candidates` - (path, score) pairs that already passed the filters on the path
+/// * `query` - The parsed query
+/// * `cap` - Maximum number of matches to collect
+/// * `timeout` - Time budget for the scan
+///
+/// # Performance
+/// One `stat` per candidate, bounded by `cap` and `timeout`
+pub fn scan_metadata(candidates: Vec<(String, f32)>, query: &ParsedQuery, cap: usize, timeout: Duration) -> MetadataScan {
+    let start = Instant::now();
+    let mut scan = MetadataScan::default();
+
+    for (path, score) in candidates {
+        if start.elapsed() >= timeout {
+            scan.timed_out = true;
+            break;
+        }
+        let metadata = PathMetadata::read(Path::new(&path));
+        if query.matches(&path, metadata.as_ref()) {
+            if scan.matches.len() >= cap {
+                scan.capped = true;
+                break;
+            }
+            scan.matches.push((path, score));
+        }
+    }
+
+    scan
+}
+
+/// Evaluates one filter, returning `None` when the required metadata is missing.
+fn evaluate(kind: &FilterKind, path: &str, metadata: Option<&PathMetadata>) -> Option<bool> {
+    match kind {
+        FilterKind::Extension(extensions) => {
+            let extension = Path::new(path)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.to_lowercase());
+            Some(extension.is_some_and(|ext| extensions.contains(&ext)))
+        }
+        FilterKind::Size(comparison, bytes) => Some(comparison.matches(metadata?.size, *bytes)),
+        FilterKind::Modified { from, to } => {
+            let modified = metadata?.modified?;
+            Some(from.is_none_or(|from| modified >= from) && to.is_none_or(|to| modified < to))
+        }
+        FilterKind::Type(entry_type) => {
+            Some(metadata?.is_dir == (*entry_type == EntryType::Directory))
+        }
+        FilterKind::InFolder(folder) => {
+            if folder.starts_with('/') || folder.contains(':') {
+                let prefix = format!("{}/", folder.trim_end_matches('/'));
+                Some(path.starts_with(&prefix))
+            } else {
+                Some(path.contains(&format!("/{}/", folder)))
+            }
+        }
+        FilterKind::Text(text) => Some(path.to_lowercase().contains(text)),
+    }
+}
+
+/// Parses a search query into free text and structured filters.
+///
+/// Supported filters (keys are case-insensitive, prefix any of them with `-` to negate):
+/// - `ext:rs`, `ext:rs,md` - file extension
+/// - `size:>10MB`, `size:<=512k` - size with `<`, `<=`, `=`, `>=`, `>` and B/KB/MB/GB/TB units (1024 based)
+/// - `modified:<7d` (changed within the last 7 days), `modified:>1y`, `modified:2024-01-31`,
+///   `modified:>=2024-01-01` - relative ages with s/m/h/d/w/y units or local calendar dates
+/// - `type:dir`, `type:file`
+/// - `in:/home/x` (below an absolute folder) or `in:src` (below any folder named `src`); `path:` is an alias
+/// - `-word` excludes paths containing `word`
+///
+/// Values containing spaces can be quoted: `in:"/home/x/My Documents"`.
+/// Tokens with an unknown key (e.g. `C:\Users`) are kept as search text.
+///
+/// # Arguments
+/// * `query` - The raw query typed by the user
+///
+/// # Returns
+/// * `Ok(ParsedQuery)` - The search text and filters
+/// * `Err(String)` - A filter value could not be parsed
+///
+/// # Example
+/// ```rust
+/// let parsed = parse_query("report ext:pdf size:>1MB -draft")?;
+/// assert_eq!(parsed.text, "report");
+/// assert_eq!(parsed.filters.len(), 3);
+/// ```
+pub fn parse_query(query: &str) -> Result<ParsedQuery, String> {
+    parse_query_at(query, chrono::Utc::now().timestamp().max(0) as u64)
+}
+
+/// Same as `parse_query`, with relative `modified:` ages measured from `now` (unix seconds).
+pub fn parse_query_at(query: &str, now: u64) -> Result<ParsedQuery, String> {
+    let mut words = Vec::new();
+    let mut filters = Vec::new();
+
+    for token in split_tokens(query) {
+        let (negated, body) = match token.strip_prefix('-') {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, token.as_str()),
+        };
+
+        let kind = match body.split_once(':') {
+            Some((key, value)) => parse_filter(&key.to_lowercase(), value, now)?,
+            None => None,
+        };
+
+        match kind {
+            Some(kind) => filters.push(QueryFilter { kind, negated }),
+            None if negated => filters.push(QueryFilter {
+                kind: FilterKind::Text(body.to_lowercase()),
+                negated: true,
+            }),
+            None => words.push(token),
+        }
+    }
+
+    Ok(ParsedQuery {
+        text: words.join(" "),
+        filters,
+    })
+}
+
+/// Parses the value of a `key:value` token. Unknown keys yield `Ok(None)`.
+fn parse_filter(key: &str, value: &str, now: u64) -> Result<Option<FilterKind>, String> {
+    let known = matches!(key, "ext" | "size" | "modified" | "type" | "in" | "path");
+    if !known {
+        return Ok(None);
+    }
+    if value.is_empty() {
+        return Err(format!("Missing value for '{}:'", key));
+    }
+
+    let kind = match key {
+        "ext" => FilterKind::Extension(
+            value
+                .split(',')
+                .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+                .filter(|ext| !ext.is_empty())
+                .collect(),
+        ),
+        "size" => {
+            let (comparison, amount) = split_comparison(value);
+            FilterKind::Size(comparison, parse_size(amount)?)
+        }
+        "modified" => parse_modified(value, now)?,
+        "type" => match value.to_lowercase().as_str() {
+            "file" | "f" => FilterKind::Type(EntryType::File),
+            "dir" | "directory" | "folder" | "d" => FilterKind::Type(EntryType::Directory),
+            other => return Err(format!("Unknown type '{}', expected 'file' or 'dir'", other)),
+        },
+        _ => FilterKind::InFolder(value.replace('\\', "/").trim_end_matches('/').to_string()),
+    };
+
+    Ok(Some(kind))
+}
+
+fn split_comparison(value: &str) -> (Comparison, &str) {
+    for (operator, comparison) in [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+    ] {
+        if let Some(rest) = value.strip_prefix(operator) {
+            return (comparison, rest);
+        }
+    }
+    (Comparison::Equal, value)
+}
+
+/// Parses sizes like `10MB`, `1.5g` or `512` (bytes).
+fn parse_size(value: &str) -> Result<u64, String> {
+    let lower = value.trim().to_lowercase();
+    let split = lower
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(lower.len());
+    let (number, unit) = lower.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid size '{}'", value))?;
+    let multiplier: u64 = match unit {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        _ => return Err(format!("Unknown size unit in '{}'", value)),
+    };
+
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parses `modified:` values into a `[from, to)` range of unix timestamps.
+fn parse_modified(value: &str, now: u64) -> Result<FilterKind, String> {
+    let (comparison, amount) = split_comparison(value);
+
+    if let Ok(date) = NaiveDate::parse_from_str(amount, "%Y-%m-%d") {
+        let day_start = local_day_start(date)?;
+        let day_end = local_day_start(date.succ_opt().ok_or("Date out of range")?)?;
+        let (from, to) = match comparison {
+            Comparison::Less => (None, Some(day_start)),
+            Comparison::LessOrEqual => (None, Some(day_end)),
+            Comparison::Equal => (Some(day_start), Some(day_end)),
+            Comparison::GreaterOrEqual => (Some(day_start), None),
+            Comparison::Greater => (Some(day_end), None),
+        };
+        return Ok(FilterKind::Modified { from, to });
+    }
+
+    // Relative ages: `<7d` means "less than 7 days old", so the comparison flips
+    let age = parse_age(amount).ok_or_else(|| {
+        format!("Invalid modified value '{}', expected e.g. '<7d' or '2024-01-31'", value)
+    })?;
+    let threshold = now.saturating_sub(age);
+    Ok(match comparison {
+        Comparison::Greater | Comparison::GreaterOrEqual => FilterKind::Modified {
+            from: None,
+            to: Some(threshold),
+        },
+        _ => FilterKind::Modified {
+            from: Some(threshold),
+            to: None,
+        },
+    })
+}
+
+/// Parses ages like `30s`, `15m`, `12h`, `7d`, `2w` or `1y` into seconds.
+fn parse_age(value: &str) -> Option<u64> {
+    let lower = value.trim().to_lowercase();
+    let split = lower.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = lower.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        _ => return None,
+    };
+    number.checked_mul(seconds)
+}
+
+fn local_day_start(date: NaiveDate) -> Result<u64, String> {
+    let midnight = date.and_hms_opt(0, 0, 0).ok_or("Invalid date")?;
+    let local = Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .ok_or_else(|| format!("Invalid local date '{}'", date))?;
+    Ok(local.timestamp().max(0) as u64)
+}
+
+/// Splits a query on whitespace, keeping double-quoted sections together
+/// and removing the quotes.
+fn split_tokens(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests_query_parser {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn filter(kind: FilterKind) -> QueryFilter {
+        QueryFilter { kind, negated: false }
+    }
+
+    fn metadata(size: u64, modified: u64, is_dir: bool) -> PathMetadata {
+        PathMetadata { size, modified: Some(modified), is_dir }
+    }
+
+    #[test]
+    fn test_plain_text_is_untouched() {
+        let parsed = parse_query_at("quarterly  report", NOW).unwrap();
+        assert_eq!(parsed.text, "quarterly report");
+        assert!(!parsed.has_filters());
+
+        // Unknown keys, drive letters and a lone dash are search text
+        let parsed = parse_query_at(r"C:\Users foo:bar -", NOW).unwrap();
+        assert_eq!(parsed.text, r"C:\Users foo:bar -");
+        assert!(!parsed.has_filters());
+    }
+
+    #[test]
+    fn test_parse_all_filters() {
+        let parsed = parse_query_at(
+            r#"report ext:PDF,.md size:>10MB modified:<7d type:dir in:"/home/x/My Docs/" -draft -ext:tmp"#,
+            NOW,
+        )
+        .unwrap();
+
+        assert_eq!(parsed.text, "report");
+        assert_eq!(
+            parsed.filters,
+            vec![
+                filter(FilterKind::Extension(vec!["pdf".to_string(), "md".to_string()])),
+                filter(FilterKind::Size(Comparison::Greater, 10 * 1024 * 1024)),
+                filter(FilterKind::Modified { from: Some(NOW - 7 * DAY), to: None }),
+                filter(FilterKind::Type(EntryType::Directory)),
+                filter(FilterKind::InFolder("/home/x/My Docs".to_string())),
+                QueryFilter { kind: FilterKind::Text("draft".to_string()), negated: true },
+                QueryFilter { kind: FilterKind::Extension(vec!["tmp".to_string()]), negated: true },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_sizes_and_ages() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("1.5k").unwrap(), 1536);
+        assert_eq!(parse_size("2GB").unwrap(), 2 << 30);
+        assert!(parse_size("ten").is_err());
+        assert!(parse_size("10xb").is_err());
+
+        assert_eq!(parse_age("30s"), Some(30));
+        assert_eq!(parse_age("2w"), Some(14 * DAY));
+        assert_eq!(parse_age("7"), None);
+
+        assert_eq!(
+            parse_query_at("modified:>1y", NOW).unwrap().filters[0].kind,
+            FilterKind::Modified { from: None, to: Some(NOW - 365 * DAY) }
+        );
+    }
+
+    #[test]
+    fn test_parse_modified_dates() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let start = local_day_start(day).unwrap();
+        let end = local_day_start(day.succ_opt().unwrap()).unwrap();
+
+        let kind = |query: &str| parse_query_at(query, NOW).unwrap().filters[0].kind.clone();
+        assert_eq!(kind("modified:2024-01-31"), FilterKind::Modified { from: Some(start), to: Some(end) });
+        assert_eq!(kind("modified:>2024-01-31"), FilterKind::Modified { from: Some(end), to: None });
+        assert_eq!(kind("modified:<=2024-01-31"), FilterKind::Modified { from: None, to: Some(end) });
+    }
+
+    #[test]
+    fn test_invalid_filters_are_errors() {
+        assert!(parse_query_at("size:", NOW).is_err());
+        assert!(parse_query_at("size:>big", NOW).is_err());
+        assert!(parse_query_at("modified:yesterday", NOW).is_err());
+        assert!(parse_query_at("type:socket", NOW).is_err());
+    }
+
+    #[test]
+    fn test_matches_filters() {
+        let parsed = parse_query_at("ext:rs size:>1k modified:<7d -test in:src", NOW).unwrap();
+        let fresh = metadata(4096, NOW - DAY, false);
+
+        assert!(parsed.matches("/repo/src/main.rs", Some(&fresh)));
+        assert!(!parsed.matches("/repo/src/main.py", Some(&fresh)));
+        assert!(!parsed.matches("/repo/src/main_test.rs", Some(&fresh)));
+        assert!(!parsed.matches("/repo/lib/main.rs", Some(&fresh)));
+        assert!(!parsed.matches("/repo/src/main.rs", Some(&metadata(100, NOW - DAY, false))));
+        assert!(!parsed.matches("/repo/src/main.rs", Some(&metadata(4096, NOW - 30 * DAY, false))));
+        assert!(!parsed.matches("/repo/src/main.rs", None));
+    }
+
+    #[test]
+    fn test_matches_type_and_absolute_folder() {
+        let parsed = parse_query_at("type:dir in:/home/x", NOW).unwrap();
+        let dir = metadata(0, NOW, true);
+
+        assert!(parsed.matches("/home/x/projects", Some(&dir)));
+        assert!(!parsed.matches("/home/x", Some(&dir)));
+        assert!(!parsed.matches("/home/xy/projects", Some(&dir)));
+        assert!(!parsed.matches("/home/x/file.txt", Some(&metadata(1, NOW, false))));
+
+        let negated = parse_query_at("-type:dir", NOW).unwrap();
+        assert!(negated.matches("/home/x/file.txt", Some(&metadata(1, NOW, false))));
+        assert!(!negated.matches("/home/x/projects", Some(&dir)));
+        assert!(!negated.matches("/home/x/unknown", None));
+    }
+
+    #[test]
+    fn test_scan_metadata_stops_at_cap_and_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let candidates: Vec<(String, f32)> = (0..5)
+            .map(|index| {
+                let path = dir.path().join(format!("{}.log", index));
+                std::fs::write(&path, vec![0u8; 2048]).unwrap();
+                (path.to_string_lossy().to_string(), 1.0)
+            })
+            .collect();
+        let parsed = parse_query("ext:log size:>1k").unwrap();
+        assert!(parsed.matches_path(&candidates[0].0));
+        assert!(!parsed.matches_path("/tmp/notes.txt"));
+
+        let scan = scan_metadata(candidates.clone(), &parsed, 10, Duration::from_secs(5));
+        assert_eq!((scan.matches.len(), scan.capped, scan.timed_out), (5, false, false));
+        let scan = scan_metadata(candidates.clone(), &parsed, 2, Duration::from_secs(5));
+        assert_eq!((scan.matches.len(), scan.capped), (2, true));
+        let scan = scan_metadata(candidates, &parsed, 10, Duration::ZERO);
+        assert!(scan.matches.is_empty() && scan.timed_out);
+    }
+}
@@ -8,8 +8,16 @@ use crate::log_info;
 #[cfg(any(feature = "search-error-logging", feature = "index-error-logging"))]
 use crate::log_error;
 use crate::search_engine::art_v5::ART;
+use crate::search_engine::content_index::ContentIndex;
 use crate::search_engine::fast_fuzzy_v2::PathMatcher;
+use crate::search_engine::index_persistence::IndexSnapshot;
 use crate::search_engine::path_cache_wrapper::PathCache;
+use crate::search_engine::pattern_search::{scan_paths, PathPattern};
+use crate::search_engine::query_parser::{scan_metadata, ParsedQuery};
+
+/// How many more candidates than `max_results` are gathered when a query has
+/// filters, so restrictive filters still leave enough results
+const FILTERED_SEARCH_OVERFETCH: usize = 20;
 
 /// Search Core that combines caching, prefix search, and fuzzy search
 /// for high-performance path completion with contextual relevance.
@@ -34,6 +42,9 @@ pub struct SearchCore {
     /// Fuzzy search engine for approximate matching
     fuzzy_matcher: PathMatcher,
 
+    /// Word index over file contents, only filled when content indexing is enabled
+    content_index: ContentIndex,
+
     /// Maximum number of results to return
     max_results: usize,
 
@@ -85,6 +96,7 @@ impl SearchCore {
             cache: PathCache::with_ttl(cache_size, ttl),
             trie: ART::new(max_results * 2),
             fuzzy_matcher: PathMatcher::new(),
+            content_index: ContentIndex::new(),
             max_results,
             current_directory: None,
             frequency_map: HashMap::new(),
@@ -295,9 +307,13 @@ impl SearchCore {
             log_info!("Boosting path score based on frequency ({}): {:.3}", freq, score);
         }
 
-        // Update all modules and clean cache
+        // Update all modules and clean cache. Re-adding a known path only refreshes
+        // its score, otherwise the fuzzy matcher would hold duplicate entries.
+        let already_indexed = self.trie.contains(&normalized_path);
         self.trie.insert(&normalized_path, score);
-        self.fuzzy_matcher.add_path(&normalized_path);
+        if !already_indexed {
+            self.fuzzy_matcher.add_path(&normalized_path);
+        }
         self.cache.purge_expired();
         
         #[cfg(feature = "index-progress-logging")]
@@ -578,6 +594,7 @@ impl SearchCore {
         // Remove from modules
         self.trie.remove(&normalized_path);
         self.fuzzy_matcher.remove_path(&normalized_path);
+        self.content_index.remove_file(&normalized_path);
 
         // Clear the entire cache (this is a simplification, because of previous bugs)
         self.cache.clear();
@@ -602,6 +619,97 @@ impl SearchCore {
         }
     }
 
+    /// Removes a path and every indexed path below it without touching the filesystem.
+    ///
+    /// Unlike `remove_paths_recursive`, this works from the index itself, so it also
+    /// cleans up directories that have already been deleted or renamed on disk.
+    ///
+    /// # Arguments
+    /// * `path` - The root path to remove from the index
+    ///
+    /// # Returns
+    /// The number of paths removed from the index
+    ///
+    /// # Performance
+    /// O(n + t) where n is the number of indexed paths and t the trigram index size
+    pub fn remove_path_tree(&mut self, path: &str) -> usize {
+        let normalized_path = self.normalize_path(path);
+
+        self.content_index.remove_files_under(&normalized_path);
+
+        let mut removed = self.fuzzy_matcher.remove_paths_under(&normalized_path);
+        if removed.is_empty() && self.trie.contains(&normalized_path) {
+            removed.push(normalized_path);
+        }
+
+        for removed_path in &removed {
+            self.trie.remove(removed_path);
+            self.frequency_map.remove(removed_path);
+            self.recency_map.remove(removed_path);
+        }
+
+        if !removed.is_empty() {
+            self.cache.clear();
+        }
+
+        #[cfg(feature = "index-progress-logging")]
+        log_info!("Removed {} indexed paths under '{}'", removed.len(), path);
+
+        removed.len()
+    }
+
+    /// Checks whether a path is currently part of the index.
+    ///
+    /// # Arguments
+    /// * `path` - The path to look up
+    ///
+    /// # Returns
+    /// `true` if the exact path is indexed
+    ///
+    /// # Performance
+    /// O(m) where m is the length of the path
+    pub fn contains_path(&mut self, path: &str) -> bool {
+        let normalized_path = self.normalize_path(path);
+        self.trie.contains(&normalized_path)
+    }
+
+    /// Adds the text content of a file to the content index.
+    ///
+    /// Replaces any content previously indexed for the same path. The path itself
+    /// is not added to the path index; use `add_path` for that.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the file
+    /// * `text` - The decoded text content of the file
+    ///
+    /// # Performance
+    /// O(w log v) where w is the number of words and v the size of the vocabulary
+    pub fn add_file_content(&mut self, path: &str, text: &str) {
+        let normalized_path = self.normalize_path(path);
+        self.content_index.add_file(&normalized_path, text);
+    }
+
+    /// Removes the indexed content of a file, e.g. once it is no longer text.
+    /// The path itself stays in the path index.
+    pub fn remove_file_content(&mut self, path: &str) {
+        let normalized_path = self.normalize_path(path);
+        self.content_index.remove_file(&normalized_path);
+    }
+
+    /// Returns the files whose indexed content contains every word of the query.
+    ///
+    /// This only narrows down the files to look at; callers still need to read
+    /// the files to find the matching lines.
+    ///
+    /// # Arguments
+    /// * `query` - The content query
+    ///
+    /// # Returns
+    /// Paths of the candidate files
+    pub fn content_candidates(&self, query: &str) -> Vec<String> {
+        self.content_index.candidates(query)
+    }
+
     /// Recursively removes a path and all its subdirectories and files from the index.
     ///
     /// This method walks the directory tree starting at the given path,
@@ -721,11 +829,90 @@ impl SearchCore {
         self.recency_map.clear();
 
         self.fuzzy_matcher = PathMatcher::new();
-        
+        self.content_index.clear();
+
         #[cfg(feature = "index-progress-logging")]
         log_info!("Engine data cleared successfully");
     }
 
+    /// Creates a serializable snapshot of the indexed data.
+    ///
+    /// The snapshot contains the trie entries, the fuzzy matcher's paths and trigram
+    /// index, the frequency/recency maps and the content index. Recency instants are converted to unix
+    /// timestamps so they stay meaningful after a restart.
+    ///
+    /// # Returns
+    /// An `IndexSnapshot` with `index_folder` left empty for the caller to fill in
+    ///
+    /// # Performance
+    /// O(n) where n is the number of indexed paths
+    pub fn export_snapshot(&self) -> IndexSnapshot {
+        let now_ms = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        let (fuzzy_paths, trigram_index) = self.fuzzy_matcher.export_index();
+
+        IndexSnapshot {
+            index_folder: None,
+            trie_entries: self.trie.export_entries(),
+            fuzzy_paths,
+            trigram_index,
+            frequency_map: self.frequency_map.clone(),
+            recency_map: self
+                .recency_map
+                .iter()
+                .map(|(path, instant)| {
+                    let age_ms = instant.elapsed().as_millis() as u64;
+                    (path.clone(), now_ms.saturating_sub(age_ms))
+                })
+                .collect(),
+            content_index: self.content_index.export(),
+        }
+    }
+
+    /// Replaces the indexed data with the contents of a snapshot.
+    ///
+    /// The existing index and cache are discarded. If the snapshot is inconsistent
+    /// the engine is left empty and an error is returned, so the caller can fall
+    /// back to a fresh index.
+    ///
+    /// # Arguments
+    /// * `snapshot` - A snapshot previously produced by `export_snapshot`
+    ///
+    /// # Returns
+    /// * `Ok(())` - The snapshot was restored
+    /// * `Err(String)` - The snapshot was invalid
+    ///
+    /// # Performance
+    /// O(n*m) where n is the number of paths and m is average path length
+    pub fn restore_snapshot(&mut self, snapshot: IndexSnapshot) -> Result<(), String> {
+        self.clear();
+
+        let fuzzy_matcher = PathMatcher::from_index(snapshot.fuzzy_paths, snapshot.trigram_index)?;
+        let content_index = ContentIndex::from_snapshot(snapshot.content_index)?;
+
+        for (path, score) in &snapshot.trie_entries {
+            self.trie.insert(path, *score);
+        }
+        self.fuzzy_matcher = fuzzy_matcher;
+        self.content_index = content_index;
+        self.frequency_map = snapshot.frequency_map;
+
+        let now_ms = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        let now = Instant::now();
+        self.recency_map = snapshot
+            .recency_map
+            .into_iter()
+            .filter_map(|(path, timestamp_ms)| {
+                let age = Duration::from_millis(now_ms.saturating_sub(timestamp_ms));
+                now.checked_sub(age).map(|instant| (path, instant))
+            })
+            .collect();
+
+        #[cfg(feature = "index-progress-logging")]
+        log_info!("Restored index snapshot with {} paths", self.trie.len());
+
+        Ok(())
+    }
+
     /// Records that a path was used, updating frequency and recency data for ranking.
     ///
     /// This improves future search results by boosting frequently and recently used paths.
@@ -980,6 +1167,145 @@ impl SearchCore {
         final_results
     }
 
+    /// Searches with a parsed structured query (see `query_parser::parse_query`).
+    ///
+    /// Without filters this is the same as `search(&query.text)`. With filters,
+    /// prefix and fuzzy matches for the text are gathered generously, filtered and
+    /// then ranked (see `filtered_candidates`, `scan_metadata` and `rank_filtered`).
+    /// Filtered results are not cached because the metadata they depend on can change.
+    ///
+    /// Metadata is read while `self` is borrowed, callers that hold the engine lock
+    /// should run the three steps themselves and release the lock for the scan.
+    ///
+    /// # Arguments
+    /// * `query` - The parsed query
+    /// * `timeout` - Time budget for reading the metadata of the candidates
+    ///
+    /// # Returns
+    /// A vector of (path, score) pairs sorted by relevance score
+    ///
+    /// # Performance
+    /// O(k) filter checks for k candidates, plus one `stat` per candidate when the query
+    /// filters on size, modification time or type, bounded by the result cap and `timeout`
+    pub fn search_with_query(&mut self, query: &ParsedQuery, timeout: Duration) -> Vec<(String, f32)> {
+        if !query.has_filters() {
+            return self.search(&query.text);
+        }
+
+        let mut candidates = self.filtered_candidates(query);
+        if query.needs_metadata() {
+            candidates = scan_metadata(candidates, query, self.filtered_result_cap(), timeout).matches;
+        }
+        self.rank_filtered(candidates, &query.text)
+    }
+
+    /// Gathers the candidates of a filtered query and applies the filters that only
+    /// look at the path. Filters on metadata are left to `scan_metadata`.
+    ///
+    /// A query consisting of filters only (e.g. `ext:rs size:>1MB`) checks every indexed path.
+    ///
+    /// # Arguments
+    /// * `query` - The parsed query, which should have filters
+    ///
+    /// # Returns
+    /// Unranked (path, score) pairs
+    ///
+    /// # Performance
+    /// O(k) path filter checks for k candidates; filter-only queries are O(n) in the index size
+    pub fn filtered_candidates(&mut self, query: &ParsedQuery) -> Vec<(String, f32)> {
+        let text = query.text.trim();
+        if text.is_empty() {
+            return self
+                .fuzzy_matcher
+                .paths()
+                .iter()
+                .filter(|path| query.matches_path(path))
+                .map(|path| (path.clone(), 0.5))
+                .collect();
+        }
+
+        let limit = self.filtered_result_cap();
+        let mut candidates = self.trie.search(text, None, false);
+        let mut seen: HashSet<String> = candidates.iter().map(|(p, _)| p.clone()).collect();
+        for (path, score) in self.fuzzy_matcher.search(text, limit) {
+            if seen.insert(path.clone()) {
+                candidates.push((path, score));
+            }
+        }
+        candidates.retain(|(path, _)| query.matches_path(path));
+        candidates
+    }
+
+    /// Ranks the results of a filtered query and cuts them to `max_results`.
+    ///
+    /// # Arguments
+    /// * `results` - The candidates that passed all filters
+    /// * `text` - The search text of the query
+    ///
+    /// # Returns
+    /// A vector of (path, score) pairs sorted by relevance score
+    pub fn rank_filtered(&mut self, mut results: Vec<(String, f32)>, text: &str) -> Vec<(String, f32)> {
+        let text = text.trim();
+        self.last_search_was_cache_hit = false;
+        self.rank_results(&mut results, text);
+        results.truncate(self.max_results);
+
+        #[cfg(feature = "search-progress-logging")]
+        log_info!("Filtered search for '{}' returned {} results", text, results.len());
+
+        results
+    }
+
+    /// How many matches filtered and pattern searches collect before ranking, so
+    /// restrictive filters still leave enough results.
+    pub fn filtered_result_cap(&self) -> usize {
+        self.max_results.saturating_mul(FILTERED_SEARCH_OVERFETCH)
+    }
+
+    /// Searches the indexed paths with a glob, regex or exact pattern.
+    ///
+    /// Every indexed path is checked against the pattern until the result cap
+    /// (`max_results` times the filter overfetch factor) is reached or the time
+    /// budget runs out. The matches are then ranked and cut to `max_results`.
+    /// Results are not cached.
+    ///
+    /// # Arguments
+    /// * `pattern` - The compiled pattern (see `PathPattern::compile`)
+    /// * `timeout` - Time budget for scanning the index
+    ///
+    /// # Returns
+    /// A vector of (path, score) pairs sorted by relevance score
+    ///
+    /// # Performance
+    /// O(n) pattern checks for n indexed paths, bounded by the cap and `timeout`
+    pub fn search_pattern(&mut self, pattern: &PathPattern, timeout: Duration) -> Vec<(String, f32)> {
+        let cap = self.filtered_result_cap();
+        let scan = scan_paths(self.fuzzy_matcher.paths(), pattern, cap, timeout);
+
+        #[cfg(feature = "search-progress-logging")]
+        log_info!(
+            "Pattern search found {} matches (capped: {}, timed out: {})",
+            scan.matches.len(),
+            scan.capped,
+            scan.timed_out
+        );
+        #[cfg(feature = "search-error-logging")]
+        if scan.timed_out {
+            log_error!("Pattern search stopped after {:?}, results are incomplete", timeout);
+        }
+
+        let literal = match pattern {
+            PathPattern::Exact(literal) => literal.as_str(),
+            _ => "",
+        };
+        let mut results: Vec<(String, f32)> = scan.matches.into_iter().map(|path| (path, 0.5)).collect();
+
+        self.last_search_was_cache_hit = false;
+        self.rank_results(&mut results, literal);
+        results.truncate(self.max_results);
+        results
+    }
+
     /// Ranks search results based on various relevance factors.
     ///
     /// Scoring factors include:
@@ -1195,6 +1521,7 @@ impl SearchCore {
     ///
     /// Similar to rank_results but accepts current directory as a parameter
     /// for thread-safe concurrent operations. Returns a new vector instead of mutating.
+    /// Used to rank content search results, which are gathered outside the engine lock.
     ///
     /// # Arguments
     /// * `results` - Reference to vector of (path, score) pairs to rank
@@ -1206,8 +1533,7 @@ impl SearchCore {
     ///
     /// # Performance
     /// O(k log k) where k is the number of results to rank
-    #[allow(dead_code)]
-    fn rank_results_with_context(&self, results: &[(String, f32)], query: &str, current_directory: Option<&str>) -> Vec<(String, f32)> {
+    pub fn rank_results_with_context(&self, results: &[(String, f32)], query: &str, current_directory: Option<&str>) -> Vec<(String, f32)> {
         // Precompute lowercase query once
         let q_lc = query.to_lowercase();
         
@@ -1314,6 +1640,7 @@ impl SearchCore {
         EngineStats {
             cache_size: self.cache.len(),
             trie_size: self.trie.len(),
+            content_index_size: self.content_index.len(),
         }
     }
 }
@@ -1327,6 +1654,8 @@ pub struct EngineStats {
     pub cache_size: usize,
     /// Number of paths in the trie index
     pub trie_size: usize,
+    /// Number of files whose content is indexed
+    pub content_index_size: usize,
 }
 
 #[cfg(test)]
@@ -1517,6 +1846,135 @@ mod tests_search_core {
         assert!(stats.cache_size >= 1);
     }
 
+    #[test]
+    fn test_add_path_twice_does_not_duplicate() {
+        let mut engine = SearchCore::new(100, 10, Duration::from_secs(300), RankingConfig::default());
+        engine.add_path("/docs/report.pdf");
+        engine.add_path("/docs/report.pdf");
+
+        assert_eq!(engine.get_stats().trie_size, 1);
+        let results = engine.search("report");
+        assert_eq!(results.iter().filter(|(p, _)| p == "/docs/report.pdf").count(), 1);
+    }
+
+    #[test]
+    fn test_remove_path_tree() {
+        let mut engine = SearchCore::new(100, 10, Duration::from_secs(300), RankingConfig::default());
+        engine.add_path("/projects/app");
+        engine.add_path("/projects/app/src/main.rs");
+        engine.add_path("/projects/app/Cargo.toml");
+        engine.add_path("/projects/application.txt");
+
+        // The directory no longer needs to exist on disk
+        let removed = engine.remove_path_tree("/projects/app");
+        assert_eq!(removed, 3);
+        assert!(!engine.contains_path("/projects/app/src/main.rs"));
+        assert!(engine.contains_path("/projects/application.txt"));
+        assert_eq!(engine.get_stats().trie_size, 1);
+    }
+
+    #[test]
+    fn test_search_with_query_filters() {
+        use crate::search_engine::query_parser::parse_query;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().replace('\\', "/");
+        fs::create_dir_all(dir.path().join("reports")).unwrap();
+        fs::write(dir.path().join("reports/report.pdf"), vec![0u8; 4096]).unwrap();
+        fs::write(dir.path().join("reports/report.txt"), "small").unwrap();
+        fs::write(dir.path().join("reports/report_draft.txt"), "draft").unwrap();
+
+        let mut engine = SearchCore::new(100, 10, Duration::from_secs(300), RankingConfig::default());
+        for name in ["reports", "reports/report.pdf", "reports/report.txt", "reports/report_draft.txt"] {
+            engine.add_path(&format!("{}/{}", root, name));
+        }
+
+        let search = |engine: &mut SearchCore, query: &str| -> Vec<String> {
+            let mut paths: Vec<String> = engine
+                .search_with_query(&parse_query(query).unwrap(), Duration::from_secs(5))
+                .into_iter()
+                .map(|(path, _)| path.strip_prefix(&root).unwrap().to_string())
+                .collect();
+            paths.sort();
+            paths
+        };
+
+        assert_eq!(search(&mut engine, "report ext:txt"), vec!["/reports/report.txt", "/reports/report_draft.txt"]);
+        assert_eq!(search(&mut engine, "report ext:txt -draft"), vec!["/reports/report.txt"]);
+        assert_eq!(search(&mut engine, "type:file size:>1k"), vec!["/reports/report.pdf"]);
+        assert_eq!(search(&mut engine, "type:dir"), vec!["/reports"]);
+        assert_eq!(search(&mut engine, "-type:dir in:reports modified:<1d ext:pdf"), vec!["/reports/report.pdf"]);
+        assert!(search(&mut engine, "report modified:>1d").is_empty());
+    }
+
+    #[test]
+    fn test_search_pattern_over_indexed_paths() {
+        use crate::search_engine::pattern_search::SearchMode;
+
+        let mut engine = SearchCore::new(100, 2, Duration::from_secs(300), RankingConfig::default());
+        engine.add_path("/repo/src/engine/search_test.rs");
+        engine.add_path("/repo/src/engine/search.rs");
+        engine.add_path("/repo/src/parser_test.rs");
+        engine.add_path("/repo/tests/api_test.rs");
+
+        let glob = PathPattern::compile("**/src/**/*_test.rs", SearchMode::Glob).unwrap().unwrap();
+        let mut paths: Vec<String> = engine
+            .search_pattern(&glob, Duration::from_secs(5))
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        paths.sort();
+        assert_eq!(paths, vec!["/repo/src/engine/search_test.rs", "/repo/src/parser_test.rs"]);
+
+        // Results are capped at max_results
+        let regex = PathPattern::compile(r"_test\.rs$", SearchMode::Regex).unwrap().unwrap();
+        assert_eq!(engine.search_pattern(&regex, Duration::from_secs(5)).len(), 2);
+    }
+
+    #[test]
+    fn test_content_index_follows_removals() {
+        let mut engine = SearchCore::new(100, 10, Duration::from_secs(300), RankingConfig::default());
+        engine.add_file_content("/projects/app/src/main.rs", "fn main() { launch(rocket); }");
+        engine.add_file_content("/projects/app/README.md", "How to launch the rocket");
+        engine.add_file_content("/projects/notes.txt", "rocket science");
+
+        assert_eq!(engine.content_candidates("rocket").len(), 3);
+        assert_eq!(engine.content_candidates("launch rocket").len(), 2);
+
+        engine.remove_path("/projects/notes.txt");
+        assert_eq!(engine.content_candidates("rocket").len(), 2);
+
+        engine.remove_path_tree("/projects/app");
+        assert!(engine.content_candidates("rocket").is_empty());
+
+        engine.add_file_content("/projects/notes.txt", "rocket science");
+        engine.clear();
+        assert_eq!(engine.get_stats().content_index_size, 0);
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let mut engine = SearchCore::new(100, 10, Duration::from_secs(300), RankingConfig::default());
+        engine.add_path("/docs/report.pdf");
+        engine.add_path("/docs/notes.txt");
+        engine.record_path_usage("/docs/report.pdf");
+
+        let snapshot = engine.export_snapshot();
+        assert_eq!(snapshot.trie_entries.len(), 2);
+        assert_eq!(snapshot.fuzzy_paths.len(), 2);
+
+        let mut restored = SearchCore::new(100, 10, Duration::from_secs(300), RankingConfig::default());
+        restored.restore_snapshot(snapshot).unwrap();
+
+        assert_eq!(restored.get_stats().trie_size, 2);
+        assert_eq!(restored.frequency_map.get("/docs/report.pdf"), Some(&1));
+        assert!(restored.recency_map.contains_key("/docs/report.pdf"));
+
+        // Prefix and fuzzy search both work on the restored index
+        assert!(restored.search("/docs/rep").iter().any(|(p, _)| p == "/docs/report.pdf"));
+        assert!(restored.search("notes").iter().any(|(p, _)| p == "/docs/notes.txt"));
+    }
+
     // Helper function to create a temporary directory structure for testing
     fn create_temp_dir_structure() -> std::path::PathBuf {
         // Create unique temp directory using timestamp and random number
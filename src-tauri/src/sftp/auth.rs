@@ -0,0 +1,168 @@
+//! The ways to log in to an SSH server.
+
+use serde::{Deserialize, Serialize};
+use ssh2::{KeyboardInteractivePrompt, Prompt, Session};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The keys OpenSSH tries when neither the user nor `~/.ssh/config` name one
+const DEFAULT_IDENTITY_FILES: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// How to authenticate to the server.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum SftpAuth {
+    Password { password: String },
+    /// A private key file. Without a path, the `IdentityFile`s of `~/.ssh/config` for the
+    /// host are tried, and then the default keys in `~/.ssh`.
+    PublicKey {
+        #[serde(default)]
+        private_key_path: Option<String>,
+        #[serde(default)]
+        passphrase: Option<String>,
+    },
+    /// The identities of the running ssh-agent
+    Agent,
+    /// Answers to the prompts of the server, in the order they are asked. Most servers
+    /// ask for the password only.
+    KeyboardInteractive { responses: Vec<String> },
+}
+
+impl fmt::Debug for SftpAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SftpAuth::Password { .. } => f.write_str("Password"),
+            SftpAuth::PublicKey { private_key_path, passphrase } => f
+                .debug_struct("PublicKey")
+                .field("private_key_path", private_key_path)
+                .field("passphrase", &passphrase.as_ref().map(|_| "***"))
+                .finish(),
+            SftpAuth::Agent => f.write_str("Agent"),
+            SftpAuth::KeyboardInteractive { responses } => {
+                write!(f, "KeyboardInteractive({} responses)", responses.len())
+            }
+        }
+    }
+}
+
+/// The default keys in `ssh_dir` that exist.
+pub fn default_identity_files(ssh_dir: &Path) -> Vec<PathBuf> {
+    DEFAULT_IDENTITY_FILES
+        .iter()
+        .map(|name| ssh_dir.join(name))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Answers keyboard-interactive prompts with the given responses, one per prompt,
+/// across all rounds the server asks.
+struct Responses<'a> {
+    responses: &'a [String],
+    next: usize,
+}
+
+impl KeyboardInteractivePrompt for Responses<'_> {
+    fn prompt<'b>(&mut self, _username: &str, _instructions: &str, prompts: &[Prompt<'b>]) -> Vec<String> {
+        prompts
+            .iter()
+            .map(|_| {
+                let response = self.responses.get(self.next).cloned().unwrap_or_default();
+                self.next += 1;
+                response
+            })
+            .collect()
+    }
+}
+
+/// Logs in on a session after the handshake.
+///
+/// # Arguments
+/// * `identity_files` - The keys to try for `PublicKey` without a path
+///
+/// # Returns
+/// * `Ok(())` - If the server accepted the login
+/// * `Err(String)` - If every attempt was refused
+pub fn authenticate(session: &Session, username: &str, auth: &SftpAuth, identity_files: &[PathBuf]) -> Result<(), String> {
+    match auth {
+        SftpAuth::Password { password } => {
+            session.userauth_password(username, password).map_err(|e| e.to_string())?;
+        }
+        SftpAuth::PublicKey { private_key_path, passphrase } => {
+            let keys = match private_key_path {
+                Some(path) => vec![PathBuf::from(path)],
+                None => identity_files.to_vec(),
+            };
+            if keys.is_empty() {
+                return Err("No private key given and none found in ~/.ssh".to_string());
+            }
+            let mut last_error = String::new();
+            for key in &keys {
+                match session.userauth_pubkey_file(username, None, key, passphrase.as_deref()) {
+                    Ok(()) => break,
+                    Err(err) => last_error = format!("{}: {}", key.display(), err),
+                }
+            }
+            if !session.authenticated() {
+                return Err(format!("No private key was accepted ({})", last_error));
+            }
+        }
+        SftpAuth::Agent => {
+            session
+                .userauth_agent(username)
+                .map_err(|e| format!("No identity of the SSH agent was accepted: {}", e))?;
+        }
+        SftpAuth::KeyboardInteractive { responses } => {
+            let mut prompt = Responses { responses, next: 0 };
+            session.userauth_keyboard_interactive(username, &mut prompt).map_err(|e| e.to_string())?;
+        }
+    }
+    if !session.authenticated() {
+        return Err("Authentication failed".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests_auth {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_auth_from_json() {
+        let auth: SftpAuth = serde_json::from_str(r#"{"method":"public_key","private_key_path":"/keys/id"}"#).unwrap();
+        assert_eq!(auth, SftpAuth::PublicKey { private_key_path: Some("/keys/id".to_string()), passphrase: None });
+        let auth: SftpAuth = serde_json::from_str(r#"{"method":"agent"}"#).unwrap();
+        assert_eq!(auth, SftpAuth::Agent);
+        assert!(serde_json::from_str::<SftpAuth>(r#"{"method":"password"}"#).is_err());
+    }
+
+    #[test]
+    fn test_debug_hides_secrets() {
+        let auth = SftpAuth::PublicKey { private_key_path: None, passphrase: Some("secret".to_string()) };
+        assert!(!format!("{:?}", auth).contains("secret"));
+        let auth = SftpAuth::Password { password: "secret".to_string() };
+        assert_eq!(format!("{:?}", auth), "Password");
+    }
+
+    #[test]
+    fn test_keyboard_interactive_responses() {
+        let responses = vec!["password".to_string(), "123456".to_string()];
+        let mut prompt = Responses { responses: &responses, next: 0 };
+        let ask = |text: &'static str| Prompt { text: Cow::Borrowed(text), echo: false };
+
+        assert_eq!(prompt.prompt("user", "", &[ask("Password:")]), vec!["password"]);
+        // A second round continues with the next response, missing ones are empty
+        assert_eq!(prompt.prompt("user", "", &[ask("Code:"), ask("Again:")]), vec!["123456", ""]);
+    }
+
+    #[test]
+    fn test_default_identity_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("id_rsa"), "").unwrap();
+        std::fs::write(dir.path().join("id_ed25519"), "").unwrap();
+        assert_eq!(
+            default_identity_files(dir.path()),
+            vec![dir.path().join("id_ed25519"), dir.path().join("id_rsa")]
+        );
+    }
+}
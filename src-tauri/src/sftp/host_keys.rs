@@ -0,0 +1,201 @@
+//! Verifying host keys against an OpenSSH `known_hosts` file, with trust on first use.
+//! Unknown keys are refused until the user trusted them, changed keys are always refused.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use ssh2::{CheckResult, HostKeyType, KnownHostFileKind, Session};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// How a host key compares to the known hosts.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostKeyStatus {
+    /// The key is in the known hosts
+    Trusted,
+    /// The host is not in the known hosts yet
+    Unknown,
+    /// The known hosts have a different key for the host
+    Changed,
+}
+
+/// The key a server presented, as shown to the user before trusting it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostKeyInfo {
+    pub host: String,
+    pub port: u16,
+    pub key_type: String,
+    /// In the format of `ssh-keygen -l`, e.g. `SHA256:uNiVztksCsDhcc0u9e8BujQXVUpKZIDTMczCvj3tD2s`
+    pub fingerprint: String,
+    pub status: HostKeyStatus,
+}
+
+fn key_type_name(kind: HostKeyType) -> Option<&'static str> {
+    match kind {
+        HostKeyType::Rsa => Some("ssh-rsa"),
+        HostKeyType::Dss => Some("ssh-dss"),
+        HostKeyType::Ecdsa256 => Some("ecdsa-sha2-nistp256"),
+        HostKeyType::Ecdsa384 => Some("ecdsa-sha2-nistp384"),
+        HostKeyType::Ecdsa521 => Some("ecdsa-sha2-nistp521"),
+        HostKeyType::Ed25519 => Some("ssh-ed25519"),
+        HostKeyType::Unknown => None,
+    }
+}
+
+/// The SHA256 fingerprint of a key blob, as OpenSSH shows it.
+pub fn fingerprint(key: &[u8]) -> String {
+    format!("SHA256:{}", base64::engine::general_purpose::STANDARD_NO_PAD.encode(Sha256::digest(key)))
+}
+
+/// The name OpenSSH stores a host under, with the port if it is not the default.
+fn host_pattern(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+/// A `known_hosts` line for a key.
+fn known_hosts_line(host: &str, port: u16, key_type: &str, key: &[u8]) -> String {
+    format!(
+        "{} {} {}",
+        host_pattern(host, port),
+        key_type,
+        base64::engine::general_purpose::STANDARD.encode(key)
+    )
+}
+
+/// Compares a key to the entries of a `known_hosts` file. Lines libssh2 cannot read,
+/// such as certificate authorities, are left out instead of failing the whole check.
+fn check_known_hosts(session: &Session, content: &str, host: &str, port: u16, key: &[u8]) -> Result<HostKeyStatus, String> {
+    let mut known_hosts = session.known_hosts().map_err(|e| e.to_string())?;
+    for line in content.lines() {
+        let _ = known_hosts.read_str(line, KnownHostFileKind::OpenSSH);
+    }
+    Ok(match known_hosts.check_port(host, port, key) {
+        CheckResult::Match => HostKeyStatus::Trusted,
+        CheckResult::Mismatch => HostKeyStatus::Changed,
+        CheckResult::NotFound | CheckResult::Failure => HostKeyStatus::Unknown,
+    })
+}
+
+/// Checks the key the server presented during the handshake.
+///
+/// # Arguments
+/// * `known_hosts` - The `known_hosts` file, a missing file has no entries
+pub fn inspect(session: &Session, known_hosts: Option<&Path>, host: &str, port: u16) -> Result<HostKeyInfo, String> {
+    let (key, kind) = session.host_key().ok_or_else(|| "The server sent no host key".to_string())?;
+    let key_type = key_type_name(kind).ok_or_else(|| "The server sent an unsupported host key".to_string())?;
+    let content = known_hosts
+        .and_then(|path| fs::read_to_string(path).ok())
+        .unwrap_or_default();
+    Ok(HostKeyInfo {
+        host: host.to_string(),
+        port,
+        key_type: key_type.to_string(),
+        fingerprint: fingerprint(key),
+        status: check_known_hosts(session, &content, host, port, key)?,
+    })
+}
+
+/// Fails unless the key the server presented is trusted. Credentials must only be sent
+/// after this succeeded.
+pub fn verify(session: &Session, known_hosts: Option<&Path>, host: &str, port: u16) -> Result<(), String> {
+    let info = inspect(session, known_hosts, host, port)?;
+    match info.status {
+        HostKeyStatus::Trusted => Ok(()),
+        HostKeyStatus::Unknown => Err(format!(
+            "The host key of {} is not trusted yet ({} {})",
+            host_pattern(host, port),
+            info.key_type,
+            info.fingerprint
+        )),
+        HostKeyStatus::Changed => Err(changed_error(host, port)),
+    }
+}
+
+fn changed_error(host: &str, port: u16) -> String {
+    format!(
+        "The host key of {} does not match the one in known_hosts, the connection may be intercepted",
+        host_pattern(host, port)
+    )
+}
+
+/// Adds the key the server presented to `known_hosts`, if it has the fingerprint the
+/// user confirmed. A changed key is not replaced, the old entry has to be removed by hand.
+///
+/// # Returns
+/// * `Ok(HostKeyInfo)` - The key, now trusted
+/// * `Err(String)` - If the fingerprint differs, the key changed or the file cannot be written
+pub fn trust(session: &Session, known_hosts: Option<&Path>, host: &str, port: u16, fingerprint: &str) -> Result<HostKeyInfo, String> {
+    let info = inspect(session, known_hosts, host, port)?;
+    match info.status {
+        HostKeyStatus::Trusted => return Ok(info),
+        HostKeyStatus::Changed => return Err(changed_error(host, port)),
+        HostKeyStatus::Unknown => {}
+    }
+    if info.fingerprint != fingerprint {
+        return Err(format!(
+            "The host key of {} has the fingerprint {}, not {}",
+            host_pattern(host, port),
+            info.fingerprint,
+            fingerprint
+        ));
+    }
+
+    let path = known_hosts.ok_or_else(|| "No known_hosts file to add the host key to".to_string())?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let (key, _) = session.host_key().ok_or_else(|| "The server sent no host key".to_string())?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| e.to_string())?;
+    writeln!(file, "{}", known_hosts_line(host, port, &info.key_type, key)).map_err(|e| e.to_string())?;
+    Ok(HostKeyInfo { status: HostKeyStatus::Trusted, ..info })
+}
+
+#[cfg(test)]
+mod tests_host_keys {
+    use super::*;
+
+    /// The blob of an ed25519 public key
+    fn ed25519_key(seed: u8) -> Vec<u8> {
+        let mut key = Vec::new();
+        key.extend_from_slice(&11u32.to_be_bytes());
+        key.extend_from_slice(b"ssh-ed25519");
+        key.extend_from_slice(&32u32.to_be_bytes());
+        key.extend_from_slice(&[seed; 32]);
+        key
+    }
+
+    #[test]
+    fn test_check_known_hosts() {
+        let session = Session::new().unwrap();
+        let key = ed25519_key(1);
+        let content = format!(
+            "# comment\n@cert-authority *.example.com ssh-ed25519 AAAA\n{}\n{}\n",
+            known_hosts_line("example.com", 22, "ssh-ed25519", &key),
+            known_hosts_line("other.com", 2222, "ssh-ed25519", &ed25519_key(2)),
+        );
+
+        let check = |host, port, key: &[u8]| check_known_hosts(&session, &content, host, port, key).unwrap();
+        assert_eq!(check("example.com", 22, &key), HostKeyStatus::Trusted);
+        assert_eq!(check("example.com", 22, &ed25519_key(3)), HostKeyStatus::Changed);
+        assert_eq!(check("unknown.com", 22, &key), HostKeyStatus::Unknown);
+        assert_eq!(check("other.com", 2222, &ed25519_key(2)), HostKeyStatus::Trusted);
+        assert_eq!(check("other.com", 2222, &key), HostKeyStatus::Changed);
+    }
+
+    #[test]
+    fn test_known_hosts_line_and_fingerprint() {
+        let key = ed25519_key(1);
+        assert!(known_hosts_line("example.com", 22, "ssh-ed25519", &key).starts_with("example.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5"));
+        assert!(known_hosts_line("example.com", 2222, "ssh-ed25519", &key).starts_with("[example.com]:2222 "));
+
+        let fingerprint = fingerprint(&key);
+        assert!(fingerprint.starts_with("SHA256:"));
+        assert_eq!(fingerprint.len(), "SHA256:".len() + 43);
+    }
+}
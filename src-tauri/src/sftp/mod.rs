@@ -0,0 +1,35 @@
+//! Setting up SSH connections for SFTP: resolving `~/.ssh/config` aliases, verifying host
+//! keys against `~/.ssh/known_hosts`, authenticating and storing the secrets of saved connections.
+
+pub(crate) mod auth;
+pub(crate) mod host_keys;
+pub(crate) mod secret_store;
+pub(crate) mod ssh_config;
+
+use ssh2::Session;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Opens a TCP connection and runs the SSH handshake, nothing is authenticated yet.
+/// The host key must be checked with `host_keys` before any credentials are sent.
+pub fn handshake(host: &str, port: u16, timeout: Duration) -> Result<Session, String> {
+    let tcp = connect(host, port, timeout)?;
+    let mut session = Session::new().map_err(|_| "Could not initialize session".to_string())?;
+    session.set_tcp_stream(tcp);
+    session.set_timeout(timeout.as_millis() as u32);
+    session.handshake().map_err(|e| e.to_string())?;
+    Ok(session)
+}
+
+/// Tries every address of `host` in turn, each within `timeout`, so an unreachable server
+/// does not block for the timeout of the operating system.
+fn connect(host: &str, port: u16, timeout: Duration) -> Result<TcpStream, String> {
+    let mut last_error = format!("Could not resolve host: {}", host);
+    for address in (host, port).to_socket_addrs().map_err(|e| e.to_string())? {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(tcp) => return Ok(tcp),
+            Err(err) => last_error = err.to_string(),
+        }
+    }
+    Err(last_error)
+}
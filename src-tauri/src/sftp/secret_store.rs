@@ -0,0 +1,379 @@
+//! Encrypted storage for the passwords and passphrases of saved connections.
+//!
+//! The secrets are kept as one JSON map, encrypted and authenticated with AES-256-GCM.
+//! The key is random and kept in the credential store of the OS (Keychain, Credential
+//! Manager or Secret Service), so copying the config directory, backups of single files
+//! or logs never expose the secrets. Systems without a credential store keep the key in a
+//! file that only the user can read instead.
+
+use crate::log_warn;
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use keyring::Entry;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Marks the file format, so other files are not mistaken for a store
+const MAGIC: &[u8; 4] = b"EXS2";
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+/// Where the key of a store is kept.
+pub struct KeyLocation {
+    /// The entry in the credential store of the OS, `None` to only use the file
+    entry: Option<Entry>,
+    /// The key file, used while there is no credential store to put the key in
+    fallback_path: PathBuf,
+}
+
+impl KeyLocation {
+    /// The key in the credential store of the OS under `service` and `user`. The file at
+    /// `fallback_path` is used where the credential store cannot be reached, a key found
+    /// there is moved into the credential store once it can.
+    pub fn credential_store(service: &str, user: &str, fallback_path: PathBuf) -> Self {
+        match Entry::new(service, user) {
+            Ok(entry) => Self { entry: Some(entry), fallback_path },
+            Err(err) => {
+                log_warn!("No credential store for the secret store key: {}", err);
+                Self::file(fallback_path)
+            }
+        }
+    }
+
+    /// The key in the file at `path` only.
+    pub fn file(path: PathBuf) -> Self {
+        Self { entry: None, fallback_path: path }
+    }
+
+    /// Loads the key. A new one is only made if `may_create` is set, so a store sealed with
+    /// a key that cannot be reached right now is never replaced.
+    fn load(&self, may_create: bool) -> io::Result<[u8; KEY_LEN]> {
+        let Some(entry) = &self.entry else {
+            return self.load_file(may_create);
+        };
+        match entry.get_secret() {
+            Ok(key) => parse_key(key),
+            Err(keyring::Error::NoEntry) => match read_key_file(&self.fallback_path)? {
+                Some(key) => {
+                    // Saved while there was no credential store, move it there
+                    if entry.set_secret(&key).is_ok() {
+                        fs::remove_file(&self.fallback_path)?;
+                    }
+                    Ok(key)
+                }
+                None if may_create => self.create(),
+                None => Err(io::Error::new(io::ErrorKind::InvalidData, "The key of the secret store is missing")),
+            },
+            Err(err @ (keyring::Error::PlatformFailure(_) | keyring::Error::NoStorageAccess(_))) => {
+                log_warn!("Credential store unavailable, using the key file of the secret store: {}", err);
+                match read_key_file(&self.fallback_path)? {
+                    Some(key) => Ok(key),
+                    None if may_create => self.create_file(),
+                    // The key may be in the credential store, so the store must not be replaced
+                    None => Err(io::Error::other(format!("The key of the secret store is unavailable: {}", err))),
+                }
+            }
+            Err(err) => Err(io::Error::other(format!("Failed to read the secret store key: {}", err))),
+        }
+    }
+
+    fn load_file(&self, may_create: bool) -> io::Result<[u8; KEY_LEN]> {
+        match read_key_file(&self.fallback_path)? {
+            Some(key) => Ok(key),
+            None if may_create => self.create_file(),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData, "The key of the secret store is missing")),
+        }
+    }
+
+    /// Stores a new random key, in the credential store if there is one.
+    fn create(&self) -> io::Result<[u8; KEY_LEN]> {
+        if let Some(entry) = &self.entry {
+            let key = new_key();
+            match entry.set_secret(&key) {
+                Ok(()) => {
+                    if let Err(err) = fs::remove_file(&self.fallback_path) {
+                        if err.kind() != io::ErrorKind::NotFound {
+                            return Err(err);
+                        }
+                    }
+                    return Ok(key);
+                }
+                Err(err) => log_warn!("Failed to store the secret store key in the credential store: {}", err),
+            }
+        }
+        self.create_file()
+    }
+
+    fn create_file(&self) -> io::Result<[u8; KEY_LEN]> {
+        let key = new_key();
+        write_private(&self.fallback_path, &key)?;
+        Ok(key)
+    }
+}
+
+fn new_key() -> [u8; KEY_LEN] {
+    let mut key = [0u8; KEY_LEN];
+    OsRng.fill_bytes(&mut key);
+    key
+}
+
+fn parse_key(bytes: Vec<u8>) -> io::Result<[u8; KEY_LEN]> {
+    bytes
+        .try_into()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "The key of the secret store is damaged"))
+}
+
+fn read_key_file(path: &Path) -> io::Result<Option<[u8; KEY_LEN]>> {
+    match fs::read(path) {
+        Ok(bytes) => parse_key(bytes).map(Some),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Where a store is persisted and the key it is encrypted with.
+struct StoreFile {
+    path: PathBuf,
+    key: [u8; KEY_LEN],
+}
+
+/// Secrets by id. The default store is only kept in memory, one opened from a file writes
+/// every change to it right away.
+#[derive(Default)]
+pub struct SecretStore {
+    secrets: HashMap<String, String>,
+    file: Option<StoreFile>,
+}
+
+impl SecretStore {
+    /// Opens the store at `path`. The store and its key are created on first use.
+    ///
+    /// # Returns
+    /// * `Ok(SecretStore)` - The store with its secrets
+    /// * `Err(io::Error)` - If the store or key cannot be read, or the store was changed or
+    ///   belongs to another key (`InvalidData`)
+    pub fn open(path: PathBuf, key: &KeyLocation) -> io::Result<Self> {
+        let sealed = match fs::read(&path) {
+            Ok(sealed) => Some(sealed),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+        let key = key.load(sealed.is_none())?;
+        let secrets = match sealed {
+            Some(sealed) => serde_json::from_slice(&unseal(&key, &sealed)?)?,
+            None => HashMap::new(),
+        };
+        Ok(Self { secrets, file: Some(StoreFile { path, key }) })
+    }
+
+    /// Starts an empty store at `path` with a new key, which replaces the store there on
+    /// the next change. Used when the existing store cannot be decrypted anymore.
+    pub fn replace(path: PathBuf, key: &KeyLocation) -> io::Result<Self> {
+        let key = key.create()?;
+        Ok(Self { secrets: HashMap::new(), file: Some(StoreFile { path, key }) })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.secrets.get(id).map(String::as_str)
+    }
+
+    pub fn set(&mut self, id: &str, secret: String) -> io::Result<()> {
+        self.secrets.insert(id.to_string(), secret);
+        self.save()
+    }
+
+    pub fn remove(&mut self, id: &str) -> io::Result<()> {
+        if self.secrets.remove(id).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let sealed = seal(&file.key, &serde_json::to_vec(&self.secrets)?);
+        write_private(&file.path, &sealed)
+    }
+}
+
+/// Writes a file only the user can read. The content is written to a temporary file
+/// with a unique name next to it and moved into place, so a crash never leaves half a
+/// file and the store and its key never share a temporary file.
+fn write_private(path: &Path, content: &[u8]) -> io::Result<()> {
+    let parent = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(parent)?;
+    // Created readable by the user only on Unix
+    let mut file = tempfile::NamedTempFile::new_in(parent)?;
+    file.write_all(content)?;
+    file.as_file().sync_all()?;
+    file.persist(path).map_err(|err| err.error)?;
+    Ok(())
+}
+
+/// `MAGIC | nonce | ciphertext and tag`, the magic is authenticated as well
+fn seal(key: &[u8; KEY_LEN], plaintext: &[u8]) -> Vec<u8> {
+    let cipher = Aes256Gcm::new(key.into());
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad: MAGIC })
+        .expect("AES-GCM encrypts messages of any size kept in memory");
+
+    let mut sealed = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    sealed
+}
+
+fn unseal(key: &[u8; KEY_LEN], sealed: &[u8]) -> io::Result<Vec<u8>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "The secret store is damaged or belongs to another key");
+    if sealed.len() < MAGIC.len() + NONCE_LEN + TAG_LEN || !sealed.starts_with(MAGIC) {
+        return Err(invalid());
+    }
+    let (nonce, ciphertext) = sealed[MAGIC.len()..].split_at(NONCE_LEN);
+    Aes256Gcm::new(key.into())
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: MAGIC })
+        .map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests_secret_store {
+    use super::*;
+    use keyring::mock::MockCredential;
+    use tempfile::tempdir;
+
+    /// A credential store entry that lives as long as the location
+    fn mock_location(fallback_path: PathBuf) -> KeyLocation {
+        KeyLocation {
+            entry: Some(Entry::new_with_credential(Box::new(MockCredential::default()))),
+            fallback_path,
+        }
+    }
+
+    #[test]
+    fn test_secrets_persist_encrypted() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config").join("secrets.bin");
+        let key_path = dir.path().join("config").join("secrets.key");
+        let key = KeyLocation::file(key_path.clone());
+
+        let mut store = SecretStore::open(path.clone(), &key).unwrap();
+        store.set("nas", "correct horse battery staple".to_string()).unwrap();
+        store.set("work", "hunter2".to_string()).unwrap();
+        store.remove("work").unwrap();
+
+        let content = fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&content).contains("correct horse"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&key_path).unwrap().permissions().mode() & 0o777, 0o600);
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        let store = SecretStore::open(path, &key).unwrap();
+        assert_eq!(store.get("nas"), Some("correct horse battery staple"));
+        assert_eq!(store.get("work"), None);
+        // No temporary files are left next to the store
+        let mut names: Vec<_> = fs::read_dir(dir.path().join("config"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["secrets.bin", "secrets.key"]);
+    }
+
+    #[test]
+    fn test_tampered_store_or_other_key_is_refused() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("secrets.bin");
+        let key = KeyLocation::file(dir.path().join("secrets.key"));
+        SecretStore::open(path.clone(), &key).unwrap().set("nas", "secret".to_string()).unwrap();
+
+        // Another key is never made for an existing store
+        let other_key = KeyLocation::file(dir.path().join("other.key"));
+        let err = SecretStore::open(path.clone(), &other_key).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(!dir.path().join("other.key").exists());
+
+        let mut content = fs::read(&path).unwrap();
+        let index = MAGIC.len() + NONCE_LEN;
+        content[index] ^= 1;
+        fs::write(&path, content).unwrap();
+        let err = SecretStore::open(path.clone(), &key).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // A replaced store is usable again
+        SecretStore::replace(path.clone(), &key).unwrap().set("nas", "new".to_string()).unwrap();
+        assert_eq!(SecretStore::open(path, &key).unwrap().get("nas"), Some("new"));
+    }
+
+    #[test]
+    fn test_key_is_kept_in_the_credential_store() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("secrets.bin");
+        let key_path = dir.path().join("secrets.key");
+
+        // A key file from a time without credential store is moved into it
+        SecretStore::open(path.clone(), &KeyLocation::file(key_path.clone()))
+            .unwrap()
+            .set("nas", "secret".to_string())
+            .unwrap();
+        let key = mock_location(key_path.clone());
+        assert_eq!(SecretStore::open(path.clone(), &key).unwrap().get("nas"), Some("secret"));
+        assert!(!key_path.exists());
+        assert_eq!(SecretStore::open(path.clone(), &key).unwrap().get("nas"), Some("secret"));
+
+        // New keys go to the credential store only
+        let path = dir.path().join("new.bin");
+        let key = mock_location(dir.path().join("new.key"));
+        SecretStore::open(path.clone(), &key).unwrap().set("nas", "secret".to_string()).unwrap();
+        assert!(!dir.path().join("new.key").exists());
+        assert_eq!(SecretStore::open(path, &key).unwrap().get("nas"), Some("secret"));
+    }
+
+    #[test]
+    fn test_unavailable_credential_store_falls_back_to_the_key_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("secrets.bin");
+        let key_path = dir.path().join("secrets.key");
+        let key = mock_location(key_path.clone());
+        let unavailable = || keyring::Error::PlatformFailure("no Secret Service".into());
+
+        // A store that does not exist yet gets a key file
+        let mock: &MockCredential = key.entry.as_ref().unwrap().get_credential().downcast_ref().unwrap();
+        mock.set_error(unavailable());
+        SecretStore::open(path.clone(), &key).unwrap().set("nas", "secret".to_string()).unwrap();
+        assert!(key_path.exists());
+
+        // An existing store whose key cannot be reached is not reported as undecryptable,
+        // so it is not replaced
+        let missing = mock_location(dir.path().join("missing.key"));
+        let mock: &MockCredential = missing.entry.as_ref().unwrap().get_credential().downcast_ref().unwrap();
+        mock.set_error(unavailable());
+        let err = SecretStore::open(path.clone(), &missing).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert_eq!(SecretStore::open(path, &KeyLocation::file(key_path)).unwrap().get("nas"), Some("secret"));
+    }
+
+    #[test]
+    fn test_seal_round_trip() {
+        let key = [7u8; KEY_LEN];
+        let plaintext = b"a secret that spans more than one AES block".to_vec();
+        let sealed = seal(&key, &plaintext);
+        assert_ne!(&sealed[MAGIC.len() + NONCE_LEN..][..plaintext.len()], &plaintext[..]);
+        assert_eq!(unseal(&key, &sealed).unwrap(), plaintext);
+        // A new nonce every time
+        assert_ne!(seal(&key, &plaintext), sealed);
+        assert!(unseal(&key, &sealed[..10]).is_err());
+        assert!(unseal(&[8u8; KEY_LEN], &sealed).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+//! Host aliases from the OpenSSH client config (`~/.ssh/config`). Only what is needed to
+//! connect is read: `HostName`, `Port`, `User` and `IdentityFile`. `Match` blocks and
+//! `Include` are not supported and left out.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The settings of the config that apply to a host.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HostConfig {
+    pub host_name: Option<String>,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    pub identity_files: Vec<PathBuf>,
+}
+
+/// Reads the settings for `alias` from `ssh_dir/config`. A missing config has no settings.
+pub fn lookup(ssh_dir: Option<&Path>, alias: &str) -> HostConfig {
+    let Some(ssh_dir) = ssh_dir else {
+        return HostConfig::default();
+    };
+    match fs::read_to_string(ssh_dir.join("config")) {
+        Ok(content) => parse(&content, alias, ssh_dir.parent()),
+        Err(_) => HostConfig::default(),
+    }
+}
+
+/// Collects the settings for `alias`. Like OpenSSH, the first value of a setting wins,
+/// except for `IdentityFile`, which can be given more than once.
+///
+/// # Arguments
+/// * `home` - The directory `~` in paths stands for
+pub fn parse(content: &str, alias: &str, home: Option<&Path>) -> HostConfig {
+    let mut config = HostConfig::default();
+    // Settings before the first `Host` apply to all hosts
+    let mut applies = true;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (keyword, value) = match line.split_once(|c: char| c.is_whitespace() || c == '=') {
+            Some((keyword, value)) => (keyword, value.trim_start_matches(|c: char| c.is_whitespace() || c == '=').trim()),
+            None => (line, ""),
+        };
+        let value = value.trim_matches('"');
+
+        match keyword.to_ascii_lowercase().as_str() {
+            "host" => applies = host_matches(value, alias),
+            "match" => applies = false,
+            _ if !applies => {}
+            "hostname" if config.host_name.is_none() => {
+                config.host_name = Some(value.replace("%h", alias).replace("%%", "%"));
+            }
+            "port" if config.port.is_none() => config.port = value.parse().ok(),
+            "user" if config.user.is_none() => config.user = Some(value.to_string()),
+            "identityfile" => config.identity_files.push(expand_home(value, home)),
+            _ => {}
+        }
+    }
+    config
+}
+
+/// Whether a `Host` line matches. A negated pattern (`!pattern`) that matches excludes
+/// the host even if another pattern matches.
+fn host_matches(patterns: &str, alias: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns.split_whitespace() {
+        match pattern.strip_prefix('!') {
+            Some(negated) if wildcard_match(negated.as_bytes(), alias.as_bytes()) => return false,
+            Some(_) => {}
+            None => matched |= wildcard_match(pattern.as_bytes(), alias.as_bytes()),
+        }
+    }
+    matched
+}
+
+/// Matches `*` and `?` wildcards, ignoring case like OpenSSH does for host names.
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| wildcard_match(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && wildcard_match(rest, &text[1..]),
+        Some((c, rest)) => {
+            text.first().is_some_and(|t| t.eq_ignore_ascii_case(c)) && wildcard_match(rest, &text[1..])
+        }
+    }
+}
+
+fn expand_home(path: &str, home: Option<&Path>) -> PathBuf {
+    match (path.strip_prefix("~/"), home) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path.replace("%d", &home.map(|home| home.to_string_lossy().to_string()).unwrap_or_default())),
+    }
+}
+
+#[cfg(test)]
+mod tests_ssh_config {
+    use super::*;
+
+    const CONFIG: &str = r#"
+# Global settings come first
+User fallback
+
+Host nas backup-*
+    HostName 192.168.1.20
+    Port 2222
+    IdentityFile ~/.ssh/nas_key
+
+Host *.internal !secret.internal
+    HostName %h.example.com
+    User admin
+
+Host work
+    HostName=work.example.com
+    Port = 8022
+    IdentityFile "/keys/work"
+    IdentityFile ~/.ssh/id_ed25519
+
+Host *
+    Port 2200
+    IdentityFile ~/.ssh/id_rsa
+"#;
+
+    fn lookup(alias: &str) -> HostConfig {
+        parse(CONFIG, alias, Some(Path::new("/home/user")))
+    }
+
+    #[test]
+    fn test_alias() {
+        let config = lookup("nas");
+        assert_eq!(config.host_name.as_deref(), Some("192.168.1.20"));
+        assert_eq!(config.port, Some(2222));
+        assert_eq!(config.user.as_deref(), Some("fallback"));
+        assert_eq!(
+            config.identity_files,
+            vec![PathBuf::from("/home/user/.ssh/nas_key"), PathBuf::from("/home/user/.ssh/id_rsa")]
+        );
+        assert_eq!(lookup("backup-1").host_name.as_deref(), Some("192.168.1.20"));
+    }
+
+    #[test]
+    fn test_equals_signs_and_quotes() {
+        let config = lookup("work");
+        assert_eq!(config.host_name.as_deref(), Some("work.example.com"));
+        assert_eq!(config.port, Some(8022));
+        assert_eq!(config.identity_files[..2], [PathBuf::from("/keys/work"), PathBuf::from("/home/user/.ssh/id_ed25519")]);
+    }
+
+    #[test]
+    fn test_wildcards_and_negation() {
+        let config = lookup("db.internal");
+        assert_eq!(config.host_name.as_deref(), Some("db.internal.example.com"));
+        // The global `User` comes first
+        assert_eq!(config.user.as_deref(), Some("fallback"));
+        assert_eq!(lookup("secret.internal").host_name, None);
+        assert_eq!(lookup("NAS").port, Some(2222));
+
+        let config = lookup("example.org");
+        assert_eq!(config.host_name, None);
+        assert_eq!(config.port, Some(2200));
+    }
+
+    #[test]
+    fn test_missing_config() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(super::lookup(Some(dir.path()), "nas"), HostConfig::default());
+        assert_eq!(super::lookup(None, "nas"), HostConfig::default());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::filesystem::disk_usage::{analyze_disk_usage, DiskUsageError, DiskUsageOptions, DiskUsageTree};
+use crate::models::VolumeInformation;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Minimum time between two partial trees of the same analysis
+const PROGRESS_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+pub type AnalysisId = u64;
+
+/// Receives the updates of disk usage analyses.
+///
+/// The commands forward these as Tauri events, tests collect them directly.
+pub type DiskUsageSink = Arc<dyn Fn(DiskUsageUpdate) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DiskUsageStatus {
+    /// The tree covers the part scanned so far, more updates follow
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// State of an analysis, sent as `disk-usage-updated` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskUsageUpdate {
+    pub analysis_id: AnalysisId,
+    pub path: String,
+    pub status: DiskUsageStatus,
+    /// The size tree, missing for cancelled and failed analyses
+    pub tree: Option<DiskUsageTree>,
+    /// The volume the path is located on
+    pub volume: Option<VolumeInformation>,
+    pub elapsed_ms: u64,
+    pub error: Option<String>,
+}
+
+/// Runs disk usage analyses in the background, each on its own thread.
+pub struct DiskUsageState {
+    next_id: AnalysisId,
+    /// Cancel flags of the running analyses
+    running: Arc<Mutex<HashMap<AnalysisId, Arc<AtomicBool>>>>,
+}
+
+impl Default for DiskUsageState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiskUsageState {
+    pub fn new() -> Self {
+        Self {
+            next_id: 1,
+            running: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Starts analyzing `path` in the background.
+    ///
+    /// # Returns
+    /// * `Ok(AnalysisId)` - The id that identifies the updates of this analysis
+    /// * `Err(String)` - If the state could not be locked
+    pub fn start(
+        &mut self,
+        path: String,
+        options: DiskUsageOptions,
+        volume: Option<VolumeInformation>,
+        sink: DiskUsageSink,
+    ) -> Result<AnalysisId, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.running
+            .lock()
+            .map_err(|_| "Failed to lock disk usage analyses")?
+            .insert(id, cancel.clone());
+
+        let running = self.running.clone();
+        thread::spawn(move || {
+            let started = Instant::now();
+            let update = |status, tree, error| DiskUsageUpdate {
+                analysis_id: id,
+                path: path.clone(),
+                status,
+                tree,
+                volume: volume.clone(),
+                elapsed_ms: started.elapsed().as_millis() as u64,
+                error,
+            };
+
+            let mut last_update = Instant::now();
+            let result = analyze_disk_usage(Path::new(&path), options, &cancel, |scan| {
+                if last_update.elapsed() >= PROGRESS_UPDATE_INTERVAL {
+                    last_update = Instant::now();
+                    sink(update(DiskUsageStatus::Running, Some(scan.snapshot()), None));
+                }
+            });
+
+            if let Ok(mut running) = running.lock() {
+                running.remove(&id);
+            }
+            sink(match result {
+                Ok(tree) => update(DiskUsageStatus::Completed, Some(tree), None),
+                Err(DiskUsageError::Cancelled) => update(DiskUsageStatus::Cancelled, None, None),
+                Err(DiskUsageError::Failed(err)) => update(DiskUsageStatus::Failed, None, Some(err)),
+            });
+        });
+        Ok(id)
+    }
+
+    /// Cancels a running analysis. It stops before the next directory and sends a final
+    /// update with the status `Cancelled`.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the analysis was cancelled
+    /// * `Err(String)` - If the analysis is unknown or has already finished
+    pub fn cancel(&self, id: AnalysisId) -> Result<(), String> {
+        let running = self.running.lock().map_err(|_| "Failed to lock disk usage analyses")?;
+        let cancel = running
+            .get(&id)
+            .ok_or_else(|| format!("No running disk usage analysis with id {}", id))?;
+        cancel.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_disk_usage_data {
+    use super::*;
+    use std::fs;
+    use std::sync::mpsc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_analysis_sends_final_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join("logs")).unwrap();
+        fs::write(temp_dir.path().join("logs/app.log"), vec![0u8; 300]).unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let sink: DiskUsageSink = Arc::new(move |update| {
+            let _ = sender.lock().unwrap().send(update);
+        });
+        let mut state = DiskUsageState::new();
+        let path = temp_dir.path().to_string_lossy().to_string();
+        let id = state.start(path.clone(), DiskUsageOptions::default(), None, sink).unwrap();
+
+        let update = loop {
+            let update: DiskUsageUpdate = receiver.recv_timeout(Duration::from_secs(20)).unwrap();
+            if update.status != DiskUsageStatus::Running {
+                break update;
+            }
+        };
+        assert_eq!(update.analysis_id, id);
+        assert_eq!(update.status, DiskUsageStatus::Completed);
+        let tree = update.tree.unwrap();
+        assert_eq!(tree.root.size_in_bytes, 300);
+        assert_eq!(tree.root.children[0].name, "logs");
+
+        // Finished analyses cannot be cancelled
+        assert!(state.cancel(id).is_err());
+        assert!(state.running.lock().unwrap().is_empty());
+    }
+}
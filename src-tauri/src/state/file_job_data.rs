@@ -0,0 +1,1038 @@
+use crate::filesystem::archive::{ArchiveOptions, ExtractOptions};
+use crate::filesystem::conflict_policy::{
+    ConflictChoice, ConflictDecision, ConflictPolicy, ConflictRecord, FileConflict,
+};
+use crate::filesystem::file_jobs;
+#[allow(unused_imports)]
+use crate::{log_error, log_info, log_warn};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::{BTreeMap, VecDeque};
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Number of jobs that run at the same time, further jobs wait in the queue
+pub const MAX_CONCURRENT_JOBS: usize = 2;
+
+/// Minimum time between two progress events of the same job
+const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often a job waiting for a conflict decision checks whether it was cancelled
+const DECISION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Number of finished jobs kept for `get_file_jobs` before the oldest are dropped
+const MAX_FINISHED_JOBS: usize = 100;
+
+pub type JobId = u64;
+
+/// Receives the progress and completion events of a job.
+///
+/// The commands forward these as Tauri events, tests collect them directly.
+pub type JobEventSink = Arc<dyn Fn(JobEvent) + Send + Sync>;
+
+/// The file operation a job performs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Copy,
+    Zip,
+    Unzip,
+    Trash,
+}
+
+/// Lifecycle of a job. `Completed`, `Failed` and `Cancelled` are final.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Paused,
+    /// Every item was processed successfully
+    Completed,
+    /// At least one item failed, the others may have succeeded
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
+/// The work a job should do, with the same inputs as the matching direct command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobRequest {
+    /// Copies every source into the destination directory
+    Copy {
+        sources: Vec<String>,
+        destination_dir: String,
+        conflict_policy: ConflictPolicy,
+    },
+    Zip { sources: Vec<String>, destination: Option<String>, options: ArchiveOptions },
+    Unzip { archives: Vec<String>, destination: Option<String>, options: ExtractOptions },
+    Trash { paths: Vec<String> },
+}
+
+impl JobRequest {
+    pub fn kind(&self) -> JobKind {
+        match self {
+            JobRequest::Copy { .. } => JobKind::Copy,
+            JobRequest::Zip { .. } => JobKind::Zip,
+            JobRequest::Unzip { .. } => JobKind::Unzip,
+            JobRequest::Trash { .. } => JobKind::Trash,
+        }
+    }
+
+    /// The top level items of the job, each reported separately in the summary.
+    pub fn items(&self) -> &[String] {
+        match self {
+            JobRequest::Copy { sources, .. } => sources,
+            JobRequest::Zip { sources, .. } => sources,
+            JobRequest::Unzip { archives, .. } => archives,
+            JobRequest::Trash { paths } => paths,
+        }
+    }
+}
+
+/// Progress of a job, sent as `file-job-progress` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobProgress {
+    pub job_id: JobId,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub files_done: u64,
+    pub files_total: u64,
+    pub current_path: Option<String>,
+    pub estimated_time_remaining: Option<u64>, // in milliseconds
+}
+
+/// Outcome of a single top level item of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum JobItemStatus {
+    Completed,
+    Failed,
+    Skipped,
+    Cancelled,
+}
+
+/// Result of a single top level item (a copied folder, a zipped file, a trashed path, ...).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobItemResult {
+    pub source: String,
+    /// Where the item ended up, if it was written somewhere
+    pub destination: Option<String>,
+    pub status: JobItemStatus,
+    pub files: u64,
+    pub bytes: u64,
+    pub error: Option<String>,
+    /// Existing destinations that were found and how each was resolved
+    pub conflicts: Vec<ConflictRecord>,
+}
+
+impl JobItemResult {
+    pub fn new(source: &str, status: JobItemStatus) -> Self {
+        Self {
+            source: source.to_string(),
+            destination: None,
+            status,
+            files: 0,
+            bytes: 0,
+            error: None,
+            conflicts: Vec::new(),
+        }
+    }
+}
+
+/// Final report of a job, sent as `file-job-finished` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobSummary {
+    pub job_id: JobId,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub items: Vec<JobItemResult>,
+    pub bytes_done: u64,
+    pub files_done: u64,
+    pub elapsed_ms: u64,
+}
+
+/// Current state of a job as returned by `get_file_jobs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobSnapshot {
+    pub progress: JobProgress,
+    /// Set once the job has finished
+    pub summary: Option<JobSummary>,
+}
+
+#[derive(Debug, Clone)]
+pub enum JobEvent {
+    Progress(JobProgress),
+    Finished(JobSummary),
+    /// A conflict waiting for `FileJobState::resolve_conflict`
+    Conflict(FileConflict),
+}
+
+/// Returned by `JobContext::checkpoint` once a job has been cancelled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JobCancelled;
+
+/// The status changes of pause, resume and the start of a job are made while these are
+/// locked, so neither can overwrite the other.
+#[derive(Debug, Default)]
+struct ControlFlags {
+    paused: bool,
+    cancelled: bool,
+    /// Whether a worker has picked the job up, used to restore the status on resume
+    started: bool,
+}
+
+/// Pause and cancel flags shared between the job manager and the worker thread.
+#[derive(Debug, Default)]
+struct JobControl {
+    flags: Mutex<ControlFlags>,
+    changed: Condvar,
+}
+
+impl JobControl {
+    fn set(&self, update: impl FnOnce(&mut ControlFlags)) {
+        if let Ok(mut flags) = self.flags.lock() {
+            update(&mut flags);
+            self.changed.notify_all();
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.flags.lock().map(|flags| flags.cancelled).unwrap_or(true)
+    }
+
+    fn is_paused(&self) -> bool {
+        self.flags.lock().map(|flags| flags.paused).unwrap_or(false)
+    }
+
+    /// Blocks while the job is paused.
+    ///
+    /// # Returns
+    /// How long the call was blocked, or `JobCancelled` if the job was cancelled
+    fn wait_while_paused(&self) -> Result<Duration, JobCancelled> {
+        let start = Instant::now();
+        let mut flags = self.flags.lock().map_err(|_| JobCancelled)?;
+        while flags.paused && !flags.cancelled {
+            flags = self.changed.wait(flags).map_err(|_| JobCancelled)?;
+        }
+        if flags.cancelled {
+            return Err(JobCancelled);
+        }
+        Ok(start.elapsed())
+    }
+}
+
+/// A job known to the manager, shared with its worker thread.
+struct JobHandle {
+    id: JobId,
+    control: JobControl,
+    progress: Mutex<JobProgress>,
+    summary: Mutex<Option<JobSummary>>,
+    /// The conflict the job is waiting on and where to send the decision
+    pending_conflict: Mutex<Option<(u64, mpsc::Sender<ConflictDecision>)>>,
+    sink: JobEventSink,
+}
+
+impl JobHandle {
+    fn new(progress: JobProgress, sink: JobEventSink) -> Self {
+        Self {
+            id: progress.job_id,
+            control: JobControl::default(),
+            progress: Mutex::new(progress),
+            summary: Mutex::new(None),
+            pending_conflict: Mutex::new(None),
+            sink,
+        }
+    }
+
+    fn snapshot(&self) -> Option<JobSnapshot> {
+        Some(JobSnapshot {
+            progress: self.progress.lock().ok()?.clone(),
+            summary: self.summary.lock().ok()?.clone(),
+        })
+    }
+
+    fn set_status(&self, status: JobStatus) -> Option<JobProgress> {
+        let mut progress = self.progress.lock().ok()?;
+        progress.status = status;
+        Some(progress.clone())
+    }
+
+    fn finish(&self, summary: JobSummary) {
+        // Under the control lock, so a pause cannot turn the finished job into a paused one
+        let mut finished = None;
+        self.control.set(|_| {
+            if let Ok(mut progress) = self.progress.lock() {
+                progress.status = summary.status;
+                progress.current_path = None;
+                progress.estimated_time_remaining = None;
+                finished = Some(progress.clone());
+            }
+        });
+        if let Some(progress) = finished {
+            (self.sink)(JobEvent::Progress(progress));
+        }
+        if let Ok(mut slot) = self.summary.lock() {
+            *slot = Some(summary.clone());
+        }
+        (self.sink)(JobEvent::Finished(summary));
+    }
+}
+
+/// Handed to the job implementations in `filesystem::file_jobs` to report
+/// progress, to honour pause and cancel requests and to ask for conflict decisions.
+///
+/// # Example
+/// ```rust
+/// ctx.set_totals(files, bytes);
+/// for chunk in chunks {
+///     ctx.checkpoint()?;
+///     write(chunk);
+///     ctx.add_bytes(chunk.len() as u64);
+/// }
+/// ctx.file_done();
+/// ```
+pub struct JobContext {
+    handle: Arc<JobHandle>,
+    started: Instant,
+    paused_for: Cell<Duration>,
+    last_event: Cell<Option<Instant>>,
+    next_conflict_id: Cell<u64>,
+    /// Set once the user answered a conflict with "apply to all"
+    remembered_choice: Cell<Option<ConflictChoice>>,
+}
+
+impl JobContext {
+    fn new(handle: Arc<JobHandle>) -> Self {
+        Self {
+            handle,
+            started: Instant::now(),
+            paused_for: Cell::new(Duration::ZERO),
+            last_event: Cell::new(None),
+            next_conflict_id: Cell::new(1),
+            remembered_choice: Cell::new(None),
+        }
+    }
+
+    /// A context that is not registered as job, for running the job
+    /// implementations directly from a command. It cannot be paused or
+    /// cancelled, and nobody answers its conflict questions, so it must not
+    /// be used with `ConflictPolicy::Ask`.
+    pub fn detached() -> Self {
+        let progress = JobProgress {
+            job_id: 0,
+            kind: JobKind::Copy,
+            status: JobStatus::Running,
+            bytes_done: 0,
+            bytes_total: 0,
+            files_done: 0,
+            files_total: 0,
+            current_path: None,
+            estimated_time_remaining: None,
+        };
+        Self::new(Arc::new(JobHandle::new(progress, Arc::new(|_| {}))))
+    }
+
+    /// Blocks while the job is paused and fails once it is cancelled.
+    /// Call this between units of work, e.g. before every copied chunk.
+    pub fn checkpoint(&self) -> Result<(), JobCancelled> {
+        let waited = self.handle.control.wait_while_paused()?;
+        if !waited.is_zero() {
+            self.paused_for.set(self.paused_for.get() + waited);
+        }
+        Ok(())
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.handle.control.is_cancelled()
+    }
+
+    /// Sets the totals found while scanning the job's input.
+    pub fn set_totals(&self, files_total: u64, bytes_total: u64) {
+        self.update(true, |progress| {
+            progress.files_total = files_total;
+            progress.bytes_total = bytes_total;
+        });
+    }
+
+    pub fn set_current_path(&self, path: &str) {
+        self.update(false, |progress| progress.current_path = Some(path.to_string()));
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        self.update(false, |progress| progress.bytes_done += bytes);
+    }
+
+    pub fn file_done(&self) {
+        self.update(false, |progress| progress.files_done += 1);
+    }
+
+    /// Counts files that were skipped as processed, so the progress still reaches its total.
+    pub fn skip(&self, files: u64, bytes: u64) {
+        self.update(false, |progress| {
+            progress.files_done += files;
+            progress.bytes_done += bytes;
+        });
+    }
+
+    /// Sends a `JobEvent::Conflict` and blocks until the decision arrives
+    /// through `FileJobState::resolve_conflict`. After a decision with
+    /// `apply_to_all` further conflicts are answered without asking.
+    ///
+    /// # Arguments
+    /// * `source` - The path that should be written
+    /// * `destination` - The path that already exists
+    ///
+    /// # Returns
+    /// The chosen resolution, or `JobCancelled` if the job was cancelled while waiting
+    pub fn ask_conflict(&self, source: &Path, destination: &Path) -> Result<ConflictChoice, JobCancelled> {
+        if let Some(choice) = self.remembered_choice.get() {
+            return Ok(choice);
+        }
+
+        let conflict_id = self.next_conflict_id.get();
+        self.next_conflict_id.set(conflict_id + 1);
+
+        let (sender, receiver) = mpsc::channel();
+        *self.handle.pending_conflict.lock().map_err(|_| JobCancelled)? = Some((conflict_id, sender));
+        (self.handle.sink)(JobEvent::Conflict(FileConflict::new(
+            self.handle.id,
+            conflict_id,
+            source,
+            destination,
+        )));
+
+        let waiting_since = Instant::now();
+        let result = loop {
+            match receiver.recv_timeout(DECISION_POLL_INTERVAL) {
+                Ok(decision) => {
+                    if decision.apply_to_all {
+                        self.remembered_choice.set(Some(decision.choice));
+                    }
+                    break Ok(decision.choice);
+                }
+                Err(RecvTimeoutError::Timeout) if !self.is_cancelled() => continue,
+                Err(_) => break Err(JobCancelled),
+            }
+        };
+
+        // Time spent waiting for the user does not count towards the estimate
+        self.paused_for.set(self.paused_for.get() + waiting_since.elapsed());
+        if let Ok(mut pending) = self.handle.pending_conflict.lock() {
+            *pending = None;
+        }
+        result
+    }
+
+    /// Time the job has been running, excluding time spent paused.
+    fn active_time(&self) -> Duration {
+        self.started.elapsed().saturating_sub(self.paused_for.get())
+    }
+
+    fn update(&self, force_event: bool, change: impl FnOnce(&mut JobProgress)) {
+        let Ok(mut progress) = self.handle.progress.lock() else {
+            return;
+        };
+        change(&mut progress);
+
+        let due = self
+            .last_event
+            .get()
+            .is_none_or(|last| last.elapsed() >= PROGRESS_EVENT_INTERVAL);
+        if !(force_event || due) {
+            return;
+        }
+
+        progress.estimated_time_remaining = estimate_remaining(
+            progress.bytes_done,
+            progress.bytes_total,
+            progress.files_done,
+            progress.files_total,
+            self.active_time(),
+        );
+        let event = progress.clone();
+        drop(progress);
+
+        self.last_event.set(Some(Instant::now()));
+        (self.handle.sink)(JobEvent::Progress(event));
+    }
+}
+
+/// Estimates the remaining time from the throughput so far, by bytes if the
+/// total size is known and by files otherwise.
+///
+/// # Returns
+/// The remaining time in milliseconds, or `None` while there is not enough data
+fn estimate_remaining(
+    bytes_done: u64,
+    bytes_total: u64,
+    files_done: u64,
+    files_total: u64,
+    active: Duration,
+) -> Option<u64> {
+    let (done, total) = if bytes_total > 0 {
+        (bytes_done, bytes_total)
+    } else {
+        (files_done, files_total)
+    };
+    if done == 0 || total == 0 || active.is_zero() {
+        return None;
+    }
+
+    let remaining = total.saturating_sub(done) as f64;
+    let per_unit_ms = active.as_secs_f64() * 1000.0 / done as f64;
+    Some((remaining * per_unit_ms) as u64)
+}
+
+#[derive(Default)]
+struct JobQueue {
+    /// All known jobs by id, in submission order
+    jobs: BTreeMap<JobId, Arc<JobHandle>>,
+    pending: VecDeque<(JobId, JobRequest)>,
+    running: usize,
+    next_id: JobId,
+}
+
+/// Queue of long running file operations (copy, zip, unzip, trash).
+///
+/// Jobs are started in submission order, at most `MAX_CONCURRENT_JOBS` at a time,
+/// each on its own thread. Every job reports its progress through the event sink
+/// it was submitted with and can be paused, resumed and cancelled by id. Finished
+/// jobs are kept with their summary until cleared.
+///
+/// # Example
+/// ```rust
+/// let jobs = FileJobState::new();
+/// let id = jobs.submit(
+///     JobRequest::Copy {
+///         sources: vec!["/data/photos".into()],
+///         destination_dir: "/backup".into(),
+///         conflict_policy: ConflictPolicy::Rename,
+///     },
+///     sink,
+/// )?;
+/// jobs.pause(id)?;
+/// jobs.resume(id)?;
+/// ```
+pub struct FileJobState {
+    queue: Arc<Mutex<JobQueue>>,
+}
+
+impl Default for FileJobState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileJobState {
+    pub fn new() -> Self {
+        Self {
+            queue: Arc::new(Mutex::new(JobQueue {
+                next_id: 1,
+                ..Default::default()
+            })),
+        }
+    }
+
+    /// Adds a job to the queue and starts it if a worker slot is free.
+    ///
+    /// # Arguments
+    /// * `request` - The operation to run
+    /// * `sink` - Receives the progress and completion events of the job
+    ///
+    /// # Returns
+    /// * `Ok(JobId)` - The id used to control and query the job
+    /// * `Err(String)` - If the job queue is unavailable
+    pub fn submit(&self, request: JobRequest, sink: JobEventSink) -> Result<JobId, String> {
+        let mut queue = self.queue.lock().map_err(|_| "Failed to lock job queue")?;
+        let id = queue.next_id;
+        queue.next_id += 1;
+
+        let progress = JobProgress {
+            job_id: id,
+            kind: request.kind(),
+            status: JobStatus::Queued,
+            bytes_done: 0,
+            bytes_total: 0,
+            files_done: 0,
+            files_total: request.items().len() as u64,
+            current_path: None,
+            estimated_time_remaining: None,
+        };
+        let handle = Arc::new(JobHandle::new(progress.clone(), sink));
+        (handle.sink)(JobEvent::Progress(progress));
+
+        queue.jobs.insert(id, handle);
+        queue.pending.push_back((id, request));
+        prune_finished(&mut queue);
+        start_pending(&self.queue, &mut queue);
+
+        log_info!("Queued file job {}", id);
+        Ok(id)
+    }
+
+    /// Pauses a queued or running job. A running job stops at its next checkpoint,
+    /// usually within one copied chunk.
+    pub fn pause(&self, id: JobId) -> Result<(), String> {
+        let handle = self.handle(id)?;
+
+        // Checked under the control lock, which `finish` takes as well
+        let mut result = Err(format!("Failed to lock job {}", id));
+        handle.control.set(|flags| {
+            result = match handle.progress.lock().map(|progress| progress.status) {
+                Ok(status) if status.is_finished() => Err(format!("Job {} has already finished", id)),
+                Ok(_) => {
+                    flags.paused = true;
+                    Ok(handle.set_status(JobStatus::Paused))
+                }
+                Err(_) => Err(format!("Failed to lock job {}", id)),
+            };
+        });
+        if let Some(progress) = result? {
+            (handle.sink)(JobEvent::Progress(progress));
+        }
+        Ok(())
+    }
+
+    /// Resumes a paused job. A queued job is started right away if a worker slot is free.
+    pub fn resume(&self, id: JobId) -> Result<(), String> {
+        let handle = self.handle(id)?;
+
+        let mut result = Err(format!("Failed to lock job {}", id));
+        handle.control.set(|flags| {
+            result = match handle.progress.lock().map(|progress| progress.status) {
+                Ok(JobStatus::Paused) => {
+                    flags.paused = false;
+                    let status = if flags.started { JobStatus::Running } else { JobStatus::Queued };
+                    Ok(handle.set_status(status))
+                }
+                Ok(_) => Err(format!("Job {} is not paused", id)),
+                Err(_) => Err(format!("Failed to lock job {}", id)),
+            };
+        });
+        if let Some(progress) = result? {
+            (handle.sink)(JobEvent::Progress(progress));
+        }
+
+        // Workers skip paused jobs, so a slot may have been left free for this one
+        let mut queue = self.queue.lock().map_err(|_| "Failed to lock job queue")?;
+        start_pending(&self.queue, &mut queue);
+        Ok(())
+    }
+
+    /// Cancels a job. A queued job is removed from the queue right away, a
+    /// running job stops at its next checkpoint and removes partially written files.
+    pub fn cancel(&self, id: JobId) -> Result<(), String> {
+        let mut queue = self.queue.lock().map_err(|_| "Failed to lock job queue")?;
+        let handle = queue
+            .jobs
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown job: {}", id))?;
+        if handle.progress.lock().map_err(|_| "Failed to lock job")?.status.is_finished() {
+            return Err(format!("Job {} has already finished", id));
+        }
+
+        handle.control.set(|flags| flags.cancelled = true);
+
+        if let Some(position) = queue.pending.iter().position(|(pending_id, _)| *pending_id == id) {
+            let (_, request) = queue.pending.remove(position).expect("position is in range");
+            drop(queue);
+            handle.finish(JobSummary {
+                job_id: id,
+                kind: request.kind(),
+                status: JobStatus::Cancelled,
+                items: request
+                    .items()
+                    .iter()
+                    .map(|item| JobItemResult::new(item, JobItemStatus::Cancelled))
+                    .collect(),
+                bytes_done: 0,
+                files_done: 0,
+                elapsed_ms: 0,
+            });
+        }
+
+        log_info!("Cancelled file job {}", id);
+        Ok(())
+    }
+
+    /// Answers the conflict a job is currently waiting on.
+    ///
+    /// # Arguments
+    /// * `id` - The job that sent the `file-conflict` event
+    /// * `conflict_id` - The id from the event, so a late answer cannot resolve a newer conflict
+    /// * `decision` - How to resolve the conflict
+    pub fn resolve_conflict(&self, id: JobId, conflict_id: u64, decision: ConflictDecision) -> Result<(), String> {
+        let handle = self.handle(id)?;
+        let mut pending = handle.pending_conflict.lock().map_err(|_| "Failed to lock job")?;
+        match pending.take() {
+            Some((pending_id, sender)) if pending_id == conflict_id => sender
+                .send(decision)
+                .map_err(|_| format!("Job {} is no longer waiting for a decision", id)),
+            other => {
+                *pending = other;
+                Err(format!("Job {} is not waiting for conflict {}", id, conflict_id))
+            }
+        }
+    }
+
+    /// Returns the state of all known jobs, oldest first.
+    pub fn jobs(&self) -> Result<Vec<JobSnapshot>, String> {
+        let queue = self.queue.lock().map_err(|_| "Failed to lock job queue")?;
+        Ok(queue.jobs.values().filter_map(|handle| handle.snapshot()).collect())
+    }
+
+    pub fn job(&self, id: JobId) -> Result<JobSnapshot, String> {
+        self.handle(id)?
+            .snapshot()
+            .ok_or_else(|| format!("Failed to read job {}", id))
+    }
+
+    /// Forgets all finished jobs.
+    ///
+    /// # Returns
+    /// The number of jobs removed
+    pub fn clear_finished(&self) -> Result<usize, String> {
+        let mut queue = self.queue.lock().map_err(|_| "Failed to lock job queue")?;
+        let before = queue.jobs.len();
+        queue.jobs.retain(|_, handle| !handle_finished(handle));
+        Ok(before - queue.jobs.len())
+    }
+
+    fn handle(&self, id: JobId) -> Result<Arc<JobHandle>, String> {
+        let queue = self.queue.lock().map_err(|_| "Failed to lock job queue")?;
+        queue
+            .jobs
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown job: {}", id))
+    }
+}
+
+fn handle_finished(handle: &JobHandle) -> bool {
+    handle
+        .progress
+        .lock()
+        .map(|progress| progress.status.is_finished())
+        .unwrap_or(false)
+}
+
+/// Drops the oldest finished jobs beyond `MAX_FINISHED_JOBS`.
+fn prune_finished(queue: &mut JobQueue) {
+    let finished: Vec<JobId> = queue
+        .jobs
+        .iter()
+        .filter(|(_, handle)| handle_finished(handle))
+        .map(|(id, _)| *id)
+        .collect();
+    for id in finished.iter().take(finished.len().saturating_sub(MAX_FINISHED_JOBS)) {
+        queue.jobs.remove(id);
+    }
+}
+
+/// Starts queued jobs while worker slots are free. Each worker starts the next
+/// queued job when it finishes. Paused jobs keep their place in the queue and are
+/// skipped until they are resumed.
+fn start_pending(shared: &Arc<Mutex<JobQueue>>, queue: &mut JobQueue) {
+    while queue.running < MAX_CONCURRENT_JOBS {
+        let Some(position) = queue.pending.iter().position(|(id, _)| {
+            !queue.jobs.get(id).is_some_and(|handle| handle.control.is_paused())
+        }) else {
+            break;
+        };
+        let (id, request) = queue.pending.remove(position).expect("position is in range");
+        let Some(handle) = queue.jobs.get(&id).cloned() else {
+            continue;
+        };
+
+        queue.running += 1;
+        let shared = shared.clone();
+        thread::spawn(move || {
+            run_job(id, request, handle);
+
+            if let Ok(mut queue) = shared.lock() {
+                queue.running -= 1;
+                start_pending(&shared, &mut queue);
+            }
+        });
+    }
+}
+
+fn run_job(id: JobId, request: JobRequest, handle: Arc<JobHandle>) {
+    // A job paused after it was picked up stays paused until its first checkpoint
+    let mut progress = None;
+    handle.control.set(|flags| {
+        flags.started = true;
+        if !flags.paused {
+            progress = handle.set_status(JobStatus::Running);
+        }
+    });
+    if let Some(progress) = progress {
+        (handle.sink)(JobEvent::Progress(progress));
+    }
+
+    let ctx = JobContext::new(handle.clone());
+    let kind = request.kind();
+    let items = match request {
+        JobRequest::Copy { sources, destination_dir, conflict_policy } => {
+            file_jobs::run_copy(&ctx, &sources, &destination_dir, conflict_policy)
+        }
+        JobRequest::Zip { sources, destination, options } => {
+            file_jobs::run_zip(&ctx, &sources, destination.as_deref(), &options)
+        }
+        JobRequest::Unzip { archives, destination, options } => {
+            file_jobs::run_unzip(&ctx, &archives, destination.as_deref(), &options)
+        }
+        JobRequest::Trash { paths } => file_jobs::run_trash(&ctx, &paths),
+    };
+
+    let status = if ctx.is_cancelled()
+        || items.iter().any(|item| item.status == JobItemStatus::Cancelled)
+    {
+        JobStatus::Cancelled
+    } else if items.iter().any(|item| item.status == JobItemStatus::Failed) {
+        JobStatus::Failed
+    } else {
+        JobStatus::Completed
+    };
+
+    let (bytes_done, files_done) = handle
+        .progress
+        .lock()
+        .map(|progress| (progress.bytes_done, progress.files_done))
+        .unwrap_or_default();
+
+    log_info!("File job {} finished with status {:?}", id, status);
+    handle.finish(JobSummary {
+        job_id: id,
+        kind,
+        status,
+        items,
+        bytes_done,
+        files_done,
+        elapsed_ms: ctx.started.elapsed().as_millis() as u64,
+    });
+}
+
+#[cfg(test)]
+mod tests_file_job_data {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn collecting_sink() -> (JobEventSink, Arc<Mutex<Vec<JobEvent>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let collected = events.clone();
+        let sink: JobEventSink = Arc::new(move |event| collected.lock().unwrap().push(event));
+        (sink, events)
+    }
+
+    fn wait_for_finish(jobs: &FileJobState, id: JobId) -> JobSummary {
+        let deadline = Instant::now() + Duration::from_secs(20);
+        loop {
+            if let Some(summary) = jobs.job(id).unwrap().summary {
+                return summary;
+            }
+            assert!(Instant::now() < deadline, "job {} did not finish in time", id);
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_copy_job_reports_progress_and_summary() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        fs::create_dir_all(source.join("nested")).unwrap();
+        fs::write(source.join("a.txt"), "hello").unwrap();
+        fs::write(source.join("nested/b.txt"), "world!").unwrap();
+        let destination = temp_dir.path().join("backup");
+        fs::create_dir(&destination).unwrap();
+
+        let jobs = FileJobState::new();
+        let (sink, events) = collecting_sink();
+        let id = jobs
+            .submit(
+                JobRequest::Copy {
+                    sources: vec![source.to_string_lossy().to_string()],
+                    destination_dir: destination.to_string_lossy().to_string(),
+                    conflict_policy: ConflictPolicy::Rename,
+                },
+                sink,
+            )
+            .unwrap();
+
+        let summary = wait_for_finish(&jobs, id);
+        assert_eq!(summary.status, JobStatus::Completed);
+        assert_eq!(summary.files_done, 2);
+        assert_eq!(summary.bytes_done, 11);
+        assert_eq!(summary.items.len(), 1);
+        assert_eq!(summary.items[0].status, JobItemStatus::Completed);
+        assert_eq!(fs::read_to_string(destination.join("source/nested/b.txt")).unwrap(), "world!");
+
+        let events = events.lock().unwrap();
+        assert!(events.iter().any(|event| matches!(event, JobEvent::Progress(p) if p.bytes_total == 11)));
+        assert!(matches!(events.last(), Some(JobEvent::Finished(s)) if s.job_id == id));
+    }
+
+    #[test]
+    fn test_pause_resume_and_cancel() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("big.bin");
+        fs::write(&source, vec![7u8; 32 * 1024 * 1024]).unwrap();
+        let destination = temp_dir.path().join("out");
+        fs::create_dir(&destination).unwrap();
+
+        let jobs = FileJobState::new();
+        let (sink, _) = collecting_sink();
+        let request = JobRequest::Copy {
+            sources: vec![source.to_string_lossy().to_string()],
+            destination_dir: destination.to_string_lossy().to_string(),
+            conflict_policy: ConflictPolicy::Rename,
+        };
+
+        // Paused before the worker gets far: nothing is finished until resumed
+        let id = jobs.submit(request.clone(), sink.clone()).unwrap();
+        jobs.pause(id).unwrap();
+        assert_eq!(jobs.job(id).unwrap().progress.status, JobStatus::Paused);
+        thread::sleep(Duration::from_millis(100));
+        assert!(jobs.job(id).unwrap().summary.is_none());
+        jobs.resume(id).unwrap();
+        assert_eq!(wait_for_finish(&jobs, id).status, JobStatus::Completed);
+        assert!(jobs.resume(id).is_err());
+
+        // Cancelled while paused: the partial copy is removed
+        let id = jobs.submit(request, sink).unwrap();
+        jobs.pause(id).unwrap();
+        jobs.cancel(id).unwrap();
+        let summary = wait_for_finish(&jobs, id);
+        assert_eq!(summary.status, JobStatus::Cancelled);
+        assert_eq!(summary.items[0].status, JobItemStatus::Cancelled);
+        assert!(!destination.join("big (1).bin").exists());
+        assert!(jobs.cancel(id).is_err());
+
+        assert_eq!(jobs.clear_finished().unwrap(), 2);
+        assert!(jobs.jobs().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_paused_queued_jobs_are_skipped() {
+        let temp_dir = TempDir::new().unwrap();
+        let big = temp_dir.path().join("big.bin");
+        fs::write(&big, vec![7u8; 8 * 1024 * 1024]).unwrap();
+        let small = temp_dir.path().join("small.txt");
+        fs::write(&small, "small").unwrap();
+        let copy = |source: &Path, destination: &str| JobRequest::Copy {
+            sources: vec![source.to_string_lossy().to_string()],
+            destination_dir: temp_dir.path().join(destination).to_string_lossy().to_string(),
+            conflict_policy: ConflictPolicy::Rename,
+        };
+
+        let jobs = FileJobState::new();
+        let (sink, _) = collecting_sink();
+        // Paused jobs that already run hold on to their worker slots
+        let running: Vec<JobId> = (0..MAX_CONCURRENT_JOBS)
+            .map(|index| {
+                let id = jobs.submit(copy(&big, &format!("running{}", index)), sink.clone()).unwrap();
+                jobs.pause(id).unwrap();
+                id
+            })
+            .collect();
+        let paused = jobs.submit(copy(&small, "paused"), sink.clone()).unwrap();
+        jobs.pause(paused).unwrap();
+        let queued = jobs.submit(copy(&small, "queued"), sink).unwrap();
+        assert_eq!(jobs.job(queued).unwrap().progress.status, JobStatus::Queued);
+
+        // The freed slots go to the job behind the paused one
+        for id in &running {
+            jobs.resume(*id).unwrap();
+        }
+        assert_eq!(wait_for_finish(&jobs, queued).status, JobStatus::Completed);
+        for id in &running {
+            wait_for_finish(&jobs, *id);
+        }
+        assert_eq!(jobs.job(paused).unwrap().progress.status, JobStatus::Paused);
+        assert!(!temp_dir.path().join("paused").exists());
+
+        // Resuming starts it in a free slot
+        jobs.resume(paused).unwrap();
+        assert_eq!(wait_for_finish(&jobs, paused).status, JobStatus::Completed);
+        assert!(temp_dir.path().join("paused/small.txt").exists());
+    }
+
+    #[test]
+    fn test_finished_jobs_cannot_be_paused() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("small.txt");
+        fs::write(&source, "small").unwrap();
+
+        let jobs = FileJobState::new();
+        let (sink, _) = collecting_sink();
+        // Pauses race with the jobs finishing, a job must end up either paused or finished
+        for index in 0..50 {
+            let request = JobRequest::Copy {
+                sources: vec![source.to_string_lossy().to_string()],
+                destination_dir: temp_dir.path().join(format!("out{}", index)).to_string_lossy().to_string(),
+                conflict_policy: ConflictPolicy::Rename,
+            };
+            let id = jobs.submit(request, sink.clone()).unwrap();
+            thread::sleep(Duration::from_micros(index * 20));
+            match jobs.pause(id) {
+                Ok(()) => {
+                    let snapshot = jobs.job(id).unwrap();
+                    assert!(snapshot.summary.is_none(), "job {} was paused after it finished", id);
+                    assert_eq!(snapshot.progress.status, JobStatus::Paused);
+                    jobs.resume(id).unwrap();
+                }
+                Err(err) => assert_eq!(err, format!("Job {} has already finished", id)),
+            }
+            let summary = wait_for_finish(&jobs, id);
+            assert_eq!(summary.status, JobStatus::Completed);
+            assert_eq!(jobs.job(id).unwrap().progress.status, JobStatus::Completed);
+            assert!(jobs.pause(id).is_err());
+        }
+    }
+
+    #[test]
+    fn test_failed_items_do_not_stop_the_job() {
+        let temp_dir = TempDir::new().unwrap();
+        let existing = temp_dir.path().join("exists.txt");
+        fs::write(&existing, "data").unwrap();
+        let missing = temp_dir.path().join("missing.txt");
+
+        let jobs = FileJobState::new();
+        let (sink, _) = collecting_sink();
+        let id = jobs
+            .submit(
+                JobRequest::Copy {
+                    sources: vec![missing.to_string_lossy().to_string(), existing.to_string_lossy().to_string()],
+                    destination_dir: temp_dir.path().join("copy").to_string_lossy().to_string(),
+                    conflict_policy: ConflictPolicy::Rename,
+                },
+                sink,
+            )
+            .unwrap();
+
+        let summary = wait_for_finish(&jobs, id);
+        assert_eq!(summary.status, JobStatus::Failed);
+        assert_eq!(summary.items[0].status, JobItemStatus::Failed);
+        assert!(summary.items[0].error.is_some());
+        assert_eq!(summary.items[1].status, JobItemStatus::Completed);
+        assert!(temp_dir.path().join("copy/exists.txt").exists());
+    }
+
+    #[test]
+    fn test_estimate_remaining() {
+        assert_eq!(estimate_remaining(0, 100, 0, 1, Duration::from_secs(1)), None);
+        assert_eq!(estimate_remaining(50, 100, 0, 1, Duration::from_secs(1)), Some(1000));
+        assert_eq!(estimate_remaining(0, 0, 1, 4, Duration::from_secs(2)), Some(6000));
+    }
+}
@@ -0,0 +1,220 @@
+use crate::filesystem::folder_size::{measure_folder, FolderSize, FolderSizeCache, FolderSizeError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Minimum time between two intermediate updates of the same folder
+const PROGRESS_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Receives the size updates of folders.
+///
+/// The commands forward these as Tauri events, tests collect them directly.
+pub type FolderSizeSink = Arc<dyn Fn(FolderSizeUpdate) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FolderSizeStatus {
+    /// Intermediate totals, more updates follow
+    Calculating,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// Size of a folder, sent as `folder-size-updated` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderSizeUpdate {
+    pub path: String,
+    pub status: FolderSizeStatus,
+    #[serde(flatten)]
+    pub size: FolderSize,
+    pub error: Option<String>,
+}
+
+/// Calculates folder sizes in the background and caches the directory listings it reads.
+///
+/// Every call to `calculate` runs on its own thread and measures its folders one after
+/// another. A folder that is already being calculated is not started a second time.
+pub struct FolderSizeState {
+    cache: Arc<FolderSizeCache>,
+    /// Cancel flags of the folders that are queued or being calculated
+    running: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+}
+
+impl Default for FolderSizeState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FolderSizeState {
+    pub fn new() -> Self {
+        Self {
+            cache: Arc::new(FolderSizeCache::new()),
+            running: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// The directory listings read so far, e.g. to fill in the sizes of listed folders.
+    pub fn cache(&self) -> Arc<FolderSizeCache> {
+        self.cache.clone()
+    }
+
+    /// Starts calculating the sizes of `paths` in the background.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<String>)` - The paths that were started, without those already being calculated
+    /// * `Err(String)` - If the state could not be locked
+    pub fn calculate(&self, paths: Vec<String>, sink: FolderSizeSink) -> Result<Vec<String>, String> {
+        let mut running = self.running.lock().map_err(|_| "Failed to lock folder size calculations")?;
+        let mut started = Vec::new();
+        for path in paths {
+            if running.contains_key(&path) || started.iter().any(|(queued, _)| *queued == path) {
+                continue;
+            }
+            let cancel = Arc::new(AtomicBool::new(false));
+            running.insert(path.clone(), cancel.clone());
+            started.push((path, cancel));
+        }
+        drop(running);
+
+        let paths = started.iter().map(|(path, _)| path.clone()).collect();
+        if !started.is_empty() {
+            let cache = self.cache.clone();
+            let running = self.running.clone();
+            thread::spawn(move || {
+                for (path, cancel) in started {
+                    let update = measure(&path, &cache, &cancel, &sink);
+
+                    if let Ok(mut running) = running.lock() {
+                        // A cancelled calculation may have been started again in the meantime
+                        if running.get(&path).is_some_and(|flag| Arc::ptr_eq(flag, &cancel)) {
+                            running.remove(&path);
+                        }
+                    }
+                    sink(update);
+                }
+            });
+        }
+        Ok(paths)
+    }
+
+    /// Cancels the calculation of the given paths, or of every folder if `paths` is `None`.
+    ///
+    /// # Returns
+    /// * `Ok(usize)` - The number of cancelled calculations
+    /// * `Err(String)` - If the state could not be locked
+    pub fn cancel(&self, paths: Option<&[String]>) -> Result<usize, String> {
+        let mut running = self.running.lock().map_err(|_| "Failed to lock folder size calculations")?;
+        let cancelled: Vec<String> = running
+            .keys()
+            .filter(|path| paths.is_none_or(|paths| paths.contains(path)))
+            .cloned()
+            .collect();
+        for path in &cancelled {
+            if let Some(cancel) = running.remove(path) {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        }
+        Ok(cancelled.len())
+    }
+}
+
+/// Measures a single folder and returns its final update.
+fn measure(path: &str, cache: &FolderSizeCache, cancel: &AtomicBool, sink: &FolderSizeSink) -> FolderSizeUpdate {
+    let mut last_update = Instant::now();
+    let result = measure_folder(Path::new(path), cache, cancel, |size| {
+        if last_update.elapsed() >= PROGRESS_UPDATE_INTERVAL {
+            last_update = Instant::now();
+            sink(FolderSizeUpdate {
+                path: path.to_string(),
+                status: FolderSizeStatus::Calculating,
+                size: *size,
+                error: None,
+            });
+        }
+    });
+
+    let (status, size, error) = match result {
+        Ok(size) => (FolderSizeStatus::Completed, size, None),
+        Err(FolderSizeError::Cancelled) => (FolderSizeStatus::Cancelled, FolderSize::default(), None),
+        Err(FolderSizeError::Failed(err)) => (FolderSizeStatus::Failed, FolderSize::default(), Some(err)),
+    };
+    FolderSizeUpdate { path: path.to_string(), status, size, error }
+}
+
+#[cfg(test)]
+mod tests_folder_size_data {
+    use super::*;
+    use std::fs;
+    use std::sync::mpsc;
+    use tempfile::TempDir;
+
+    fn channel_sink() -> (FolderSizeSink, mpsc::Receiver<FolderSizeUpdate>) {
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let sink: FolderSizeSink = Arc::new(move |update| {
+            let _ = sender.lock().unwrap().send(update);
+        });
+        (sink, receiver)
+    }
+
+    fn final_update(receiver: &mpsc::Receiver<FolderSizeUpdate>) -> FolderSizeUpdate {
+        loop {
+            let update = receiver.recv_timeout(Duration::from_secs(20)).expect("no final update in time");
+            if update.status != FolderSizeStatus::Calculating {
+                return update;
+            }
+        }
+    }
+
+    #[test]
+    fn test_calculate_reports_sizes() {
+        let temp_dir = TempDir::new().unwrap();
+        let folder = temp_dir.path().join("folder");
+        fs::create_dir_all(folder.join("nested")).unwrap();
+        fs::write(folder.join("a.txt"), "abc").unwrap();
+        fs::write(folder.join("nested/b.txt"), "de").unwrap();
+        let folder = folder.to_string_lossy().to_string();
+        let missing = temp_dir.path().join("missing").to_string_lossy().to_string();
+
+        let state = FolderSizeState::new();
+        let (sink, receiver) = channel_sink();
+        let started = state.calculate(vec![folder.clone(), folder.clone(), missing.clone()], sink).unwrap();
+        assert_eq!(started, vec![folder.clone(), missing.clone()]);
+
+        let update = final_update(&receiver);
+        assert_eq!(update.path, folder);
+        assert_eq!(update.status, FolderSizeStatus::Completed);
+        assert_eq!(update.size, FolderSize { size_in_bytes: 5, file_count: 2, dir_count: 1 });
+
+        let update = final_update(&receiver);
+        assert_eq!(update.path, missing);
+        assert_eq!(update.status, FolderSizeStatus::Failed);
+        assert!(update.error.is_some());
+    }
+
+    #[test]
+    fn test_cancel_calculation() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().to_string_lossy().to_string();
+        let state = FolderSizeState::new();
+
+        // Mark the folder as running, so `calculate` does not start it again before it is cancelled
+        let cancel = Arc::new(AtomicBool::new(false));
+        state.running.lock().unwrap().insert(path.clone(), cancel.clone());
+        assert!(state.calculate(vec![path.clone()], Arc::new(|_| {})).unwrap().is_empty());
+
+        assert_eq!(state.cancel(Some(std::slice::from_ref(&path))).unwrap(), 1);
+        assert!(cancel.load(Ordering::Relaxed));
+        assert_eq!(state.cancel(None).unwrap(), 0);
+
+        let (sink, receiver) = channel_sink();
+        let update = measure(&path, &state.cache, &AtomicBool::new(true), &sink);
+        assert_eq!(update.status, FolderSizeStatus::Cancelled);
+        assert!(receiver.try_recv().is_err());
+    }
+}
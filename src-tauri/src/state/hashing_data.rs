@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+pub type HashRunId = u64;
+
+/// Keeps track of running hash calculations so they can be cancelled.
+#[derive(Default)]
+pub struct HashingState {
+    next_id: HashRunId,
+    /// The path and cancel flag of every running calculation
+    running: HashMap<HashRunId, (String, Arc<AtomicBool>)>,
+}
+
+impl HashingState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a calculation of `path`.
+    ///
+    /// # Returns
+    /// The id to pass to `finish` and the flag the calculation has to check
+    pub fn start(&mut self, path: &str) -> (HashRunId, Arc<AtomicBool>) {
+        let id = self.next_id;
+        self.next_id += 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.running.insert(id, (path.to_string(), cancel.clone()));
+        (id, cancel)
+    }
+
+    /// Removes a calculation once it has finished.
+    pub fn finish(&mut self, id: HashRunId) {
+        self.running.remove(&id);
+    }
+
+    /// Cancels all calculations of `path`, or every calculation if `path` is `None`.
+    ///
+    /// # Returns
+    /// The number of cancelled calculations
+    pub fn cancel(&self, path: Option<&str>) -> usize {
+        self.running
+            .values()
+            .filter(|(running_path, _)| path.is_none_or(|path| path == running_path))
+            .inspect(|(_, cancel)| cancel.store(true, Ordering::Relaxed))
+            .count()
+    }
+}
@@ -21,16 +21,35 @@
 //! States can then be accessed in command handlers using the `#[tauri::command]` macro
 //! and appropriate state parameters.
 
+pub mod disk_usage_data;
+pub mod file_job_data;
+pub mod folder_size_data;
+pub mod hashing_data;
 pub mod meta_data;
+pub mod operation_journal_data;
 pub mod searchengine_data;
 pub mod settings_data;
+pub mod sftp_connection_data;
+pub mod sftp_session_data;
 pub mod logging;
 
 pub use settings_data::*;
 
 use logging::Logger;
+use crate::constants::{
+    OPERATION_JOURNAL_ABS_PATH, SEARCH_INDEX_ABS_PATH, SFTP_CONNECTIONS_ABS_PATH, SFTP_SECRETS_ABS_PATH,
+    SFTP_SECRETS_KEYRING_SERVICE, SFTP_SECRETS_KEYRING_USER, SFTP_SECRETS_KEY_ABS_PATH,
+};
+use crate::sftp::secret_store::KeyLocation;
 use crate::state::searchengine_data::SearchEngineState;
+use disk_usage_data::DiskUsageState;
+use file_job_data::FileJobState;
+use folder_size_data::FolderSizeState;
+use hashing_data::HashingState;
 use meta_data::MetaDataState;
+use operation_journal_data::OperationJournalState;
+use sftp_connection_data::SftpConnectionState;
+use sftp_session_data::SftpSessionState;
 use std::sync::{Arc, Mutex};
 use tauri::{Builder, Wry};
 
@@ -38,13 +57,52 @@ pub fn setup_app_state(app: Builder<Wry>) -> Builder<Wry> {
     // Create our shared state instances
     let meta_data_state = Arc::new(Mutex::new(MetaDataState::new()));
     let settings_state = Arc::new(Mutex::new(SettingsState::new()));
-    let search_engine_state = Arc::new(Mutex::new(SearchEngineState::new(settings_state.clone())));
-    
+
     // Initialize the logger with the settings state
     Logger::init(settings_state.clone());
-    
+
+    // Restore the search index from disk so searches work before the first re-index
+    let mut search_engine = SearchEngineState::new(settings_state.clone());
+    search_engine.enable_index_persistence(SEARCH_INDEX_ABS_PATH.to_path_buf());
+    let search_engine_state = Arc::new(Mutex::new(search_engine));
+    let file_job_state = Arc::new(Mutex::new(FileJobState::new()));
+    let folder_size_state = Arc::new(Mutex::new(FolderSizeState::new()));
+    let disk_usage_state = Arc::new(Mutex::new(DiskUsageState::new()));
+    let hashing_state = Arc::new(Mutex::new(HashingState::new()));
+
+    // Restore the undo history of the previous session
+    let mut operation_journal = OperationJournalState::new();
+    operation_journal.enable_persistence(OPERATION_JOURNAL_ABS_PATH.to_path_buf());
+    let operation_journal_state = Arc::new(Mutex::new(operation_journal));
+
+    // Keep pooled SFTP sessions alive while they are idle
+    let sftp_session_state = Arc::new(Mutex::new(SftpSessionState::new()));
+    sftp_session_data::start_keepalive(&sftp_session_state);
+
+    // Saved connections, with their secrets in an encrypted store next to them whose key
+    // is kept in the credential store of the OS
+    let mut sftp_connections = SftpConnectionState::new();
+    let secrets_key = KeyLocation::credential_store(
+        SFTP_SECRETS_KEYRING_SERVICE,
+        SFTP_SECRETS_KEYRING_USER,
+        SFTP_SECRETS_KEY_ABS_PATH.to_path_buf(),
+    );
+    sftp_connections.enable_persistence(
+        SFTP_CONNECTIONS_ABS_PATH.to_path_buf(),
+        SFTP_SECRETS_ABS_PATH.to_path_buf(),
+        &secrets_key,
+    );
+    let sftp_connection_state = Arc::new(Mutex::new(sftp_connections));
+
     //To add more just .manage
     app.manage(meta_data_state)
         .manage(settings_state)
         .manage(search_engine_state)
+        .manage(file_job_state)
+        .manage(folder_size_state)
+        .manage(disk_usage_state)
+        .manage(hashing_state)
+        .manage(operation_journal_state)
+        .manage(sftp_session_state)
+        .manage(sftp_connection_state)
 }
@@ -0,0 +1,215 @@
+use crate::filesystem::operation_journal::JournalOperation;
+#[allow(unused_imports)]
+use crate::{log_error, log_info, log_warn};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of operations that can be undone, older ones are dropped
+pub const MAX_JOURNAL_ENTRIES: usize = 100;
+
+/// A recorded operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub operation: JournalOperation,
+    pub recorded_at: u64, // as milliseconds since epoch
+}
+
+/// The undo and redo stacks, the last entry of each is the next one to apply.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JournalHistory {
+    next_id: u64,
+    undo: VecDeque<JournalEntry>,
+    redo: VecDeque<JournalEntry>,
+}
+
+/// Journal of the reversible filesystem operations performed through the commands.
+///
+/// Recording a new operation clears the redo stack. Both stacks are bounded by
+/// `MAX_JOURNAL_ENTRIES` and written to disk after every change once persistence
+/// is enabled, so the history survives restarts.
+#[derive(Debug, Default)]
+pub struct OperationJournalState {
+    history: JournalHistory,
+    file_path: Option<PathBuf>,
+}
+
+impl OperationJournalState {
+    /// Creates an empty journal that is only kept in memory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the journal from `path` and saves every later change to it.
+    ///
+    /// An unreadable journal file is logged and replaced by an empty history.
+    pub fn enable_persistence(&mut self, path: PathBuf) {
+        match fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice::<JournalHistory>(&bytes) {
+                Ok(history) => {
+                    log_info!("Restored {} undoable operations from disk", history.undo.len());
+                    self.history = history;
+                }
+                Err(err) => log_error!("Failed to parse operation journal {}: {}", path.display(), err),
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => log_error!("Failed to read operation journal {}: {}", path.display(), err),
+        }
+        self.file_path = Some(path);
+    }
+
+    /// Records an operation that has just been performed.
+    ///
+    /// # Returns
+    /// The id of the new journal entry
+    pub fn record(&mut self, operation: JournalOperation) -> u64 {
+        let id = self.history.next_id;
+        self.history.next_id += 1;
+        let recorded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or(0);
+
+        push_bounded(&mut self.history.undo, JournalEntry { id, operation, recorded_at });
+        self.history.redo.clear();
+        self.save();
+        id
+    }
+
+    /// Reverts the most recent operation and moves it to the redo stack.
+    ///
+    /// If the operation cannot be reverted, e.g. because the file was changed in the
+    /// meantime, it is dropped from the history so it does not block older entries.
+    ///
+    /// # Returns
+    /// * `Ok(JournalEntry)` - The entry that was undone
+    /// * `Err(String)` - There is nothing to undo, or reverting failed
+    pub fn undo(&mut self) -> Result<JournalEntry, String> {
+        let entry = self.history.undo.pop_back().ok_or("There is no operation to undo")?;
+        let result = entry.operation.undo();
+        if result.is_ok() {
+            push_bounded(&mut self.history.redo, entry.clone());
+        }
+        self.save();
+        result.map(|_| entry)
+    }
+
+    /// Performs the most recently undone operation again and moves it back to the undo stack.
+    ///
+    /// # Returns
+    /// * `Ok(JournalEntry)` - The entry that was redone
+    /// * `Err(String)` - There is nothing to redo, or performing it failed
+    pub fn redo(&mut self) -> Result<JournalEntry, String> {
+        let mut entry = self.history.redo.pop_back().ok_or("There is no operation to redo")?;
+        let result = entry.operation.redo();
+        if result.is_ok() {
+            push_bounded(&mut self.history.undo, entry.clone());
+        }
+        self.save();
+        result.map(|_| entry)
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.file_path else {
+            return;
+        };
+        if let Err(err) = write_history(path, &self.history) {
+            log_error!("Failed to save operation journal {}: {}", path.display(), err);
+        }
+    }
+}
+
+fn push_bounded(stack: &mut VecDeque<JournalEntry>, entry: JournalEntry) {
+    stack.push_back(entry);
+    while stack.len() > MAX_JOURNAL_ENTRIES {
+        stack.pop_front();
+    }
+}
+
+fn write_history(path: &PathBuf, history: &JournalHistory) -> std::io::Result<()> {
+    let serialized = serde_json::to_vec(history)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Write next to the journal and move it into place, so a crash never leaves half a file
+    let tmp_path = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(&serialized)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests_operation_journal_data {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_file_operation(path: &std::path::Path) -> JournalOperation {
+        JournalOperation::CreateFile { path: path.to_string_lossy().to_string() }
+    }
+
+    #[test]
+    fn test_undo_redo_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("new.txt");
+        fs::File::create(&file).unwrap();
+        let mut journal = OperationJournalState::new();
+        journal.record(create_file_operation(&file));
+
+        let undone = journal.undo().unwrap();
+        assert_eq!(undone.operation, create_file_operation(&file));
+        assert!(!file.exists());
+        assert!(journal.undo().is_err(), "Nothing should be left to undo");
+
+        journal.redo().unwrap();
+        assert!(file.exists());
+        assert!(journal.redo().is_err(), "Nothing should be left to redo");
+
+        // A new operation makes the undone ones unreachable
+        journal.undo().unwrap();
+        journal.record(create_file_operation(&temp_dir.path().join("other.txt")));
+        assert!(journal.redo().is_err());
+    }
+
+    #[test]
+    fn test_failed_undo_drops_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = temp_dir.path().join("first.txt");
+        let second = temp_dir.path().join("second.txt");
+        fs::File::create(&first).unwrap();
+        fs::write(&second, "changed after creation").unwrap();
+        let mut journal = OperationJournalState::new();
+        journal.record(create_file_operation(&first));
+        journal.record(create_file_operation(&second));
+
+        assert!(journal.undo().is_err());
+        assert!(second.exists());
+
+        journal.undo().unwrap();
+        assert!(!first.exists());
+        assert!(journal.redo().is_ok(), "Only the successful undo should be redoable");
+        assert!(journal.redo().is_err());
+    }
+
+    #[test]
+    fn test_history_is_bounded_and_persisted() {
+        let temp_dir = TempDir::new().unwrap();
+        let journal_path = temp_dir.path().join("config").join("operation_journal.json");
+        let mut journal = OperationJournalState::new();
+        journal.enable_persistence(journal_path.clone());
+        for i in 0..MAX_JOURNAL_ENTRIES + 5 {
+            journal.record(create_file_operation(&temp_dir.path().join(format!("{}.txt", i))));
+        }
+
+        let mut restored = OperationJournalState::new();
+        restored.enable_persistence(journal_path);
+        assert_eq!(restored.history.undo.len(), MAX_JOURNAL_ENTRIES);
+        assert_eq!(restored.history.undo.front().unwrap().id, 5);
+        assert_eq!(restored.history.next_id, (MAX_JOURNAL_ENTRIES + 5) as u64);
+    }
+}
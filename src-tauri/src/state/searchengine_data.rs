@@ -1,16 +1,37 @@
+use crate::commands::preview_commands::read_text_content;
 use crate::models::search_engine_config::SearchEngineConfig;
+use crate::search_engine::content_index::{find_matching_lines, ContentSearchResult, MAX_CONTENT_INDEX_BYTES};
+use crate::search_engine::index_persistence::{
+    load_index_snapshot, read_index_folder, remove_index_snapshot, save_index_snapshot,
+};
+use crate::search_engine::index_watcher::IndexWatcher;
+use crate::search_engine::pattern_search::{PathPattern, SearchMode};
+use crate::search_engine::query_parser::{parse_query, scan_metadata, ParsedQuery};
 use crate::search_engine::search_core::{EngineStats, SearchCore};
 use crate::state::SettingsState;
 #[allow(unused_imports)]
 use crate::{log_error, log_info, log_warn};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, RwLock};
-use std::time::{Instant};
+use std::time::{Duration, Instant};
 use std::{fs};
 use tokio;
 
+/// Upper bound on files read to answer a single content query
+const MAX_CONTENT_CANDIDATES: usize = 5000;
 
+/// Time budget for pattern searches when `search_timeout_ms` is not configured
+const DEFAULT_SEARCH_TIMEOUT_MS: u64 = 5000;
+
+/// Chunk size for rebuilding an index whose file could not be restored, as used by `add_paths_recursive`
+const REBUILD_CHUNK_SIZE: usize = 150;
+
+/// A validated search query, ready to run against the engine
+enum SearchRequest {
+    Fuzzy(ParsedQuery),
+    Pattern(PathPattern),
+}
 
 /// Current operational status of the search engine.
 ///
@@ -104,6 +125,7 @@ impl Default for RecentActivity {
 pub struct EngineStatsSerializable {
     pub cache_size: usize,
     pub trie_size: usize,
+    pub content_index_size: usize,
 }
 
 impl From<EngineStats> for EngineStatsSerializable {
@@ -111,6 +133,7 @@ impl From<EngineStats> for EngineStatsSerializable {
         Self {
             cache_size: stats.cache_size,
             trie_size: stats.trie_size,
+            content_index_size: stats.content_index_size,
         }
     }
 }
@@ -169,6 +192,10 @@ pub struct SearchEngineState {
     pub data: Arc<Mutex<SearchEngine>>,
     pub engine: Arc<RwLock<SearchCore>>,
     settings_state: Arc<Mutex<SettingsState>>,
+    /// Location of the on-disk index, `None` keeps the index in memory only
+    index_file_path: Option<PathBuf>,
+    /// Keeps the index in sync with filesystem changes in the indexed folder
+    watcher: Arc<Mutex<IndexWatcher>>,
 }
 
 impl SearchEngineState {
@@ -215,12 +242,202 @@ impl SearchEngineState {
             ranking_config,
         );
 
+        let engine = Arc::new(RwLock::new(engine));
+        let watcher = Arc::new(Mutex::new(IndexWatcher::new(Arc::clone(&engine))));
+
         Self {
             data: Arc::new(Mutex::new(Self::save_default_search_engine_in_state(
                 config,
             ))),
-            engine: Arc::new(RwLock::new(engine)),
+            engine,
             settings_state,
+            index_file_path: None,
+            watcher,
+        }
+    }
+
+    /// Starts watching the indexed folder so creates, deletes and renames reach the index.
+    ///
+    /// Indexing a folder replaces the previous index, so any previously watched
+    /// folder is dropped first. Failing to watch (e.g. because the inotify watch limit
+    /// is reached) is logged and otherwise ignored; the index itself stays usable.
+    ///
+    /// # Arguments
+    ///
+    /// * `folder` - The folder that is (or is about to be) indexed
+    /// * `excluded_patterns` - Patterns of paths the watcher must not add
+    /// * `index_contents` - Whether the watcher re-reads the contents of changed files
+    fn watch_indexed_folder(&self, folder: &Path, excluded_patterns: Vec<String>, index_contents: bool) {
+        let mut watcher = match self.watcher.lock() {
+            Ok(watcher) => watcher,
+            Err(_) => {
+                log_error!("Failed to lock filesystem watcher");
+                return;
+            }
+        };
+
+        watcher.unwatch_all();
+        if let Err(err) = watcher.watch(folder, excluded_patterns, index_contents) {
+            log_warn!("Live index updates disabled for '{}': {}", folder.display(), err);
+        }
+    }
+
+    /// Stops watching all indexed folders.
+    pub fn stop_watching(&self) {
+        match self.watcher.lock() {
+            Ok(mut watcher) => watcher.unwatch_all(),
+            Err(_) => log_error!("Failed to lock filesystem watcher"),
+        }
+    }
+
+    /// Returns the folders currently watched for live index updates.
+    #[cfg(test)]
+    pub fn watched_folders(&self) -> Vec<PathBuf> {
+        self.watcher
+            .lock()
+            .map(|watcher| watcher.watched_roots())
+            .unwrap_or_default()
+    }
+
+    /// Enables on-disk persistence of the search index and loads any existing index.
+    ///
+    /// After this call the index is written to `path` whenever an indexing run
+    /// completes or the folder watcher has applied changes, and removed again when
+    /// the engine is cleared. A corrupt or version-mismatched file that still names
+    /// its indexed folder is rebuilt in the background, otherwise the engine starts empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File the index is stored in (usually `SEARCH_INDEX_ABS_PATH`)
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let mut search_engine = SearchEngineState::new(settings_state);
+    /// search_engine.enable_index_persistence(SEARCH_INDEX_ABS_PATH.to_path_buf());
+    /// ```
+    pub fn enable_index_persistence(&mut self, path: PathBuf) {
+        self.index_file_path = Some(path.clone());
+
+        // Changes from the watcher are saved as well, or the next start restores a stale index
+        let (data, engine) = (Arc::clone(&self.data), Arc::clone(&self.engine));
+        let on_save = Arc::new(move || {
+            // A running indexing saves the complete index when it is done
+            if data.lock().is_ok_and(|data| matches!(data.status, SearchEngineStatus::Indexing)) {
+                return;
+            }
+            if let Err(err) = persist_snapshot(&path, &data, &engine) {
+                log_warn!("Failed to persist search index after watcher changes: {}", err);
+            }
+        });
+        match self.watcher.lock() {
+            Ok(watcher) => watcher.set_save_callback(on_save),
+            Err(_) => log_error!("Failed to lock filesystem watcher"),
+        }
+
+        match self.load_persisted_index() {
+            Ok(true) => log_info!("Search index restored from disk"),
+            Ok(false) => log_info!("No usable search index on disk, starting with an empty index"),
+            Err(err) => log_error!("Failed to restore search index: {}", err),
+        }
+    }
+
+    /// Loads the persisted index into the engine.
+    ///
+    /// If the file cannot be used it is deleted and the engine is left empty,
+    /// so a stale or damaged index never serves results. When the indexed folder can
+    /// still be read from the file, it is indexed again on a background thread.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` - The index was restored from disk
+    /// * `Ok(false)` - Persistence is disabled, or there was no valid index to load
+    /// * `Err(String)` - The engine or data locks could not be acquired
+    pub fn load_persisted_index(&self) -> Result<bool, String> {
+        let path = match &self.index_file_path {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+
+        let snapshot = match load_index_snapshot(path) {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => return Ok(false),
+            Err(err) => {
+                log_warn!("Discarding persisted search index at {}: {}", path.display(), err);
+                let index_folder = read_index_folder(path);
+                remove_index_snapshot(path);
+                self.rebuild_index(index_folder);
+                return Ok(false);
+            }
+        };
+
+        let index_folder = snapshot.index_folder.clone();
+        let mut engine = self.engine.write().map_err(|_| "Failed to acquire write lock on search engine for index restore")?;
+        if let Err(err) = engine.restore_snapshot(snapshot) {
+            log_warn!("Discarding persisted search index at {}: {}", path.display(), err);
+            engine.clear();
+            remove_index_snapshot(path);
+            drop(engine);
+            self.rebuild_index(index_folder);
+            return Ok(false);
+        }
+        let restored_paths = engine.get_stats().trie_size;
+        drop(engine);
+
+        let mut data = self.data.lock().map_err(|_| "Failed to lock search engine data for index restore")?;
+        data.index_folder = index_folder.map(PathBuf::from).unwrap_or_default();
+        data.progress.files_indexed = restored_paths;
+        data.progress.files_discovered = restored_paths;
+        data.last_updated = chrono::Utc::now().timestamp_millis() as u64;
+
+        // Pick up changes made from now on; changes while the app was closed need a re-index
+        let index_folder = data.index_folder.clone();
+        let excluded_patterns = data.config.excluded_patterns.clone().unwrap_or_default();
+        let index_contents = data.config.index_file_contents;
+        drop(data);
+        if index_folder.is_dir() {
+            self.watch_indexed_folder(&index_folder, excluded_patterns, index_contents);
+        }
+
+        Ok(true)
+    }
+
+    /// Indexes the folder of a discarded index file again on a background thread.
+    fn rebuild_index(&self, index_folder: Option<String>) {
+        let Some(folder) = index_folder.map(PathBuf::from).filter(|folder| folder.is_dir()) else {
+            return;
+        };
+        log_info!("Rebuilding the search index of {}", folder.display());
+        let state = self.clone();
+        let spawned = std::thread::Builder::new()
+            .name("index-rebuild".to_string())
+            .spawn(move || {
+                if let Err(err) = state.start_chunked_indexing(folder, REBUILD_CHUNK_SIZE) {
+                    log_error!("Failed to rebuild the search index: {}", err);
+                }
+            });
+        if let Err(err) = spawned {
+            log_error!("Failed to spawn index rebuild thread: {}", err);
+        }
+    }
+
+    /// Writes the current index to disk if persistence is enabled.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The index was written, or persistence is disabled
+    /// * `Err(String)` - Locking, serialization or IO failed
+    pub fn persist_index(&self) -> Result<(), String> {
+        match &self.index_file_path {
+            Some(path) => persist_snapshot(path, &self.data, &self.engine),
+            None => Ok(()),
+        }
+    }
+
+    /// Deletes the persisted index file if persistence is enabled.
+    pub fn remove_persisted_index(&self) {
+        if let Some(path) = &self.index_file_path {
+            remove_index_snapshot(path);
         }
     }
 
@@ -325,6 +542,10 @@ impl SearchEngineState {
             drop(data);
             drop(engine);
 
+            // Watch before walking so changes made during indexing are not missed;
+            // this path does not index file contents, so neither does the watcher
+            self.watch_indexed_folder(&folder, excluded_patterns.clone().unwrap_or_default(), false);
+
             // Get the engine again for the recursive operation
             {
                 let mut engine = self.engine.write().map_err(|_| "Failed to acquire write lock on search engine")?;
@@ -357,6 +578,12 @@ impl SearchEngineState {
                     folder.display(),
                     elapsed
                 );
+
+                drop(engine);
+                drop(data);
+                if let Err(err) = self.persist_index() {
+                    log_warn!("Failed to persist search index: {}", err);
+                }
             }
         } else {
             data.status = SearchEngineStatus::Failed;
@@ -421,6 +648,7 @@ impl SearchEngineState {
 
         // Get excluded patterns from config
         let excluded_patterns = data.config.excluded_patterns.clone();
+        let index_contents = data.config.index_file_contents;
 
         // Actually start the chunked indexing
         if let Some(_folder_str) = folder.to_str() {
@@ -428,6 +656,9 @@ impl SearchEngineState {
             drop(data);
             drop(engine);
 
+            // Watch before walking so changes made during indexing are not missed
+            self.watch_indexed_folder(&folder, excluded_patterns.clone().unwrap_or_default(), index_contents);
+
             // Initialize progress tracking with immediate update
             {
                 let mut data = self.data.lock().map_err(|_| "Failed to lock search engine data for progress update")?;
@@ -443,7 +674,7 @@ impl SearchEngineState {
 
             // Use streaming indexing instead of collecting all paths first
             let patterns = excluded_patterns.unwrap_or_default();
-            self.index_directory_streaming(&folder, &patterns, chunk_size)?;
+            self.index_directory_streaming(&folder, &patterns, chunk_size, index_contents)?;
         } else {
             data.status = SearchEngineStatus::Failed;
             return Err("Invalid folder path".to_string());
@@ -454,11 +685,13 @@ impl SearchEngineState {
 
     /// Index a directory using streaming approach - discover and index files as we go
     /// Optimized to prevent stack overflow using iterative traversal
+    /// When `index_contents` is set, the text content of each file is indexed as well
     fn index_directory_streaming(
         &self,
         dir: &PathBuf,
         excluded_patterns: &[String],
         chunk_size: usize,
+        index_contents: bool,
     ) -> Result<(), String> {
         let mut discovered_files = 0;
         let mut indexed_files = 0;
@@ -476,11 +709,12 @@ impl SearchEngineState {
             &mut indexed_files,
             &mut current_batch,
             chunk_size,
+            index_contents,
         )?;
 
         // Process any remaining files in the batch
         if !current_batch.is_empty() {
-            self.process_batch(&current_batch, &mut indexed_files, discovered_files)?;
+            self.process_batch(&current_batch, &mut indexed_files, discovered_files, index_contents)?;
         }
 
         // Update final status
@@ -506,6 +740,12 @@ impl SearchEngineState {
                 indexed_files,
                 elapsed
             );
+
+            drop(engine);
+            drop(data);
+            if let Err(err) = self.persist_index() {
+                log_warn!("Failed to persist search index: {}", err);
+            }
         }
 
         Ok(())
@@ -521,6 +761,7 @@ impl SearchEngineState {
         indexed_files: &mut usize,
         current_batch: &mut Vec<String>,
         chunk_size: usize,
+        index_contents: bool,
     ) -> Result<(), String> {
         use std::collections::VecDeque;
         
@@ -591,7 +832,7 @@ impl SearchEngineState {
 
                             // Process batch when it reaches chunk_size to prevent memory buildup
                             if current_batch.len() >= chunk_size {
-                                self.process_batch(current_batch, indexed_files, *discovered_files)?;
+                                self.process_batch(current_batch, indexed_files, *discovered_files, index_contents)?;
                                 current_batch.clear();
                                 current_batch.reserve(chunk_size); // Pre-allocate for next batch
                             }
@@ -643,6 +884,7 @@ impl SearchEngineState {
         batch: &[String],
         indexed_files: &mut usize,
         total_discovered: usize,
+        index_contents: bool,
     ) -> Result<(), String> {
         if batch.is_empty() {
             return Ok(());
@@ -675,6 +917,23 @@ impl SearchEngineState {
                 engine.add_paths_batch(batch_refs, None);
             } // Release write lock immediately
 
+            if index_contents {
+                // Read files without holding the engine lock
+                let contents: Vec<(&String, String)> = chunk
+                    .iter()
+                    .filter_map(|path| {
+                        read_text_content(Path::new(path), MAX_CONTENT_INDEX_BYTES).map(|text| (path, text))
+                    })
+                    .collect();
+
+                if !contents.is_empty() {
+                    let mut engine = self.engine.write().map_err(|_| "Failed to acquire write lock on search engine for content indexing")?;
+                    for (path, text) in &contents {
+                        engine.add_file_content(path, text);
+                    }
+                }
+            }
+
             *indexed_files += chunk.len();
 
             // Update progress after each sub-batch to keep UI responsive
@@ -721,6 +980,10 @@ impl SearchEngineState {
     /// Results are ranked by relevance and limited by the configured maximum results.
     /// This method will fail if the engine is currently indexing or searching.
     ///
+    /// The query may contain structured filters such as `ext:rs`, `size:>10MB`,
+    /// `modified:<7d`, `type:dir`, `in:/home/x` and `-word`; see
+    /// `query_parser::parse_query` for the full syntax.
+    ///
     /// # Arguments
     ///
     /// * `query` - The search string to find matching files
@@ -728,7 +991,7 @@ impl SearchEngineState {
     /// # Returns
     ///
     /// * `Ok(Vec<(String, f32)>)` - List of matching paths and their relevance scores
-    /// * `Err(String)` - An error occurred during searching
+    /// * `Err(String)` - An error occurred during searching or a filter could not be parsed
     ///
     /// # Example
     ///
@@ -741,6 +1004,39 @@ impl SearchEngineState {
     /// }
     /// ```
     pub fn search(&self, query: &str) -> Result<Vec<(String, f32)>, String> {
+        self.search_with_mode(query, SearchMode::Fuzzy)
+    }
+
+    /// Performs a search, interpreting the query according to `mode`.
+    ///
+    /// `SearchMode::Fuzzy` behaves exactly like `search`. Glob, regex and exact
+    /// queries are matched against every indexed path, capped in the number of
+    /// matches and limited to `search_timeout_ms` of the configuration, and so is
+    /// reading the metadata for `size:`, `modified:` and `type:` filters. Structured
+    /// filters are only parsed in fuzzy mode, so patterns may contain `:` and `-`.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search string, glob, regex or literal
+    /// * `mode` - How the query is interpreted
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<(String, f32)>)` - List of matching paths and their relevance scores
+    /// * `Err(String)` - An error occurred during searching or the query is invalid for the mode
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let tests = search_engine.search_with_mode("**/src/**/*_test.rs", SearchMode::Glob)?;
+    /// ```
+    pub fn search_with_mode(&self, query: &str, mode: SearchMode) -> Result<Vec<(String, f32)>, String> {
+        // Compile the query before touching any state so invalid input is reported cleanly
+        let request = match PathPattern::compile(query, mode)? {
+            Some(pattern) => SearchRequest::Pattern(pattern),
+            None => SearchRequest::Fuzzy(parse_query(query)?),
+        };
+
         let mut data = self.data.lock().map_err(|_| "Failed to lock search engine data for search operation")?;
 
         // Check if search engine is enabled
@@ -756,6 +1052,9 @@ impl SearchEngineState {
 
         // Get current directory context
         let current_dir = data.current_directory.clone();
+        let timeout = Duration::from_millis(
+            data.config.search_timeout_ms.unwrap_or(DEFAULT_SEARCH_TIMEOUT_MS),
+        );
         
         // Check if engine is already in a search operation
         if matches!(data.status, SearchEngineStatus::Searching) {
@@ -771,25 +1070,37 @@ impl SearchEngineState {
 
         // Always use write lock to ensure caching works properly
         // Update directory context if needed, then perform cached search
-        let results = {
-            let mut engine = self.engine.write().map_err(|_| "Failed to acquire write lock on search engine for search operation")?;
-            
-            // Update directory context if needed
-            if let Some(current_dir) = &current_dir {
-                engine.set_current_directory(Some(current_dir.clone()));
-            } else {
-                engine.set_current_directory(None);
+        let start_time = Instant::now();
+        let mut engine = self.engine.write().map_err(|_| "Failed to acquire write lock on search engine for search operation")?;
+
+        // Update directory context if needed
+        if let Some(current_dir) = &current_dir {
+            engine.set_current_directory(Some(current_dir.clone()));
+        } else {
+            engine.set_current_directory(None);
+        }
+
+        // Perform search with caching enabled
+        let search_results = match &request {
+            SearchRequest::Fuzzy(parsed_query) if parsed_query.needs_metadata() => {
+                // Reading metadata touches the filesystem for every candidate, so it
+                // runs without the engine lock and within the search time budget
+                let candidates = engine.filtered_candidates(parsed_query);
+                let cap = engine.filtered_result_cap();
+                drop(engine);
+                let scan = scan_metadata(candidates, parsed_query, cap, timeout);
+                if scan.timed_out {
+                    log_warn!("Filtered search stopped after {:?}, results are incomplete", timeout);
+                }
+                engine = self.engine.write().map_err(|_| "Failed to acquire write lock on search engine for search operation")?;
+                engine.rank_filtered(scan.matches, &parsed_query.text)
             }
-            
-            // Perform search with caching enabled
-            let start_time = Instant::now();
-            let results = engine.search(query);
-            let search_time = start_time.elapsed();
-            let was_cache_hit = engine.was_last_search_cache_hit();
-            (results, search_time, was_cache_hit)
+            SearchRequest::Fuzzy(parsed_query) => engine.search_with_query(parsed_query, timeout),
+            SearchRequest::Pattern(pattern) => engine.search_pattern(pattern, timeout),
         };
-
-        let (search_results, search_time, was_cache_hit) = results;
+        let search_time = start_time.elapsed();
+        let was_cache_hit = engine.was_last_search_cache_hit();
+        drop(engine);
 
         // Update metrics
         let mut data = self.data.lock().map_err(|_| "Failed to lock search engine data for metrics update")?;
@@ -1001,6 +1312,91 @@ impl SearchEngineState {
         Ok(results)
     }
 
+    /// Searches the text content of indexed files.
+    ///
+    /// Only files indexed with `index_file_contents` enabled are searched. The content
+    /// index narrows the query down to files containing every query word, then those
+    /// files are read (without holding the engine lock) to find the lines containing
+    /// the whole query, ignoring case. Files are ranked by their number of matching
+    /// lines combined with the same `RankingConfig` factors as path search.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The text to look for inside files
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<ContentSearchResult>)` - Matching files with line numbers and snippets, best first
+    /// * `Err(String)` - The engine is disabled, busy indexing or a lock failed
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let results = search_engine.search_content("TODO").unwrap();
+    /// for result in results {
+    ///     for m in result.matches {
+    ///         println!("{}:{}: {}", result.path, m.line_number, m.snippet);
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Performance
+    ///
+    /// Dominated by reading the candidate files; at most `MAX_CONTENT_CANDIDATES`
+    /// files of up to `MAX_CONTENT_INDEX_BYTES` each are read per query.
+    pub fn search_content(&self, query: &str) -> Result<Vec<ContentSearchResult>, String> {
+        let (current_dir, max_results) = {
+            let data = self.data.lock().map_err(|_| "Failed to lock search engine data for content search")?;
+
+            if !data.config.search_engine_enabled {
+                log_error!("Search engine is disabled in configuration.");
+                return Err("Search engine is disabled in configuration".to_string());
+            }
+
+            if matches!(data.status, SearchEngineStatus::Indexing) {
+                return Err("Engine is currently indexing".to_string());
+            }
+
+            (data.current_directory.clone(), data.config.max_results)
+        };
+
+        let candidates = {
+            let engine = self.engine.read().map_err(|_| "Failed to acquire read lock on search engine for content search")?;
+            engine.content_candidates(query)
+        };
+
+        let mut matches_by_path = std::collections::HashMap::new();
+        for path in candidates.into_iter().take(MAX_CONTENT_CANDIDATES) {
+            let Some(text) = read_text_content(Path::new(&path), MAX_CONTENT_INDEX_BYTES) else {
+                continue;
+            };
+            let (match_count, matches) = find_matching_lines(&text, query);
+            if match_count > 0 {
+                matches_by_path.insert(path, (match_count, matches));
+            }
+        }
+
+        // More matching lines give a higher base score, approaching 1
+        let scored: Vec<(String, f32)> = matches_by_path
+            .iter()
+            .map(|(path, (match_count, _))| (path.clone(), 1.0 - 1.0 / (1.0 + *match_count as f32)))
+            .collect();
+
+        let ranked = {
+            let engine = self.engine.read().map_err(|_| "Failed to acquire read lock on search engine for ranking")?;
+            engine.rank_results_with_context(&scored, query, current_dir.as_deref())
+        };
+
+        Ok(ranked
+            .into_iter()
+            .take(max_results)
+            .filter_map(|(path, score)| {
+                let (match_count, matches) = matches_by_path.remove(&path)?;
+                Some(ContentSearchResult { path, score, match_count, matches })
+            })
+            .collect())
+    }
+
     /// Updates the progress information for an ongoing indexing operation.
     /// Enhanced to support both traditional and chunked indexing progress tracking.
     ///
@@ -1088,7 +1484,7 @@ impl SearchEngineState {
                     progress: IndexingProgress::default(),
                     metrics: SearchEngineMetrics::default(),
                     recent_activity: RecentActivity::default(),
-                    stats: EngineStatsSerializable { cache_size: 0, trie_size: 0 },
+                    stats: EngineStatsSerializable { cache_size: 0, trie_size: 0, content_index_size: 0 },
                     last_updated: 0,
                 };
             }
@@ -1317,10 +1713,28 @@ impl Clone for SearchEngineState {
             data: Arc::clone(&self.data),
             engine: Arc::clone(&self.engine),
             settings_state: Arc::clone(&self.settings_state),
+            index_file_path: self.index_file_path.clone(),
+            watcher: Arc::clone(&self.watcher),
         }
     }
 }
 
+/// Writes a snapshot of `engine` with the indexed folder of `data` to `path`.
+fn persist_snapshot(path: &Path, data: &Mutex<SearchEngine>, engine: &RwLock<SearchCore>) -> Result<(), String> {
+    let index_folder = {
+        let data = data.lock().map_err(|_| "Failed to lock search engine data for index persistence")?;
+        data.index_folder.to_str().map(|s| s.to_string())
+    };
+
+    let mut snapshot = {
+        let engine = engine.read().map_err(|_| "Failed to acquire read lock on search engine for index persistence")?;
+        engine.export_snapshot()
+    };
+    snapshot.index_folder = index_folder;
+
+    save_index_snapshot(path, &snapshot)
+}
+
 #[cfg(test)]
 // Helper function to get test data directory
 fn get_test_data_path() -> PathBuf {
@@ -2243,6 +2657,102 @@ mod tests_searchengine_state {
         }
     }
 
+    #[test]
+    fn test_indexing_watches_folder_for_changes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let first_root = temp_dir.path().join("first");
+        let second_root = temp_dir.path().join("second");
+        fs::create_dir_all(&first_root).unwrap();
+        fs::create_dir_all(&second_root).unwrap();
+
+        let settings_state = Arc::new(Mutex::new(SettingsState::new()));
+        let state = SearchEngineState::new(settings_state);
+        state.start_chunked_indexing(first_root.clone(), 10).unwrap();
+        assert_eq!(state.watched_folders(), vec![first_root.clone()]);
+
+        // A file created after indexing shows up without re-indexing
+        let created = first_root.join("watched_new_file.txt");
+        fs::write(&created, "content").unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        while !state.engine.write().unwrap().contains_path(created.to_str().unwrap())
+            && std::time::Instant::now() < deadline
+        {
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert!(state.engine.write().unwrap().contains_path(created.to_str().unwrap()));
+
+        // Indexing another folder replaces the watched folder
+        state.start_chunked_indexing(second_root.clone(), 10).unwrap();
+        assert_eq!(state.watched_folders(), vec![second_root]);
+
+        state.stop_watching();
+        assert!(state.watched_folders().is_empty());
+    }
+
+    #[test]
+    fn test_index_persistence_roundtrip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let index_root = temp_dir.path().join("files");
+        fs::create_dir_all(index_root.join("docs")).unwrap();
+        fs::write(index_root.join("docs").join("persisted_report.txt"), "content").unwrap();
+        let index_file = temp_dir.path().join("search_index.bin");
+
+        // Index once with persistence enabled
+        let settings_state = Arc::new(Mutex::new(SettingsState::new()));
+        let mut state = SearchEngineState::new(settings_state.clone());
+        state.enable_index_persistence(index_file.clone());
+        state.start_chunked_indexing(index_root.clone(), 10).unwrap();
+        assert!(index_file.exists(), "Index file should be written after indexing");
+
+        // A fresh state picks the index up without re-indexing
+        let mut restored = SearchEngineState::new(settings_state.clone());
+        restored.enable_index_persistence(index_file.clone());
+        let results = restored.search("persisted_report").unwrap();
+        assert!(results.iter().any(|(path, _)| path.ends_with("persisted_report.txt")));
+        assert_eq!(restored.data.lock().unwrap().index_folder, index_root);
+
+        // A corrupt file is discarded and the engine starts empty
+        fs::write(&index_file, b"garbage").unwrap();
+        let mut rebuilt = SearchEngineState::new(settings_state);
+        rebuilt.enable_index_persistence(index_file.clone());
+        assert_eq!(rebuilt.get_stats().trie_size, 0);
+        assert!(!index_file.exists(), "Corrupt index file should be removed");
+    }
+
+    #[test]
+    fn test_unusable_index_is_rebuilt() {
+        use crate::search_engine::index_persistence::{IndexSnapshot, INDEX_FORMAT_VERSION};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let index_root = temp_dir.path().join("files");
+        fs::create_dir_all(&index_root).unwrap();
+        fs::write(index_root.join("rebuilt_notes.txt"), "content").unwrap();
+        let index_file = temp_dir.path().join("search_index.bin");
+
+        // An index written by another format version, which still names its folder
+        let snapshot = IndexSnapshot {
+            index_folder: Some(index_root.to_string_lossy().to_string()),
+            ..IndexSnapshot::default()
+        };
+        save_index_snapshot(&index_file, &snapshot).unwrap();
+        let mut bytes = fs::read(&index_file).unwrap();
+        bytes[6..10].copy_from_slice(&(INDEX_FORMAT_VERSION + 1).to_le_bytes());
+        fs::write(&index_file, bytes).unwrap();
+
+        let mut state = SearchEngineState::new(Arc::new(Mutex::new(SettingsState::new())));
+        state.enable_index_persistence(index_file.clone());
+
+        let deadline = Instant::now() + Duration::from_secs(20);
+        while !index_file.exists() || state.data.lock().unwrap().status != SearchEngineStatus::Idle {
+            assert!(Instant::now() < deadline, "The index should be rebuilt");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(state.data.lock().unwrap().index_folder, index_root);
+        let results = state.search("rebuilt_notes").unwrap();
+        assert!(results.iter().any(|(path, _)| path.ends_with("rebuilt_notes.txt")));
+        assert!(load_index_snapshot(&index_file).unwrap().is_some());
+    }
+
     #[test]
     fn test_interactive_search_scenarios() {
         // This test simulates a user interacting with the search engine
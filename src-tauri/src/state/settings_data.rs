@@ -33,8 +33,9 @@ pub enum FontSize {
 /// Direction for sorting files and directories.
 ///
 /// Controls whether items are sorted in ascending or descending order.
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
 pub enum SortDirection {
+    #[serde(alias = "Ascending")] // spelling used by the frontend
     Acscending,
     Descending,
 }
@@ -42,10 +43,12 @@ pub enum SortDirection {
 /// Property used for sorting files and directories.
 ///
 /// Determines which attribute is used when ordering items.
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
 pub enum SortBy {
     Name,
     Size,
+    /// Modification date
+    #[serde(alias = "Modified")] // name used by the frontend
     Date,
     Type,
 }
@@ -832,6 +835,9 @@ mod tests_settings {
         let result = state.update_setting_field("backend_settings.default_checksum_hash", json!("MD5"));
         assert!(result.is_ok());
         assert_eq!(result.unwrap().backend_settings.default_checksum_hash, ChecksumMethod::MD5);
+
+        let result = state.update_setting_field("backend_settings.default_checksum_hash", json!("BLAKE3"));
+        assert_eq!(result.unwrap().backend_settings.default_checksum_hash, ChecksumMethod::BLAKE3);
     }
 
     /// Tests updating the custom_themes setting field.
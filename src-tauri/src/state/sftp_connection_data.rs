@@ -0,0 +1,383 @@
+use crate::sftp::auth::SftpAuth;
+use crate::sftp::secret_store::{KeyLocation, SecretStore};
+#[allow(unused_imports)]
+use crate::{log_error, log_info};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// How a saved connection logs in. The secret of the method, if it has one, is kept in
+/// the secret store.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SftpAuthMethod {
+    /// The secret is the password
+    #[default]
+    Password,
+    /// The secret is the passphrase of the key, if it has one
+    PublicKey,
+    Agent,
+    /// The secret is the answer to the password prompt
+    KeyboardInteractive,
+}
+
+/// A saved remote connection. It never contains the secret itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSftpConnection {
+    pub id: String,
+    pub name: String,
+    /// A host name, address or alias of `~/.ssh/config`
+    pub host: String,
+    /// Defaults to the port of `~/.ssh/config` or 22
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Defaults to the user of `~/.ssh/config`
+    #[serde(default)]
+    pub username: Option<String>,
+    pub auth_method: SftpAuthMethod,
+    /// For `PublicKey`, defaults to the keys of `~/.ssh/config` or `~/.ssh/id_*`
+    #[serde(default)]
+    pub private_key_path: Option<String>,
+    /// The directory to open after connecting, defaults to the home directory on the server
+    #[serde(default)]
+    pub default_directory: Option<String>,
+    /// Whether a password or passphrase is stored
+    #[serde(default)]
+    pub has_secret: bool,
+}
+
+/// A connection to add or update, as sent by the frontend.
+#[derive(Clone, Deserialize)]
+pub struct SftpConnectionInput {
+    pub name: String,
+    pub host: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub auth_method: SftpAuthMethod,
+    #[serde(default)]
+    pub private_key_path: Option<String>,
+    #[serde(default)]
+    pub default_directory: Option<String>,
+    /// The password or passphrase. `None` keeps the stored one, an empty string removes it.
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+/// Empty strings from form fields mean "not set".
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+/// Registry of saved remote connections.
+///
+/// The connections are written to a JSON file next to the other config files after every
+/// change once persistence is enabled. Passwords and passphrases go to an encrypted
+/// `SecretStore` instead, keyed by the connection id, so they never have to be sent from
+/// the frontend again.
+#[derive(Default)]
+pub struct SftpConnectionState {
+    connections: Vec<SavedSftpConnection>,
+    file_path: Option<PathBuf>,
+    secrets: SecretStore,
+    /// Why the secret store could not be opened. Secrets are not accepted while it is set,
+    /// they would only be kept until the app is closed.
+    secrets_error: Option<String>,
+}
+
+impl SftpConnectionState {
+    /// Creates an empty registry that is only kept in memory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the connections from `path` and the secrets from `secrets_path`, and saves
+    /// every later change to them.
+    ///
+    /// An unreadable connections file is logged and replaced by an empty registry. A store
+    /// that cannot be decrypted anymore is replaced on the next change, one that cannot be
+    /// opened at all (e.g. its key is in a locked credential store) makes saving secrets fail.
+    pub fn enable_persistence(&mut self, path: PathBuf, secrets_path: PathBuf, key: &KeyLocation) {
+        match fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice::<Vec<SavedSftpConnection>>(&bytes) {
+                Ok(connections) => {
+                    log_info!("Loaded {} saved SFTP connections", connections.len());
+                    self.connections = connections;
+                }
+                Err(err) => log_error!("Failed to parse SFTP connections {}: {}", path.display(), err),
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => log_error!("Failed to read SFTP connections {}: {}", path.display(), err),
+        }
+        let opened = match SecretStore::open(secrets_path.clone(), key) {
+            Err(err) if err.kind() == std::io::ErrorKind::InvalidData => {
+                // The secrets are lost, but new ones must still be stored
+                log_error!("Secret store {} cannot be decrypted, starting a new one: {}", secrets_path.display(), err);
+                SecretStore::replace(secrets_path.clone(), key)
+            }
+            opened => opened,
+        };
+        match opened {
+            Ok(secrets) => {
+                self.secrets = secrets;
+                self.secrets_error = None;
+            }
+            Err(err) => {
+                log_error!("Failed to open secret store {}: {}", secrets_path.display(), err);
+                self.secrets_error = Some(err.to_string());
+            }
+        }
+        for connection in &mut self.connections {
+            connection.has_secret = self.secrets.get(&connection.id).is_some();
+        }
+        self.file_path = Some(path);
+    }
+
+    pub fn list(&self) -> Vec<SavedSftpConnection> {
+        self.connections.clone()
+    }
+
+    /// Adds a connection, or updates the one with `id`.
+    ///
+    /// # Returns
+    /// * `Ok(SavedSftpConnection)` - The saved connection
+    /// * `Err(String)` - If the name or host is missing, the name is taken, there is no connection
+    ///   with `id`, or the secret could not be stored or the secret store is unavailable
+    pub fn save(&mut self, id: Option<String>, input: SftpConnectionInput) -> Result<SavedSftpConnection, String> {
+        let name = input.name.trim().to_string();
+        let host = input.host.trim().to_string();
+        if name.is_empty() || host.is_empty() {
+            return Err("A connection needs a name and a host".to_string());
+        }
+        // Paths refer to connections by name, so names must be unique
+        if self.connections.iter().any(|c| c.name == name && Some(&c.id) != id.as_ref()) {
+            return Err(format!("A connection named {} already exists", name));
+        }
+
+        let id = match id {
+            Some(id) if self.connections.iter().any(|c| c.id == id) => id,
+            Some(id) => return Err(format!("Unknown SFTP connection: {}", id)),
+            None => self.new_id(),
+        };
+        if let (Some(secret), Some(err)) = (&input.secret, &self.secrets_error) {
+            if !secret.is_empty() {
+                return Err(format!("The secret store is unavailable: {}", err));
+            }
+        }
+        match input.secret {
+            Some(secret) if secret.is_empty() => self.secrets.remove(&id),
+            Some(secret) => self.secrets.set(&id, secret),
+            None => Ok(()),
+        }
+        .map_err(|e| format!("Failed to store the secret: {}", e))?;
+
+        let connection = SavedSftpConnection {
+            has_secret: self.secrets.get(&id).is_some(),
+            id: id.clone(),
+            name,
+            host,
+            port: input.port,
+            username: non_empty(input.username),
+            auth_method: input.auth_method,
+            private_key_path: non_empty(input.private_key_path),
+            default_directory: non_empty(input.default_directory),
+        };
+        match self.connections.iter_mut().find(|c| c.id == id) {
+            Some(existing) => *existing = connection.clone(),
+            None => self.connections.push(connection.clone()),
+        }
+        self.save_connections();
+        Ok(connection)
+    }
+
+    /// Removes a connection and its secret.
+    pub fn remove(&mut self, id: &str) -> Result<(), String> {
+        let index = self
+            .connections
+            .iter()
+            .position(|c| c.id == id)
+            .ok_or_else(|| format!("Unknown SFTP connection: {}", id))?;
+        self.secrets.remove(id).map_err(|e| format!("Failed to remove the secret: {}", e))?;
+        self.connections.remove(index);
+        self.save_connections();
+        Ok(())
+    }
+
+    /// The connection with `id` and how to log in with it, including its secret.
+    pub fn login(&self, id: &str) -> Result<(SavedSftpConnection, SftpAuth), String> {
+        let connection = self
+            .connections
+            .iter()
+            .find(|c| c.id == id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown SFTP connection: {}", id))?;
+        let secret = self.secrets.get(id).map(str::to_string);
+        let auth = match connection.auth_method {
+            SftpAuthMethod::Password => SftpAuth::Password { password: secret.unwrap_or_default() },
+            SftpAuthMethod::PublicKey => SftpAuth::PublicKey {
+                private_key_path: connection.private_key_path.clone(),
+                passphrase: secret,
+            },
+            SftpAuthMethod::Agent => SftpAuth::Agent,
+            SftpAuthMethod::KeyboardInteractive => SftpAuth::KeyboardInteractive {
+                responses: secret.into_iter().collect(),
+            },
+        };
+        Ok((connection, auth))
+    }
+
+    fn new_id(&self) -> String {
+        loop {
+            let id = format!("{:016x}", rand::random::<u64>());
+            if !self.connections.iter().any(|c| c.id == id) {
+                return id;
+            }
+        }
+    }
+
+    fn save_connections(&self) {
+        let Some(path) = &self.file_path else {
+            return;
+        };
+        if let Err(err) = write_connections(path, &self.connections) {
+            log_error!("Failed to save SFTP connections {}: {}", path.display(), err);
+        }
+    }
+}
+
+fn write_connections(path: &Path, connections: &[SavedSftpConnection]) -> std::io::Result<()> {
+    let serialized = serde_json::to_vec_pretty(connections)?;
+    let parent = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(parent)?;
+
+    // Write to a uniquely named file next to it and move that into place, so a crash never
+    // leaves half a file
+    let mut file = tempfile::NamedTempFile::new_in(parent)?;
+    file.write_all(&serialized)?;
+    file.as_file().sync_all()?;
+    file.persist(path).map_err(|err| err.error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests_sftp_connection_data {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn input(name: &str, auth_method: SftpAuthMethod, secret: Option<&str>) -> SftpConnectionInput {
+        SftpConnectionInput {
+            name: name.to_string(),
+            host: "nas.local".to_string(),
+            port: Some(2222),
+            username: Some("backup".to_string()),
+            auth_method,
+            private_key_path: Some(String::new()),
+            default_directory: Some("/srv/backup".to_string()),
+            secret: secret.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_connections_and_secrets_persist() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("sftp_connections.json");
+        let secrets_path = dir.path().join("sftp_secrets.bin");
+        let key_path = dir.path().join("sftp_secrets.key");
+        let key = KeyLocation::file(key_path.clone());
+
+        let mut state = SftpConnectionState::new();
+        state.enable_persistence(path.clone(), secrets_path.clone(), &key);
+        let saved = state.save(None, input("NAS", SftpAuthMethod::Password, Some("hunter2"))).unwrap();
+        assert!(saved.has_secret);
+        assert_eq!(saved.private_key_path, None);
+        assert!(!fs::read_to_string(&path).unwrap().contains("hunter2"));
+
+        let mut restored = SftpConnectionState::new();
+        restored.enable_persistence(path.clone(), secrets_path.clone(), &key);
+        assert_eq!(restored.list(), vec![saved.clone()]);
+        let (connection, auth) = restored.login(&saved.id).unwrap();
+        assert_eq!(connection.default_directory.as_deref(), Some("/srv/backup"));
+        assert_eq!(auth, SftpAuth::Password { password: "hunter2".to_string() });
+
+        // Without its key the secrets are gone, but new ones are stored again
+        fs::remove_file(&key_path).unwrap();
+        let mut restored = SftpConnectionState::new();
+        restored.enable_persistence(path.clone(), secrets_path.clone(), &key);
+        assert!(!restored.list()[0].has_secret);
+        restored.save(Some(saved.id.clone()), input("NAS", SftpAuthMethod::Password, Some("hunter3"))).unwrap();
+        let mut restored = SftpConnectionState::new();
+        restored.enable_persistence(path, secrets_path, &key);
+        assert_eq!(restored.login(&saved.id).unwrap().1, SftpAuth::Password { password: "hunter3".to_string() });
+    }
+
+    #[test]
+    fn test_secrets_are_refused_while_the_store_is_unavailable() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("sftp_connections.json");
+        // A directory cannot be read as a store, but is not undecryptable either
+        let secrets_path = dir.path().join("sftp_secrets.bin");
+        fs::create_dir(&secrets_path).unwrap();
+        let key = KeyLocation::file(dir.path().join("sftp_secrets.key"));
+
+        let mut state = SftpConnectionState::new();
+        state.enable_persistence(path.clone(), secrets_path.clone(), &key);
+        let err = state.save(None, input("NAS", SftpAuthMethod::Password, Some("hunter2"))).unwrap_err();
+        assert!(err.contains("secret store is unavailable"), "{}", err);
+        assert!(state.list().is_empty());
+
+        // Connections without a secret can still be saved
+        let saved = state.save(None, input("NAS", SftpAuthMethod::Agent, None)).unwrap();
+        assert!(!saved.has_secret);
+        assert!(state.save(Some(saved.id.clone()), input("NAS", SftpAuthMethod::Agent, Some(""))).is_ok());
+
+        // Once the store can be opened again, secrets are stored
+        fs::remove_dir(&secrets_path).unwrap();
+        let mut restored = SftpConnectionState::new();
+        restored.enable_persistence(path, secrets_path, &key);
+        assert!(restored.save(Some(saved.id), input("NAS", SftpAuthMethod::Password, Some("hunter2"))).unwrap().has_secret);
+    }
+
+    #[test]
+    fn test_update_keeps_or_replaces_the_secret() {
+        let mut state = SftpConnectionState::new();
+        let saved = state.save(None, input("NAS", SftpAuthMethod::PublicKey, Some("passphrase"))).unwrap();
+
+        // No secret given keeps the stored one
+        let mut update = input("NAS backup", SftpAuthMethod::PublicKey, None);
+        update.private_key_path = Some("~/.ssh/nas".to_string());
+        let updated = state.save(Some(saved.id.clone()), update).unwrap();
+        assert_eq!((updated.id.as_str(), updated.name.as_str()), (saved.id.as_str(), "NAS backup"));
+        assert_eq!(
+            state.login(&saved.id).unwrap().1,
+            SftpAuth::PublicKey { private_key_path: Some("~/.ssh/nas".to_string()), passphrase: Some("passphrase".to_string()) }
+        );
+
+        // An empty secret removes it
+        let updated = state.save(Some(saved.id.clone()), input("NAS backup", SftpAuthMethod::Agent, Some(""))).unwrap();
+        assert!(!updated.has_secret);
+        assert_eq!(state.login(&saved.id).unwrap().1, SftpAuth::Agent);
+        assert_eq!(state.list().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_saves_and_removal() {
+        let mut state = SftpConnectionState::new();
+        let saved = state.save(None, input("NAS", SftpAuthMethod::KeyboardInteractive, Some("secret"))).unwrap();
+        assert_eq!(
+            state.login(&saved.id).unwrap().1,
+            SftpAuth::KeyboardInteractive { responses: vec!["secret".to_string()] }
+        );
+
+        assert!(state.save(None, input("NAS", SftpAuthMethod::Password, None)).unwrap_err().contains("already exists"));
+        assert!(state.save(None, input(" ", SftpAuthMethod::Password, None)).is_err());
+        assert!(state.save(Some("missing".to_string()), input("Other", SftpAuthMethod::Password, None)).is_err());
+
+        state.remove(&saved.id).unwrap();
+        assert!(state.list().is_empty());
+        assert!(state.login(&saved.id).is_err());
+        assert!(state.remove(&saved.id).is_err());
+    }
+}
@@ -0,0 +1,420 @@
+use crate::sftp::auth::{self, SftpAuth};
+use crate::sftp::host_keys::{self, HostKeyInfo};
+use crate::sftp::{handshake, ssh_config};
+use crate::{log_info, log_warn};
+use home::home_dir;
+use ssh2::{Session, Sftp};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub type SftpSessionId = u64;
+
+/// How often idle sessions send a keepalive, and how long a session may be idle before
+/// it is checked before use
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a blocking call on a session may take before it fails
+const SESSION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The server and user a session is connected as. Sessions are shared per key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SftpConnectionKey {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+}
+
+/// Everything needed to log in again after a connection dropped.
+#[derive(Debug, Clone, PartialEq)]
+struct SftpLogin {
+    auth: SftpAuth,
+    /// The keys to try for public key authentication without a key path
+    identity_files: Vec<PathBuf>,
+    known_hosts: Option<PathBuf>,
+}
+
+/// The SSH session and its SFTP channel.
+struct SftpLink {
+    session: Session,
+    sftp: Sftp,
+}
+
+impl SftpLink {
+    fn open(key: &SftpConnectionKey, login: &SftpLogin) -> Result<Self, String> {
+        let session = handshake(&key.host, key.port, SESSION_TIMEOUT)?;
+        // The host key is checked before any credentials are sent
+        host_keys::verify(&session, login.known_hosts.as_deref(), &key.host, key.port)?;
+        auth::authenticate(&session, &key.username, &login.auth, &login.identity_files)?;
+
+        // Keepalives are only sent by `keepalive_send`, the server does not need to reply
+        session.set_keepalive(false, KEEPALIVE_INTERVAL.as_secs() as u32);
+        let sftp = session.sftp().map_err(|e| e.to_string())?;
+        Ok(Self { session, sftp })
+    }
+}
+
+/// A pooled connection. It keeps the credentials, so a dropped connection can be
+/// reestablished without asking the user again.
+struct SftpConnection {
+    key: SftpConnectionKey,
+    login: SftpLogin,
+    /// `None` once the connection dropped, until it is reconnected
+    link: Option<SftpLink>,
+    last_used: Instant,
+}
+
+impl SftpConnection {
+    /// Connects, verifies the host key and authenticates.
+    ///
+    /// # Returns
+    /// * `Ok(SftpConnection)` - The connected session
+    /// * `Err(String)` - If the server cannot be reached, its host key is not trusted or authentication fails
+    fn open(key: SftpConnectionKey, login: SftpLogin) -> Result<Self, String> {
+        let link = SftpLink::open(&key, &login)?;
+        Ok(Self { key, login, link: Some(link), last_used: Instant::now() })
+    }
+
+    /// Whether the server still answers. Costs a round trip.
+    fn is_alive(&self) -> bool {
+        self.link.as_ref().is_some_and(|link| link.sftp.realpath(Path::new(".")).is_ok())
+    }
+
+    fn reconnect(&mut self) -> Result<(), String> {
+        self.link = None;
+        self.link = Some(SftpLink::open(&self.key, &self.login)?);
+        Ok(())
+    }
+
+    /// Reconnects a connection that dropped, or that was idle and does not answer anymore.
+    fn ensure_connected(&mut self) -> Result<(), String> {
+        if self.link.is_none() || (self.last_used.elapsed() >= KEEPALIVE_INTERVAL && !self.is_alive()) {
+            log_info!("Reconnecting SFTP session to {}@{}:{}", self.key.username, self.key.host, self.key.port);
+            self.reconnect()?;
+        }
+        Ok(())
+    }
+
+    fn sftp(&self) -> Result<&Sftp, String> {
+        self.link.as_ref().map(|link| &link.sftp).ok_or_else(|| "SFTP session is not connected".to_string())
+    }
+
+    /// Sends a keepalive if the connection was idle for a while, and marks the
+    /// connection as dropped if that fails.
+    fn keep_alive(&mut self) {
+        let Some(link) = &self.link else {
+            return;
+        };
+        if self.last_used.elapsed() < KEEPALIVE_INTERVAL {
+            return;
+        }
+        if let Err(err) = link.session.keepalive_send() {
+            log_warn!("SFTP session to {} dropped: {}", self.key.host, err);
+            self.link = None;
+        }
+    }
+
+    fn disconnect(&mut self) {
+        if let Some(link) = self.link.take() {
+            if let Err(err) = link.session.disconnect(None, "Closed by the user", None) {
+                log_warn!("Failed to close SFTP session to {}: {}", self.key.host, err);
+            }
+        }
+    }
+}
+
+/// A session of the pool. The key and login are kept next to the connection, so
+/// sessions can be found without waiting for commands that are using them.
+struct PooledSession {
+    key: SftpConnectionKey,
+    login: SftpLogin,
+    connection: Arc<Mutex<SftpConnection>>,
+}
+
+/// Pool of SFTP sessions, so commands reuse a connection instead of connecting every time.
+/// Connecting again with the same server, user and authentication returns the existing session.
+pub struct SftpSessionState {
+    next_id: SftpSessionId,
+    sessions: HashMap<SftpSessionId, PooledSession>,
+    /// The directory with the SSH `config` and `known_hosts`, usually `~/.ssh`
+    ssh_dir: Option<PathBuf>,
+}
+
+impl Default for SftpSessionState {
+    fn default() -> Self {
+        Self { next_id: 0, sessions: HashMap::new(), ssh_dir: home_dir().map(|home| home.join(".ssh")) }
+    }
+}
+
+impl SftpSessionState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A pool that reads the SSH config and known hosts from another directory than `~/.ssh`.
+    #[cfg(test)]
+    pub fn with_ssh_dir(ssh_dir: PathBuf) -> Self {
+        Self { ssh_dir: Some(ssh_dir), ..Self::default() }
+    }
+
+    /// Finds a session for `key` that was opened with the same login.
+    fn find(&self, key: &SftpConnectionKey, login: &SftpLogin) -> Option<SftpSessionId> {
+        self.sessions
+            .iter()
+            .find(|(_, session)| session.key == *key && session.login == *login)
+            .map(|(id, _)| *id)
+    }
+
+    fn insert(&mut self, connection: SftpConnection) -> SftpSessionId {
+        let id = self.next_id;
+        self.next_id += 1;
+        let session = PooledSession {
+            key: connection.key.clone(),
+            login: connection.login.clone(),
+            connection: Arc::new(Mutex::new(connection)),
+        };
+        self.sessions.insert(id, session);
+        id
+    }
+
+    fn get(&self, id: SftpSessionId) -> Option<Arc<Mutex<SftpConnection>>> {
+        self.sessions.get(&id).map(|session| session.connection.clone())
+    }
+
+    fn connections(&self) -> Vec<Arc<Mutex<SftpConnection>>> {
+        self.sessions.values().map(|session| session.connection.clone()).collect()
+    }
+}
+
+fn lock_pool(pool: &Arc<Mutex<SftpSessionState>>) -> Result<MutexGuard<'_, SftpSessionState>, String> {
+    pool.lock().map_err(|_| "SFTP session pool is poisoned".to_string())
+}
+
+/// A host as the user gave it, with the gaps filled in from the SSH config.
+struct ResolvedHost {
+    host: String,
+    port: u16,
+    user: Option<String>,
+    identity_files: Vec<PathBuf>,
+    known_hosts: Option<PathBuf>,
+}
+
+/// Resolves a host alias of the SSH config. A port or user given explicitly wins over
+/// the config. Without a port in either, the default port 22 is used.
+fn resolve_host(pool: &Arc<Mutex<SftpSessionState>>, host: String, port: Option<u16>, user: Option<String>) -> Result<ResolvedHost, String> {
+    let ssh_dir = lock_pool(pool)?.ssh_dir.clone();
+    let config = ssh_config::lookup(ssh_dir.as_deref(), &host);
+    let identity_files = match (&ssh_dir, config.identity_files.is_empty()) {
+        (Some(ssh_dir), true) => auth::default_identity_files(ssh_dir),
+        _ => config.identity_files,
+    };
+    Ok(ResolvedHost {
+        host: config.host_name.unwrap_or(host),
+        port: port.or(config.port).unwrap_or(22),
+        user: user.filter(|user| !user.is_empty()).or(config.user),
+        identity_files,
+        known_hosts: ssh_dir.map(|dir| dir.join("known_hosts")),
+    })
+}
+
+/// Returns the session for a server and user, connecting only if there is none yet.
+/// The pool is not locked while connecting, so other sessions stay usable.
+///
+/// # Arguments
+/// * `host` - A host name, address or alias of the SSH config
+/// * `port` - The port, defaults to the one of the SSH config or 22
+/// * `username` - The user, defaults to the one of the SSH config
+///
+/// # Returns
+/// * `Ok(SftpSessionId)` - The id commands use to refer to the session
+/// * `Err(String)` - If the server cannot be reached, its host key is not trusted or authentication fails
+pub fn connect_session(
+    pool: &Arc<Mutex<SftpSessionState>>,
+    host: String,
+    port: Option<u16>,
+    username: Option<String>,
+    auth: SftpAuth,
+) -> Result<SftpSessionId, String> {
+    let resolved = resolve_host(pool, host, port, username)?;
+    let username = resolved
+        .user
+        .ok_or_else(|| format!("No username given for {}", resolved.host))?;
+    let key = SftpConnectionKey { host: resolved.host, port: resolved.port, username };
+    let login = SftpLogin { auth, identity_files: resolved.identity_files, known_hosts: resolved.known_hosts };
+
+    if let Some(id) = lock_pool(pool)?.find(&key, &login) {
+        return Ok(id);
+    }
+    let connection = SftpConnection::open(key.clone(), login.clone())?;
+    log_info!("Connected SFTP session to {}@{}:{}", key.username, key.host, key.port);
+
+    let mut state = lock_pool(pool)?;
+    // Another command may have connected in the meantime
+    if let Some(id) = state.find(&key, &login) {
+        return Ok(id);
+    }
+    Ok(state.insert(connection))
+}
+
+/// Connects to a server without logging in and compares its host key to the known hosts.
+///
+/// # Returns
+/// * `Ok(HostKeyInfo)` - The key of the server and whether it is trusted
+/// * `Err(String)` - If the server cannot be reached
+pub fn check_host_key(pool: &Arc<Mutex<SftpSessionState>>, host: String, port: Option<u16>) -> Result<HostKeyInfo, String> {
+    let resolved = resolve_host(pool, host, port, None)?;
+    let session = handshake(&resolved.host, resolved.port, SESSION_TIMEOUT)?;
+    host_keys::inspect(&session, resolved.known_hosts.as_deref(), &resolved.host, resolved.port)
+}
+
+/// Adds the host key of a server to the known hosts, if it has the fingerprint the user
+/// confirmed after `check_host_key`.
+///
+/// # Returns
+/// * `Ok(HostKeyInfo)` - The key, now trusted
+/// * `Err(String)` - If the server presents another key or the known hosts cannot be written
+pub fn trust_host_key(
+    pool: &Arc<Mutex<SftpSessionState>>,
+    host: String,
+    port: Option<u16>,
+    fingerprint: &str,
+) -> Result<HostKeyInfo, String> {
+    let resolved = resolve_host(pool, host, port, None)?;
+    let session = handshake(&resolved.host, resolved.port, SESSION_TIMEOUT)?;
+    let info = host_keys::trust(&session, resolved.known_hosts.as_deref(), &resolved.host, resolved.port, fingerprint)?;
+    log_info!("Trusted host key {} of {}:{}", info.fingerprint, info.host, info.port);
+    Ok(info)
+}
+
+/// Removes a session from the pool and closes its connection. A command that is still
+/// using the session finishes first.
+///
+/// # Returns
+/// * `Ok(())` - If the session was closed
+/// * `Err(String)` - If there is no such session
+pub fn disconnect_session(pool: &Arc<Mutex<SftpSessionState>>, id: SftpSessionId) -> Result<(), String> {
+    let session = lock_pool(pool)?
+        .sessions
+        .remove(&id)
+        .ok_or_else(|| format!("Unknown SFTP session: {}", id))?;
+    let mut connection = session.connection.lock().map_err(|_| "SFTP session is poisoned".to_string())?;
+    connection.disconnect();
+    log_info!("Disconnected SFTP session to {}@{}:{}", session.key.username, session.key.host, session.key.port);
+    Ok(())
+}
+
+/// Runs `operation` on the SFTP channel of a session. A session whose connection dropped
+/// is reconnected first. The operation is not run again if the connection drops while it
+/// runs, since it may already have changed something on the server.
+///
+/// # Returns
+/// * `Ok(T)` - The result of the operation
+/// * `Err(String)` - If the session does not exist, cannot be reconnected or the operation failed
+pub fn with_session<T>(
+    pool: &Arc<Mutex<SftpSessionState>>,
+    id: SftpSessionId,
+    operation: impl FnMut(&Sftp) -> Result<T, String>,
+) -> Result<T, String> {
+    run_on_session(pool, id, false, operation)
+}
+
+/// Like `with_session`, but if the connection drops while `operation` runs it is run once
+/// more on a new connection. Only for operations that only read and can safely be repeated.
+///
+/// # Returns
+/// * `Ok(T)` - The result of the operation
+/// * `Err(String)` - If the session does not exist, cannot be reconnected or the operation failed
+pub fn with_session_retrying<T>(
+    pool: &Arc<Mutex<SftpSessionState>>,
+    id: SftpSessionId,
+    operation: impl FnMut(&Sftp) -> Result<T, String>,
+) -> Result<T, String> {
+    run_on_session(pool, id, true, operation)
+}
+
+fn run_on_session<T>(
+    pool: &Arc<Mutex<SftpSessionState>>,
+    id: SftpSessionId,
+    retry: bool,
+    mut operation: impl FnMut(&Sftp) -> Result<T, String>,
+) -> Result<T, String> {
+    let connection = lock_pool(pool)?
+        .get(id)
+        .ok_or_else(|| format!("Unknown SFTP session: {}", id))?;
+    let mut connection = connection.lock().map_err(|_| "SFTP session is poisoned".to_string())?;
+
+    connection.ensure_connected()?;
+    let result = match operation(connection.sftp()?) {
+        Err(err) if retry && !connection.is_alive() => {
+            log_warn!("SFTP session {} dropped, reconnecting: {}", id, err);
+            connection.reconnect()?;
+            operation(connection.sftp()?)
+        }
+        result => result,
+    };
+    connection.last_used = Instant::now();
+    result
+}
+
+/// Sends keepalives for idle sessions in the background, so servers and firewalls do not
+/// close them. Sessions that are in use are skipped. The thread ends with the pool.
+pub fn start_keepalive(pool: &Arc<Mutex<SftpSessionState>>) {
+    let pool: Weak<Mutex<SftpSessionState>> = Arc::downgrade(pool);
+    thread::spawn(move || loop {
+        thread::sleep(KEEPALIVE_INTERVAL);
+        let Some(pool) = pool.upgrade() else {
+            break;
+        };
+        let connections = match pool.lock() {
+            Ok(pool) => pool.connections(),
+            Err(_) => break,
+        };
+        for connection in connections {
+            if let Ok(mut connection) = connection.try_lock() {
+                connection.keep_alive();
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests_sftp_session_data {
+    use super::*;
+
+    #[test]
+    fn test_unknown_session() {
+        let pool = Arc::new(Mutex::new(SftpSessionState::new()));
+        let err = with_session(&pool, 7, |_| Ok(())).unwrap_err();
+        assert_eq!(err, "Unknown SFTP session: 7");
+        let err = with_session_retrying(&pool, 7, |_| Ok(())).unwrap_err();
+        assert_eq!(err, "Unknown SFTP session: 7");
+        assert_eq!(disconnect_session(&pool, 7).unwrap_err(), "Unknown SFTP session: 7");
+    }
+
+    #[test]
+    fn test_connect_to_unreachable_server_fails() {
+        let pool = Arc::new(Mutex::new(SftpSessionState::new()));
+        let auth = SftpAuth::Password { password: "explorer".to_string() };
+        assert!(connect_session(&pool, "127.0.0.1".to_string(), Some(1), Some("explorer".to_string()), auth).is_err());
+        assert!(pool.lock().unwrap().sessions.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_host_from_ssh_config() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("config"), "Host nas\n  HostName 192.168.1.20\n  Port 2222\n  User backup\n").unwrap();
+        let pool = Arc::new(Mutex::new(SftpSessionState::with_ssh_dir(dir.path().to_path_buf())));
+
+        let resolved = resolve_host(&pool, "nas".to_string(), None, None).unwrap();
+        assert_eq!((resolved.host.as_str(), resolved.port), ("192.168.1.20", 2222));
+        assert_eq!(resolved.user.as_deref(), Some("backup"));
+        assert_eq!(resolved.known_hosts, Some(dir.path().join("known_hosts")));
+
+        // Explicit values win over the config
+        let resolved = resolve_host(&pool, "nas".to_string(), Some(22), Some("admin".to_string())).unwrap();
+        assert_eq!((resolved.port, resolved.user.as_deref()), (22, Some("admin")));
+        let resolved = resolve_host(&pool, "example.com".to_string(), None, None).unwrap();
+        assert_eq!((resolved.host.as_str(), resolved.port, resolved.user), ("example.com", 22, None));
+    }
+}
@@ -4,6 +4,7 @@ import { useFileSystem } from '../../providers/FileSystemProvider';
 import { useHistory } from '../../providers/HistoryProvider';
 import { showSuccess, showError } from '../../utils/NotificationSystem';
 import AddSftpConnectionView from '../sidebar/AddSftpConnectionView';
+import { listSftpConnections, saveSftpConnection } from '../../utils/sftpConnections';
 import './networkView.css';
 
 /**
@@ -19,10 +20,9 @@ const NetworkView = () => {
 
     // Load SFTP connections
     useEffect(() => {
-        const loadConnections = () => {
+        const loadConnections = async () => {
             try {
-                const saved = JSON.parse(localStorage.getItem('fileExplorerSftpConnections') || '[]');
-                setLocalSftpConnections(saved);
+                setLocalSftpConnections(await listSftpConnections());
             } catch (err) {
                 setLocalSftpConnections([]);
             }
@@ -33,29 +33,19 @@ const NetworkView = () => {
         // Listen for connection updates
         const handler = () => loadConnections();
         window.addEventListener('sftp-connections-updated', handler);
-        window.addEventListener('storage', (e) => {
-            if (e.key === 'fileExplorerSftpConnections') loadConnections();
-        });
         
         return () => {
             window.removeEventListener('sftp-connections-updated', handler);
         };
     }, []);
 
-    // Add SFTP connection
-    const addSftpConnection = (conn) => {
+    // Add SFTP connection, its password is kept in the encrypted store of the backend
+    const addSftpConnection = async (conn) => {
         try {
-            const existing = JSON.parse(localStorage.getItem('fileExplorerSftpConnections') || '[]');
-            const newConnections = [...existing, conn];
-            localStorage.setItem('fileExplorerSftpConnections', JSON.stringify(newConnections));
-            window.dispatchEvent(new CustomEvent('sftp-connections-updated'));
-            window.dispatchEvent(new StorageEvent('storage', {
-                key: 'fileExplorerSftpConnections',
-                newValue: JSON.stringify(newConnections)
-            }));
+            await saveSftpConnection(conn);
             showSuccess(`SFTP connection "${conn.name}" added successfully`);
         } catch (err) {
-            showError('Failed to add SFTP connection');
+            showError(`Failed to add SFTP connection: ${err.message || err}`);
         }
         setIsAddSftpModalOpen(false);
     };
@@ -65,7 +55,7 @@ const NetworkView = () => {
         try {
             const sftpData = await navigateToSftpConnection(connection);
             if (sftpData) {
-                const sftpPath = createSftpUrl(connection, '.');
+                const sftpPath = createSftpUrl(connection, connection.defaultDirectory || '.');
                 await loadDirectory(sftpPath);
                 navigateTo(sftpPath);
                 showSuccess(`Connected to ${connection.name}`);
@@ -114,7 +104,7 @@ const NetworkView = () => {
                         </div>
                     ) : (
                         localSftpConnections.map((connection) => (
-                            <div key={connection.name} className="connection-card">
+                            <div key={connection.id} className="connection-card">
                                 <div className="connection-icon">
                                     <span className="icon icon-network"></span>
                                 </div>
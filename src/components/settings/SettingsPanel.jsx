@@ -93,10 +93,13 @@ const SettingsPanel = ({ isOpen, onClose }) => {
      */
     const hashAlgorithms = [
         { id: 'MD5', label: 'MD5' },
+        { id: 'SHA1', label: 'SHA-1' },
         { id: 'SHA256', label: 'SHA-256' },
         { id: 'SHA384', label: 'SHA-384' },
         { id: 'SHA512', label: 'SHA-512' },
-        { id: 'CRC32', label: 'CRC32' }
+        { id: 'CRC32', label: 'CRC32' },
+        { id: 'BLAKE3', label: 'BLAKE3' },
+        { id: 'XXH3', label: 'XXH3' }
     ];
 
     /**
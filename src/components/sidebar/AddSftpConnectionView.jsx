@@ -2,6 +2,7 @@
 import React, { useState } from 'react';
 import Modal from '../common/Modal';
 import Button from '../common/Button';
+import { ensureSftpHostKeyTrusted, sftpAuthFor } from '../../providers/SftpProvider';
 
 /**
  * AddSftpConnectionView - Modal for adding a new SFTP connection
@@ -16,6 +17,10 @@ const AddSftpConnectionView = ({ isOpen, onClose, onAdd }) => {
 	const [port, setPort] = useState('22');
 	const [username, setUsername] = useState('');
 	const [password, setPassword] = useState('');
+	const [authMethod, setAuthMethod] = useState('password');
+	const [privateKeyPath, setPrivateKeyPath] = useState('');
+	const [passphrase, setPassphrase] = useState('');
+	const [defaultDirectory, setDefaultDirectory] = useState('');
 	const [testing, setTesting] = useState(false);
 	const [testResult, setTestResult] = useState(null);
 	const [error, setError] = useState(null);
@@ -25,32 +30,37 @@ const AddSftpConnectionView = ({ isOpen, onClose, onAdd }) => {
 		setTestResult(null);
 		setError(null);
 		try {
-			// Use Tauri invoke to test SFTP connection by calling load_dir on "."
+			// Use Tauri invoke to test SFTP connection, the session is kept for browsing
 			const { invoke } = await import('@tauri-apps/api/core');
-			await invoke('load_dir', {
+			const portNumber = port.trim() ? parseInt(port, 10) : null;
+			await ensureSftpHostKeyTrusted(host, portNumber);
+			await invoke('connect_to_sftp', {
 				host,
-				port: parseInt(port, 10),
-				username,
-				password,
-				directory: "."
+				port: portNumber,
+				username: username || null,
+				auth: sftpAuthFor({ authMethod, password, privateKeyPath, passphrase })
 			});
 			setTestResult('Connection successful!');
 		} catch (e) {
 			setTestResult(null);
-			setError(e?.toString() || 'Connection failed');
+			setError(e?.message || e?.toString() || 'Connection failed');
 		} finally {
 			setTesting(false);
 		}
 	};
 
 	const handleAdd = () => {
-		if (!name.trim() || !host.trim() || !port.trim() || !username.trim()) return;
-		onAdd({ name, host, port, username, password });
+		if (!name.trim() || !host.trim()) return;
+		onAdd({ name, host, port, username, password, authMethod, privateKeyPath, passphrase, defaultDirectory });
 		setName('');
 		setHost('localhost');
 		setPort('22');
 		setUsername('');
 		setPassword('');
+		setAuthMethod('password');
+		setPrivateKeyPath('');
+		setPassphrase('');
+		setDefaultDirectory('');
 		setTestResult(null);
 		setError(null);
 	};
@@ -61,6 +71,10 @@ const AddSftpConnectionView = ({ isOpen, onClose, onAdd }) => {
 		setPort('22');
 		setUsername('');
 		setPassword('');
+		setAuthMethod('password');
+		setPrivateKeyPath('');
+		setPassphrase('');
+		setDefaultDirectory('');
 		setTestResult(null);
 		setError(null);
 		onClose();
@@ -78,14 +92,14 @@ const AddSftpConnectionView = ({ isOpen, onClose, onAdd }) => {
 					<Button
 						variant="secondary"
 						onClick={handleTestConnection}
-						disabled={testing || !host.trim() || !port.trim() || !username.trim()}
+						disabled={testing || !host.trim()}
 					>
 						{testing ? 'Testing...' : 'Test Connection'}
 					</Button>
 					<Button
 						variant="primary"
 						onClick={handleAdd}
-						disabled={!name.trim() || !host.trim() || !port.trim() || !username.trim()}
+						disabled={!name.trim() || !host.trim()}
 					>
 						Add
 					</Button>
@@ -113,7 +127,7 @@ const AddSftpConnectionView = ({ isOpen, onClose, onAdd }) => {
 						className="input"
 						value={host}
 						onChange={e => setHost(e.target.value)}
-						placeholder="localhost or IP address"
+						placeholder="localhost, IP address or ~/.ssh/config alias"
 					/>
 				</div>
 				<div className="form-group">
@@ -136,20 +150,73 @@ const AddSftpConnectionView = ({ isOpen, onClose, onAdd }) => {
 						className="input"
 						value={username}
 						onChange={e => setUsername(e.target.value)}
-						placeholder="Username"
+						placeholder="Username (optional with ~/.ssh/config)"
 					/>
 				</div>
 				<div className="form-group">
-					<label htmlFor="sftp-password">Password</label>
+					<label htmlFor="sftp-default-directory">Default directory</label>
 					<input
-						type="password"
-						id="sftp-password"
+						type="text"
+						id="sftp-default-directory"
 						className="input"
-						value={password}
-						onChange={e => setPassword(e.target.value)}
-						placeholder="Password"
+						value={defaultDirectory}
+						onChange={e => setDefaultDirectory(e.target.value)}
+						placeholder="Remote directory to open, defaults to the home directory"
 					/>
 				</div>
+				<div className="form-group">
+					<label htmlFor="sftp-auth-method">Authentication</label>
+					<select
+						id="sftp-auth-method"
+						className="input"
+						value={authMethod}
+						onChange={e => setAuthMethod(e.target.value)}
+					>
+						<option value="password">Password</option>
+						<option value="public_key">Private key</option>
+						<option value="agent">SSH agent</option>
+						<option value="keyboard_interactive">Keyboard-interactive</option>
+					</select>
+				</div>
+				{authMethod === 'public_key' && (
+					<>
+						<div className="form-group">
+							<label htmlFor="sftp-private-key">Private key</label>
+							<input
+								type="text"
+								id="sftp-private-key"
+								className="input"
+								value={privateKeyPath}
+								onChange={e => setPrivateKeyPath(e.target.value)}
+								placeholder="Path, defaults to ~/.ssh/config or ~/.ssh/id_*"
+							/>
+						</div>
+						<div className="form-group">
+							<label htmlFor="sftp-passphrase">Passphrase</label>
+							<input
+								type="password"
+								id="sftp-passphrase"
+								className="input"
+								value={passphrase}
+								onChange={e => setPassphrase(e.target.value)}
+								placeholder="Passphrase of the key, if any"
+							/>
+						</div>
+					</>
+				)}
+				{(authMethod === 'password' || authMethod === 'keyboard_interactive') && (
+					<div className="form-group">
+						<label htmlFor="sftp-password">Password</label>
+						<input
+							type="password"
+							id="sftp-password"
+							className="input"
+							value={password}
+							onChange={e => setPassword(e.target.value)}
+							placeholder="Password"
+						/>
+					</div>
+				)}
 				{testResult && <div className="input-hint" style={{ color: 'var(--success)' }}>{testResult}</div>}
 				{error && <div className="input-hint" style={{ color: 'var(--danger)' }}>{error}</div>}
 			</form>
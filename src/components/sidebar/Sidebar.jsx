@@ -13,6 +13,7 @@ import PermissionHelper from '../common/PermissionHelper';
 import {open} from '@tauri-apps/plugin-dialog';
 import './sidebar.css';
 import {showConfirm, showError, showSuccess} from "../../utils/NotificationSystem.js";
+import {listSftpConnections, removeSftpConnection as removeSavedSftpConnection, saveSftpConnection} from '../../utils/sftpConnections';
 
 /**
  * Sidebar component - Provides navigation, favorites, and quick access
@@ -58,11 +59,10 @@ const Sidebar = ({ onTerminalToggle, isTerminalOpen, currentView }) => {
             console.error(`Failed to browse to ${folderName}:`, error);
         }
     };
-    // Load SFTP connections from localStorage
-    const loadSftpConnections = React.useCallback(() => {
+    // Load the saved SFTP connections
+    const loadSftpConnections = React.useCallback(async () => {
         try {
-            const saved = JSON.parse(localStorage.getItem('fileExplorerSftpConnections') || '[]');
-            setSftpConnections(saved);
+            setSftpConnections(await listSftpConnections());
         } catch (err) {
             setSftpConnections([]);
         }
@@ -73,47 +73,30 @@ const Sidebar = ({ onTerminalToggle, isTerminalOpen, currentView }) => {
         loadSftpConnections();
         const handler = () => loadSftpConnections();
         window.addEventListener('sftp-connections-updated', handler);
-        window.addEventListener('storage', (e) => {
-            if (e.key === 'fileExplorerSftpConnections') loadSftpConnections();
-        });
         return () => {
             window.removeEventListener('sftp-connections-updated', handler);
         };
     }, [loadSftpConnections]);
 
     // Add SFTP connection
-    const addSftpConnection = (conn) => {
+    const addSftpConnection = async (conn) => {
         try {
-            const existing = JSON.parse(localStorage.getItem('fileExplorerSftpConnections') || '[]');
-            const newConnections = [...existing, conn];
-            localStorage.setItem('fileExplorerSftpConnections', JSON.stringify(newConnections));
-            window.dispatchEvent(new CustomEvent('sftp-connections-updated'));
-            window.dispatchEvent(new StorageEvent('storage', {
-                key: 'fileExplorerSftpConnections',
-                newValue: JSON.stringify(newConnections)
-            }));
+            await saveSftpConnection(conn);
         } catch (err) {
-            // ignore
+            showError('Failed to add SFTP connection: ' + (err.message || err), 'error');
         }
         setIsAddSftpModalOpen(false);
     };
 
     // Remove SFTP connection with confirmation
-    const removeSftpConnection = async (name) => {
-        const confirmRemove = await showConfirm(`Are you sure you want to remove the SFTP connection "${name}"?`);
+    const removeSftpConnection = async (conn) => {
+        const confirmRemove = await showConfirm(`Are you sure you want to remove the SFTP connection "${conn.name}"?`);
         if (!confirmRemove) return;
         try {
-            const existing = JSON.parse(localStorage.getItem('fileExplorerSftpConnections') || '[]');
-            const newConnections = existing.filter(c => c.name !== name);
-            localStorage.setItem('fileExplorerSftpConnections', JSON.stringify(newConnections));
-            window.dispatchEvent(new CustomEvent('sftp-connections-updated'));
-            window.dispatchEvent(new StorageEvent('storage', {
-                key: 'fileExplorerSftpConnections',
-                newValue: JSON.stringify(newConnections)
-            }));
-            showSuccess('SFTP COnnection "${name}" removed successfully.', 'success');
+            await removeSavedSftpConnection(conn.id);
+            showSuccess(`SFTP connection "${conn.name}" removed successfully.`, 'success');
         } catch (err) {
-            showError('Failed to remove SFTP connection: ' + err.message, 'error');
+            showError('Failed to remove SFTP connection: ' + (err.message || err), 'error');
         }
     };
 
@@ -614,7 +597,7 @@ const Sidebar = ({ onTerminalToggle, isTerminalOpen, currentView }) => {
                                 ) : (
                                     sftpConnections.map((conn) => (
                                         <SidebarItem
-                                            key={conn.name}
+                                            key={conn.id}
                                             icon="network"
                                             name={conn.name}
                                             path={`sftp://${conn.username}@${conn.host}:${conn.port}`}
@@ -624,7 +607,7 @@ const Sidebar = ({ onTerminalToggle, isTerminalOpen, currentView }) => {
                                                 try {
                                                     const sftpData = await navigateToSftpConnection(conn);
                                                     if (sftpData) {
-                                                        const sftpPath = createSftpUrl(conn, '.');
+                                                        const sftpPath = createSftpUrl(conn, conn.defaultDirectory || '.');
                                                         await loadDirectory(sftpPath);
                                                         navigateTo(sftpPath);
                                                     }
@@ -635,7 +618,7 @@ const Sidebar = ({ onTerminalToggle, isTerminalOpen, currentView }) => {
                                             actions={[{
                                                 icon: 'x',
                                                 tooltip: 'Remove SFTP Connection',
-                                                onClick: () => removeSftpConnection(conn.name)
+                                                onClick: () => removeSftpConnection(conn)
                                             }]}
                                         />
                                     ))
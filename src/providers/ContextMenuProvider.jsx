@@ -274,10 +274,10 @@ export default function ContextMenuProvider({ children }) {
                             continue;
                         }
                     } else {
-                        // Local file system move
-                        await invoke('rename', {
-                            oldPath: sourcePath,
-                            newPath: destPath
+                        // Local file system move, also works across drives
+                        await invoke('move_file_or_dir', {
+                            sourcePath: sourcePath,
+                            destinationPath: destPath
                         });
                     }
                 } else {
@@ -1,6 +1,7 @@
 import React, { createContext, useContext, useState, useCallback, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { showError, showSuccess } from '../utils/NotificationSystem';
+import { listSftpConnections } from '../utils/sftpConnections';
 
 const SftpContext = createContext({
     sftpConnections: [],
@@ -19,22 +20,74 @@ const SftpContext = createContext({
     parseSftpPath: () => null,
     navigateToSftpConnection: () => {},
     disconnectSftp: () => {},
+    getSessionId: async () => null,
     createSftpUrl: () => null,
     createSftpPath: () => null
 });
 
 export const useSftp = () => useContext(SftpContext);
 
+/**
+ * Builds the authentication of a connection that is not saved yet for `connect_to_sftp`.
+ * Saved connections connect with `connect_sftp_connection` and their stored secret.
+ * @param {Object} connection - The connection from the add form
+ * @returns {Object} The auth argument of `connect_to_sftp`
+ */
+export function sftpAuthFor(connection) {
+    switch (connection.authMethod) {
+        case 'public_key':
+            return {
+                method: 'public_key',
+                private_key_path: connection.privateKeyPath || null,
+                passphrase: connection.passphrase || null
+            };
+        case 'agent':
+            return { method: 'agent' };
+        case 'keyboard_interactive':
+            return { method: 'keyboard_interactive', responses: [connection.password || ''] };
+        default:
+            return { method: 'password', password: connection.password || '' };
+    }
+}
+
+/**
+ * Makes sure the host key of a server is trusted before connecting. An unknown key is
+ * shown to the user and added to known_hosts if they accept it, a changed key is refused.
+ * @param {string} host - Host name, address or ~/.ssh/config alias
+ * @param {number|null} port - Port, or null for the one of ~/.ssh/config
+ */
+export async function ensureSftpHostKeyTrusted(host, port) {
+    const hostKey = await invoke('check_sftp_host_key', { host, port });
+    if (hostKey.status === 'trusted') return;
+    if (hostKey.status === 'changed') {
+        throw new Error(`The host key of ${host} changed. The connection may be intercepted, remove the old key from ~/.ssh/known_hosts if the change is expected.`);
+    }
+    const accepted = window.confirm(
+        `The authenticity of host ${hostKey.host}:${hostKey.port} can't be established.\n` +
+        `${hostKey.key_type} key fingerprint is ${hostKey.fingerprint}.\n\n` +
+        'Do you want to trust this host and continue connecting?'
+    );
+    if (!accepted) {
+        throw new Error(`Host key of ${host} was not trusted`);
+    }
+    await invoke('trust_sftp_host_key', { host, port, fingerprint: hostKey.fingerprint });
+}
+
+function parsePort(port) {
+    const parsed = parseInt(port, 10);
+    return Number.isNaN(parsed) ? null : parsed;
+}
+
 export default function SftpProvider({ children }) {
     const [sftpConnections, setSftpConnections] = useState([]);
     const [currentSftpConnection, setCurrentSftpConnection] = useState(null);
     const [currentSftpPath, setCurrentSftpPath] = useState(null);
     const connectionCache = useRef(new Map());
 
-    // Load SFTP connections from localStorage
-    const loadSftpConnections = useCallback(() => {
+    // Load the saved SFTP connections from the backend
+    const loadSftpConnections = useCallback(async () => {
         try {
-            const saved = JSON.parse(localStorage.getItem('fileExplorerSftpConnections') || '[]');
+            const saved = await listSftpConnections();
             setSftpConnections(saved);
             return saved;
         } catch (err) {
@@ -43,6 +96,19 @@ export default function SftpProvider({ children }) {
         }
     }, []);
 
+    // Returns the backend session for a saved connection, connecting on first use.
+    // The backend keeps the session alive and reconnects it if it drops.
+    const getSessionId = useCallback(async (connection) => {
+        if (connectionCache.current.has(connection.id)) {
+            return connectionCache.current.get(connection.id);
+        }
+        await ensureSftpHostKeyTrusted(connection.host, parsePort(connection.port));
+        // The stored secret is looked up by the backend
+        const sessionId = await invoke('connect_sftp_connection', { connectionId: connection.id });
+        connectionCache.current.set(connection.id, sessionId);
+        return sessionId;
+    }, []);
+
     // Initialize SFTP connections on mount
     React.useEffect(() => {
         loadSftpConnections();
@@ -66,16 +132,7 @@ export default function SftpProvider({ children }) {
         }
 
         try {
-            // Get fresh connections from localStorage if our state is empty
-            let connections = sftpConnections;
-            if (connections.length === 0) {
-                try {
-                    connections = JSON.parse(localStorage.getItem('fileExplorerSftpConnections') || '[]');
-                    console.log('Loaded fresh connections from localStorage:', connections);
-                } catch (err) {
-                    connections = [];
-                }
-            }
+            const connections = sftpConnections;
             console.log('Available SFTP connections:', connections);
 
             // Handle different SFTP path formats:
@@ -88,7 +145,7 @@ export default function SftpProvider({ children }) {
                 const connection = connections.find(conn => 
                     conn.username === url.username && 
                     conn.host === url.hostname && 
-                    conn.port === (url.port || '22')
+                    (conn.port || '22') === (url.port || '22')
                 );
                 console.log('Found connection for URL format:', connection);
                 return {
@@ -144,7 +201,8 @@ export default function SftpProvider({ children }) {
     }, []);
 
     // Navigate to an SFTP connection
-    const navigateToSftpConnection = useCallback(async (connection, remotePath = '.') => {
+    // Without a path the default directory of the connection is opened
+    const navigateToSftpConnection = useCallback(async (connection, remotePath = connection?.defaultDirectory || '.') => {
         console.log('navigateToSftpConnection called with:', connection, remotePath);
         
         if (!connection) {
@@ -162,10 +220,7 @@ export default function SftpProvider({ children }) {
             
             // Load directory using existing SFTP command
             const result = await invoke('load_dir', {
-                host: connection.host,
-                port: parseInt(connection.port, 10),
-                username: connection.username,
-                password: connection.password,
+                sessionId: await getSessionId(connection),
                 directory: sftpPath
             });
             
@@ -207,7 +262,7 @@ export default function SftpProvider({ children }) {
             showError(`Failed to connect to ${connection.name}: ${error.message || error}`);
             return null;
         }
-    }, [createSftpUrl]);
+    }, [createSftpUrl, getSessionId]);
 
     // Load SFTP directory
     const loadSftpDirectory = useCallback(async (sftpPath) => {
@@ -240,10 +295,7 @@ export default function SftpProvider({ children }) {
         try {
             const filePath = `${parsed.remotePath}/${fileName}`.replace(/\/+/g, '/');
             await invoke('create_file_sftp', {
-                host: parsed.connection.host,
-                port: parseInt(parsed.connection.port, 10),
-                username: parsed.connection.username,
-                password: parsed.connection.password,
+                sessionId: await getSessionId(parsed.connection),
                 filePath: filePath
             });
             
@@ -254,7 +306,7 @@ export default function SftpProvider({ children }) {
             showError(`Failed to create file: ${error.message || error}`);
             return false;
         }
-    }, [parseSftpPath]);
+    }, [parseSftpPath, getSessionId]);
 
     const createSftpDirectory = useCallback(async (sftpPath, dirName) => {
         const parsed = parseSftpPath(sftpPath);
@@ -263,10 +315,7 @@ export default function SftpProvider({ children }) {
         try {
             const dirPath = `${parsed.remotePath}/${dirName}`.replace(/\/+/g, '/');
             await invoke('create_directory_sftp', {
-                host: parsed.connection.host,
-                port: parseInt(parsed.connection.port, 10),
-                username: parsed.connection.username,
-                password: parsed.connection.password,
+                sessionId: await getSessionId(parsed.connection),
                 directoryPath: dirPath
             });
             
@@ -277,7 +326,7 @@ export default function SftpProvider({ children }) {
             showError(`Failed to create directory: ${error.message || error}`);
             return false;
         }
-    }, [parseSftpPath]);
+    }, [parseSftpPath, getSessionId]);
 
     const deleteSftpItem = useCallback(async (sftpPath) => {
         const parsed = parseSftpPath(sftpPath);
@@ -288,10 +337,7 @@ export default function SftpProvider({ children }) {
             // For now, try both and handle errors
             try {
                 await invoke('delete_file_sftp', {
-                    host: parsed.connection.host,
-                    port: parseInt(parsed.connection.port, 10),
-                    username: parsed.connection.username,
-                    password: parsed.connection.password,
+                    sessionId: await getSessionId(parsed.connection),
                     filePath: parsed.remotePath
                 });
                 showSuccess(`File deleted successfully`);
@@ -299,10 +345,7 @@ export default function SftpProvider({ children }) {
             } catch (fileError) {
                 // Try as directory if file deletion failed
                 await invoke('delete_directory_sftp', {
-                    host: parsed.connection.host,
-                    port: parseInt(parsed.connection.port, 10),
-                    username: parsed.connection.username,
-                    password: parsed.connection.password,
+                    sessionId: await getSessionId(parsed.connection),
                     directoryPath: parsed.remotePath
                 });
                 showSuccess(`Directory deleted successfully`);
@@ -313,7 +356,7 @@ export default function SftpProvider({ children }) {
             showError(`Failed to delete item: ${error.message || error}`);
             return false;
         }
-    }, [parseSftpPath]);
+    }, [parseSftpPath, getSessionId]);
 
     const renameSftpItem = useCallback(async (sftpPath, newName) => {
         const parsed = parseSftpPath(sftpPath);
@@ -327,19 +370,13 @@ export default function SftpProvider({ children }) {
             // Try both file and directory rename
             try {
                 await invoke('rename_file_sftp', {
-                    host: parsed.connection.host,
-                    port: parseInt(parsed.connection.port, 10),
-                    username: parsed.connection.username,
-                    password: parsed.connection.password,
+                    sessionId: await getSessionId(parsed.connection),
                     oldPath: parsed.remotePath,
                     newPath: newPath
                 });
             } catch (fileError) {
                 await invoke('rename_directory_sftp', {
-                    host: parsed.connection.host,
-                    port: parseInt(parsed.connection.port, 10),
-                    username: parsed.connection.username,
-                    password: parsed.connection.password,
+                    sessionId: await getSessionId(parsed.connection),
                     oldPath: parsed.remotePath,
                     newPath: newPath
                 });
@@ -352,7 +389,7 @@ export default function SftpProvider({ children }) {
             showError(`Failed to rename item: ${error.message || error}`);
             return false;
         }
-    }, [parseSftpPath]);
+    }, [parseSftpPath, getSessionId]);
 
     const copySftpItem = useCallback(async (sftpPath, targetPath) => {
         const sourceParsed = parseSftpPath(sftpPath);
@@ -373,19 +410,13 @@ export default function SftpProvider({ children }) {
             // Try both file and directory copy
             try {
                 await invoke('copy_file_sftp', {
-                    host: sourceParsed.connection.host,
-                    port: parseInt(sourceParsed.connection.port, 10),
-                    username: sourceParsed.connection.username,
-                    password: sourceParsed.connection.password,
+                    sessionId: await getSessionId(sourceParsed.connection),
                     sourcePath: sourceParsed.remotePath,
                     destinationPath: targetParsed.remotePath
                 });
             } catch (fileError) {
                 await invoke('copy_directory_sftp', {
-                    host: sourceParsed.connection.host,
-                    port: parseInt(sourceParsed.connection.port, 10),
-                    username: sourceParsed.connection.username,
-                    password: sourceParsed.connection.password,
+                    sessionId: await getSessionId(sourceParsed.connection),
                     sourcePath: sourceParsed.remotePath,
                     destinationPath: targetParsed.remotePath
                 });
@@ -398,7 +429,7 @@ export default function SftpProvider({ children }) {
             showError(`Failed to copy item: ${error.message || error}`);
             return false;
         }
-    }, [parseSftpPath]);
+    }, [parseSftpPath, getSessionId]);
 
     const moveSftpItem = useCallback(async (sftpPath, targetPath) => {
         const sourceParsed = parseSftpPath(sftpPath);
@@ -419,19 +450,13 @@ export default function SftpProvider({ children }) {
             // Try both file and directory move
             try {
                 await invoke('move_file_sftp', {
-                    host: sourceParsed.connection.host,
-                    port: parseInt(sourceParsed.connection.port, 10),
-                    username: sourceParsed.connection.username,
-                    password: sourceParsed.connection.password,
+                    sessionId: await getSessionId(sourceParsed.connection),
                     sourcePath: sourceParsed.remotePath,
                     destinationPath: targetParsed.remotePath
                 });
             } catch (fileError) {
                 await invoke('move_directory_sftp', {
-                    host: sourceParsed.connection.host,
-                    port: parseInt(sourceParsed.connection.port, 10),
-                    username: sourceParsed.connection.username,
-                    password: sourceParsed.connection.password,
+                    sessionId: await getSessionId(sourceParsed.connection),
                     sourcePath: sourceParsed.remotePath,
                     destinationPath: targetParsed.remotePath
                 });
@@ -444,7 +469,7 @@ export default function SftpProvider({ children }) {
             showError(`Failed to move item: ${error.message || error}`);
             return false;
         }
-    }, [parseSftpPath]);
+    }, [parseSftpPath, getSessionId]);
 
     const openSftpFile = useCallback(async (sftpPath) => {
         const parsed = parseSftpPath(sftpPath);
@@ -452,10 +477,7 @@ export default function SftpProvider({ children }) {
 
         try {
             const content = await invoke('open_file_sftp', {
-                host: parsed.connection.host,
-                port: parseInt(parsed.connection.port, 10),
-                username: parsed.connection.username,
-                password: parsed.connection.password,
+                sessionId: await getSessionId(parsed.connection),
                 filePath: parsed.remotePath
             });
             
@@ -465,7 +487,7 @@ export default function SftpProvider({ children }) {
             showError(`Failed to open file: ${error.message || error}`);
             return null;
         }
-    }, [parseSftpPath]);
+    }, [parseSftpPath, getSessionId]);
 
     const downloadAndOpenSftpFile = useCallback(async (sftpPath, openFile = true) => {
         const parsed = parseSftpPath(sftpPath);
@@ -476,10 +498,7 @@ export default function SftpProvider({ children }) {
 
         try {
             const result = await invoke('download_and_open_sftp_file', {
-                host: parsed.connection.host,
-                port: parseInt(parsed.connection.port, 10),
-                username: parsed.connection.username,
-                password: parsed.connection.password,
+                sessionId: await getSessionId(parsed.connection),
                 filePath: parsed.remotePath,
                 openFile: openFile
             });
@@ -496,12 +515,20 @@ export default function SftpProvider({ children }) {
             showError(`Failed to download file: ${error.message || error}`);
             return null;
         }
-    }, [parseSftpPath]);
+    }, [parseSftpPath, getSessionId]);
 
-    const disconnectSftp = useCallback(() => {
+    const disconnectSftp = useCallback(async () => {
         setCurrentSftpConnection(null);
         setCurrentSftpPath(null);
+        const sessionIds = [...connectionCache.current.values()];
         connectionCache.current.clear();
+        for (const sessionId of sessionIds) {
+            try {
+                await invoke('disconnect_sftp', { sessionId });
+            } catch (error) {
+                console.error('Failed to disconnect SFTP session:', error);
+            }
+        }
     }, []);
 
     const value = {
@@ -521,6 +548,7 @@ export default function SftpProvider({ children }) {
         parseSftpPath,
         navigateToSftpConnection,
         disconnectSftp,
+        getSessionId,
         createSftpUrl,
         createSftpPath
     };
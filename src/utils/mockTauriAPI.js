@@ -210,6 +210,10 @@ export const mockInvoke = async (command, params) => {
             console.log(`Mock: Renaming ${params.old_path} to ${params.new_path}`);
             return;
 
+        case 'move_file_or_dir':
+            console.log(`Mock: Moving ${params.source_path} to ${params.destination_path}`);
+            return;
+
         case 'move_to_trash':
             console.log(`Mock: Moving ${params.path} to trash`);
             return;
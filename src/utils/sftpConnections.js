@@ -0,0 +1,115 @@
+import { invoke } from '@tauri-apps/api/core';
+
+/** Key under which connections were kept, with their passwords, before the backend stored them */
+const LEGACY_STORAGE_KEY = 'fileExplorerSftpConnections';
+
+// Several components list the connections on mount, the migration must only run once
+let migration = null;
+
+/**
+ * Converts a connection of the backend to the shape the components use.
+ * @param {Object} saved - A connection from `list_sftp_connections`
+ * @returns {Object} The connection
+ */
+function fromSaved(saved) {
+    return {
+        id: saved.id,
+        name: saved.name,
+        host: saved.host,
+        port: saved.port == null ? '' : String(saved.port),
+        username: saved.username || '',
+        authMethod: saved.auth_method,
+        privateKeyPath: saved.private_key_path || '',
+        defaultDirectory: saved.default_directory || '',
+        hasSecret: saved.has_secret
+    };
+}
+
+/**
+ * Converts a connection of the add form to the input of `save_sftp_connection`.
+ * The password or passphrase becomes the secret, depending on the auth method.
+ * @param {Object} connection - The connection from the form
+ * @returns {Object} The connection input
+ */
+function toInput(connection) {
+    const port = parseInt(connection.port, 10);
+    const authMethod = connection.authMethod || 'password';
+    const secret = authMethod === 'public_key' ? connection.passphrase : connection.password;
+    return {
+        name: connection.name,
+        host: connection.host,
+        port: Number.isNaN(port) ? null : port,
+        username: connection.username || null,
+        auth_method: authMethod,
+        private_key_path: connection.privateKeyPath || null,
+        default_directory: connection.defaultDirectory || null,
+        secret: authMethod === 'agent' ? '' : secret || null
+    };
+}
+
+/**
+ * Moves connections saved in localStorage by older versions to the backend, so their
+ * passwords end up in the encrypted store. Migrated connections are removed from
+ * localStorage, the ones that failed stay there and are tried again on the next start.
+ */
+async function migrateLegacyConnections() {
+    let legacy;
+    try {
+        legacy = JSON.parse(localStorage.getItem(LEGACY_STORAGE_KEY) || '[]');
+    } catch (err) {
+        legacy = [];
+    }
+    const failed = [];
+    for (const connection of legacy) {
+        try {
+            await invoke('save_sftp_connection', { connectionId: null, connection: toInput(connection) });
+        } catch (err) {
+            console.error(`Failed to migrate SFTP connection "${connection.name}":`, err);
+            failed.push(connection);
+        }
+    }
+    if (failed.length > 0) {
+        localStorage.setItem(LEGACY_STORAGE_KEY, JSON.stringify(failed));
+    } else {
+        localStorage.removeItem(LEGACY_STORAGE_KEY);
+    }
+}
+
+function notifyConnectionsUpdated() {
+    window.dispatchEvent(new CustomEvent('sftp-connections-updated'));
+}
+
+/**
+ * Lists the saved SFTP connections. Secrets are not part of them.
+ * @returns {Promise<Array<Object>>} The saved connections
+ */
+export const listSftpConnections = async () => {
+    if (localStorage.getItem(LEGACY_STORAGE_KEY) !== null || migration) {
+        migration = migration || migrateLegacyConnections();
+        await migration;
+    }
+    const saved = await invoke('list_sftp_connections');
+    return saved.map(fromSaved);
+};
+
+/**
+ * Adds a connection, or updates the one with `connectionId`.
+ * @param {Object} connection - The connection from the add form
+ * @param {string|null} connectionId - The connection to update, or null to add one
+ * @returns {Promise<Object>} The saved connection
+ */
+export const saveSftpConnection = async (connection, connectionId = null) => {
+    const saved = await invoke('save_sftp_connection', { connectionId, connection: toInput(connection) });
+    notifyConnectionsUpdated();
+    return fromSaved(saved);
+};
+
+/**
+ * Removes a saved connection and its secret.
+ * @param {string} connectionId - The id of the connection
+ * @returns {Promise<void>}
+ */
+export const removeSftpConnection = async (connectionId) => {
+    await invoke('remove_sftp_connection', { connectionId });
+    notifyConnectionsUpdated();
+};